@@ -0,0 +1,112 @@
+//! Compares the throughput of executing a filter for all the routes in one
+//! BGP UPDATE message with `exec_batch`, against executing it for every route
+//! separately.
+
+use std::collections::BTreeSet;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+
+use roto::blocks::Scope::Filter;
+use roto::pipeline;
+use roto::types::builtin::basic_route::{PeerId, PeerRibType, Provenance};
+use roto::types::builtin::{explode_announcements, NlriStatus, RouteContext};
+use roto::types::collections::{BytesRecord, Record};
+use roto::types::lazyrecord_types::BgpUpdateMessage;
+
+use inetnum::asn::Asn;
+use routecore::bgp::message::SessionConfig;
+
+const SOURCE_CODE: &str = r#"
+    filter batch-filter {
+        define {
+            rx route: Route;
+        }
+
+        term is-slash-64 {
+            match {
+                route.prefix.len() == 64;
+            }
+        }
+
+        apply {
+            filter match is-slash-64 matching {
+                return accept;
+            };
+            return reject;
+        }
+    }
+"#;
+
+fn update() -> BytesRecord<BgpUpdateMessage> {
+    // BGP UPDATE message containing MP_REACH_NLRI path attribute,
+    // comprising 5 IPv6 NLRIs
+    let buf = bytes::Bytes::from(vec![
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0xff, 0xff, 0xff, 0xff, 0xff, 0x00, 0x88, 0x02, 0x00, 0x00, 0x00,
+        0x71, 0x80, 0x0e, 0x5a, 0x00, 0x02, 0x01, 0x20, 0xfc, 0x00, 0x00,
+        0x10, 0x00, 0x01, 0x00, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x10, 0xfe, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0xfc, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x10, 0x40, 0x20, 0x01, 0x0d, 0xb8, 0xff, 0xff, 0x00,
+        0x00, 0x40, 0x20, 0x01, 0x0d, 0xb8, 0xff, 0xff, 0x00, 0x01, 0x40,
+        0x20, 0x01, 0x0d, 0xb8, 0xff, 0xff, 0x00, 0x02, 0x40, 0x20, 0x01,
+        0x0d, 0xb8, 0xff, 0xff, 0x00, 0x03, 0x40, 0x01, 0x01, 0x00, 0x40,
+        0x02, 0x06, 0x02, 0x01, 0x00, 0x00, 0x00, 0xc8, 0x80, 0x04, 0x04,
+        0x00, 0x00, 0x00, 0x00,
+    ]);
+
+    BytesRecord::<BgpUpdateMessage>::new(buf, SessionConfig::modern())
+        .unwrap()
+}
+
+fn exec_batch(c: &mut Criterion) {
+    let name = Filter("batch-filter".into());
+    let rotolo = pipeline::run_test(SOURCE_CODE, None).unwrap();
+    let filter = rotolo.retrieve_compiled_filter(&name).unwrap();
+
+    let update = update();
+
+    #[allow(clippy::mutable_key_type)]
+    let mut nlri_set = BTreeSet::new();
+    let routes =
+        explode_announcements(update.bytes_parser(), &mut nlri_set).unwrap();
+
+    let provenance = Provenance {
+        timestamp: chrono::Utc::now(),
+        connection_id: "[fe80::1]:178".parse().unwrap(),
+        peer_id: PeerId {
+            addr: "fe80::1".parse().unwrap(),
+            asn: Asn::from(65534),
+        },
+        peer_bgp_id: [0; 4].into(),
+        peer_distuingisher: [0; 8],
+        peer_rib_type: PeerRibType::OutPost,
+    };
+
+    let context =
+        RouteContext::new(Some(update), NlriStatus::InConvergence, provenance);
+
+    let mut ctx = filter.new_execution_context(context).unwrap();
+
+    let mut group = c.benchmark_group("exec_batch");
+    group.throughput(Throughput::Elements(routes.len() as u64));
+
+    group.bench_function("per_route", |b| {
+        b.iter(|| {
+            routes
+                .iter()
+                .map(|rws| ctx.exec(rws.clone(), None::<Record>).unwrap())
+                .collect::<Vec<_>>()
+        })
+    });
+
+    group.bench_function("batch", |b| {
+        b.iter(|| ctx.exec_batch(routes.iter().cloned()).unwrap())
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, exec_batch);
+criterion_main!(benches);
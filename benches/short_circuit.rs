@@ -0,0 +1,193 @@
+//! Compares the execution time of a term that calls a data source on the
+//! right hand side of a logical expression, for the case where the left
+//! hand side already decides the outcome (the data source is never called)
+//! and the case where it does not (the data source is called on every run).
+//!
+//! Before each case is measured, the number of calls to the data source per
+//! run is printed.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use roto::pipeline::DataSourceRegistry;
+use roto::runtime::Runtime;
+use roto::traits::RotoDataSource;
+use roto::types::builtin::{
+    BuiltinTypeValue, NlriStatus, PeerId, PeerRibType, Provenance,
+    RouteContext,
+};
+use roto::types::collections::Record;
+use roto::types::datasources::DataSourceMethod;
+use roto::types::typedef::TypeDef;
+use roto::types::typevalue::TypeValue;
+use roto::vm::VmError;
+
+use inetnum::asn::Asn;
+
+const SOURCE_SIZE: u32 = 10_000;
+const COUNTED_RUNS: usize = 1_000;
+
+fn src_code(code_line: &str) -> String {
+    format!(
+        r###"
+        filter-map in-filter-map {{
+            define {{
+                rx_tx msg: BmpMsg;
+            }}
+
+            term in-source-asns {{
+                match {{
+                    {}
+                }}
+            }}
+
+            apply {{
+                filter match in-source-asns matching {{ return accept; }};
+                return reject;
+            }}
+        }}
+
+        type BmpMsg {{
+            type: U8,
+            asn: Asn
+        }}
+    "###,
+        code_line
+    )
+}
+
+/// A list of ASNs that is scanned on every lookup, and that counts the
+/// lookups.
+struct SourceAsns {
+    asns: Vec<Asn>,
+    calls: AtomicUsize,
+}
+
+impl SourceAsns {
+    fn new() -> Self {
+        Self {
+            asns: (0..SOURCE_SIZE).map(Asn::from_u32).collect(),
+            calls: AtomicUsize::new(0),
+        }
+    }
+
+    fn take_calls(&self) -> usize {
+        self.calls.swap(0, Ordering::Relaxed)
+    }
+}
+
+impl RotoDataSource for SourceAsns {
+    fn methods(&self) -> Vec<DataSourceMethod> {
+        vec![DataSourceMethod::new(
+            "contains",
+            vec![TypeDef::Asn],
+            TypeDef::Bool,
+        )]
+    }
+
+    fn exec_method(
+        &self,
+        method: usize,
+        args: &[&TypeValue],
+    ) -> Result<TypeValue, VmError> {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        match (method, args) {
+            (0, [TypeValue::Builtin(BuiltinTypeValue::Asn(asn))]) => {
+                Ok(self.asns.contains(asn).into())
+            }
+            _ => Err(VmError::InvalidMethodCall),
+        }
+    }
+}
+
+fn context() -> RouteContext {
+    let peer_ip = "192.0.2.10".parse().unwrap();
+
+    let provenance = Provenance {
+        timestamp: chrono::Utc::now(),
+        connection_id: "192.0.2.10:178".parse().unwrap(),
+        peer_id: PeerId {
+            addr: peer_ip,
+            asn: Asn::from(65534),
+        },
+        peer_bgp_id: [0; 4].into(),
+        peer_distuingisher: [0; 8],
+        peer_rib_type: PeerRibType::OutPost,
+    };
+
+    RouteContext::new(None, NlriStatus::InConvergence, provenance)
+}
+
+fn payload() -> Record {
+    let ty = TypeDef::new_record_type(vec![
+        ("type", Box::new(TypeDef::U8)),
+        ("asn", Box::new(TypeDef::Asn)),
+    ])
+    .unwrap();
+
+    // The ASN we're looking for is not in the source, so that a call to
+    // the source will always scan all of its ASNs.
+    Record::create_instance_with_ordered_fields(
+        &ty,
+        vec![
+            ("type", TypeValue::from(1_u8)),
+            ("asn", Asn::from_u32(211321).into()),
+        ],
+    )
+    .unwrap()
+}
+
+fn short_circuit(c: &mut Criterion) {
+    let mut group = c.benchmark_group("short_circuit");
+
+    let cases = [
+        (
+            "or_left_decides",
+            "msg.type == 1 || source_asns.contains(msg.asn);",
+        ),
+        (
+            "or_right_decides",
+            "msg.type == 2 || source_asns.contains(msg.asn);",
+        ),
+        (
+            "and_left_decides",
+            "msg.type == 2 && source_asns.contains(msg.asn);",
+        ),
+        (
+            "and_right_decides",
+            "msg.type == 1 && source_asns.contains(msg.asn);",
+        ),
+    ];
+
+    for (id, code_line) in cases {
+        let source_asns = Arc::new(SourceAsns::new());
+        let mut sources = DataSourceRegistry::new();
+        sources.register("source_asns", source_asns.clone()).unwrap();
+
+        let runtime =
+            Runtime::from_string_with_sources(src_code(code_line), sources)
+                .unwrap();
+        let mut filter = runtime.filter("in-filter-map").unwrap();
+        filter.bind_context(context());
+
+        for _ in 0..COUNTED_RUNS {
+            filter.run(payload()).unwrap();
+        }
+        println!(
+            "short_circuit/{}: {} data source calls per run",
+            id,
+            source_asns.take_calls() as f64 / COUNTED_RUNS as f64
+        );
+
+        group.bench_function(BenchmarkId::from_parameter(id), |b| {
+            b.iter(|| filter.run(payload()).unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, short_circuit);
+criterion_main!(benches);
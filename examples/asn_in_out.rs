@@ -1,13 +1,13 @@
-use roto::blocks::Scope::{self, FilterMap};
-use roto::types::builtin::RouteContext;
+use roto::runtime::Runtime;
+use roto::types::builtin::basic_route::{PeerId, PeerRibType, Provenance};
+use roto::types::builtin::{NlriStatus, RouteContext};
 use roto::types::collections::Record;
 use roto::types::typedef::TypeDef;
-use roto::{pipeline, vm};
 
 use inetnum::asn::Asn;
 
 fn test_data(
-    name: Scope,
+    name: &str,
     source_code: &'static str,
 ) -> Result<(), Box<dyn std::error::Error>> {
     println!("Evaluate module {}...", name);
@@ -15,8 +15,8 @@ fn test_data(
     env_logger::init();
 
     // Compile the source code in this example
-    let rotolo = pipeline::run_test(source_code, None)?;
-    let roto_pack = rotolo.retrieve_pack_as_refs(&name)?;
+    let runtime = Runtime::from_string(source_code)?;
+    let mut filter = runtime.filter(name)?;
 
     // BGP UPDATE message containing MP_REACH_NLRI path attribute,
     // comprising 5 IPv6 NLRIs
@@ -38,31 +38,27 @@ fn test_data(
 
     let payload_type = TypeDef::new_record_type(vec![("asn", Box::new(TypeDef::Asn))])?;
     let payload = Record::create_instance_with_ordered_fields(&payload_type, vec![("asn", Asn::from(65534_u32).into())])?;
-    // Create the VM
     println!("Used Arguments");
-    println!("{:#?}", &roto_pack.get_arguments());
+    println!("{:#?}", filter.compiled().get_arguments());
     println!("Used Data Sources");
-    println!("{:#?}", &roto_pack.get_data_sources());
+    println!("{:#?}", filter.compiled().get_data_sources());
 
+    let provenance = Provenance {
+        timestamp: chrono::Utc::now(),
+        connection_id: "192.0.2.10:179".parse().unwrap(),
+        peer_id: PeerId {
+            addr: "192.0.2.10".parse().unwrap(),
+            asn: Asn::from(65534),
+        },
+        peer_bgp_id: [0; 4].into(),
+        peer_distuingisher: [0; 8],
+        peer_rib_type: PeerRibType::OutPost,
+    };
 
-    let ds_ref = roto_pack.get_data_sources();
-    // let args = rotolo.compile_arguments(name, filter_map_arguments)?;
-    
-    let mut vm = vm::VmBuilder::<_,RouteContext,_>::new()
-        // .with_arguments(args)
-        .with_data_sources(ds_ref)
-        .with_mir_code(roto_pack.get_mir())
-        .build()?;
+    let context = RouteContext::new(None, NlriStatus::Empty, provenance);
 
-    let mem = &mut vm::LinearMemory::uninit();
-    let res = vm.exec(
-        payload,
-        None::<Record>,
-        // Some(filter_map_arguments),
-        None,
-        mem,
-    )
-    .unwrap();
+    filter.bind_context(context);
+    let res = filter.run(payload).unwrap();
 
     println!("\nRESULT");
     println!("action: {}", res.accept_reject);
@@ -74,7 +70,7 @@ fn test_data(
 
 fn main() {
     test_data(
-        FilterMap("my-filter-map".into()),
+        "my-filter-map",
         r###"
             filter-map my-filter-map {
                 define {
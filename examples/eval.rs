@@ -1,8 +1,9 @@
-use roto::types::builtin::{BuiltinTypeValue, RouteContext};
+use roto::types::builtin::basic_route::{PeerId, PeerRibType, Provenance};
+use roto::types::builtin::{BuiltinTypeValue, NlriStatus, RouteContext};
 use roto::types::collections::{ElementTypeValue, List, Record};
 use roto::types::typedef::TypeDef;
 use roto::types::typevalue::TypeValue;
-use roto::{pipeline, vm};
+use roto::runtime::Runtime;
 use roto::blocks::Scope::{self, FilterMap};
 
 use routecore::bgp::communities::HumanReadableCommunity as Community;
@@ -15,8 +16,8 @@ fn test_data(
     println!("Evaluate filter {}...", name);
 
     // Compile the source code in this example
-    let rotolo = pipeline::run_test(source_code, None)?;
-    let roto_pack = rotolo.retrieve_pack_as_refs(&name)?;
+    let runtime = Runtime::from_string(source_code)?;
+    let mut filter = runtime.filter(name.as_str())?;
 
     // Create a payload type and instance to feed into a VM.
     let _count: TypeValue = 1_u32.into();
@@ -79,36 +80,31 @@ fn test_data(
     .unwrap();
 
 
-    // Create the VM
     println!("Used Arguments");
-    println!("{:#?}", &roto_pack.arguments);
+    println!("{:#?}", filter.compiled().get_arguments());
     println!("Used Data Sources");
-    println!("{:#?}", &roto_pack.data_sources);
+    println!("{:#?}", filter.compiled().get_data_sources());
 
     let filter_map_arguments = vec![(
         "extra_asn",
         // use Roto type coercion
         TypeValue::from(65534_u32)
     )];
-
-    let ds_ref = roto_pack.data_sources;
-    let args = rotolo.compile_arguments(&name, filter_map_arguments)?;
-
-    let mut vm = vm::VmBuilder::<_,RouteContext,_>::new()
-        .with_arguments(args)
-        .with_data_sources(ds_ref)
-        .with_mir_code(roto_pack.mir)
-        .build()?;
-
-    let mem = &mut vm::LinearMemory::uninit();
-    let res = vm.exec(
-        my_payload,
-        None::<Record>,
-        // Some(filter_map_arguments),
-        None,
-        mem,
-    )
-    .unwrap();
+    filter.bind_arguments(filter_map_arguments)?;
+
+    let prov = Provenance {
+        timestamp: chrono::Utc::now(),
+        connection_id: "172.0.0.1:179".parse().unwrap(),
+        peer_id: PeerId { addr: "172.0.0.1".parse().unwrap(), asn: Asn::from(65530)},
+        peer_bgp_id: [0,0,0,0].into(),
+        peer_distuingisher: [0; 8],
+        peer_rib_type: PeerRibType::OutPost,
+    };
+    filter.bind_context(
+        RouteContext::new(None, NlriStatus::InConvergence, prov)
+    );
+
+    let res = filter.run(my_payload)?;
 
     println!("\nRESULT");
     println!("action: {}", res.accept_reject);
@@ -2,11 +2,10 @@ use std::net::SocketAddrV4;
 
 use roto::types::builtin::basic_route::{PeerId, PeerRibType, Provenance};
 use roto::types::builtin::{Nlri, NlriStatus, PrefixRoute, RouteContext};
-use roto::types::collections::{BytesRecord, Record};
+use roto::types::collections::BytesRecord;
 use roto::types::lazyrecord_types::BgpUpdateMessage;
 use roto::types::typevalue::TypeValue;
-use roto::{pipeline, vm};
-use roto::blocks::Scope::{self, FilterMap};
+use roto::runtime::Runtime;
 use inetnum::asn::Asn;
 use routecore::bgp::message::SessionConfig;
 use inetnum::addr::Prefix;
@@ -15,14 +14,14 @@ use routecore::bgp::nlri::afisafi::Ipv6UnicastNlri;
 use routecore::bgp::nlri::afisafi::IsPrefix;
 
 fn test_data(
-    name: Scope,
+    name: &str,
     source_code: &'static str,
 ) -> Result<(), Box<dyn std::error::Error>> {
     println!("Evaluate module {}...", name);
 
     // Compile the source code in this example
-    let rotolo = pipeline::run_test(source_code, None)?;
-    let roto_pack = rotolo.retrieve_pack_as_refs(&name)?;
+    let runtime = Runtime::from_string(source_code)?;
+    let mut filter = runtime.filter(name)?;
 
     // BGP UPDATE message containing MP_REACH_NLRI path attribute,
     // comprising 5 IPv6 NLRIs
@@ -75,11 +74,10 @@ fn test_data(
 
     let payload = PrefixRoute::from(rws);
 
-    // Create the VM
     println!("Used Arguments");
-    println!("{:#?}", &roto_pack.arguments);
+    println!("{:#?}", filter.compiled().get_arguments());
     println!("Used Data Sources");
-    println!("{:#?}", &roto_pack.data_sources);
+    println!("{:#?}", filter.compiled().get_data_sources());
 
     let filter_map_arguments = vec![(
         "extra_asn",
@@ -87,25 +85,9 @@ fn test_data(
         TypeValue::from(65534_u32)
     )];
 
-    let ds_ref = roto_pack.data_sources;
-    let args = rotolo.compile_arguments(&name, filter_map_arguments)?;
-
-    let mut vm = vm::VmBuilder::<_,RouteContext,_>::new()
-        .with_arguments(args)
-        .with_data_sources(ds_ref)
-        .with_mir_code(roto_pack.mir)
-        .with_context(context)
-        .build()?;
-
-    let mem = &mut vm::LinearMemory::uninit();
-    let res = vm.exec(
-        payload,
-        None::<Record>,
-        // Some(filter_map_arguments),
-        None,
-        mem,
-    )
-    .unwrap();
+    filter.bind_arguments(filter_map_arguments)?;
+    filter.bind_context(context);
+    let res = filter.run(payload).unwrap();
 
     println!("\nRESULT");
     println!("action: {}", res.accept_reject);
@@ -117,7 +99,7 @@ fn test_data(
 
 fn main() {
     test_data(
-        FilterMap("in-filter-map".into()),
+        "in-filter-map",
         r###"
             filter-map in-filter-map with my_asn: Asn {
                 define for ext_r: ExtRoute with extra_asn: Asn {
@@ -7,7 +7,7 @@ use inetnum::asn::Asn;
 
 use crate::compiler::error::CompileError;
 use crate::first_into_compile_err;
-use crate::parser::span::Spanned;
+use crate::parser::span::{Span, Spanned};
 use crate::types::typevalue::TypeValue;
 
 #[derive(Clone, Debug, Default)]
@@ -23,6 +23,7 @@ pub enum RootExpr {
     Table(Table),
     OutputStream(OutputStream),
     Ty(RecordTypeAssignment),
+    Pipeline(Pipeline),
 }
 
 impl RootExpr {
@@ -87,6 +88,17 @@ pub struct FilterMap {
     pub body: FilterMapBody,
 }
 
+/// A chain of filter(-map)s that runs as one unit. The route is handed to
+/// the stages in order, the tx (or rx) of one stage becomes the rx of the
+/// next one, until a stage rejects it.
+//
+// Pipeline ::= 'pipeline' Identifier '{' (Identifier ';')+ '}'
+#[derive(Clone, Debug)]
+pub struct Pipeline {
+    pub ident: Spanned<Identifier>,
+    pub stages: Vec<Spanned<Identifier>>,
+}
+
 #[derive(Clone, Debug)]
 pub struct FilterMapBody {
     pub define: Define,
@@ -199,7 +211,7 @@ pub struct ApplySection {
 #[derive(Clone, Debug)]
 pub struct ApplyBody {
     pub scopes: Vec<ApplyScope>,
-    pub accept_reject: Option<AcceptReject>,
+    pub accept_reject: Option<ReturnExpr>,
 }
 
 #[derive(Clone, Debug)]
@@ -224,7 +236,7 @@ pub struct FilterMatchActionExpr {
     pub operator: MatchOperator,
     pub filter_ident: Spanned<ValueExpr>,
     pub negate: bool,
-    pub actions: Vec<(Option<Spanned<ValueExpr>>, Option<AcceptReject>)>,
+    pub actions: Vec<(Option<Spanned<ValueExpr>>, Option<ReturnExpr>)>,
 }
 
 /// A complete pattern match on a variable where every match arm can have
@@ -266,7 +278,16 @@ pub struct PatternMatchActionArm {
     pub variant_id: Spanned<Identifier>,
     pub data_field: Option<Spanned<Identifier>>,
     pub guard: Option<TermCallExpr>,
-    pub actions: Vec<(Option<ActionCallExpr>, Option<AcceptReject>)>,
+    pub actions: Vec<(Option<ActionCallExpr>, Option<ReturnExpr>)>,
+}
+
+/// A verdict returned from the apply section, with an optional reason,
+/// e.g. `return reject with "rpki-invalid";`. The reason should be a string
+/// or an enum variant, it ends up in the result of the filter(-map).
+#[derive(Clone, Debug)]
+pub struct ReturnExpr {
+    pub accept_reject: AcceptReject,
+    pub reason: Option<Spanned<ValueExpr>>,
 }
 
 /// A TermMatchExpr describes a variant of an enum together with its data field
@@ -285,13 +306,15 @@ pub struct TermMatchExpr {
 
 //------------ Rib -----------------------------------------------------------
 
-// Rib ::= "rib" Identifier 'contains' TypeIdentifier '{' RibBody '}'
+// Rib ::= "rib" Identifier 'contains' TypeIdentifier ( '{' RibBody '}' )?
 
 #[derive(Clone, Debug)]
 pub struct Rib {
     pub ident: Spanned<Identifier>,
     pub contain_ty: Spanned<TypeIdentifier>,
-    pub body: RibBody,
+    /// The fields of the contained record type, `None` if the rib contains
+    /// a built-in type, e.g. `rib vrps contains Vrp`.
+    pub body: Option<RibBody>,
 }
 
 #[derive(Clone, Debug)]
@@ -310,7 +333,11 @@ pub enum RibField {
 pub struct Table {
     pub ident: Spanned<Identifier>,
     pub contain_ty: Spanned<TypeIdentifier>,
-    pub body: RibBody,
+    /// The field the records are indexed on, if any.
+    pub key: Option<Spanned<Identifier>>,
+    /// The fields of the contained record type, `None` if the table
+    /// contains a built-in type, e.g. `table aspas contains Aspa`.
+    pub body: Option<RibBody>,
 }
 
 #[derive(Clone, Debug)]
@@ -566,12 +593,15 @@ pub struct ByteStringLiteral(pub SmallVec<[u8; 24]>);
 
 /// Every filter needs to return either a 'accept' or 'reject' statement.
 /// failing to set it properly ends in the whole thing being cancelled.
+/// 'continue' hands the route over to the next filter in line, without
+/// accepting or rejecting it.
 #[derive(
     Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize,
 )]
 pub enum AcceptReject {
     Accept,
     Reject,
+    Continue,
     NoReturn,
 }
 
@@ -581,6 +611,7 @@ impl std::fmt::Display for AcceptReject {
             AcceptReject::Accept => write!(f, "accept"),
             AcceptReject::NoReturn => write!(f, "no return"),
             AcceptReject::Reject => write!(f, "reject"),
+            AcceptReject::Continue => write!(f, "continue"),
         }
     }
 }
@@ -635,7 +666,7 @@ pub enum ValueExpr {
     LiteralAccessExpr(Spanned<LiteralAccessExpr>),
     /// a JunOS style prefix match expression, e.g. `0.0.0.0/0
     /// prefix-length-range /12-/16`
-    PrefixMatchExpr(PrefixMatchExpr),
+    PrefixMatchExpr(Spanned<PrefixMatchExpr>),
     /// an access receiver (an expression named with a single identifier, e.g.
     /// `my_var`), or a chain of field accesses and/or methods on an access
     /// receiver.
@@ -860,6 +891,21 @@ pub enum LogicalExpr {
     BooleanExpr(BooleanExpr),
 }
 
+impl LogicalExpr {
+    /// The location of the whole expression in the source code, if all of
+    /// its parts carry one.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            LogicalExpr::OrExpr(OrExpr { left, right })
+            | LogicalExpr::AndExpr(AndExpr { left, right }) => {
+                Some(left.span()?.merge(right.span()?))
+            }
+            LogicalExpr::NotExpr(NotExpr { expr }) => expr.span(),
+            LogicalExpr::BooleanExpr(expr) => expr.span(),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum CompareArg {
     /// A "stand-alone" left|right-hand side argument of a comparison
@@ -900,7 +946,25 @@ pub enum BooleanExpr {
     ListCompareExpr(Box<Spanned<ListCompareExpr>>),
     /// syntactic sugar for a method on a prefix function that returns a
     /// boolean.
-    PrefixMatchExpr(PrefixMatchExpr),
+    PrefixMatchExpr(Spanned<PrefixMatchExpr>),
+}
+
+impl BooleanExpr {
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            BooleanExpr::GroupedLogicalExpr(GroupedLogicalExpr { expr }) => {
+                expr.span()
+            }
+            BooleanExpr::BooleanLiteral(lit) => Some(lit.span),
+            BooleanExpr::CompareExpr(expr) => {
+                Some(expr.left.span.merge(expr.right.span))
+            }
+            BooleanExpr::ComputeExpr(expr) => Some(expr.span),
+            BooleanExpr::LiteralAccessExpr(expr) => Some(expr.span),
+            BooleanExpr::ListCompareExpr(expr) => Some(expr.span),
+            BooleanExpr::PrefixMatchExpr(expr) => Some(expr.span),
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -43,17 +43,20 @@ use crate::{
         self, DepsGraph, GlobalSymbolTable, MatchActionType, Symbol,
         SymbolKind, SymbolTable,
     },
-    traits::Token,
+    traits::{IntoRotoArgs, Token},
     types::{
-        datasources::{DataSource, Table},
+        builtin::RouteContext,
+        datasources::DataSource,
         typedef::{RecordTypeDef, TypeDef},
         typevalue::TypeValue,
     },
     vm::{
-        compute_hash, Command, CommandArg, CompiledCollectionField,
-        CompiledField, CompiledPrimitiveField, CompiledVariable,
-        ExtDataSource, FieldIndex, FilterMapArg, FilterMapArgs, OpCode,
-        StackRefPos, VariablesRefTable,
+        coerce_argument, compute_hash, compute_mem_size, Command,
+        CommandArg, CompiledCollectionField, CompiledField,
+        CompiledPrimitiveField, CompiledVariable, DataSourceHandle,
+        ExecutionContext, ExtDataSource, FieldIndex, FilterMapArg,
+        FilterMapArgs, FilterMetrics, FilterMetricsSnapshot, OpCode,
+        StackRefPos, TracePoint, VariablesRefTable, VmError,
     },
 };
 
@@ -106,6 +109,14 @@ impl Rotolo {
             .chain(mp)
     }
 
+    /// The scope of the filter or filter-map called `name`, if it was
+    /// compiled into this Rotolo (with or without errors).
+    pub(crate) fn find_scope(&self, name: &str) -> Option<&Scope> {
+        self.iter()
+            .map(|(scope, _)| scope)
+            .find(|scope| scope.as_str() == name)
+    }
+
     // Not public, because it would leak the (private) RotoPack.
     fn retrieve_pack<'a, T: From<&'a RotoPack>>(
         &'a self,
@@ -127,13 +138,13 @@ impl Rotolo {
         }
     }
 
-    /// Retrieves a pack by name, returns a Result over a pack that contains
-    /// `&T` for every collection-type attribute: T inside the pack. An error
-    /// indicates a mis-compilation for this Filter(Map).
-    pub fn retrieve_pack_as_refs<'a>(
-        &'a self,
-        name: &'a Scope,
-    ) -> Result<RotoPackRef, CompileError> {
+    /// Retrieves a pack by name as a CompiledFilter, that can be shared
+    /// between threads. An error indicates a mis-compilation for this
+    /// Filter(Map).
+    pub fn retrieve_compiled_filter(
+        &self,
+        name: &Scope,
+    ) -> Result<CompiledFilter, CompileError> {
         self.retrieve_pack(name)
     }
 
@@ -153,121 +164,72 @@ impl Rotolo {
             .into())
         }
     }
-}
-
-//------------ InternalPack -------------------------------------------------
-
-/// A compiled Filter(Map)
-///
-/// RotoPacks are the public representation of the compiled packs, where the
-/// collection-type fields can be `&T`, `Arc<T>`, or really any other
-/// `T: AsRef<[T]>.`.
-#[derive(Debug)]
-pub struct InternalPack<
-    'a,
-    M: AsRef<[MirBlock]>,
-    A: AsRef<[(&'a str, TypeDef)]>,
-    EDS: AsRef<[ExtDataSource]>,
-> {
-    pub filter_map_name: &'a Scope,
-    pub filter_type: FilterType,
-    pub mir: M,
-    pub rx_type: TypeDef,
-    pub tx_type: Option<TypeDef>,
-    pub arguments: A,
-    pub data_sources: EDS,
-    pub hash_id: u64,
-}
-
-pub type RotoPackArc<'a> = InternalPack<
-    'a,
-    Arc<[MirBlock]>,
-    Arc<[(&'a str, TypeDef)]>,
-    Arc<[ExtDataSource]>,
->;
-
-pub type RotoPackRef<'a> = InternalPack<
-    'a,
-    &'a [MirBlock],
-    Vec<(&'a str, TypeDef)>,
-    Vec<ExtDataSource>,
->;
-
-impl<
-        'a,
-        M: AsRef<[MirBlock]>,
-        A: AsRef<[(&'a str, TypeDef)]>,
-        EDS: AsRef<[ExtDataSource]>,
-    > InternalPack<'a, M, A, EDS>
-{
-    pub fn get_arguments(&'a self) -> &'a [(&str, TypeDef)] {
-        self.arguments.as_ref()
-    }
 
-    pub fn get_mir(&'a self) -> &'a [MirBlock] {
-        self.mir.as_ref()
-    }
-
-    pub fn get_data_sources(&'a self) -> &[ExtDataSource] {
-        self.data_sources.as_ref()
+    /// Compile the arguments for the filter-map `name` from a Rust value,
+    /// see `IntoRotoArgs`.
+    pub fn compile_args(
+        &self,
+        name: &Scope,
+        args: impl IntoRotoArgs,
+    ) -> Result<FilterMapArgs, CompileError> {
+        self.compile_arguments(name, args.into_roto_args())
     }
+}
 
-    pub fn set_source(
-        &mut self,
-        source: DataSource,
-    ) -> Result<(), CompileError> {
-        let name = source.get_name();
+/// Store `source` in the data source with the same name in `data_sources`,
+/// checking that the types of their records match.
+pub(crate) fn set_data_source(
+    data_sources: &[ExtDataSource],
+    source: &DataSource,
+) -> Result<(), CompileError> {
+    let name = source.get_name();
 
-        let f_ds = self
-            .data_sources
-            .as_ref()
-            .iter()
-            .find(|ds| ds.get_name() == name);
-        let s_ty = source.get_type();
-
-        let f_ds = if let Some(ds) = f_ds {
-            if ds.get_value_type() != s_ty {
-                trace!("{:?} != {:?}", ds.get_value_type(), s_ty);
-                return Err(CompileError::from(
-                    format!(
-                        "Fatal: Data source with name {} has the wrong content type, expected {}, but found {}",
-                        name,
-                        s_ty,
-                        ds.get_value_type(),
-                    )
-                ));
-            }
-            ds
-        } else {
-            return Err(CompileError::from(format!(
-                "Fatal: Cannot find data source with name: {} in source code",
-                name
-            )));
-        };
+    let f_ds = if let Some(ds) =
+        data_sources.iter().find(|ds| ds.get_name() == name)
+    {
+        ds
+    } else {
+        return Err(CompileError::from(format!(
+            "Fatal: Cannot find data source with name: {} in source code",
+            name
+        )));
+    };
 
-        f_ds.get_source().store(match source {
-            DataSource::Table(ref t) => Some(
-                DataSource::Table(Table {
-                    name,
-                    ty: s_ty,
-                    records: t.records.clone(),
-                })
-                .into(),
-            ),
-            DataSource::Rib(ref r) => Some(DataSource::Rib(r.clone()).into()),
-        });
+    f_ds.update_source(|_| prepare_source(f_ds, source))?;
 
-        Ok(())
-    }
+    Ok(())
+}
 
-    pub fn check_rx_payload_type(&self, payload: &TypeValue) -> bool {
-        trace!("compare payload {} with type {:?}", payload, self.rx_type);
-        self.rx_type == *payload
+/// Check that the records of `source` have the type of the records of
+/// `ds`, and create the source to store in it.
+pub(crate) fn prepare_source(
+    ds: &ExtDataSource,
+    source: &DataSource,
+) -> Result<Arc<DataSource>, CompileError> {
+    let s_ty = source.get_type();
+
+    if ds.get_value_type() != s_ty {
+        trace!("{:?} != {:?}", ds.get_value_type(), s_ty);
+        return Err(CompileError::from(
+            format!(
+                "Fatal: Data source with name {} has the wrong content type, expected {}, but found {}",
+                ds.get_name(),
+                s_ty,
+                ds.get_value_type(),
+            )
+        ));
     }
 
-    pub fn check_tx_payload_type(&self, payload: &TypeValue) -> Option<bool> {
-        self.tx_type.as_ref().map(|tx| tx == payload)
-    }
+    // Tables that declare a key in the source code are indexed on it.
+    Ok(Arc::new(match source {
+        DataSource::Table(t) => {
+            DataSource::Table(t.rekeyed(ds.get_name(), ds.get_table_key()))
+        }
+        DataSource::Rib(r) => DataSource::Rib(r.clone()),
+        DataSource::Custom(name, c) => {
+            DataSource::Custom(name.clone(), c.clone())
+        }
+    }))
 }
 
 //------------ RotoPack -----------------------------------------------------
@@ -283,6 +245,7 @@ pub struct RotoPack {
     arguments: FilterMapArgs,
     data_sources: Vec<ExtDataSource>,
     hash_id: u64,
+    mem_size: usize,
 }
 
 impl RotoPack {
@@ -298,6 +261,7 @@ impl RotoPack {
         // This hash is used to determine, whether reloading or replacing mir
         // code is actually necessary.
         let hash_id = compute_hash(&mir, &data_sources);
+        let mem_size = compute_mem_size(&mir);
 
         RotoPack {
             filter_map_name,
@@ -308,13 +272,10 @@ impl RotoPack {
             arguments,
             data_sources,
             hash_id,
+            mem_size,
         }
     }
 
-    pub fn get_mir(&self) -> &[MirBlock] {
-        self.mir.as_slice()
-    }
-
     pub fn get_data_sources(&self) -> &[ExtDataSource] {
         self.data_sources.as_slice()
     }
@@ -323,6 +284,10 @@ impl RotoPack {
         (&self.filter_map_name).into()
     }
 
+    pub fn get_mem_size(&self) -> usize {
+        self.mem_size
+    }
+
     // fn compile_arguments(
     //     &self,
     //     args: Vec<(&str, TypeValue)>,
@@ -410,34 +375,192 @@ impl RotoPack {
     // }
 }
 
-// PublicRotoPack<&[MirBlock], &[(&'a str, TypeDef)], &[ExtDataSource]>
+//------------ CompiledFilter -----------------------------------------------
+
+/// An immutable, compiled Filter(Map) that can be shared between threads.
+///
+/// A CompiledFilter is created from a RotoPack and holds no state that is
+/// tied to a single execution, so it is `Send + Sync` and can be executed by
+/// many threads at the same time, e.g. by wrapping it in an Arc. Each thread
+/// creates its own ExecutionContext with `new_execution_context` for that,
+/// which only clones a few Arcs.
+///
+/// The data sources are shared by all the execution contexts created from a
+/// CompiledFilter (and its clones), so a source that is (re)placed with
+/// `set_source` is picked up by every one of them on its next run.
+#[derive(Debug, Clone)]
+pub struct CompiledFilter {
+    pub(crate) filter_map_name: Scope,
+    pub(crate) filter_type: FilterType,
+    pub(crate) mir: Arc<[MirBlock]>,
+    pub(crate) rx_type: TypeDef,
+    pub(crate) tx_type: Option<TypeDef>,
+    pub(crate) arguments: FilterMapArgs,
+    pub(crate) data_sources: Arc<[ExtDataSource]>,
+    pub(crate) hash_id: u64,
+    pub(crate) mem_size: usize,
+    pub(crate) metrics: Option<Arc<FilterMetrics>>,
+    pub(crate) trace: bool,
+}
+
+impl CompiledFilter {
+    /// Set the values for the filter-map level arguments, as created by
+    /// `Rotolo::compile_arguments`, for all the execution contexts that are
+    /// created from this filter afterwards.
+    pub fn with_arguments(mut self, arguments: FilterMapArgs) -> Self {
+        self.arguments = arguments;
+        self
+    }
+
+    /// Create the filter-map level arguments for this filter from `args`,
+    /// coercing each value into its declared type. The result can be used
+    /// with `with_arguments`, or per execution with
+    /// `ExecutionContext::exec_with_arguments`.
+    pub fn compile_arguments(
+        &self,
+        args: Vec<(&str, TypeValue)>,
+    ) -> Result<FilterMapArgs, CompileError> {
+        self.arguments.compile_arguments(args)
+    }
+
+    /// Create the filter-map level arguments for this filter from a Rust
+    /// value, see `IntoRotoArgs`.
+    pub fn compile_args(
+        &self,
+        args: impl IntoRotoArgs,
+    ) -> Result<FilterMapArgs, CompileError> {
+        self.compile_arguments(args.into_roto_args())
+    }
+
+    /// Turn the runtime metrics for this filter on or off, for all the
+    /// execution contexts that are created from this filter afterwards.
+    /// Turning them on starts a fresh set of counters.
+    pub fn with_metrics(mut self, enabled: bool) -> Self {
+        self.metrics = enabled.then(|| {
+            Arc::new(FilterMetrics::new(
+                self.filter_map_name.clone(),
+                &self.mir,
+            ))
+        });
+        self
+    }
+
+    /// Record a trace of every execution of this filter, that is returned
+    /// in the `trace` field of the VmResult, for all the execution contexts
+    /// that are created from this filter afterwards. Off by default, since
+    /// it clones the rx and tx values for every action that is executed.
+    pub fn with_trace(mut self, trace: bool) -> Self {
+        self.trace = trace;
+        self
+    }
+
+    /// A snapshot of the runtime metrics of this filter, if they are
+    /// turned on.
+    pub fn metrics(&self) -> Option<FilterMetricsSnapshot> {
+        self.metrics.as_ref().map(|m| m.snapshot())
+    }
+
+    /// Create the context in which one thread can execute this filter.
+    ///
+    /// All the data sources of this filter should have a source at this
+    /// point.
+    pub fn new_execution_context<C>(
+        &self,
+        context: C,
+    ) -> Result<ExecutionContext<C>, VmError>
+    where
+        C: AsRef<RouteContext> + std::hash::Hash,
+    {
+        ExecutionContext::new(self, context)
+    }
+
+    /// (Re)place the source for one of the data sources of this filter. All
+    /// the execution contexts of this filter will use the new source from
+    /// their next run onwards.
+    pub fn set_source(&self, source: DataSource) -> Result<(), CompileError> {
+        set_data_source(&self.data_sources, &source)
+    }
+
+    /// A handle to update the data source `name` while this filter runs,
+    /// see [`DataSourceHandle`].
+    pub fn data_source(
+        &self,
+        name: &str,
+    ) -> Result<DataSourceHandle, CompileError> {
+        DataSourceHandle::new(name, [&self.data_sources]).ok_or_else(|| {
+            CompileError::from(format!(
+                "Cannot find data source with name: {} in source code",
+                name
+            ))
+        })
+    }
+
+    pub fn get_filter_map_name(&self) -> &Scope {
+        &self.filter_map_name
+    }
 
-impl<'a> From<&'a RotoPack> for RotoPackArc<'a> {
+    pub fn get_filter_type(&self) -> FilterType {
+        self.filter_type
+    }
+
+    pub fn get_data_sources(&self) -> &[ExtDataSource] {
+        &self.data_sources
+    }
+
+    pub fn get_arguments(&self) -> Vec<(&str, TypeDef)> {
+        self.arguments.inspect_arguments()
+    }
+
+    pub fn get_rx_type(&self) -> &TypeDef {
+        &self.rx_type
+    }
+
+    pub fn get_tx_type(&self) -> Option<&TypeDef> {
+        self.tx_type.as_ref()
+    }
+
+    pub fn get_hash_id(&self) -> u64 {
+        self.hash_id
+    }
+
+    /// The number of memory positions the VM needs to run this filter.
+    pub fn get_mem_size(&self) -> usize {
+        self.mem_size
+    }
+}
+
+impl<'a> From<&'a RotoPack> for CompiledFilter {
     fn from(rp: &'a RotoPack) -> Self {
-        RotoPackArc {
-            filter_map_name: &rp.filter_map_name,
+        CompiledFilter {
+            filter_map_name: rp.filter_map_name.clone(),
+            filter_type: rp.filter_type,
             mir: rp.mir.as_slice().into(),
             rx_type: rp.rx_type.clone(),
             tx_type: rp.tx_type.clone(),
-            arguments: rp.arguments.inspect_arguments().into(),
+            arguments: rp.arguments.clone(),
             data_sources: rp.data_sources.clone().into(),
-            filter_type: rp.filter_type,
             hash_id: rp.hash_id,
+            mem_size: rp.mem_size,
+            metrics: None,
+            trace: false,
         }
     }
 }
 
-impl<'a> From<&'a RotoPack> for RotoPackRef<'a> {
-    fn from(rp: &'a RotoPack) -> Self {
-        RotoPackRef {
-            filter_map_name: &rp.filter_map_name,
-            mir: rp.mir.as_slice(),
-            rx_type: rp.rx_type.clone(),
-            tx_type: rp.tx_type.clone(),
-            arguments: rp.arguments.inspect_arguments(),
-            data_sources: rp.data_sources.clone(),
+impl From<RotoPack> for CompiledFilter {
+    fn from(rp: RotoPack) -> Self {
+        CompiledFilter {
+            filter_map_name: rp.filter_map_name,
             filter_type: rp.filter_type,
+            mir: rp.mir.into(),
+            rx_type: rp.rx_type,
+            tx_type: rp.tx_type,
+            arguments: rp.arguments,
+            data_sources: rp.data_sources.into(),
             hash_id: rp.hash_id,
+            mem_size: rp.mem_size,
+            metrics: None,
+            trace: false,
         }
     }
 }
@@ -503,10 +626,17 @@ pub(crate) struct CompilerState<'a> {
     var_read_only: bool,
     compiled_terms: TermSections<'a>,
     compiled_action_sections: ActionSections<'a>,
+    /// Counter to create unique names for the labels that are the targets
+    /// of short-circuiting jumps.
+    label_counter: usize,
+    /// The name of the symbol that is being compiled, it's recorded in the
+    /// commands that are created for it.
+    pub(crate) cur_symbol: Option<ShortString>,
 }
 
 impl<'a> CompilerState<'a> {
     pub(crate) fn push_command(&mut self, op: OpCode, args: Vec<CommandArg>) {
+        let symbol = self.cur_symbol.clone();
         if let Some(cur_rec_var) = &mut self.cur_partial_variable {
             match cur_rec_var.iter_mut().last() {
                 Some(CompiledField::Primitive(p)) => {
@@ -520,7 +650,8 @@ impl<'a> CompilerState<'a> {
                         {
                             cur_rec_var.append_primitive(
                                 CompiledPrimitiveField::new(
-                                    vec![Command::new(op, args.clone())],
+                                    vec![Command::new(op, args.clone())
+                                        .with_symbol(symbol.clone())],
                                     self.cur_record_field_index.clone(),
                                 ),
                             )
@@ -535,7 +666,8 @@ impl<'a> CompilerState<'a> {
                     {
                         cur_rec_var.append_primitive(
                             CompiledPrimitiveField::new(
-                                vec![Command::new(op, args.clone())],
+                                vec![Command::new(op, args.clone())
+                                    .with_symbol(symbol.clone())],
                                 self.cur_record_field_index.clone(),
                             ),
                         );
@@ -546,10 +678,27 @@ impl<'a> CompilerState<'a> {
         trace!("after push cur_rec_var {:?}", self.cur_partial_variable);
         self.cur_mir_block
             .command_stack
-            .push_back(Command::new(op, args));
+            .push_back(Command::new(op, args).with_symbol(symbol));
         trace!("after push cur_mir_block {:?}", self.cur_mir_block);
     }
 
+    /// Push a trace point straight onto the current MIR block. It is never
+    /// part of the code of a (record) variable that is being compiled.
+    pub(crate) fn push_trace(&mut self, point: TracePoint) {
+        self.cur_mir_block.command_stack.push_back(Command::new(
+            OpCode::Trace,
+            vec![CommandArg::TracePoint(point)],
+        ));
+    }
+
+    /// Create a label name that is unique for this filter-map, so that a
+    /// targeted skip will never stop at a label that belongs to another
+    /// (nested) expression.
+    pub(crate) fn new_label(&mut self, prefix: &str) -> ShortString {
+        self.label_counter += 1;
+        format!("{} {}", prefix, self.label_counter).as_str().into()
+    }
+
     pub(crate) fn extend_commands(&mut self, commands: Vec<Command>) {
         if let Some(cur_rec_var) = &mut self.cur_partial_variable {
             match cur_rec_var.iter_mut().last() {
@@ -671,20 +820,8 @@ impl Compiler {
                         )
                     ))?;
 
-                if _arg.ty == arg.1 {
-                    _arg.value = arg.1.clone();
-                } else {
-                    return Err(
-                        CompileError::from(
-                            format!(
-                                "Argument '{}' has the wrong type, expected '{}', got '{}'",
-                                arg.0,
-                                _arg.ty,
-                                TypeDef::from(&arg.1),
-                            )
-                        )
-                    );
-                }
+                _arg.value =
+                    coerce_argument(&arg.0, &_arg.ty, arg.1.clone())?;
             }
         }
 
@@ -774,8 +911,9 @@ impl Compiler {
 
 //------------ MirBlock & Mir -----------------------------------------------
 
+/// A block of VM commands.
 #[derive(Debug)]
-pub struct MirBlock {
+pub(crate) struct MirBlock {
     command_stack: VecDeque<Command>,
 }
 
@@ -790,7 +928,7 @@ impl MirBlock {
         self.command_stack.iter()
     }
 
-    pub fn extend(&mut self, commands: Vec<Command>) {
+    pub(crate) fn extend(&mut self, commands: Vec<Command>) {
         self.command_stack.extend(commands);
     }
 }
@@ -825,6 +963,7 @@ impl Clone for MirBlock {
                 Command {
                     op: c.op,
                     args: c.args.clone(),
+                    symbol: c.symbol.clone(),
                 }
             })
             .collect::<VecDeque<_>>();
@@ -953,6 +1092,8 @@ fn compile_filter_map(
         cur_record_type: None,
         var_read_only: false,
         cur_mem_pos: 0,
+        label_counter: 0,
+        cur_symbol: None,
     };
 
     // initialize the command stack
@@ -995,13 +1136,15 @@ fn compile_filter_map(
     state.cur_mir_block = MirBlock::new();
     state.push_command(
         OpCode::Exit(state.cur_filter_map.get_default_action()),
-        vec![],
+        exit_args(state.cur_filter_map.get_default_reason()),
     );
 
     mir.push(state.cur_mir_block);
 
     trace!("\n");
 
+    // Arguments that were injected at compile time keep their value, all
+    // the others have to be supplied at runtime.
     let args = state
         .used_arguments
         .iter_mut()
@@ -1010,7 +1153,11 @@ fn compile_filter_map(
                 &a.1.name,
                 a.1.token.clone(),
                 a.1.ty.clone(),
-                TypeValue::UnInit,
+                if a.1.has_unknown_value() {
+                    TypeValue::UnInit
+                } else {
+                    a.1.value.clone()
+                },
             )
         })
         .collect::<Vec<_>>()
@@ -1406,6 +1553,10 @@ fn compile_apply_section(
                                     == TypeDef::AcceptReject(
                                         AcceptReject::Reject
                                     )
+                                || action_section.ty
+                                    == TypeDef::AcceptReject(
+                                        AcceptReject::Continue
+                                    )
                                 || action_section.ty
                                     == TypeDef::AcceptReject(
                                         AcceptReject::NoReturn
@@ -1512,7 +1663,7 @@ fn compile_apply_section(
                         if accept_reject != AcceptReject::NoReturn {
                             state.push_command(
                                 OpCode::Exit(accept_reject),
-                                vec![],
+                                exit_args(&action_section.value),
                             );
                         }
                     }
@@ -1607,6 +1758,18 @@ fn compile_apply_section(
                                     .into(),
                                 )],
                             ),
+                            Command::new(
+                                OpCode::Trace,
+                                vec![CommandArg::TracePoint(
+                                    TracePoint::MatchAction {
+                                        term: ma_name.clone(),
+                                        negate: false,
+                                        span: match_action
+                                            .get_match_action()
+                                            .span,
+                                    },
+                                )],
+                            ),
                             Command::new(OpCode::CondFalseSkipToEOB, vec![]),
                         ]);
                     }
@@ -1624,6 +1787,18 @@ fn compile_apply_section(
                                     .into(),
                                 )],
                             ),
+                            Command::new(
+                                OpCode::Trace,
+                                vec![CommandArg::TracePoint(
+                                    TracePoint::MatchAction {
+                                        term: ma_name.clone(),
+                                        negate: true,
+                                        span: match_action
+                                            .get_match_action()
+                                            .span,
+                                    },
+                                )],
+                            ),
                             Command::new(OpCode::CondTrueSkipToEOB, vec![]),
                         ]);
                     }
@@ -1706,13 +1881,25 @@ fn compile_match_action<'a>(
         // Add an early return if the type of the match action is either `Reject` or
         // `Accept`.
         if accept_reject != AcceptReject::NoReturn {
-            state.push_command(OpCode::Exit(accept_reject), vec![]);
+            state.push_command(
+                OpCode::Exit(accept_reject),
+                exit_args(&action_call.value),
+            );
         }
     }
 
     Ok(state)
 }
 
+// The arguments for an `Exit` command: the reason for the verdict, if there
+// is one.
+fn exit_args(reason: &TypeValue) -> Vec<CommandArg> {
+    match reason {
+        TypeValue::Unknown => vec![],
+        reason => vec![CommandArg::ConstantValue(reason.clone())],
+    }
+}
+
 fn compile_action_section<'a>(
     action_section: &'a symbols::Symbol,
     // A block of code that retrieves the action argument and puts it on the
@@ -1727,8 +1914,18 @@ fn compile_action_section<'a>(
     // Set a Label so that each term block is identifiable for humans.
     trace!("compiling ACTION SECTION {}...", action_section.name);
 
+    state.push_trace(TracePoint::ActionSection {
+        name: action_section.name.clone(),
+        span: action_section.span,
+    });
+
     for action in &action_section.args {
-        state = compile_action(action, argument_code_block, state)?;
+        state = compile_action(
+            action,
+            &action_section.name,
+            argument_code_block,
+            state,
+        )?;
     }
 
     Ok(state)
@@ -1736,6 +1933,8 @@ fn compile_action_section<'a>(
 
 fn compile_action<'a>(
     action: Action<'a>,
+    // The name of the action section this action belongs to.
+    section: &ShortString,
     // The argument code block is needed each time a argument is referenced
     // in the action.
     argument_code_block: &[Command],
@@ -1745,8 +1944,13 @@ fn compile_action<'a>(
         // A symbol with an RxType token, should be an access receiver.
         SymbolKind::AccessReceiver => {
             trace!("compiling ACTION {:#?}", action);
+            state.push_trace(TracePoint::Action {
+                section: section.clone(),
+                span: action.span,
+            });
             state = recurse_compile(action, state, None, false)?;
             state.cur_mem_pos += 1;
+            state.push_trace(TracePoint::ActionEnd);
         }
         // Variable arguments that are passed in into an action appear as
         // Constants in the args of the ActionSection, so they end up
@@ -1804,13 +2008,26 @@ fn compile_term_section<'a>(
     );
 
     let terms = &term_section.args;
-    let mut terms = terms.iter().peekable();
+    let mut terms = terms.iter().enumerate().peekable();
+
+    while let Some((index, arg)) = terms.next() {
+        // Mark the start of the line, so that an error in its code is
+        // reported at this line, and not at the one before it.
+        state.push_trace(TracePoint::TermStart {
+            term: term_section.name.clone(),
+            span: arg.span,
+        });
 
-    while let Some(arg) = &mut terms.next() {
         state = compile_term(arg, state)?;
 
         assert_ne!(state.cur_mir_block.command_stack.len(), 0);
 
+        state.push_trace(TracePoint::Term {
+            term: term_section.name.clone(),
+            index,
+            span: arg.span,
+        });
+
         // Since terms are ANDed we can create an early return after
         // each term that isn't last.
         if terms.peek().is_some() {
@@ -1842,26 +2059,46 @@ pub(crate) fn compile_term<'a>(
                 .command_stack
                 .push_back(Command::new(OpCode::Cmp, vec![op.into()]));
         }
+        // The logical operators short-circuit: the left hand side is
+        // compiled first and if its value decides the outcome of the whole
+        // expression, the VM skips over the right hand side, leaving the
+        // left hand value on the stack as the result.
         SymbolKind::OrExpr => {
             let args = term.get_args_checked(2)?;
+            let end_label = state.new_label("END OR");
             state = compile_term(&args[0], state)?;
+
+            state.push_command(
+                OpCode::CondTrueSkipToLabel,
+                vec![CommandArg::Label(end_label.clone())],
+            );
+            state.push_command(OpCode::PopStack, vec![]);
+
             state.cur_mem_pos += 1;
             state = compile_term(&args[1], state)?;
 
-            state.cur_mir_block.command_stack.push_back(Command::new(
-                OpCode::Cmp,
-                vec![CommandArg::CompareOp(ast::CompareOp::Or)],
-            ));
+            state.push_command(
+                OpCode::Label,
+                vec![CommandArg::Label(end_label)],
+            );
         }
         SymbolKind::AndExpr => {
             let args = term.get_args_checked(2)?;
+            let end_label = state.new_label("END AND");
             state = compile_term(&args[0], state)?;
+
+            state.push_command(
+                OpCode::CondFalseSkipToLabel,
+                vec![CommandArg::Label(end_label.clone())],
+            );
+            state.push_command(OpCode::PopStack, vec![]);
+
             state.cur_mem_pos += 1;
             state = compile_term(&args[1], state)?;
 
             state.push_command(
-                OpCode::Cmp,
-                vec![CommandArg::CompareOp(ast::CompareOp::And)],
+                OpCode::Label,
+                vec![CommandArg::Label(end_label)],
             );
         }
         SymbolKind::NotExpr => {
@@ -1874,8 +2111,17 @@ pub(crate) fn compile_term<'a>(
             let args = term.get_args_with_checked_min_len(2)?;
             state.cur_mem_pos += 1;
             let orig_mem_pos = state.cur_mem_pos;
+            let end_label = state.new_label("END LIST");
 
-            for arg in &args[1..] {
+            // `in` is decided by the first element that matches, `not in`
+            // by the first element that does.
+            let skip_op = match op {
+                ast::CompareOp::NotIn => OpCode::CondFalseSkipToLabel,
+                _ => OpCode::CondTrueSkipToLabel,
+            };
+
+            let mut elms = args[1..].iter().peekable();
+            while let Some(arg) = elms.next() {
                 // retrieve the left hand assignment and put it on the stack
                 state = recurse_compile(&args[0], state, None, false)?;
                 state.cur_mem_pos += 1;
@@ -1883,19 +2129,30 @@ pub(crate) fn compile_term<'a>(
                 // retrieve the next value from the right hand list
                 state = recurse_compile(arg, state, None, false)?;
 
-                state.cur_mir_block.command_stack.push_back(Command::new(
+                state.push_command(
                     OpCode::Cmp,
                     vec![CommandArg::CompareOp(op)],
-                ));
+                );
 
-                state.cur_mir_block.command_stack.push_back(Command::new(
-                    OpCode::CondTrueSkipToEOB,
-                    vec![],
-                ));
+                // The result of the last comparison is the result of the
+                // whole expression, every other one either decides the
+                // outcome, or gets popped before the next comparison.
+                if elms.peek().is_some() {
+                    state.push_command(
+                        skip_op,
+                        vec![CommandArg::Label(end_label.clone())],
+                    );
+                    state.push_command(OpCode::PopStack, vec![]);
+                }
 
                 // restore old mem_pos, let's not waste memory
                 state.cur_mem_pos = orig_mem_pos;
             }
+
+            state.push_command(
+                OpCode::Label,
+                vec![CommandArg::Label(end_label)],
+            );
         }
         SymbolKind::GlobalEnum => {
             trace!(
@@ -1940,12 +2197,20 @@ pub(crate) fn compile_term<'a>(
                         ),
                     );
 
-                    // The next label should be next variant, so if this is
-                    // not the right variant, jump there.
+                    // If this is not the right variant, jump to the next
+                    // variant, or to the end of the enum for the last one.
+                    // The jump is targeted, so it won't stop at a label
+                    // inside the term block of this variant.
+                    let next_label = match variants.peek() {
+                        Some(next) => format!("VARIANT_{}", next.name),
+                        None => format!("END ENUM {}", term.name),
+                    };
                     state.cur_mir_block.command_stack.push_back(
                         Command::new(
                             OpCode::CondFalseSkipToLabel,
-                            vec![CommandArg::Variant(variant_index)],
+                            vec![CommandArg::Label(
+                                next_label.as_str().into(),
+                            )],
                         ),
                     );
 
@@ -1970,6 +2235,13 @@ pub(crate) fn compile_term<'a>(
                 }
             }
 
+            state.cur_mir_block.command_stack.push_back(Command::new(
+                OpCode::Label,
+                vec![CommandArg::Label(
+                    format!("END ENUM {}", term.name).as_str().into(),
+                )],
+            ));
+
             // restore old mem_pos, let's not waste memory
             state.cur_mem_pos = orig_mem_pos;
         }
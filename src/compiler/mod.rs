@@ -2,6 +2,6 @@ pub mod compile;
 pub mod recurse_compile;
 pub mod error;
 
-pub use compile::{Compiler, MirBlock, RotoPack, RotoPackArc};
+pub use compile::{CompiledFilter, Compiler, RotoPack};
 
 pub use error::CompileError;
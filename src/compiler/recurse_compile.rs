@@ -14,16 +14,36 @@ use crate::vm::{Command, CommandArg, CompiledCollectionField, OpCode, VmError, F
 /// This function is the heart of the compiler, all the recursion in the
 /// compilation process happens here. The other compile_* functions just
 /// trigger this function to recurse into the symbols map.
+///
+/// The commands that are created for `symbol` carry its name, so that a
+/// command that fails can be reported with it.
 pub(crate) fn recurse_compile<'a>(
     symbol: &'a Symbol,
     mut state: CompilerState<'a>,
     // the token of the parent (the holder of the `args` field),
     // needed to retrieve methods from.
-    mut parent_token: Option<Token>,
+    parent_token: Option<Token>,
     // whether to increase the cur_mem_pos value in the CompilerState.
     // Setting this to false, allows for creating code recursively that
     // modifies the current memory position.
     inc_mem_pos: bool,
+) -> Result<CompilerState<'a>, CompileError> {
+    // Anonymous symbols are part of the symbol around them.
+    let outer_symbol = state.cur_symbol.clone();
+    if !symbol.name.is_empty() {
+        state.cur_symbol = Some(symbol.name.clone());
+    }
+    let mut state =
+        recurse_compile_symbol(symbol, state, parent_token, inc_mem_pos)?;
+    state.cur_symbol = outer_symbol;
+    Ok(state)
+}
+
+fn recurse_compile_symbol<'a>(
+    symbol: &'a Symbol,
+    mut state: CompilerState<'a>,
+    mut parent_token: Option<Token>,
+    inc_mem_pos: bool,
 ) -> Result<CompilerState<'a>, CompileError> {
     // Compute expression trees always should have the form:
     //
@@ -17,6 +17,7 @@ use crate::symbols::Symbol;
 use crate::symbols::SymbolKind;
 use crate::traits::Token;
 use crate::types::builtin::BuiltinTypeValue;
+use crate::types::datasources;
 use crate::types::enum_types::GlobalEnumTypeDef;
 use crate::types::typedef::NamedTypeDef;
 use crate::types::typedef::RecordTypeDef;
@@ -148,21 +149,34 @@ impl<'a> ast::Rib {
         &'a self,
         symbols: &'_ mut symbols::SymbolTable,
     ) -> Result<(), CompileError> {
-        let child_kvs = self.body.eval(self.ident.ident.clone(), symbols)?;
-
-        // create a new user-defined type for the record type in the RIB
-        let rec_type = TypeDef::new_record_type_from_short_string(child_kvs)?;
-
-        // add a symbol for the user-defined type, the name is derived from
-        // the 'contains' clause
-        symbols.add_variable(
-            self.contain_ty.ident.clone(),
-            None,
-            symbols::SymbolKind::NamedType,
-            rec_type.clone(),
-            vec![],
-            TypeValue::Unknown,
-        )?;
+        let rec_type = if let Some(body) = &self.body {
+            let child_kvs = body.eval(self.ident.ident.clone(), symbols)?;
+
+            // create a new user-defined type for the record type in the RIB
+            let rec_type =
+                TypeDef::new_record_type_from_short_string(child_kvs)?;
+
+            // add a symbol for the user-defined type, the name is derived
+            // from the 'contains' clause
+            symbols.add_variable(
+                self.contain_ty.ident.clone(),
+                None,
+                symbols::SymbolKind::NamedType,
+                rec_type.clone(),
+                vec![],
+                TypeValue::Unknown,
+            )?;
+            rec_type
+        } else {
+            // a RIB without a body contains a built-in type, e.g. `Vrp`
+            datasources::builtin_record_type(&self.contain_ty.ident)
+                .ok_or_else(|| {
+                    CompileError::from(format!(
+                        "RIB '{}' contains unknown built-in type '{}'",
+                        self.ident.ident, self.contain_ty.ident
+                    ))
+                })?
+        };
 
         // add a symbol for the RIB itself, using the newly created record
         // type
@@ -184,30 +198,60 @@ impl<'a> ast::Table {
         &'a self,
         symbols: &'_ mut symbols::SymbolTable,
     ) -> Result<(), CompileError> {
-        let child_kvs = self.body.eval(self.ident.ident.clone(), symbols)?;
-
-        // create a new user-defined type for the record type in the table
-        let rec_type = TypeDef::new_record_type_from_short_string(child_kvs)?;
+        let rec_type = if let Some(body) = &self.body {
+            let child_kvs = body.eval(self.ident.ident.clone(), symbols)?;
+
+            // create a new user-defined type for the record type in the
+            // table
+            let rec_type =
+                TypeDef::new_record_type_from_short_string(child_kvs)?;
+
+            // add a symbol for the user-defined type, the name is derived
+            // from the 'contains' clause
+            symbols.add_variable(
+                self.contain_ty.ident.clone(),
+                None,
+                symbols::SymbolKind::NamedType,
+                rec_type.clone(),
+                vec![],
+                TypeValue::Unknown,
+            )?;
+            rec_type
+        } else {
+            // a table without a body contains a built-in type, e.g. `Aspa`
+            datasources::builtin_record_type(&self.contain_ty.ident)
+                .ok_or_else(|| {
+                    CompileError::from(format!(
+                        "Table '{}' contains unknown built-in type '{}'",
+                        self.ident.ident, self.contain_ty.ident
+                    ))
+                })?
+        };
 
-        // add a symbol for the user-defined type, the name is derived from
-        // the 'contains' clause
-        symbols.add_variable(
-            self.contain_ty.ident.clone(),
-            None,
-            symbols::SymbolKind::NamedType,
-            rec_type.clone(),
-            vec![],
-            TypeValue::Unknown,
-        )?;
+        // the key of the table, if any, is stored as the only argument of
+        // the table symbol
+        let args = if let Some(key) = &self.key {
+            let (key_ty, token, _) =
+                rec_type.has_fields_chain(&[key.clone()])?;
+            vec![symbols::Symbol::new(
+                key.ident.clone(),
+                symbols::SymbolKind::FieldAccess,
+                key_ty,
+                vec![],
+                token,
+            )]
+        } else {
+            vec![]
+        };
 
-        // add a symbol for the RIB itself, using the newly created record
+        // add a symbol for the table itself, using the newly created record
         // type
         symbols.add_variable(
             self.ident.ident.clone(),
             None,
             symbols::SymbolKind::Table,
             rec_type,
-            vec![],
+            args,
             TypeValue::Unknown,
         )?;
 
@@ -719,6 +763,7 @@ impl ast::TermSection {
             // that type can be used when invoking a code block for
             // retrieving that argument.
             logical_formula.ty = argument_type.clone();
+            logical_formula.span = term.span();
             add_logical_formula(
                 self.ident.ident.clone(),
                 term_section_index,
@@ -974,6 +1019,7 @@ impl ast::ActionSection {
             )?;
 
             s.kind = SymbolKind::AccessReceiver;
+            s.span = Some(compute_expr.span);
 
             action_exprs.push(s);
         }
@@ -986,7 +1032,8 @@ impl ast::ActionSection {
             action_section_type,
             action_exprs,
             Token::ActionSection(action_section_index),
-        );
+        )
+        .with_span(Some(self.ident.span));
 
         add_action_section(
             self.ident.ident.clone(),
@@ -1007,6 +1054,14 @@ impl ast::ApplySection {
         symbols: symbols::GlobalSymbolTable,
         scope: Scope,
     ) -> Result<(), CompileError> {
+        // The reason has to be evaluated before the symbols are borrowed
+        // mutably.
+        let (default_action, default_reason) = match &self.body.accept_reject
+        {
+            Some(ret) => ret.eval(symbols.clone(), scope.clone())?,
+            None => (AcceptReject::Accept, TypeValue::Unknown),
+        };
+
         let mut _symbols = symbols.borrow_mut();
         let _filter_map_symbols =
             _symbols.get_mut(&scope).ok_or_else(|| {
@@ -1016,11 +1071,8 @@ impl ast::ApplySection {
         // There can only be one `apply` section in a filter_map, so we can
         // set the default action from the apply section for the whole
         // filter_map.
-        if let Some(accept_reject) = self.body.accept_reject {
-            _filter_map_symbols.set_default_action(accept_reject);
-        } else {
-            _filter_map_symbols.set_default_action(AcceptReject::Accept);
-        }
+        _filter_map_symbols
+            .set_default_action(default_action, default_reason);
 
         drop(_symbols);
         for a_scope in &self.body.scopes {
@@ -1059,6 +1111,36 @@ impl ast::ApplySection {
     }
 }
 
+impl ast::ReturnExpr {
+    /// Returns the verdict together with the value of its reason, that
+    /// should be a constant string or enum variant. If there's no reason
+    /// the value is `TypeValue::Unknown`.
+    fn eval(
+        &self,
+        symbols: symbols::GlobalSymbolTable,
+        scope: Scope,
+    ) -> Result<(AcceptReject, TypeValue), CompileError> {
+        let Some(reason) = &self.reason else {
+            return Ok((self.accept_reject, TypeValue::Unknown));
+        };
+
+        let s = reason.eval(symbols, scope, &[])?;
+        match (&s.token, &s.ty) {
+            (Token::Constant(_), TypeDef::StringLiteral)
+            | (Token::ConstEnumVariant, TypeDef::ConstEnumVariant(_))
+                if s.args.is_empty() =>
+            {
+                Ok((self.accept_reject, s.value))
+            }
+            _ => Err(CompileError::from(format!(
+                "The reason for '{}' should be a string or an enum \
+                variant, but found type: {}",
+                self.accept_reject, s.ty
+            ))),
+        }
+    }
+}
+
 impl ast::ApplyScope {
     fn eval(
         &self,
@@ -1085,7 +1167,16 @@ impl ast::ApplyScope {
                     .get_term_section_type_and_token(&term.name)?;
 
                 let mut args_vec = vec![];
-                for (action_expr, accept_reject) in &fma.actions {
+                for (action_expr, ret) in &fma.actions {
+                    let (accept_reject, reason) = match ret {
+                        Some(ret) => {
+                            let (accept_reject, reason) =
+                                ret.eval(symbols.clone(), scope.clone())?;
+                            (Some(accept_reject), reason)
+                        }
+                        None => (None, TypeValue::Unknown),
+                    };
+
                     if let Some(action_call) = action_expr.clone() {
                         // If there's one or more actions in the filter block we
                         // will store them as args in the vector.
@@ -1096,7 +1187,7 @@ impl ast::ApplyScope {
                         let (_ty, token) = filter_map_symbols
                             .get_action_section(&action_name)?;
 
-                        let s = symbols::Symbol::new(
+                        let mut s = symbols::Symbol::new(
                             action_name,
                             symbols::SymbolKind::ActionCall,
                             TypeDef::AcceptReject(
@@ -1106,6 +1197,7 @@ impl ast::ApplyScope {
                             vec![],
                             token,
                         );
+                        s.value = reason;
                         args_vec.push(s);
                     } else {
                         // If there's no Action mentioned in a filter block, we will
@@ -1113,7 +1205,7 @@ impl ast::ApplyScope {
                         // can invoke the right accept/reject commands. The action
                         // symbol gets to have the name of the term, but it is never
                         // inspected, since it doesn't exist in any symbol map.
-                        let s = symbols::Symbol::new(
+                        let mut s = symbols::Symbol::new(
                             term.name.clone(),
                             symbols::SymbolKind::ActionCall,
                             TypeDef::AcceptReject(accept_reject.ok_or_else(
@@ -1127,6 +1219,7 @@ impl ast::ApplyScope {
                             vec![],
                             Token::NoAction,
                         );
+                        s.value = reason;
                         args_vec.push(s);
                     }
                 }
@@ -1148,7 +1241,8 @@ impl ast::ApplyScope {
                     TypeDef::Unknown,
                     args_vec,
                     token,
-                ))
+                )
+                .with_span(Some(fma.filter_ident.span)))
             }
             MatchActionExpr::PatternMatchAction(pma) => {
                 // this code is all very similar to `eval_as_match_expression`
@@ -1233,7 +1327,7 @@ impl ast::ApplyScope {
                         }
                         for action in &variant.actions {
                             match action {
-                                (Some(action_call), accept_reject) => {
+                                (Some(action_call), ret) => {
                                     // If there's one or more action calls in
                                     // the filter block we will store them as
                                     // args in the vector.
@@ -1247,24 +1341,38 @@ impl ast::ApplyScope {
                                         &local_scope,
                                     )?;
 
-                                    action_s.ty = TypeDef::AcceptReject(
-                                        accept_reject.unwrap_or(
-                                            ast::AcceptReject::NoReturn,
-                                        ),
-                                    );
+                                    let (accept_reject, reason) =
+                                        match ret {
+                                            Some(ret) => ret.eval(
+                                                symbols.clone(),
+                                                scope.clone(),
+                                            )?,
+                                            None => (
+                                                AcceptReject::NoReturn,
+                                                TypeValue::Unknown,
+                                            ),
+                                        };
+                                    action_s.ty =
+                                        TypeDef::AcceptReject(accept_reject);
+                                    action_s.value = reason;
                                     args_vec.push(action_s);
                                 }
                                 // There are no action calls in the variant
                                 // body, but there is an AcceptReject
                                 // expression (indicating an early return).
-                                (None, Some(accept_reject)) => {
-                                    let s = symbols::Symbol::new(
+                                (None, Some(ret)) => {
+                                    let (accept_reject, reason) = ret.eval(
+                                        symbols.clone(),
+                                        scope.clone(),
+                                    )?;
+                                    let mut s = symbols::Symbol::new(
                                         variant.variant_id.inner.clone().ident,
                                         symbols::SymbolKind::ActionCall,
-                                        TypeDef::AcceptReject(*accept_reject),
+                                        TypeDef::AcceptReject(accept_reject),
                                         vec![],
                                         Token::NoAction,
                                     );
+                                    s.value = reason;
                                     args_vec.push(s);
                                 }
                                 // No action call, no AcceptReject either.
@@ -1404,7 +1512,14 @@ impl ast::ComputeExpr {
 
         // Use the type of the access receiver to put on the arguments.
         let mut ty = match ar_token {
-            Token::Table(_) => TypeDef::Table(Box::new(s.ty.clone())),
+            Token::Table(_) => TypeDef::Table((
+                Box::new(s.ty.clone()),
+                table_key(&symbols, &s.name),
+            )),
+            // Custom data sources have their methods on their own type.
+            Token::Rib(_) if matches!(s.ty, TypeDef::Custom(_)) => {
+                s.ty.clone()
+            }
             Token::Rib(_) => TypeDef::Rib((Box::new(s.ty.clone()), None)),
             Token::OutputStream(_) => {
                 TypeDef::OutputStream(Box::new(s.ty.clone()))
@@ -2400,6 +2515,18 @@ impl ast::ListCompareExpr {
 
 //============ Helper functions =============================================
 
+/// The name of the key field of the table `name`, if it has one.
+fn table_key(
+    symbols: &symbols::GlobalSymbolTable,
+    name: &ShortString,
+) -> Option<ShortString> {
+    let symbols = symbols.borrow();
+    match symbols.get(&Scope::Global)?.get_data_source(name) {
+        Ok((TypeDef::Table((_, key)), _)) => key,
+        _ => None,
+    }
+}
+
 fn check_type_identifier(
     ty: ast::TypeIdentifier,
     symbols: symbols::GlobalSymbolTable,
@@ -8,4 +8,6 @@ mod symbols;
 pub mod traits;
 pub mod types;
 pub mod vm;
-pub mod pipeline;
\ No newline at end of file
+pub mod pipeline;
+pub mod runtime;
+pub mod unstable;
\ No newline at end of file
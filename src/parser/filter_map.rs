@@ -5,9 +5,9 @@ use crate::ast::{
     DefineBody, FilterMap, FilterMapBody, FilterMapExpr,
     FilterMatchActionExpr, FilterType, GroupedLogicalExpr, ListCompareExpr,
     LogicalExpr, MatchActionExpr, MatchOperator, NotExpr, OrExpr,
-    PatternMatchActionArm, PatternMatchActionExpr, RxTxType, TermBody,
-    TermCallExpr, TermPatternMatchArm, TermScope, TermSection,
-    TypeIdentField, ValueExpr,
+    PatternMatchActionArm, PatternMatchActionExpr, Pipeline, ReturnExpr,
+    RxTxType, TermBody, TermCallExpr, TermPatternMatchArm, TermScope,
+    TermSection, TypeIdentField, ValueExpr,
 };
 
 use super::{
@@ -52,6 +52,33 @@ impl<'source> Parser<'source> {
         })
     }
 
+    /// Parse a pipeline of filter-maps and filters
+    ///
+    /// ```ebnf
+    /// Pipeline ::= 'pipeline' Identifier '{' (Identifier ';')+ '}'
+    /// ```
+    pub(super) fn pipeline(&mut self) -> ParseResult<Pipeline> {
+        self.take(Token::Pipeline)?;
+        let ident = self.identifier()?;
+        self.take(Token::CurlyLeft)?;
+
+        let mut stages = Vec::new();
+        while !self.next_is(Token::CurlyRight) {
+            stages.push(self.identifier()?);
+            self.take(Token::SemiColon)?;
+        }
+
+        if stages.is_empty() {
+            return Err(ParseError::expected(
+                "at least one filter-map or filter in the pipeline",
+                Token::CurlyRight,
+                ident.span,
+            ));
+        }
+
+        Ok(Pipeline { ident, stages })
+    }
+
     /// Parse the body of a filter-map or filter
     ///
     /// ```ebnf
@@ -213,6 +240,7 @@ impl<'source> Parser<'source> {
         while !(self.peek_is(Token::Return)
             || self.peek_is(Token::Accept)
             || self.peek_is(Token::Reject)
+            || self.peek_is(Token::Continue)
             || self.peek_is(Token::CurlyRight))
         {
             scopes.push(self.apply_scope()?);
@@ -395,40 +423,49 @@ impl<'source> Parser<'source> {
         Ok(ActionCallExpr { action_id, args })
     }
 
-    /// Parse a statement returning accept or reject
+    /// Parse a statement returning accept, reject or continue, with an
+    /// optional reason
     ///
     /// ```ebnf
-    /// AcceptReject ::= ('return'? ( 'accept' | 'reject' ) ';')?
+    /// AcceptReject ::= ('return'? ( 'accept' | 'reject' | 'continue' )
+    ///                  ( 'with' ValueExpr )? ';')?
     /// ```
-    fn try_accept_reject(&mut self) -> ParseResult<Option<AcceptReject>> {
-        if self.next_is(Token::Return) {
+    fn try_accept_reject(&mut self) -> ParseResult<Option<ReturnExpr>> {
+        let accept_reject = if self.next_is(Token::Return) {
             let (token, span) = self.next()?;
-            let value = match token {
+            match token {
                 Token::Accept => AcceptReject::Accept,
                 Token::Reject => AcceptReject::Reject,
+                Token::Continue => AcceptReject::Continue,
                 _ => {
                     return Err(ParseError::expected(
-                        "`accept` or `reject` after `return`",
+                        "`accept`, `reject` or `continue` after `return`",
                         token,
                         span,
                     ))
                 }
-            };
-            self.take(Token::SemiColon)?;
-            return Ok(Some(value));
-        }
-
-        if self.next_is(Token::Accept) {
-            self.take(Token::SemiColon)?;
-            return Ok(Some(AcceptReject::Accept));
-        }
+            }
+        } else if self.next_is(Token::Accept) {
+            AcceptReject::Accept
+        } else if self.next_is(Token::Reject) {
+            AcceptReject::Reject
+        } else if self.next_is(Token::Continue) {
+            AcceptReject::Continue
+        } else {
+            return Ok(None);
+        };
 
-        if self.next_is(Token::Reject) {
-            self.take(Token::SemiColon)?;
-            return Ok(Some(AcceptReject::Reject));
-        }
+        let reason = if self.next_is(Token::With) {
+            Some(self.value_expr()?)
+        } else {
+            None
+        };
 
-        Ok(None)
+        self.take(Token::SemiColon)?;
+        Ok(Some(ReturnExpr {
+            accept_reject,
+            reason,
+        }))
     }
 
     /// Parse a match operator
@@ -306,7 +306,7 @@ impl<'source> Parser<'source> {
     /// Parse a root expression
     ///
     /// ```ebnf
-    /// Root ::= Rib | Table | OutputStream | FilterMap | Type
+    /// Root ::= Rib | Table | OutputStream | FilterMap | Type | Pipeline
     /// ```
     fn root(&mut self) -> ParseResult<RootExpr> {
         let end_of_input = ParseError {
@@ -326,10 +326,12 @@ impl<'source> Parser<'source> {
                 RootExpr::FilterMap(Box::new(self.filter_map()?))
             }
             Token::Type => RootExpr::Ty(self.record_type_assignment()?),
+            Token::Pipeline => RootExpr::Pipeline(self.pipeline()?),
             _ => {
                 let (token, span) = self.next()?;
                 return Err(ParseError::expected(
-                    "a rib, table, output-stream, filter or filter-map",
+                    "a rib, table, output-stream, filter, filter-map or \
+                    pipeline",
                     token,
                     span,
                 ));
@@ -349,8 +351,13 @@ impl<'source> Parser<'source> {
         let (token, span) = self.next()?;
         let ident = match token {
             Token::Ident(s) => s,
-            // 'contains' and `type` is already used as both a keyword and an identifier
+            // 'contains', `continue`, `key`, `match`, `pipeline` and `type`
+            // are used as both a keyword and an identifier
             Token::Contains => "contains",
+            Token::Continue => "continue",
+            Token::Key => "key",
+            Token::Match => "match",
+            Token::Pipeline => "pipeline",
             Token::Type => "type",
             _ => {
                 return Err(ParseError::expected(
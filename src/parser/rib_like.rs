@@ -16,14 +16,20 @@ impl<'source> Parser<'source> {
     /// ```ebnf
     /// Rib ::= 'rib' Identifier
     ///         'contains' TypeIdentifier
-    ///         RibBody
+    ///         RibBody?
     /// ```
+    ///
+    /// A rib without a body contains a built-in type.
     pub(super) fn rib(&mut self) -> ParseResult<Rib> {
         self.take(Token::Rib)?;
         let ident = self.identifier()?;
         self.take(Token::Contains)?;
         let contain_ty = self.type_identifier()?;
-        let body = self.rib_body()?;
+        let body = if self.peek_is(Token::CurlyLeft) {
+            Some(self.rib_body()?)
+        } else {
+            None
+        };
 
         Ok(Rib {
             ident,
@@ -37,18 +43,31 @@ impl<'source> Parser<'source> {
     /// ```ebnf
     /// Table ::= 'table' Identifier
     ///           'contains' TypeIdentifier
-    ///           RibBody
+    ///           ( 'key' Identifier )?
+    ///           RibBody?
     /// ```
+    ///
+    /// A table without a body contains a built-in type.
     pub(super) fn table(&mut self) -> ParseResult<Table> {
         self.take(Token::Table)?;
         let ident = self.identifier()?;
         self.take(Token::Contains)?;
         let contain_ty = self.type_identifier()?;
-        let body = self.rib_body()?;
+        let key = if self.next_is(Token::Key) {
+            Some(self.identifier()?)
+        } else {
+            None
+        };
+        let body = if self.peek_is(Token::CurlyLeft) {
+            Some(self.rib_body()?)
+        } else {
+            None
+        };
 
         Ok(Table {
             ident,
             contain_ty,
+            key,
             body,
         })
     }
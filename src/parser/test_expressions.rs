@@ -1,3 +1,4 @@
+use crate::parser::span::Span;
 use crate::parser::Parser;
 
 //------------ Logical Expressions parsing ----------------------------------
@@ -89,6 +90,16 @@ fn test_compute_expr_5() {
     assert!(r.is_ok());
 }
 
+#[test]
+fn test_compute_expr_6() {
+    let r = Parser::run_parser(
+        Parser::value_expr,
+        0,
+        "route.continue.pipeline(a, b)",
+    );
+    assert!(r.is_ok());
+}
+
 //------------ Other Expressions --------------------------------------------
 
 #[test]
@@ -123,3 +134,10 @@ fn test_prefix_expr_3() {
     );
     assert!(s.is_ok());
 }
+
+#[test]
+fn test_prefix_expr_span() {
+    let input = "192.0.2.0/24 upto /28";
+    let expr = Parser::run_parser(Parser::logical_expr, 0, input).unwrap();
+    assert_eq!(expr.span(), Some(Span::new(0, 0..input.len())));
+}
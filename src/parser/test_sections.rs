@@ -29,3 +29,107 @@ fn test_logical_expr_2() {
     );
     assert!(r.is_ok());
 }
+
+//------------ Apply section parsing ----------------------------------------
+
+#[test]
+fn test_apply_return_1() {
+    let r = Parser::run_parser(
+        Parser::filter_map,
+        0,
+        r###"
+        filter-map my-filter-map {
+            define {
+                rx_tx msg: BmpMsg;
+            }
+
+            apply {
+                filter match rpki-invalid matching {
+                    return reject with "rpki-invalid";
+                };
+                filter match from-transit matching { continue; };
+                accept with "ok";
+            }
+        }
+        "###,
+    );
+    assert!(r.is_ok());
+}
+
+#[test]
+fn test_apply_return_2() {
+    let r = Parser::run_parser(
+        Parser::filter_map,
+        0,
+        r###"
+        filter-map my-filter-map {
+            define {
+                rx_tx msg: BmpMsg;
+            }
+
+            apply {
+                return reject with;
+            }
+        }
+        "###,
+    );
+    assert!(r.is_err());
+}
+
+//------------ Table parsing ------------------------------------------------
+
+#[test]
+fn test_table_key_1() {
+    let r = Parser::run_parser(
+        Parser::table,
+        0,
+        r###"
+        table customers contains Customer key asn {
+            asn: Asn,
+            name: String
+        }
+        "###,
+    );
+    assert!(r.unwrap().key.is_some());
+}
+
+#[test]
+fn test_table_key_2() {
+    let r = Parser::run_parser(
+        Parser::table,
+        0,
+        r###"
+        table customers contains Customer key {
+            asn: Asn,
+            name: String
+        }
+        "###,
+    );
+    assert!(r.is_err());
+}
+
+#[test]
+fn test_table_without_body() {
+    let r = Parser::run_parser(
+        Parser::table,
+        0,
+        r###"
+        table aspas contains Aspa
+        "###,
+    );
+    assert!(r.unwrap().body.is_none());
+}
+
+//------------ Ribs ---------------------------------------------------------
+
+#[test]
+fn test_rib_without_body() {
+    let r = Parser::run_parser(
+        Parser::rib,
+        0,
+        r###"
+        rib vrps contains Vrp
+        "###,
+    );
+    assert!(r.unwrap().body.is_none());
+}
@@ -71,6 +71,8 @@ pub enum Token<'s> {
     Apply,
     #[token("contains")]
     Contains,
+    #[token("continue")]
+    Continue,
     #[token("define")]
     Define,
     #[token("exact")]
@@ -87,6 +89,8 @@ pub enum Token<'s> {
     Import,
     #[token("in")]
     In,
+    #[token("key")]
+    Key,
     #[token("longer")]
     Longer,
     #[token("match")]
@@ -103,6 +107,8 @@ pub enum Token<'s> {
     OrLonger,
     #[token("output-stream")]
     OutputStream,
+    #[token("pipeline")]
+    Pipeline,
     #[token("prefix-length-range")]
     PrefixLengthRange,
     #[token("reject")]
@@ -203,6 +209,7 @@ impl<'source> Display for Token<'source> {
             Token::All => "all",
             Token::Apply => "apply",
             Token::Contains => "contains",
+            Token::Continue => "continue",
             Token::Define => "define",
             Token::Exact => "exact",
             Token::ExactlyOne => "exactly-one",
@@ -211,6 +218,7 @@ impl<'source> Display for Token<'source> {
             Token::For => "for",
             Token::Import => "import",
             Token::In => "in",
+            Token::Key => "key",
             Token::Longer => "longer",
             Token::Match => "match",
             Token::Matching => "matching",
@@ -219,6 +227,7 @@ impl<'source> Display for Token<'source> {
             Token::Not => "not",
             Token::OrLonger => "or-longer",
             Token::OutputStream => "output-stream",
+            Token::Pipeline => "pipeline",
             Token::PrefixLengthRange => "prefix-length-range",
             Token::Reject => "reject",
             Token::Return => "return",
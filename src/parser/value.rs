@@ -107,11 +107,15 @@ impl<'source> Parser<'source> {
         // If not, it can be an access expression
         if let LiteralExpr::PrefixLiteral(prefix) = &literal.inner {
             if let Some(ty) = self.try_prefix_match_type()? {
-                return Ok(ValueExpr::PrefixMatchExpr(PrefixMatchExpr {
-                    prefix: prefix.clone(),
-                    ty,
-                })
-                .with_span(literal.span));
+                let span = literal.span.merge(ty.span);
+                return Ok(ValueExpr::PrefixMatchExpr(
+                    PrefixMatchExpr {
+                        prefix: prefix.clone(),
+                        ty: ty.inner,
+                    }
+                    .with_span(span),
+                )
+                .with_span(span));
             }
         }
 
@@ -383,23 +387,39 @@ impl<'source> Parser<'source> {
     /// ```
     fn try_prefix_match_type(
         &mut self,
-    ) -> ParseResult<Option<PrefixMatchType>> {
-        let match_type = if self.next_is(Token::Exact) {
-            PrefixMatchType::Exact
-        } else if self.next_is(Token::Longer) {
-            PrefixMatchType::Longer
-        } else if self.next_is(Token::OrLonger) {
-            PrefixMatchType::OrLonger
-        } else if self.next_is(Token::PrefixLengthRange) {
-            PrefixMatchType::PrefixLengthRange(
-                self.prefix_length_range()?.inner,
-            )
-        } else if self.next_is(Token::UpTo) {
-            PrefixMatchType::UpTo(self.prefix_length()?.inner)
-        } else if self.next_is(Token::NetMask) {
-            PrefixMatchType::NetMask(self.ip_address()?.inner)
-        } else {
-            return Ok(None);
+    ) -> ParseResult<Option<Spanned<PrefixMatchType>>> {
+        let (token, span) = match self.peek() {
+            Some(
+                Token::Exact
+                | Token::Longer
+                | Token::OrLonger
+                | Token::PrefixLengthRange
+                | Token::UpTo
+                | Token::NetMask,
+            ) => self.next()?,
+            _ => return Ok(None),
+        };
+
+        let match_type = match token {
+            Token::Exact => PrefixMatchType::Exact.with_span(span),
+            Token::Longer => PrefixMatchType::Longer.with_span(span),
+            Token::OrLonger => PrefixMatchType::OrLonger.with_span(span),
+            Token::PrefixLengthRange => {
+                let range = self.prefix_length_range()?;
+                PrefixMatchType::PrefixLengthRange(range.inner)
+                    .with_span(span.merge(range.span))
+            }
+            Token::UpTo => {
+                let len = self.prefix_length()?;
+                PrefixMatchType::UpTo(len.inner)
+                    .with_span(span.merge(len.span))
+            }
+            Token::NetMask => {
+                let addr = self.ip_address()?;
+                PrefixMatchType::NetMask(addr.inner)
+                    .with_span(span.merge(addr.span))
+            }
+            _ => unreachable!("peeked a prefix match type"),
         };
 
         Ok(Some(match_type))
@@ -1,11 +1,23 @@
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
+
 use crate::{
-    ast::SyntaxTree,
+    ast::{ShortString, SyntaxTree},
     blocks::Scope,
-    compiler::{compile::Rotolo, CompileError, Compiler},
+    compiler::{compile::Rotolo, CompileError, CompiledFilter, Compiler},
     parser::{ParseError, Parser},
     symbols::GlobalSymbolTable,
-    typechecker::error::{Level, TypeError},
-    types::typevalue::TypeValue,
+    traits::{RotoDataSource, RotoType},
+    typechecker::{
+        error::{Level, TypeError},
+        types::custom_method,
+    },
+    types::{
+        builtin::RouteContext, datasources::DataSource,
+        typedef::CustomTypeDef, typevalue::TypeValue,
+    },
+    vm::{ExecutionContext, VmError, VmErrorContext, VmResult},
 };
 
 #[derive(Clone, Debug)]
@@ -14,6 +26,15 @@ pub struct SourceFile {
     contents: String,
 }
 
+impl SourceFile {
+    pub fn new(name: impl Into<String>, contents: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            contents: contents.into(),
+        }
+    }
+}
+
 #[derive(Debug)]
 enum RotoError {
     Read(String, std::io::Error),
@@ -21,6 +42,8 @@ enum RotoError {
     Type(TypeError),
     Evaluate(CompileError),
     Compile(CompileError),
+    Reload(CompileError),
+    Runtime(VmError),
 }
 
 #[derive(Debug)]
@@ -110,6 +133,51 @@ impl std::fmt::Display for RotoReport {
                 RotoError::Compile(e) => {
                     write!(f, "{e}")?;
                 }
+                RotoError::Reload(e) => {
+                    write!(f, "Reload rejected: {e}")?;
+                }
+                RotoError::Runtime(e) => {
+                    let Some(span) = e.span() else {
+                        writeln!(f, "Runtime error: {e}")?;
+                        continue;
+                    };
+
+                    let (name, section) = match e.context() {
+                        Some(VmErrorContext { name, section, .. }) => {
+                            (name.as_ref(), section.as_ref())
+                        }
+                        None => (None, None),
+                    };
+                    let label_message = match (name, section) {
+                        (Some(name), Some(section)) => {
+                            format!("at '{name}' while executing '{section}'")
+                        }
+                        (None, Some(section)) => {
+                            format!("while executing '{section}'")
+                        }
+                        (Some(name), None) => format!("at '{name}'"),
+                        (None, None) => "while executing this".to_string(),
+                    };
+                    let label = Label::new((
+                        self.filename(span.file),
+                        span.start..span.end,
+                    ))
+                    .with_message(label_message)
+                    .with_color(Color::Red);
+
+                    let file = self.filename(span.file);
+
+                    let report =
+                        Report::build(ReportKind::Error, file, span.start)
+                            .with_message(format!("Runtime error: {}", e))
+                            .with_label(label)
+                            .finish();
+
+                    let mut v = Vec::new();
+                    report.write(&mut file_cache, &mut v).unwrap();
+                    let s = String::from_utf8_lossy(&v);
+                    write!(f, "{s}")?;
+                }
             }
         }
 
@@ -118,6 +186,16 @@ impl std::fmt::Display for RotoReport {
 }
 
 impl RotoReport {
+    /// Create a report for an error that happened while executing a
+    /// filter(-map) compiled from `files`, so that it points at the term or
+    /// action in the source code that failed.
+    pub fn runtime(files: &[SourceFile], error: VmError) -> Self {
+        RotoReport {
+            files: files.to_vec(),
+            errors: vec![RotoError::Runtime(error)],
+        }
+    }
+
     fn filename(&self, i: usize) -> String {
         self.files[i].name.clone()
     }
@@ -163,7 +241,7 @@ pub fn run_test(
     Ok(compile(&files, &symbols, arguments)?.remove(0))
 }
 
-fn read_files(
+pub(crate) fn read_files(
     files: impl IntoIterator<Item = String>,
 ) -> Result<Vec<SourceFile>, RotoReport> {
     let results: Vec<_> = files
@@ -223,9 +301,21 @@ pub fn typecheck(
     files: &[SourceFile],
     trees: &[SyntaxTree],
 ) -> Result<(), RotoReport> {
+    typecheck_with_sources(files, trees, &DataSourceRegistry::new())
+}
+
+/// Type check the trees, with the custom data sources in `sources`.
+pub fn typecheck_with_sources(
+    files: &[SourceFile],
+    trees: &[SyntaxTree],
+    sources: &DataSourceRegistry,
+) -> Result<(), RotoReport> {
+    let type_defs = sources.type_defs();
     let results: Vec<_> = trees
         .iter()
-        .map(crate::typechecker::typecheck)
+        .map(|t| {
+            crate::typechecker::typecheck_with_sources(t, &type_defs)
+        })
         .collect();
 
     let mut errors = Vec::new();
@@ -249,10 +339,21 @@ pub fn evaluate(
     files: &[SourceFile],
     trees: &[SyntaxTree],
 ) -> Result<Vec<GlobalSymbolTable>, RotoReport> {
+    evaluate_with_sources(files, trees, &DataSourceRegistry::new())
+}
+
+/// Evaluate the trees, with the custom data sources in `sources`.
+pub fn evaluate_with_sources(
+    files: &[SourceFile],
+    trees: &[SyntaxTree],
+    sources: &DataSourceRegistry,
+) -> Result<Vec<GlobalSymbolTable>, RotoReport> {
+    let type_defs = sources.type_defs();
     let results: Vec<_> = trees
         .iter()
         .map(|t| {
             let symbols = GlobalSymbolTable::new();
+            symbols.add_custom_sources(&type_defs)?;
             t.eval(symbols.clone())?;
             Ok(symbols)
         })
@@ -305,3 +406,388 @@ pub fn compile(
         })
     }
 }
+
+//------------ DataSourceRegistry -------------------------------------------
+
+/// The custom data sources that can be used in the source code, by name.
+///
+/// A custom data source is implemented outside of roto, with the
+/// [`RotoDataSource`] trait. The sources have to be registered before the
+/// source code is type checked, since the type checker needs to know their
+/// methods. A source can then be used like a rib that is declared in the
+/// source code, e.g. `bogons.contains(route.prefix)`.
+#[derive(Clone, Default)]
+pub struct DataSourceRegistry {
+    sources: Vec<(ShortString, Arc<dyn RotoDataSource>)>,
+}
+
+impl DataSourceRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Make `source` available to the source code as `name`.
+    ///
+    /// Fails if `name` is already registered, or if one of the methods of
+    /// `source` has a type that can't be used in roto.
+    pub fn register(
+        &mut self,
+        name: &str,
+        source: Arc<dyn RotoDataSource>,
+    ) -> Result<(), CompileError> {
+        if self.get(name).is_some() {
+            return Err(CompileError::from(format!(
+                "A data source with name {} is already registered",
+                name
+            )));
+        }
+        if let Some(method) = source
+            .methods()
+            .iter()
+            .find(|m| custom_method(name, m).is_none())
+        {
+            return Err(CompileError::from(format!(
+                "Method {} of data source {} has a type that can't be used \
+                in roto",
+                method.name, name
+            )));
+        }
+        self.sources.push((name.into(), source));
+        Ok(())
+    }
+
+    /// The source registered as `name`, to bind to the filters that use it.
+    pub fn get(&self, name: &str) -> Option<DataSource> {
+        self.sources
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(n, s)| DataSource::Custom(n.clone(), Arc::clone(s)))
+    }
+
+    /// The names of the sources with their methods.
+    fn type_defs(&self) -> Vec<CustomTypeDef> {
+        self.sources
+            .iter()
+            .map(|(name, source)| (name.clone(), source.methods()))
+            .collect()
+    }
+}
+
+impl std::fmt::Debug for DataSourceRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.sources.iter().map(|(name, _)| name))
+            .finish()
+    }
+}
+
+//------------ ReloadableFilter ---------------------------------------------
+
+/// A compiled filter(-map) that can be replaced by a newer version while
+/// routes are being processed.
+///
+/// The current version of the filter is stored in an ArcSwap. A reload
+/// compiles the new source code and validates the filter(-map) with the same
+/// name against the current one, before it is swapped in in one atomic
+/// operation. Executions that are running on the old version, keep their
+/// reference to it, and finish on it. Nothing is swapped if the new version
+/// fails to compile or fails validation, the errors are returned in a
+/// RotoReport instead.
+///
+/// The new version of the filter is valid if it has the same rx and tx
+/// types, the same arguments (with the same types), and uses only data
+/// sources that are bound to the current version, with the same types. The
+/// new version shares the sources with the current version, so that the
+/// [`DataSourceHandle`]s keep working, and takes over its argument values.
+///
+/// [`DataSourceHandle`]: crate::vm::DataSourceHandle
+#[derive(Debug)]
+pub struct ReloadableFilter {
+    name: Scope,
+    filter: ArcSwap<CompiledFilter>,
+}
+
+impl ReloadableFilter {
+    pub fn new(filter: CompiledFilter) -> Self {
+        Self {
+            name: filter.get_filter_map_name().clone(),
+            filter: ArcSwap::from_pointee(filter),
+        }
+    }
+
+    /// Compile the filter(-map) with the name `name` from a file.
+    pub fn from_file(
+        file: impl Into<String>,
+        name: Scope,
+    ) -> Result<Self, RotoReport> {
+        let files = read_files([file.into()])?;
+        compile_filter(&files, &name).map(Self::new)
+    }
+
+    pub fn get_name(&self) -> &Scope {
+        &self.name
+    }
+
+    /// Returns the current version of the filter. The returned filter stays
+    /// valid (and unchanged) when a reload happens in the meantime.
+    pub fn current(&self) -> Arc<CompiledFilter> {
+        self.filter.load_full()
+    }
+
+    /// Recompile this filter from `file` and swap it in if it's valid.
+    ///
+    /// Returns whether the filter was actually replaced, i.e. `false` if the
+    /// new version compiles to the exact same filter.
+    pub fn reload_file(
+        &self,
+        file: impl Into<String>,
+    ) -> Result<bool, RotoReport> {
+        let files = read_files([file.into()])?;
+        self.reload(&files)
+    }
+
+    /// Recompile this filter from a string and swap it in if it's valid.
+    pub fn reload_string(&self, script: String) -> Result<bool, RotoReport> {
+        let files = vec![SourceFile {
+            name: "script".into(),
+            contents: script,
+        }];
+        self.reload(&files)
+    }
+
+    fn reload(&self, files: &[SourceFile]) -> Result<bool, RotoReport> {
+        let new = compile_filter(files, &self.name)?;
+        let cur = self.filter.load();
+
+        if new.get_hash_id() == cur.get_hash_id() {
+            return Ok(false);
+        }
+
+        let errors = validate_reload(&cur, &new);
+        if !errors.is_empty() {
+            return Err(RotoReport {
+                files: files.to_vec(),
+                errors: errors.into_iter().map(RotoError::Reload).collect(),
+            });
+        }
+
+        // Share the bound sources, so that the updates of the current
+        // version (and of its handles) are updates of the new one, and take
+        // over the argument values, and the metrics and trace settings. The
+        // metrics start from zero, since the terms may have changed.
+        let data_sources = new
+            .get_data_sources()
+            .iter()
+            .map(|new_ds| {
+                match cur
+                    .get_data_sources()
+                    .iter()
+                    .find(|ds| ds.get_name() == new_ds.get_name())
+                {
+                    Some(cur_ds) => new_ds.sharing(cur_ds),
+                    None => new_ds.clone(),
+                }
+            })
+            .collect();
+        let new = CompiledFilter {
+            data_sources,
+            ..new
+        }
+        .with_arguments(cur.arguments.clone())
+        .with_metrics(cur.metrics.is_some())
+        .with_trace(cur.trace);
+
+        // Only swap if the current version is still the one we validated
+        // against, otherwise another reload came in first.
+        let prev = self.filter.compare_and_swap(&cur, Arc::new(new));
+        if !Arc::ptr_eq(&*prev, &*cur) {
+            return Err(RotoReport {
+                files: files.to_vec(),
+                errors: vec![RotoError::Reload(CompileError::from(
+                    "The filter was reloaded concurrently",
+                ))],
+            });
+        }
+
+        Ok(true)
+    }
+}
+
+/// Compile all the files and retrieve the filter(-map) with `name`.
+fn compile_filter(
+    files: &[SourceFile],
+    name: &Scope,
+) -> Result<CompiledFilter, RotoReport> {
+    let trees = parse(files)?;
+    typecheck(files, &trees)?;
+    let symbols = evaluate(files, &trees)?;
+    let rotolos = compile(files, &symbols, None)?;
+
+    let mut errors = vec![];
+    for rotolo in rotolos {
+        match rotolo.retrieve_compiled_filter(name) {
+            Ok(filter) => return Ok(filter),
+            Err(e) => errors.push(RotoError::Compile(e)),
+        }
+    }
+
+    Err(RotoReport {
+        files: files.to_vec(),
+        errors,
+    })
+}
+
+/// Check that `new` can take the place of `cur`, returns all the reasons it
+/// can't.
+fn validate_reload(
+    cur: &CompiledFilter,
+    new: &CompiledFilter,
+) -> Vec<CompileError> {
+    let mut errors = vec![];
+
+    if cur.get_rx_type() != new.get_rx_type() {
+        errors.push(CompileError::from(format!(
+            "The rx type changed from {} to {}",
+            cur.get_rx_type(),
+            new.get_rx_type()
+        )));
+    }
+
+    if cur.get_tx_type() != new.get_tx_type() {
+        errors.push(CompileError::from(format!(
+            "The tx type changed from {:?} to {:?}",
+            cur.get_tx_type(),
+            new.get_tx_type()
+        )));
+    }
+
+    let cur_args = cur.get_arguments();
+    let new_args = new.get_arguments();
+    for (name, ty) in &new_args {
+        match cur_args.iter().find(|(n, _)| n == name) {
+            Some((_, cur_ty)) if cur_ty != ty => {
+                errors.push(CompileError::from(format!(
+                    "The type of argument '{}' changed from {} to {}",
+                    name, cur_ty, ty
+                )));
+            }
+            Some(_) => {}
+            None => errors.push(CompileError::from(format!(
+                "The argument '{}' is not bound to the current filter",
+                name
+            ))),
+        }
+    }
+    for (name, _) in &cur_args {
+        if !new_args.iter().any(|(n, _)| n == name) {
+            errors.push(CompileError::from(format!(
+                "The argument '{}' was removed",
+                name
+            )));
+        }
+    }
+    if errors.is_empty() && cur_args != new_args {
+        errors.push(CompileError::from(
+            "The order of the arguments changed".to_string(),
+        ));
+    }
+
+    for new_ds in new.get_data_sources() {
+        match cur
+            .get_data_sources()
+            .iter()
+            .find(|ds| ds.get_name() == new_ds.get_name())
+        {
+            Some(cur_ds) if cur_ds.get_type() != new_ds.get_type() => {
+                errors.push(CompileError::from(format!(
+                    "The type of data source '{}' changed from {} to {}",
+                    new_ds.get_name(),
+                    cur_ds.get_type(),
+                    new_ds.get_type()
+                )));
+            }
+            Some(_) => {}
+            None => errors.push(CompileError::from(format!(
+                "The data source '{}' is not bound to the current filter",
+                new_ds.get_name()
+            ))),
+        }
+    }
+
+    errors
+}
+
+//------------ ReloadingExecutionContext ------------------------------------
+
+/// An execution context for a ReloadableFilter.
+///
+/// Before every execution it checks whether the filter was reloaded, and if
+/// so, it moves over to the new version. An execution that has started,
+/// always finishes on the version it started on.
+pub struct ReloadingExecutionContext<C>
+where
+    C: AsRef<RouteContext> + std::hash::Hash + Clone,
+{
+    handle: Arc<ReloadableFilter>,
+    filter: Arc<CompiledFilter>,
+    context: C,
+    exec_ctx: ExecutionContext<C>,
+}
+
+impl<C> ReloadingExecutionContext<C>
+where
+    C: AsRef<RouteContext> + std::hash::Hash + Clone,
+{
+    /// Create an execution context that follows the reloads of `handle`.
+    pub fn new(
+        handle: Arc<ReloadableFilter>,
+        context: C,
+    ) -> Result<Self, VmError> {
+        let filter = handle.current();
+        let exec_ctx = filter.new_execution_context(context.clone())?;
+
+        Ok(Self {
+            handle,
+            filter,
+            context,
+            exec_ctx,
+        })
+    }
+
+    fn refresh(&mut self) -> Result<(), VmError> {
+        let cur = self.handle.filter.load();
+        if !Arc::ptr_eq(&*cur, &self.filter) {
+            self.exec_ctx =
+                cur.new_execution_context(self.context.clone())?;
+            self.filter = arc_swap::Guard::into_inner(cur);
+        }
+        Ok(())
+    }
+
+    /// The version of the filter the next execution will run on.
+    pub fn filter(&self) -> &Arc<CompiledFilter> {
+        &self.filter
+    }
+
+    pub fn exec(
+        &mut self,
+        rx: impl RotoType,
+        tx: Option<impl RotoType>,
+    ) -> Result<VmResult, VmError> {
+        self.refresh()?;
+        self.exec_ctx.exec(rx, tx)
+    }
+
+    pub fn exec_batch<R: RotoType>(
+        &mut self,
+        rxs: impl IntoIterator<Item = R>,
+    ) -> Result<Vec<VmResult>, VmError> {
+        self.refresh()?;
+        self.exec_ctx.exec_batch(rxs)
+    }
+
+    pub fn update_context(&mut self, context: C) {
+        self.context = context.clone();
+        self.exec_ctx.update_context(context);
+    }
+}
@@ -0,0 +1,655 @@
+//! The embedding API.
+//!
+//! A [`Runtime`] compiles roto source code, a [`Filter`] is one filter(-map)
+//! out of it, with its data sources, arguments and context bound by name,
+//! that runs one route at a time:
+//!
+//! ```ignore
+//! let runtime = Runtime::from_string(source_code)?;
+//! let mut filter = runtime.filter("my-filter-map")?;
+//! filter.bind_table("customer_asns", records)?;
+//! filter.bind_arguments(MyArgs { my_asn: 65534 })?;
+//! filter.bind_context(context);
+//!
+//! let verdict = filter.run(route)?;
+//! ```
+//!
+//! Tables can also be read from CSV or JSON-lines files, with the record
+//! type that is declared for them in the source code, with
+//! [`Filter::bind_table_from_file`].
+//!
+//! Filters that are chained with a `pipeline` declaration in the source
+//! code are run as one unit with a [`Pipeline`]:
+//!
+//! ```ignore
+//! let mut pipeline = runtime.pipeline("import")?;
+//! pipeline.bind_context(context);
+//!
+//! let verdict = pipeline.run(route)?;
+//! ```
+//!
+//! Data sources that are implemented outside of roto, with the
+//! [`RotoDataSource`](crate::traits::RotoDataSource) trait, are registered
+//! by name in a [`DataSourceRegistry`], before the source code is compiled
+//! with [`Runtime::from_string_with_sources`]. They are bound to the
+//! filters that use them automatically.
+//!
+//! Data sources can be updated while a filter runs, through a
+//! [`DataSourceHandle`] from [`Filter::data_source`]. Every run of the
+//! filter sees the sources as they were when it started, and every run of
+//! a pipeline sees them as they were when the route entered it.
+//!
+//! Errors that happen while running a filter can be rendered against the
+//! source code with [`Filter::report`].
+
+use std::path::Path;
+
+use crate::{
+    ast::{AcceptReject, RootExpr, ShortString},
+    blocks::Scope,
+    compiler::{
+        compile::{set_data_source, Rotolo},
+        CompileError, CompiledFilter,
+    },
+    pipeline::{self, DataSourceRegistry, RotoReport, SourceFile},
+    traits::{IntoRotoArgs, RotoType},
+    types::{
+        builtin::{RouteContext, SPECIAL_PURPOSE_VERSION},
+        collections::Record,
+        datasources::DataSource,
+        typedef::TypeDef,
+        typevalue::TypeValue,
+    },
+    vm::{
+        DataSourceHandle, ExecutionContext, ExtDataSource,
+        OutputStreamQueue, PinnedSources, Trace, VmError, VmResult,
+    },
+};
+
+//------------ Runtime ------------------------------------------------------
+
+/// Compiled roto source code, from which filters can be taken by name.
+#[derive(Debug)]
+pub struct Runtime {
+    files: Vec<SourceFile>,
+    rotolos: Vec<Rotolo>,
+    // The names of the pipelines with the names of their stages.
+    pipelines: Vec<(ShortString, Vec<ShortString>)>,
+    sources: DataSourceRegistry,
+}
+
+impl Runtime {
+    /// Compile all the roto files in `files`.
+    pub fn from_files(
+        files: impl IntoIterator<Item = String>,
+    ) -> Result<Self, RotoReport> {
+        Self::from_files_with_sources(files, DataSourceRegistry::new())
+    }
+
+    /// Compile all the roto files in `files`, that can use the custom data
+    /// sources in `sources`.
+    pub fn from_files_with_sources(
+        files: impl IntoIterator<Item = String>,
+        sources: DataSourceRegistry,
+    ) -> Result<Self, RotoReport> {
+        Self::compile(pipeline::read_files(files)?, sources)
+    }
+
+    /// Compile a roto script.
+    pub fn from_string(
+        script: impl Into<String>,
+    ) -> Result<Self, RotoReport> {
+        Self::from_string_with_sources(script, DataSourceRegistry::new())
+    }
+
+    /// Compile a roto script, that can use the custom data sources in
+    /// `sources`.
+    pub fn from_string_with_sources(
+        script: impl Into<String>,
+        sources: DataSourceRegistry,
+    ) -> Result<Self, RotoReport> {
+        Self::compile(vec![SourceFile::new("script", script)], sources)
+    }
+
+    fn compile(
+        files: Vec<SourceFile>,
+        sources: DataSourceRegistry,
+    ) -> Result<Self, RotoReport> {
+        let trees = pipeline::parse(&files)?;
+        pipeline::typecheck_with_sources(&files, &trees, &sources)?;
+        let symbols =
+            pipeline::evaluate_with_sources(&files, &trees, &sources)?;
+        let rotolos = pipeline::compile(&files, &symbols, None)?;
+
+        let pipelines = trees
+            .iter()
+            .flat_map(|tree| &tree.expressions)
+            .filter_map(|expr| match expr {
+                RootExpr::Pipeline(p) => Some((
+                    p.ident.ident.clone(),
+                    p.stages.iter().map(|s| s.ident.clone()).collect(),
+                )),
+                _ => None,
+            })
+            .collect();
+
+        Ok(Self {
+            files,
+            rotolos,
+            pipelines,
+            sources,
+        })
+    }
+
+    /// Take the filter or filter-map with the name `name`. The custom data
+    /// sources it uses are bound to it.
+    pub fn filter(&self, name: &str) -> Result<Filter, CompileError> {
+        for rotolo in &self.rotolos {
+            if let Some(scope) = rotolo.find_scope(name) {
+                let filter = Filter::new(
+                    rotolo.retrieve_compiled_filter(scope)?,
+                    self.files.clone(),
+                );
+                for ds in filter.compiled.data_sources.iter() {
+                    if let Some(source) = self.sources.get(&ds.get_name()) {
+                        filter.bind_source(source)?;
+                    }
+                }
+                return Ok(filter);
+            }
+        }
+
+        Err(CompileError::from(format!(
+            "Can't find a filter or filter-map with name: {}",
+            name
+        )))
+    }
+
+    /// Take the pipeline with the name `name`.
+    pub fn pipeline(&self, name: &str) -> Result<Pipeline, CompileError> {
+        let (name, stages) = self
+            .pipelines
+            .iter()
+            .find(|(p, _)| p == name)
+            .ok_or_else(|| {
+                CompileError::from(format!(
+                    "Can't find a pipeline with name: {}",
+                    name
+                ))
+            })?;
+
+        let mut stages = stages
+            .iter()
+            .map(|stage| self.filter(stage))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // The stages share the data sources with the same name, so that a
+        // source is bound and updated once for the whole pipeline.
+        for i in 1..stages.len() {
+            let (earlier, later) = stages.split_at_mut(i);
+            let stage = &mut later[0];
+            let data_sources = stage
+                .compiled
+                .data_sources
+                .iter()
+                .map(|ds| {
+                    let first = earlier
+                        .iter()
+                        .flat_map(|s| s.compiled.data_sources.iter())
+                        .find(|d| d.get_name() == ds.get_name());
+                    ds.sharing(first.unwrap_or(ds))
+                })
+                .collect();
+            stage.compiled.data_sources = data_sources;
+        }
+
+        // The pipeline only has the route to pass on, so it can't create
+        // the tx value of a stage that has separate rx and tx types.
+        if let Some(stage) =
+            stages.iter().find(|s| s.compiled.get_tx_type().is_some())
+        {
+            return Err(CompileError::from(format!(
+                "The {} in pipeline {} should declare its payload with \
+                 `rx_tx` instead of `rx` and `tx`",
+                stage.get_name(),
+                name
+            )));
+        }
+
+        Ok(Pipeline {
+            name: name.clone(),
+            stages,
+        })
+    }
+}
+
+//------------ Filter -------------------------------------------------------
+
+/// One filter(-map) that is ready to be run, once its data sources and its
+/// context are bound.
+///
+/// A Filter is used by one thread, for multiple threads take the shareable
+/// [`CompiledFilter`] out of it with [`Filter::compiled`].
+pub struct Filter {
+    compiled: CompiledFilter,
+    files: Vec<SourceFile>,
+    context: Option<RouteContext>,
+    exec_ctx: Option<ExecutionContext<RouteContext>>,
+}
+
+impl Filter {
+    fn new(compiled: CompiledFilter, files: Vec<SourceFile>) -> Self {
+        Self {
+            compiled,
+            files,
+            context: None,
+            exec_ctx: None,
+        }
+    }
+
+    pub fn get_name(&self) -> &Scope {
+        self.compiled.get_filter_map_name()
+    }
+
+    /// The compiled filter(-map), with the data sources and arguments that
+    /// were bound to this Filter.
+    pub fn compiled(&self) -> &CompiledFilter {
+        &self.compiled
+    }
+
+    /// (Re)place the source of the data source with the same name as
+    /// `source`.
+    pub fn bind_source(
+        &self,
+        source: DataSource,
+    ) -> Result<(), CompileError> {
+        self.compiled.set_source(source)
+    }
+
+    /// (Re)place the source of the table `name` with `records`.
+    pub fn bind_table(
+        &self,
+        name: &str,
+        records: Vec<Record>,
+    ) -> Result<(), CompileError> {
+        let source = DataSource::table_from_records(name, records)
+            .map_err(|e| CompileError::from(e.to_string()))?;
+        self.bind_source(source)
+    }
+
+    /// (Re)place the source of the table `name` with the records in the
+    /// file at `path`, see [`table_from_file`] for the formats.
+    pub fn bind_table_from_file(
+        &self,
+        name: &str,
+        path: impl AsRef<Path>,
+    ) -> Result<(), CompileError> {
+        let ty = table_type(&self.compiled.data_sources, name)?;
+        self.bind_source(table_from_file(name, &ty, path.as_ref())?)
+    }
+
+    /// A handle to update the data source `name` while the filter runs,
+    /// see [`DataSourceHandle`].
+    pub fn data_source(
+        &self,
+        name: &str,
+    ) -> Result<DataSourceHandle, CompileError> {
+        self.compiled.data_source(name)
+    }
+
+    /// Set the values for the `with` arguments of the filter(-map).
+    pub fn bind_arguments(
+        &mut self,
+        args: impl IntoRotoArgs,
+    ) -> Result<(), CompileError> {
+        let args = self.compiled.compile_args(args)?;
+        self.compiled = self.compiled.clone().with_arguments(args);
+        self.exec_ctx = None;
+        Ok(())
+    }
+
+    /// Record a trace of the terms and actions of every run, that is
+    /// returned in the `trace` field of the Verdict. Off by default.
+    pub fn set_trace(&mut self, trace: bool) {
+        self.compiled = self.compiled.clone().with_trace(trace);
+        self.exec_ctx = None;
+    }
+
+    /// Set the context the routes are run in.
+    pub fn bind_context(&mut self, context: RouteContext) {
+        if let Some(exec_ctx) = &mut self.exec_ctx {
+            exec_ctx.update_context(context.clone());
+        }
+        self.context = Some(context);
+    }
+
+    /// Run the filter(-map) for one route.
+    pub fn run(&mut self, rx: impl RotoType) -> Result<Verdict, VmError> {
+        self.exec_ctx()?.exec(rx, None::<TypeValue>).map(Verdict::from)
+    }
+
+    /// Run the filter(-map) for one route, with the data sources in
+    /// `pinned` as they were pinned.
+    fn run_pinned(
+        &mut self,
+        rx: impl RotoType,
+        pinned: &PinnedSources,
+    ) -> Result<Verdict, VmError> {
+        self.exec_ctx()?.exec_pinned(rx, pinned).map(Verdict::from)
+    }
+
+    /// Run the filter(-map) for one route, with a tx value.
+    pub fn run_with_tx(
+        &mut self,
+        rx: impl RotoType,
+        tx: impl RotoType,
+    ) -> Result<Verdict, VmError> {
+        self.exec_ctx()?.exec(rx, Some(tx)).map(Verdict::from)
+    }
+
+    /// Create a report for an error returned by `run`, that points at the
+    /// term or action in the source code that failed.
+    pub fn report(&self, error: VmError) -> RotoReport {
+        RotoReport::runtime(&self.files, error)
+    }
+
+    fn exec_ctx(
+        &mut self,
+    ) -> Result<&mut ExecutionContext<RouteContext>, VmError> {
+        if self.exec_ctx.is_none() {
+            let context =
+                self.context.clone().ok_or(VmError::IncompleteContext)?;
+            self.exec_ctx =
+                Some(self.compiled.new_execution_context(context)?);
+        }
+
+        self.exec_ctx.as_mut().ok_or(VmError::IncompleteContext)
+    }
+}
+
+//------------ Pipeline -----------------------------------------------------
+
+/// A chain of filter(-map)s, declared with `pipeline` in the source code,
+/// that runs as one unit.
+///
+/// A route is run through the stages in order. The rx of a stage, as it was
+/// modified by the stage, is the rx of the next stage, so stages declare
+/// their payload with `rx_tx`. A reject stops the chain, otherwise the
+/// verdict of the last stage is the verdict of the pipeline.
+pub struct Pipeline {
+    name: ShortString,
+    stages: Vec<Filter>,
+}
+
+impl Pipeline {
+    pub fn get_name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    pub fn stages(&self) -> &[Filter] {
+        &self.stages
+    }
+
+    /// (Re)place the source of the data source with the same name as
+    /// `source`, for all the stages that use it.
+    pub fn bind_source(
+        &self,
+        source: DataSource,
+    ) -> Result<(), CompileError> {
+        let name = source.get_name();
+
+        // The stages share their data sources, so binding the source to
+        // the first stage that uses it binds it to all of them.
+        let data_sources = self
+            .stages
+            .iter()
+            .map(|stage| &stage.compiled.data_sources)
+            .find(|data_sources| {
+                data_sources.iter().any(|ds| ds.get_name() == name)
+            })
+            .ok_or_else(|| {
+                CompileError::from(format!(
+                    "No stage of pipeline {} uses a data source with name: \
+                     {}",
+                    self.name, name
+                ))
+            })?;
+        set_data_source(data_sources, &source)
+    }
+
+    /// (Re)place the source of the table `name` with `records`, for all the
+    /// stages that use it.
+    pub fn bind_table(
+        &self,
+        name: &str,
+        records: Vec<Record>,
+    ) -> Result<(), CompileError> {
+        let source = DataSource::table_from_records(name, records)
+            .map_err(|e| CompileError::from(e.to_string()))?;
+        self.bind_source(source)
+    }
+
+    /// (Re)place the source of the table `name` with the records in the
+    /// file at `path`, for all the stages that use it. See
+    /// [`table_from_file`] for the formats.
+    pub fn bind_table_from_file(
+        &self,
+        name: &str,
+        path: impl AsRef<Path>,
+    ) -> Result<(), CompileError> {
+        let ty = self
+            .stages
+            .iter()
+            .find_map(|s| table_type(&s.compiled.data_sources, name).ok())
+            .ok_or_else(|| {
+                CompileError::from(format!(
+                    "No stage of pipeline {} uses a table with name: {}",
+                    self.name, name
+                ))
+            })?;
+        self.bind_source(table_from_file(name, &ty, path.as_ref())?)
+    }
+
+    /// A handle to update the data source `name` while the pipeline runs,
+    /// for all the stages that use it, see [`DataSourceHandle`].
+    pub fn data_source(
+        &self,
+        name: &str,
+    ) -> Result<DataSourceHandle, CompileError> {
+        DataSourceHandle::new(
+            name,
+            self.stages.iter().map(|s| &s.compiled.data_sources),
+        )
+        .ok_or_else(|| {
+            CompileError::from(format!(
+                "No stage of pipeline {} uses a data source with name: {}",
+                self.name, name
+            ))
+        })
+    }
+
+    /// Set the values for the `with` arguments of the stage `stage`.
+    pub fn bind_arguments(
+        &mut self,
+        stage: &str,
+        args: impl IntoRotoArgs,
+    ) -> Result<(), CompileError> {
+        let name = &self.name;
+        self.stages
+            .iter_mut()
+            .find(|f| f.get_name().as_str() == stage)
+            .ok_or_else(|| {
+                CompileError::from(format!(
+                    "Pipeline {} has no stage with name: {}",
+                    name, stage
+                ))
+            })?
+            .bind_arguments(args)
+    }
+
+    /// Set the context the routes are run in, for all the stages.
+    pub fn bind_context(&mut self, context: RouteContext) {
+        for stage in &mut self.stages {
+            stage.bind_context(context.clone());
+        }
+    }
+
+    /// Run the route through the stages of the pipeline. All the stages
+    /// run with the data sources as they were when the route entered the
+    /// pipeline, also if they are updated meanwhile.
+    pub fn run(&mut self, rx: impl RotoType) -> Result<Verdict, VmError> {
+        let mut rx: TypeValue = rx.into();
+        let mut output_stream_queue = OutputStreamQueue::new();
+        let mut verdict = None;
+
+        let mut pinned = PinnedSources::default();
+        for stage in &self.stages {
+            pinned.pin(&stage.compiled.data_sources);
+        }
+
+        for stage in &mut self.stages {
+            let mut res = stage.run_pinned(rx, &pinned)?;
+            for msg in std::mem::take(&mut res.output_stream_queue) {
+                output_stream_queue.push(msg);
+            }
+
+            if res.is_rejected() {
+                verdict = Some(res);
+                break;
+            }
+
+            rx = res.rx.clone();
+            verdict = Some(res);
+        }
+
+        // The parser doesn't allow empty pipelines, so there's always a
+        // verdict.
+        let mut verdict = verdict.ok_or(VmError::UnexpectedTermination)?;
+        verdict.output_stream_queue = output_stream_queue;
+        Ok(verdict)
+    }
+
+    /// Create a report for an error returned by `run`, that points at the
+    /// term or action in the source code that failed.
+    pub fn report(&self, error: VmError) -> RotoReport {
+        match self.stages.first() {
+            Some(stage) => stage.report(error),
+            None => RotoReport::runtime(&[], error),
+        }
+    }
+}
+
+//------------ Table files --------------------------------------------------
+
+/// The record type that is declared for the table `name`.
+fn table_type(
+    data_sources: &[ExtDataSource],
+    name: &str,
+) -> Result<TypeDef, CompileError> {
+    data_sources
+        .iter()
+        .find(|ds| ds.get_name().as_str() == name)
+        .and_then(|ds| match ds.get_type() {
+            TypeDef::Table((rec, _)) => Some(rec.as_ref().clone()),
+            _ => None,
+        })
+        .ok_or_else(|| {
+            CompileError::from(format!(
+                "Cannot find table with name: {} in source code",
+                name
+            ))
+        })
+}
+
+/// Read the table `name`, with records of type `ty`, from the file at
+/// `path`.
+///
+/// The format of the file is taken from its extension: `.csv` for CSV,
+/// `.json` or `.jsonl` for JSON lines. Errors in the file are reported with
+/// their row and column.
+pub fn table_from_file(
+    name: &str,
+    ty: &TypeDef,
+    path: &Path,
+) -> Result<DataSource, CompileError> {
+    let file = std::fs::File::open(path).map_err(|e| {
+        CompileError::from(format!("Cannot open {}: {}", path.display(), e))
+    })?;
+    let reader = std::io::BufReader::new(file);
+
+    let source = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("csv") => DataSource::table_from_csv(name, ty, reader),
+        Some("json" | "jsonl") => {
+            DataSource::table_from_json_lines(name, ty, reader)
+        }
+        _ => {
+            return Err(CompileError::from(format!(
+                "Cannot load table {} from {}, expected a .csv, .json or \
+                .jsonl file",
+                name,
+                path.display()
+            )))
+        }
+    };
+
+    source.map_err(|e| {
+        CompileError::from(format!(
+            "Cannot load table {} from {}: {}",
+            name,
+            path.display(),
+            e
+        ))
+    })
+}
+
+//------------ Verdict ------------------------------------------------------
+
+/// The outcome of running a filter(-map) for one route.
+#[derive(Debug, Clone)]
+pub struct Verdict {
+    pub accept_reject: AcceptReject,
+    /// The reason given with the verdict, e.g. `return reject with
+    /// "rpki-invalid";`.
+    pub reason: Option<TypeValue>,
+    pub rx: TypeValue,
+    pub tx: Option<TypeValue>,
+    pub output_stream_queue: OutputStreamQueue,
+    /// The terms and actions of the run, if tracing is turned on with
+    /// [`Filter::set_trace`].
+    pub trace: Option<Trace>,
+}
+
+impl Verdict {
+    pub fn is_accepted(&self) -> bool {
+        self.accept_reject == AcceptReject::Accept
+    }
+
+    pub fn is_rejected(&self) -> bool {
+        self.accept_reject == AcceptReject::Reject
+    }
+
+    /// The route should be handed over to the next filter.
+    pub fn is_continued(&self) -> bool {
+        self.accept_reject == AcceptReject::Continue
+    }
+
+    /// The version of the lists of special-purpose prefixes and ASNs that
+    /// the `is_bogon`, `is_martian` etc. methods used for this verdict, see
+    /// [`SPECIAL_PURPOSE_VERSION`].
+    pub fn special_purpose_version(&self) -> u32 {
+        SPECIAL_PURPOSE_VERSION
+    }
+}
+
+impl From<VmResult> for Verdict {
+    fn from(res: VmResult) -> Self {
+        Self {
+            accept_reject: res.accept_reject,
+            reason: res.reason,
+            rx: res.rx,
+            tx: res.tx,
+            output_stream_queue: res.output_stream_queue,
+            trace: res.trace,
+        }
+    }
+}
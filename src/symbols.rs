@@ -12,11 +12,12 @@ use crate::{
     ast::{AcceptReject, CompareOp, FilterType, Identifier, ShortString},
     blocks::Scope,
     compiler::compile::CompileError,
+    parser::span::Span,
     traits::{RotoType, Token},
     types::{
         collections::{ElementTypeValue, Record},
         enum_types::GlobalEnumTypeDef,
-        typedef::TypeDef,
+        typedef::{CustomTypeDef, TypeDef},
         typevalue::TypeValue,
     },
     vm::VmError,
@@ -32,7 +33,10 @@ pub(crate) struct Symbol {
     pub ty: TypeDef,
     pub args: Vec<Symbol>,
     pub value: TypeValue,
-    pub token: Token, // location: Location,
+    pub token: Token,
+    // The location in the source code this symbol was created from, if
+    // known. Only used to attach source locations to execution traces.
+    pub span: Option<Span>,
 }
 
 impl Symbol {
@@ -56,6 +60,7 @@ impl Symbol {
             self.ty,
             TypeDef::Rib(_)
                 | TypeDef::Table(_)
+                | TypeDef::Custom(_)
                 | TypeDef::List(_)
                 | TypeDef::Record(_)
                 | TypeDef::PrefixRoute
@@ -256,6 +261,7 @@ impl Symbol {
             args: vec![],
             value: TypeValue::Unknown,
             token,
+            span: None,
         }
     }
 
@@ -273,6 +279,7 @@ impl Symbol {
             args,
             value: TypeValue::Unknown,
             token,
+            span: None,
         }
     }
 
@@ -290,9 +297,15 @@ impl Symbol {
             args,
             value,
             token,
+            span: None,
         }
     }
 
+    pub fn with_span(mut self, span: Option<Span>) -> Self {
+        self.span = span;
+        self
+    }
+
     /// Return the type of:
     /// - this symbol if it represents a Record OR
     /// - this symbol if it's a leaf node (args are empty) OR
@@ -565,6 +578,9 @@ pub struct SymbolTable {
     /// The action that will be activated when all of the match_actions are
     /// processed and no early return has been issued
     default_action: crate::ast::AcceptReject,
+    /// The reason that goes with the default action, `TypeValue::Unknown`
+    /// if there is none.
+    default_reason: TypeValue,
 }
 
 /// The global symbol table.
@@ -596,6 +612,34 @@ impl GlobalSymbolTable {
     pub fn new() -> Self {
         GlobalSymbolTable(Rc::new(RefCell::new(HashMap::new())))
     }
+
+    /// Add the custom data sources `sources` to the global scope, so that
+    /// they can be used like the ribs that are declared in the source code.
+    pub(crate) fn add_custom_sources(
+        &self,
+        sources: &[CustomTypeDef],
+    ) -> Result<(), CompileError> {
+        let mut symbols = self.borrow_mut();
+        let global_table =
+            symbols.entry(Scope::Global).or_insert_with(|| {
+                let mut global_table = SymbolTable::new(&Scope::Global);
+                global_table.create_global_methods();
+                global_table
+            });
+
+        for (name, methods) in sources {
+            global_table.add_variable(
+                name.clone(),
+                None,
+                SymbolKind::Rib,
+                TypeDef::Custom((name.clone(), methods.clone())),
+                vec![],
+                TypeValue::Unknown,
+            )?;
+        }
+
+        Ok(())
+    }
 }
 
 impl Clone for GlobalSymbolTable {
@@ -636,6 +680,7 @@ impl SymbolTable {
             action_sections: HashMap::new(),
             match_action_sections: vec![],
             default_action: crate::ast::AcceptReject::Accept,
+            default_reason: TypeValue::Unknown,
         }
     }
 
@@ -747,6 +792,7 @@ impl SymbolTable {
                 args,
                 value,
                 token,
+                span: None,
             },
         );
         Ok(())
@@ -814,6 +860,7 @@ impl SymbolTable {
                         args,
                         value,
                         token,
+                        span: None,
                     },
                 );
             }
@@ -839,6 +886,7 @@ impl SymbolTable {
                 args: vec![child_symbol],
                 value: TypeValue::Unknown,
                 token: Token::TermSection(term_section_index),
+                span: None,
             });
         } else {
             let child_args = &mut self
@@ -1032,14 +1080,20 @@ impl SymbolTable {
     pub(crate) fn set_default_action(
         &mut self,
         default_action: AcceptReject,
+        default_reason: TypeValue,
     ) {
         self.default_action = default_action;
+        self.default_reason = default_reason;
     }
 
     pub(crate) fn get_default_action(&self) -> AcceptReject {
         self.default_action
     }
 
+    pub(crate) fn get_default_reason(&self) -> &TypeValue {
+        &self.default_reason
+    }
+
     pub(crate) fn get_match_action_sections(&self) -> Vec<&MatchAction> {
         self.match_action_sections.iter().collect::<Vec<_>>()
     }
@@ -1060,9 +1114,14 @@ impl SymbolTable {
             Token::Rib(_) => {
                 r.get_kind_type_and_token().map(|ktt| (ktt.1, ktt.2))
             }
-            Token::Table(_) => {
-                Ok((TypeDef::Table(Box::new(r.ty.clone())), r.token.clone()))
-            }
+            // The key of a table is stored as its only argument.
+            Token::Table(_) => Ok((
+                TypeDef::Table((
+                    Box::new(r.ty.clone()),
+                    r.args.first().map(|key| key.name.clone()),
+                )),
+                r.token.clone(),
+            )),
             _ => Err(CompileError::new(format!(
                 "No data source named '{}' found.",
                 name
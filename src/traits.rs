@@ -1,18 +1,21 @@
 // =========== RotoFilter trait ============================================
 
+use std::sync::Arc;
+
+use inetnum::addr::Prefix;
 use serde::Serialize;
 
 use crate::{
     ast::ShortString,
     compiler::compile::CompileError,
     types::{
-        collections::Record,
-        datasources::DataSourceMethodValue,
+        datasources::{DataSourceMethod, DataSourceMethodValue},
         enum_types::GlobalEnumTypeDef,
         typedef::{MethodProps, TypeDef},
         typevalue::TypeValue,
     },
-    vm::{StackValue, VmError, FieldIndex},
+    unstable::StackValue,
+    vm::{VmError, FieldIndex},
 };
 
 #[derive(Debug, Clone, Eq, PartialEq, PartialOrd, Ord, Hash, Serialize)]
@@ -202,7 +205,9 @@ pub trait RotoRib: Send + Sync {
         res_type: TypeDef,
     ) -> Result<DataSourceMethodValue, VmError>;
 
-    fn get_by_key<'a>(&'a self, key: &str) -> Option<&'a Record>;
+    /// The record for the key `key`, e.g. for a RIB the record stored for
+    /// exactly the prefix in `key`.
+    fn get_by_key(&self, key: &str) -> Option<TypeValue>;
 
     fn len(&self) -> usize;
 
@@ -211,4 +216,105 @@ pub trait RotoRib: Send + Sync {
     fn get_name(&self) -> ShortString;
 
     fn get_type(&self) -> TypeDef;
+
+    /// A copy of this rib with `records` as the records for `prefix`, or
+    /// without `prefix` if `records` is empty. This is how a rib is updated
+    /// through a [`DataSourceHandle`](crate::vm::DataSourceHandle), ribs
+    /// that don't support it return an error.
+    fn with_prefix_records(
+        &self,
+        _prefix: Prefix,
+        _records: Vec<TypeValue>,
+    ) -> Result<Arc<dyn RotoRib>, VmError> {
+        Err(VmError::InvalidWrite)
+    }
+}
+
+/// A data source that is implemented outside of roto, e.g. a bloom filter
+/// of bogon prefixes or an as-set derived from the IRR.
+///
+/// The source declares its methods with their signatures, so that calls to
+/// them in the source code, e.g. `bogons.contains(route.prefix)`, are
+/// checked by the type checker and the compiler like the methods of a rib
+/// or a table. A source is made available to the source code under a name
+/// with a [`DataSourceRegistry`](crate::pipeline::DataSourceRegistry).
+pub trait RotoDataSource: Send + Sync {
+    /// The methods of the source. The VM calls a method by its position in
+    /// this list, so it should always return the same list.
+    fn methods(&self) -> Vec<DataSourceMethod>;
+
+    /// Execute the method at position `method` in [`Self::methods`], with
+    /// arguments of the types it declares. The returned value should have
+    /// the return type it declares.
+    fn exec_method(
+        &self,
+        method: usize,
+        args: &[&TypeValue],
+    ) -> Result<TypeValue, VmError>;
+}
+
+/// A set of values for the `with` arguments of a filter-map, e.g. a struct
+/// with a field for every argument.
+///
+/// The values are converted into the types of the arguments in the source
+/// code when they're compiled with `Rotolo::compile_args` or
+/// `CompiledFilter::compile_args`, that report every missing or mistyped
+/// argument at once. The `impl_into_roto_args!` macro implements this trait
+/// for a struct.
+pub trait IntoRotoArgs {
+    /// The names of the arguments with their values.
+    fn into_roto_args(self) -> Vec<(&'static str, TypeValue)>;
+}
+
+impl IntoRotoArgs for Vec<(&'static str, TypeValue)> {
+    fn into_roto_args(self) -> Vec<(&'static str, TypeValue)> {
+        self
+    }
+}
+
+/// Implement `IntoRotoArgs` for a struct, where the names of the listed
+/// fields are the names of the arguments in the filter-map. An argument
+/// with a name that isn't a Rust identifier, e.g. one with a hyphen, is
+/// given as `field => "roto-name"`.
+///
+/// ```
+/// use inetnum::asn::Asn;
+///
+/// struct MyArgs {
+///     my_asn: Asn,
+///     extra_asn: u32,
+/// }
+///
+/// roto::impl_into_roto_args!(MyArgs { my_asn, extra_asn => "extra-asn" });
+/// ```
+#[macro_export]
+macro_rules! impl_into_roto_args {
+    (
+        $ty: ty { $( $field: ident $( => $name: literal )? ),* $(,)? }
+    ) => {
+        impl $crate::traits::IntoRotoArgs for $ty {
+            fn into_roto_args(
+                self,
+            ) -> Vec<(&'static str, $crate::types::typevalue::TypeValue)> {
+                vec![$(
+                    (
+                        $crate::roto_arg_name!($field $( => $name )?),
+                        self.$field.into(),
+                    )
+                ),*]
+            }
+        }
+    };
+}
+
+/// The name of an argument in `impl_into_roto_args!`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! roto_arg_name {
+    ( $field: ident ) => {
+        stringify!($field)
+    };
+    ( $field: ident => $name: literal ) => {
+        $name
+    };
 }
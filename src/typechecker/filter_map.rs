@@ -4,7 +4,11 @@ use crate::{
     typechecker::error,
 };
 
-use super::{scope::Scope, types::Type, TypeChecker, TypeResult};
+use super::{
+    scope::Scope,
+    types::{Primitive, Type},
+    TypeChecker, TypeResult,
+};
 
 impl TypeChecker<'_> {
     pub fn filter_map(
@@ -53,6 +57,69 @@ impl TypeChecker<'_> {
         })
     }
 
+    /// Check that every stage of a pipeline is a filter-map or filter, and
+    /// that the rx type of every stage is the output type of the stage
+    /// before it.
+    pub fn pipeline(
+        &mut self,
+        filter_maps: &[&ast::FilterMap],
+        pipeline: &ast::Pipeline,
+    ) -> TypeResult<()> {
+        if let Some(f) = filter_maps
+            .iter()
+            .find(|f| f.ident.ident == pipeline.ident.ident)
+        {
+            return Err(error::simple(
+                &format!(
+                    "pipeline `{}` has the same name as a {}",
+                    pipeline.ident.ident,
+                    if f.ty.is_filter() { "filter" } else { "filter-map" }
+                ),
+                "declared multiple times",
+                pipeline.ident.span,
+            ));
+        }
+
+        let mut output: Option<&Spanned<ast::TypeIdentifier>> = None;
+        for stage in &pipeline.stages {
+            let Some(f) =
+                filter_maps.iter().find(|f| f.ident.ident == stage.ident)
+            else {
+                return Err(error::simple(
+                    &format!(
+                        "`{}` is not a filter-map or filter",
+                        stage.ident
+                    ),
+                    "not found",
+                    stage.span,
+                ));
+            };
+
+            let (rx, tx) = match &f.body.define.body.rx_tx_type {
+                ast::RxTxType::RxOnly(rx)
+                | ast::RxTxType::PassThrough(rx) => (&rx.ty, &rx.ty),
+                ast::RxTxType::Split(rx, tx) => (&rx.ty, &tx.ty),
+            };
+
+            if let Some(output) = output {
+                if output.ident != rx.ident {
+                    return Err(error::simple(
+                        &format!(
+                            "`{}` takes `{}`, but the stage before it \
+                            produces `{}`",
+                            stage.ident, rx.ident, output.ident
+                        ),
+                        &format!("expected `{}`", output.ident),
+                        stage.span,
+                    ));
+                }
+            }
+            output = Some(tx);
+        }
+
+        Ok(())
+    }
+
     fn define_section(
         &mut self,
         scope: &mut Scope,
@@ -258,10 +325,14 @@ impl TypeChecker<'_> {
             body:
                 ast::ApplyBody {
                     scopes,
-                    accept_reject: _,
+                    accept_reject,
                 },
         } = apply_section;
 
+        if let Some(ret) = accept_reject {
+            self.return_expr(scope, ret)?;
+        }
+
         for ast::ApplyScope {
             scope: apply_scope,
             match_action,
@@ -286,8 +357,8 @@ impl TypeChecker<'_> {
                                     "The grammar should have forbidden this."
                                 )
                             }
-                            (None, Some(_)) => {
-                                // do nothing
+                            (None, Some(ret)) => {
+                                self.return_expr(scope, ret)?;
                             }
                             (Some(expr), None) => {
                                 self.expr(scope, expr)?;
@@ -465,8 +536,8 @@ impl TypeChecker<'_> {
                                         }
                                     }
                                 }
-                                (None, Some(_accept_reject)) => {
-                                    // always ok (for now)
+                                (None, Some(ret)) => {
+                                    self.return_expr(&inner_scope, ret)?;
                                 }
                             }
                         }
@@ -487,6 +558,59 @@ impl TypeChecker<'_> {
         Ok(())
     }
 
+    /// The reason of a verdict should be a constant: a string literal or
+    /// a global enum variant, since it's evaluated when the filter-map is
+    /// compiled.
+    fn return_expr(
+        &mut self,
+        scope: &Scope,
+        ret: &ast::ReturnExpr,
+    ) -> TypeResult<()> {
+        let Some(reason) = &ret.reason else {
+            return Ok(());
+        };
+
+        let ty = self.expr(scope, reason)?;
+        let ty = self.resolve_type(&ty);
+        let is_constant = match &reason.inner {
+            ast::ValueExpr::LiteralAccessExpr(expr) => {
+                expr.access_expr.is_empty()
+                    && matches!(
+                        expr.literal.inner,
+                        ast::LiteralExpr::StringLiteral(_)
+                    )
+            }
+            ast::ValueExpr::ComputeExpr(expr) => {
+                expr.access_expr.is_empty()
+                    && matches!(ty, Type::Enum(_, _))
+                    && expr
+                        .receiver
+                        .get_ident()
+                        .is_some_and(|ident| scope.is_global(ident))
+            }
+            _ => false,
+        };
+
+        if is_constant {
+            return Ok(());
+        }
+
+        match ty {
+            Type::Primitive(Primitive::String) | Type::Enum(_, _) => {
+                Err(error::simple(
+                    &format!("the reason for `{}` should be a string literal or an enum variant", ret.accept_reject),
+                    "not a constant",
+                    reason.span,
+                ))
+            }
+            ty => Err(error::simple(
+                &format!("the reason for `{}` should be a string or an enum, but got type `{ty}`", ret.accept_reject),
+                &format!("has type `{ty}`"),
+                reason.span,
+            )),
+        }
+    }
+
     fn with_clause(
         &mut self,
         scope: &mut Scope,
@@ -17,6 +17,7 @@
 use crate::{
     ast::{self, Identifier, ShortString, TypeIdentField, TypeIdentifier},
     parser::span::{Span, Spanned, WithSpan},
+    types::{datasources::builtin_record_type, typedef::CustomTypeDef},
 };
 use scope::Scope;
 use std::collections::{hash_map::Entry, HashMap};
@@ -46,19 +47,44 @@ pub struct TypeChecker<'methods> {
     methods: &'methods [Method],
     /// The list of built-in static methods.
     static_methods: &'methods [Method],
+    /// The variables in the global scope, with their types.
+    globals: Vec<(String, Type)>,
 }
 
 pub type TypeResult<T> = Result<T, TypeError>;
 
 pub fn typecheck(tree: &ast::SyntaxTree) -> TypeResult<()> {
-    let methods = types::methods();
+    typecheck_with_sources(tree, &[])
+}
+
+/// Type check `tree` with the custom data sources `sources` in the global
+/// scope, see [`RotoDataSource`](crate::traits::RotoDataSource).
+pub fn typecheck_with_sources(
+    tree: &ast::SyntaxTree,
+    sources: &[CustomTypeDef],
+) -> TypeResult<()> {
+    let mut methods = types::methods();
     let static_methods = types::static_methods();
+    let mut globals = types::globals();
+
+    for (name, source_methods) in sources {
+        // The registry rejects sources with methods that can't be used in
+        // roto. Such methods are left out here, so that a call to one is
+        // reported where it's made.
+        methods.extend(
+            source_methods
+                .iter()
+                .filter_map(|m| types::custom_method(name, m)),
+        );
+        globals.push((name.to_string(), Type::Custom(name.to_string())));
+    }
 
     let mut type_checker = TypeChecker {
         unionfind: UnionFind::new(),
         types: HashMap::new(),
         methods: &methods,
         static_methods: &static_methods,
+        globals,
     };
 
     type_checker.check_syntax_tree(tree)
@@ -93,7 +119,7 @@ impl<'methods> TypeChecker<'methods> {
 
         let mut root_scope = Scope::default();
 
-        for (v, t) in types::globals() {
+        for (v, t) in self.globals.clone() {
             root_scope.insert_var(
                 &Identifier {
                     ident: ShortString::from(&*v),
@@ -104,30 +130,46 @@ impl<'methods> TypeChecker<'methods> {
         }
 
         let mut filter_maps = Vec::new();
+        let mut pipelines = Vec::new();
         for expr in &tree.expressions {
             match expr {
                 // We'll do all filter-maps after all type declarations.
                 // This guarantees that all types have been declared once
                 // we get to the filter-maps.
-                ast::RootExpr::FilterMap(x) => filter_maps.push(x),
+                ast::RootExpr::FilterMap(x) => filter_maps.push(&**x),
+                // Pipelines refer to filter-maps, so they are checked
+                // after the filter-maps.
+                ast::RootExpr::Pipeline(x) => pipelines.push(x),
                 ast::RootExpr::Rib(ast::Rib {
                     ident,
                     contain_ty,
                     body,
                 }) => {
-                    let ty =
-                        create_contains_type(&mut types, contain_ty, body)?;
+                    let ty = match body {
+                        Some(body) => create_contains_type(
+                            &mut types, contain_ty, body,
+                        )?,
+                        None => builtin_contains_type(&types, contain_ty)?,
+                    };
                     root_scope.insert_var(ident, Type::Rib(Box::new(ty)))?;
                 }
                 ast::RootExpr::Table(ast::Table {
                     ident,
                     contain_ty,
+                    key,
                     body,
                 }) => {
-                    let ty =
-                        create_contains_type(&mut types, contain_ty, body)?;
-                    root_scope
-                        .insert_var(ident, Type::Table(Box::new(ty)))?;
+                    let ty = match body {
+                        Some(body) => create_contains_type(
+                            &mut types, contain_ty, body,
+                        )?,
+                        None => builtin_contains_type(&types, contain_ty)?,
+                    };
+                    let key_ty = table_key_type(&ty, key.as_ref())?;
+                    root_scope.insert_var(
+                        ident,
+                        Type::Table(Box::new(ty), Box::new(key_ty)),
+                    )?;
                 }
                 ast::RootExpr::OutputStream(ast::OutputStream {
                     ident,
@@ -183,10 +225,14 @@ impl<'methods> TypeChecker<'methods> {
             )
         })?;
 
-        for f in filter_maps {
+        for f in &filter_maps {
             self.filter_map(&root_scope, f)?;
         }
 
+        for p in pipelines {
+            self.pipeline(&filter_maps, p)?;
+        }
+
         Ok(())
     }
 
@@ -247,8 +293,11 @@ impl<'methods> TypeChecker<'methods> {
                 subs.insert(x, t);
                 true
             }
-            (Type::Table(a), Type::Table(b))
-            | (Type::OutputStream(a), Type::OutputStream(b))
+            (Type::Table(a, a_key), Type::Table(b, b_key)) => {
+                self.subtype_inner(&a, &b, subs)
+                    && self.subtype_inner(&a_key, &b_key, subs)
+            }
+            (Type::OutputStream(a), Type::OutputStream(b))
             | (Type::List(a), Type::List(b))
             | (Type::Rib(a), Type::Rib(b)) => {
                 self.subtype_inner(&a, &b, subs)
@@ -334,9 +383,10 @@ impl<'methods> TypeChecker<'methods> {
                 self.unionfind.set(b, a.clone());
                 a.clone()
             }
-            (Table(a), Table(b)) => {
-                Table(Box::new(self.unify_inner(&a, &b)?))
-            }
+            (Table(a, a_key), Table(b, b_key)) => Table(
+                Box::new(self.unify_inner(&a, &b)?),
+                Box::new(self.unify_inner(&a_key, &b_key)?),
+            ),
             (OutputStream(a), OutputStream(b)) => {
                 OutputStream(Box::new(self.unify_inner(&a, &b)?))
             }
@@ -510,7 +560,7 @@ impl<'methods> TypeChecker<'methods> {
                 // no need to recurse into them.
                 Ok(())
             }
-            Type::Table(t)
+            Type::Table(t, _)
             | Type::OutputStream(t)
             | Type::Rib(t)
             | Type::List(t) => self.visit(visited, t),
@@ -571,6 +621,54 @@ fn create_contains_type(
     Ok(ty)
 }
 
+/// The built-in type of a data source without a body, e.g. `Vrp` in
+/// `rib vrps contains Vrp` or `Aspa` in `table aspas contains Aspa`.
+fn builtin_contains_type(
+    types: &HashMap<String, MaybeDeclared>,
+    contain_ty: &Spanned<ast::TypeIdentifier>,
+) -> TypeResult<Type> {
+    let name = contain_ty.ident.as_str();
+    match types.get(name) {
+        Some(MaybeDeclared::Declared(ty, None))
+            if builtin_record_type(name).is_some() =>
+        {
+            Ok(ty.clone())
+        }
+        _ => Err(error::simple(
+            &format!("`{name}` is not a built-in type of a data source"),
+            "expected a built-in type, or a body with the fields",
+            contain_ty.span,
+        )),
+    }
+}
+
+/// The type of the key of a table. Tables without a key are looked up by
+/// their first field, which is an Asn.
+fn table_key_type(
+    ty: &Type,
+    key: Option<&Spanned<Identifier>>,
+) -> TypeResult<Type> {
+    let Some(key) = key else {
+        return Ok(Type::Primitive(types::Primitive::AsNumber));
+    };
+
+    if let Type::NamedRecord(record_name, fields) = ty {
+        if let Some((_, t)) =
+            fields.iter().find(|(s, _)| s == key.ident.as_str())
+        {
+            return Ok(t.clone());
+        }
+
+        return Err(error::simple(
+            &format!("record `{record_name}` does not have a field `{key}`"),
+            &format!("`{record_name}` does not have this field"),
+            key.span,
+        ));
+    }
+
+    Ok(Type::Primitive(types::Primitive::AsNumber))
+}
+
 fn evaluate_record_type(
     types: &mut HashMap<String, MaybeDeclared>,
     fields: &[ast::RibField],
@@ -41,6 +41,17 @@ impl<'a> Scope<'a> {
             .or_else(|e| self.parent.ok_or(e).and_then(|s| s.get_var(k)))
     }
 
+    /// Whether `k` is a variable of the outermost scope, i.e. a global,
+    /// that isn't shadowed by a variable of an inner scope.
+    pub fn is_global(&self, k: &Identifier) -> bool {
+        let name: &str = k.as_ref();
+        match (self.variables.contains_key(name), self.parent) {
+            (true, parent) => parent.is_none(),
+            (false, Some(parent)) => parent.is_global(k),
+            (false, None) => false,
+        }
+    }
+
     pub fn insert_var(
         &mut self,
         v: &Spanned<Identifier>,
@@ -109,6 +109,62 @@ fn table_contains_record() {
     assert!(typecheck(src).is_ok());
 }
 
+#[test]
+fn table_with_key() {
+    let src = "
+        table customers contains Customer key id {
+            asn: Asn,
+            id: U32
+        }
+
+        filter-map foo {
+            define {
+                rx id: U32;
+            }
+
+            term bla {
+                match {
+                    customers.contains(id);
+                    customers.find(id).asn == AS65534;
+                    customers.find_all(id).first().asn == AS65534;
+                    customers.version() == 1;
+                }
+            }
+        }
+    ";
+    typecheck(src).unwrap();
+
+    // The key has to be a field of the records.
+    let src = "
+        table customers contains Customer key name {
+            asn: Asn,
+            id: U32
+        }
+    ";
+    assert!(typecheck(src).is_err());
+
+    // Tables are looked up by their key.
+    let src = "
+        table customers contains Customer key id {
+            asn: Asn,
+            id: U32
+        }
+
+        filter-map foo {
+            define {
+                rx id: U32;
+            }
+
+            term bla {
+                match {
+                    customers.contains(AS65534);
+                }
+            }
+        }
+    ";
+    assert!(typecheck(src).is_err());
+}
+
 #[test]
 fn output_stream_contains_record() {
     let src = "
@@ -127,6 +183,151 @@ fn rib_contains_record() {
     assert!(typecheck(src).is_ok());
 }
 
+#[test]
+fn rib_methods() {
+    let src = "
+        rib rov contains RovEntry { prefix: Prefix, origin: Asn }
+
+        filter-map foo {
+            define {
+                rx p: Prefix;
+            }
+
+            term bla {
+                match {
+                    rov.contains(p);
+                    rov.get(p).origin == AS65534;
+                    rov.longest_match(p).origin == AS65534;
+                    rov.match(p).contains(rov.get(p));
+                    rov.more_specifics(p).contains(rov.get(p));
+                    rov.less_specifics(p).first().origin == AS65534;
+                }
+            }
+        }
+    ";
+    typecheck(src).unwrap();
+
+    let src = "
+        rib rov contains RovEntry { prefix: Prefix, origin: Asn }
+
+        filter-map foo {
+            define {
+                rx p: Prefix;
+            }
+
+            term bla {
+                match {
+                    rov.contains(AS65534);
+                }
+            }
+        }
+    ";
+    assert!(typecheck(src).is_err());
+}
+
+#[test]
+fn rib_vrps() {
+    let src = "
+        rib vrps contains Vrp
+
+        filter-map foo {
+            define {
+                rx r: Route;
+            }
+
+            term bla {
+                match {
+                    vrps.validate(r.prefix, r.as-path.origin()) == ROV_INVALID;
+                    vrps.get(r.prefix).max_length == r.prefix.len();
+                    vrps.version() == 1;
+                }
+            }
+        }
+    ";
+    typecheck(src).unwrap();
+
+    // Only the built-in VRP data source can validate routes.
+    let src = "
+        rib rov contains RovEntry { prefix: Prefix, origin: Asn }
+
+        filter-map foo {
+            define {
+                rx r: Route;
+            }
+
+            term bla {
+                match {
+                    rov.validate(r.prefix, r.as-path.origin()) == ROV_INVALID;
+                }
+            }
+        }
+    ";
+    assert!(typecheck(src).is_err());
+
+    // A rib without a body has to contain a built-in type.
+    let src = "
+        rib rov contains RovEntry
+    ";
+    assert!(typecheck(src).is_err());
+}
+
+#[test]
+fn table_aspas() {
+    let src = "
+        table aspas contains Aspa
+
+        filter-map foo {
+            define {
+                rx r: Route;
+            }
+
+            term bla {
+                match {
+                    aspas.validate(r.as-path, ROLE_PROVIDER) == ASPA_INVALID;
+                    aspas.find(r.as-path.origin()).providers.contains(AS1);
+                }
+            }
+        }
+    ";
+    typecheck(src).unwrap();
+
+    // The role is required.
+    let src = "
+        table aspas contains Aspa
+
+        filter-map foo {
+            define {
+                rx r: Route;
+            }
+
+            term bla {
+                match {
+                    aspas.validate(r.as-path) == ASPA_INVALID;
+                }
+            }
+        }
+    ";
+    assert!(typecheck(src).is_err());
+
+    // The status of ASPA is not the status of ROV.
+    let src = "
+        table aspas contains Aspa
+
+        filter-map foo {
+            define {
+                rx r: Route;
+            }
+
+            term bla {
+                match {
+                    aspas.validate(r.as-path, ROLE_CUSTOMER) == ROV_INVALID;
+                }
+            }
+        }
+    ";
+    assert!(typecheck(src).is_err());
+}
+
 #[test]
 fn filter_map() {
     let src = r#"
@@ -551,3 +752,77 @@ fn record_inference() {
     ";
     assert!(typecheck(src).is_err());
 }
+
+#[test]
+fn return_with_reason() {
+    let src = r#"
+        filter-map foo {
+            define {
+                rx r: U32;
+            }
+
+            term bla {
+                match {
+                    r == 8;
+                }
+            }
+
+            apply {
+                filter match bla matching {
+                    return reject with "rpki-invalid";
+                };
+                continue;
+            }
+        }
+    "#;
+    typecheck(src).unwrap();
+
+    let src = r#"
+        filter-map foo {
+            define {
+                rx r: U32;
+            }
+
+            apply {
+                return reject with 8;
+            }
+        }
+    "#;
+    assert!(typecheck(src).is_err());
+}
+
+#[test]
+fn pipeline() {
+    let filter_maps = r#"
+        type A { a: U32 }
+        type B { b: U32 }
+
+        filter-map first {
+            define {
+                rx r: A;
+                tx t: B;
+            }
+        }
+
+        filter-map second {
+            define {
+                rx_tx r: B;
+            }
+        }
+    "#;
+
+    let src = format!("{filter_maps} pipeline both {{ first; second; }}");
+    typecheck(&src).unwrap();
+
+    // The stages have to exist.
+    let src = format!("{filter_maps} pipeline both {{ first; third; }}");
+    assert!(typecheck(&src).is_err());
+
+    // The tx of `second` is not the rx of `first`.
+    let src = format!("{filter_maps} pipeline both {{ second; first; }}");
+    assert!(typecheck(&src).is_err());
+
+    // Pipelines and filter-maps share their names.
+    let src = format!("{filter_maps} pipeline first {{ first; }}");
+    assert!(typecheck(&src).is_err());
+}
@@ -1,5 +1,7 @@
 use std::fmt::{Debug, Display};
 
+use crate::types::{datasources::DataSourceMethod, typedef::TypeDef};
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Type {
     Var(usize),
@@ -8,9 +10,12 @@ pub enum Type {
     RecordVar(usize, Vec<(String, Type)>),
     Primitive(Primitive),
     List(Box<Type>),
-    Table(Box<Type>),
+    /// A table with the type of its records and the type of its key.
+    Table(Box<Type>, Box<Type>),
     OutputStream(Box<Type>),
     Rib(Box<Type>),
+    /// A data source that is implemented outside of roto, by its name.
+    Custom(String),
     Record(Vec<(String, Type)>),
     NamedRecord(String, Vec<(String, Type)>),
     Enum(String, Vec<(String, Option<Type>)>),
@@ -79,9 +84,10 @@ impl Display for Type {
             ),
             Type::Primitive(p) => write!(f, "{p}"),
             Type::List(t) => write!(f, "List<{t}>"),
-            Type::Table(t) => write!(f, "Table<{t}>"),
+            Type::Table(t, _) => write!(f, "Table<{t}>"),
             Type::OutputStream(t) => write!(f, "OutputStream<{t}>"),
             Type::Rib(t) => write!(f, "Rib<{t}>"),
+            Type::Custom(x) => write!(f, "{x}"),
             Type::NamedRecord(x, _) => write!(f, "{x}"),
             Type::Enum(x, _) => write!(f, "{x}"),
             Type::Term(args) => write!(f, "Term({})", fmt_args(args)),
@@ -101,6 +107,36 @@ pub struct Arrow {
 }
 
 impl Type {
+    /// The type of the values of `ty`, for the types that can be used in
+    /// the signature of the methods of a custom data source.
+    pub fn from_typedef(ty: &TypeDef) -> Option<Self> {
+        use self::Primitive::*;
+
+        Some(match ty {
+            TypeDef::U32 => U32.into(),
+            TypeDef::U16 => U16.into(),
+            TypeDef::U8 => U8.into(),
+            TypeDef::Bool => Bool.into(),
+            TypeDef::StringLiteral => String.into(),
+            TypeDef::Prefix => Prefix.into(),
+            TypeDef::PrefixLength => PrefixLength.into(),
+            TypeDef::IpAddr => IpAddress.into(),
+            TypeDef::Asn => AsNumber.into(),
+            TypeDef::AsPath => AsPath.into(),
+            TypeDef::Community => Community.into(),
+            TypeDef::List(t) => Type::List(Box::new(Self::from_typedef(t)?)),
+            TypeDef::Record(fields) => Type::Record(
+                fields
+                    .iter()
+                    .map(|(n, t)| {
+                        Self::from_typedef(t).map(|t| (n.to_string(), t))
+                    })
+                    .collect::<Option<_>>()?,
+            ),
+            _ => return None,
+        })
+    }
+
     pub fn substitute(&self, from: &Self, to: &Self) -> Self {
         if self == from {
             return to.clone();
@@ -110,7 +146,7 @@ impl Type {
 
         match self {
             Type::List(x) => Type::List(Box::new(f(x))),
-            Type::Table(x) => Type::Table(Box::new(f(x))),
+            Type::Table(x, k) => Type::Table(Box::new(f(x)), Box::new(f(k))),
             Type::OutputStream(x) => Type::OutputStream(Box::new(f(x))),
             Type::Rib(x) => Type::Rib(Box::new(f(x))),
             Type::RecordVar(x, fields) => Type::RecordVar(
@@ -132,7 +168,7 @@ impl Type {
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Method {
     pub receiver_type: Type,
-    pub name: &'static str,
+    pub name: String,
     pub vars: Vec<&'static str>,
     pub argument_types: Vec<Type>,
     pub return_type: Type,
@@ -151,7 +187,7 @@ impl Method {
     {
         Self {
             receiver_type: receiver_type.into(),
-            name,
+            name: name.into(),
             vars: vars.to_vec(),
             argument_types: argument_types
                 .iter()
@@ -163,6 +199,27 @@ impl Method {
     }
 }
 
+/// The method `method` of the custom data source `name`, see
+/// [`RotoDataSource`](crate::traits::RotoDataSource). Returns `None` if the
+/// method has a type that can't be used in roto.
+pub fn custom_method(
+    name: &str,
+    method: &DataSourceMethod,
+) -> Option<Method> {
+    let argument_types = method
+        .arg_types
+        .iter()
+        .map(Type::from_typedef)
+        .collect::<Option<_>>()?;
+    Some(Method {
+        receiver_type: Type::Custom(name.into()),
+        name: method.name.to_string(),
+        vars: vec![],
+        argument_types,
+        return_type: Type::from_typedef(&method.return_type)?,
+    })
+}
+
 pub fn globals() -> Vec<(String, Type)> {
     [
         ("BLACKHOLE", Type::Primitive(Primitive::Community)),
@@ -172,6 +229,17 @@ pub fn globals() -> Vec<(String, Type)> {
         ("IPV6", Type::Name("Afi".into())),
         ("VPNV4", Type::Name("Afi".into())),
         ("VPNV6", Type::Name("Afi".into())),
+        ("ROV_VALID", Type::Name("RovStatus".into())),
+        ("ROV_INVALID", Type::Name("RovStatus".into())),
+        ("ROV_NOT_FOUND", Type::Name("RovStatus".into())),
+        ("ASPA_VALID", Type::Name("AspaStatus".into())),
+        ("ASPA_INVALID", Type::Name("AspaStatus".into())),
+        ("ASPA_UNKNOWN", Type::Name("AspaStatus".into())),
+        ("ROLE_CUSTOMER", Type::Name("PeerRole".into())),
+        ("ROLE_PEER", Type::Name("PeerRole".into())),
+        ("ROLE_PROVIDER", Type::Name("PeerRole".into())),
+        ("ROLE_ROUTE_SERVER", Type::Name("PeerRole".into())),
+        ("ROLE_RS_CLIENT", Type::Name("PeerRole".into())),
     ]
     .into_iter()
     .map(|(s, t)| (s.into(), t))
@@ -214,17 +282,117 @@ pub fn methods() -> Vec<Method> {
             &[] as &[Type],
             ExplicitVar("T")
         ),
+        // The methods on tables look up the records by their key, which
+        // is the first field, an Asn, if the table doesn't declare one.
         Method::new(
-            Table(Box::new(ExplicitVar("T"))),
+            Table(Box::new(ExplicitVar("T")), Box::new(ExplicitVar("K"))),
             "contains",
+            &["T", "K"],
+            &[ExplicitVar("K")],
+            Bool,
+        ),
+        Method::new(
+            Table(Box::new(ExplicitVar("T")), Box::new(ExplicitVar("K"))),
+            "find",
+            &["T", "K"],
+            &[ExplicitVar("K")],
+            ExplicitVar("T"),
+        ),
+        Method::new(
+            Table(Box::new(ExplicitVar("T")), Box::new(ExplicitVar("K"))),
+            "find_all",
+            &["T", "K"],
+            &[ExplicitVar("K")],
+            List(Box::new(ExplicitVar("T"))),
+        ),
+        // The version of the data source, that goes up with every update.
+        // It stays at the maximum of a U32 once it gets there.
+        Method::new(
+            Table(Box::new(ExplicitVar("T")), Box::new(ExplicitVar("K"))),
+            "version",
+            &["T", "K"],
+            &[] as &[Type],
+            U32,
+        ),
+        // The methods on ribs look up the records for a prefix.
+        Method::new(
+            Rib(Box::new(ExplicitVar("T"))),
+            "match",
             &["T"],
-            &[ExplicitVar("T")],
+            &[Prefix],
+            List(Box::new(ExplicitVar("T"))),
+        ),
+        Method::new(
+            Rib(Box::new(ExplicitVar("T"))),
+            "longest_match",
+            &["T"],
+            &[Prefix],
+            ExplicitVar("T"),
+        ),
+        Method::new(
+            Rib(Box::new(ExplicitVar("T"))),
+            "get",
+            &["T"],
+            &[Prefix],
+            ExplicitVar("T"),
+        ),
+        Method::new(
+            Rib(Box::new(ExplicitVar("T"))),
+            "contains",
+            &["T"],
+            &[Prefix],
             Bool,
         ),
+        Method::new(
+            Rib(Box::new(ExplicitVar("T"))),
+            "more_specifics",
+            &["T"],
+            &[Prefix],
+            List(Box::new(ExplicitVar("T"))),
+        ),
+        Method::new(
+            Rib(Box::new(ExplicitVar("T"))),
+            "less_specifics",
+            &["T"],
+            &[Prefix],
+            List(Box::new(ExplicitVar("T"))),
+        ),
+        Method::new(
+            Rib(Box::new(ExplicitVar("T"))),
+            "version",
+            &["T"],
+            &[] as &[Type],
+            U32,
+        ),
+        // Route origin validation against the VRPs of a built-in rib.
+        Method::new(
+            Rib(Box::new(Type::Name("Vrp".into()))),
+            "validate",
+            &[],
+            &[Prefix, AsNumber],
+            Type::Name("RovStatus".into()),
+        ),
+        // AS path verification against the ASPA records of a built-in
+        // table.
+        Method::new(
+            Table(
+                Box::new(Type::Name("Aspa".into())),
+                Box::new(AsNumber),
+            ),
+            "validate",
+            &[],
+            &[AsPath, Type::Name("PeerRole".into())],
+            Type::Name("AspaStatus".into()),
+        ),
         Method::new(AsPath, "contains", &[], &[AsNumber], Bool),
         Method::new(Prefix, "contains", &[], &[IpAddress], Bool),
         Method::new(Prefix, "covers", &[], &[Prefix], Bool),
         Method::new(Prefix, "is_covered_by", &[], &[Prefix], Bool),
+        Method::new(Prefix, "is_bogon", &[], &[] as &[Type], Bool),
+        Method::new(Prefix, "is_martian", &[], &[] as &[Type], Bool),
+        Method::new(AsNumber, "is_private", &[], &[] as &[Type], Bool),
+        Method::new(AsNumber, "is_reserved", &[], &[] as &[Type], Bool),
+        Method::new(AsNumber, "is_documentation", &[], &[] as &[Type], Bool),
         Method::new(AsPath, "len", &[], &[] as &[Type], U32),
         Method::new(AsPath, "origin", &[], &[] as &[Type], AsNumber),
         Method::new(Nlri, "afi", &[], &[] as &[Type], Type::Name("Afi".into())),
@@ -257,6 +425,7 @@ pub fn default_types() -> Vec<(&'static str, Type)> {
         ("Bool", Bool),
         ("String", String),
         ("Prefix", Prefix),
+        ("PrefixLength", PrefixLength),
         ("IpAddress", IpAddress),
         ("Asn", AsNumber),
         ("AsPath", AsPath),
@@ -404,6 +573,36 @@ pub fn default_types() -> Vec<(&'static str, Type)> {
             ],
         ),
         Enum("Safi", vec![("Unicast", None), ("Multicast", None)]),
+        Record(
+            "Vrp",
+            vec![
+                ("prefix", "Prefix"),
+                ("max_length", "PrefixLength"),
+                ("asn", "Asn"),
+            ],
+        ),
+        Enum(
+            "RovStatus",
+            vec![("Valid", None), ("Invalid", None), ("NotFound", None)],
+        ),
+        Record(
+            "Aspa",
+            vec![("customer", "Asn"), ("providers", "[Asn]")],
+        ),
+        Enum(
+            "AspaStatus",
+            vec![("Valid", None), ("Invalid", None), ("Unknown", None)],
+        ),
+        Enum(
+            "PeerRole",
+            vec![
+                ("Customer", None),
+                ("Peer", None),
+                ("Provider", None),
+                ("RouteServer", None),
+                ("RsClient", None),
+            ],
+        ),
         Record("Nlri", vec![("afi", "Afi"), ("safi", "Safi")]),
         Record(
             "BgpUpdateMessage",
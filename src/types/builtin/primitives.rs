@@ -1,5 +1,5 @@
 use std::fmt::{Display, Formatter};
-use std::net::IpAddr;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 
 use log::{debug, error, trace};
 use paste::paste;
@@ -816,6 +816,8 @@ createtoken!(Prefix;
     Covers = 5
     IsCoveredBy = 6
     Contains = 7
+    IsBogon = 8
+    IsMartian = 9
 );
 
 impl RotoType for inetnum::addr::Prefix {
@@ -867,6 +869,16 @@ impl RotoType for inetnum::addr::Prefix {
                 PrefixToken::Contains.into(),
                 vec![TypeDef::IpAddr],
             )),
+            "is_bogon" => Ok(MethodProps::new(
+                TypeDef::Bool,
+                PrefixToken::IsBogon.into(),
+                vec![],
+            )),
+            "is_martian" => Ok(MethodProps::new(
+                TypeDef::Bool,
+                PrefixToken::IsMartian.into(),
+                vec![],
+            )),
             _ => Err(format!(
                 "Unknown method: '{}' for type Prefix",
                 method_name.ident
@@ -942,6 +954,8 @@ impl RotoType for inetnum::addr::Prefix {
                     Ok(TypeValue::Unknown)
                 }
             }
+            PrefixToken::IsBogon => Ok(is_bogon(self).into()),
+            PrefixToken::IsMartian => Ok(is_martian(self).into()),
         }
     }
 
@@ -1019,6 +1033,147 @@ impl TryFrom<&TypeValue> for inetnum::addr::Prefix {
     }
 }
 
+//------------ Special-purpose prefixes and ASNs ----------------------------
+
+/// The version of the lists of special-purpose prefixes and ASNs that the
+/// `is_bogon`, `is_martian`, `is_private`, `is_reserved` and
+/// `is_documentation` methods use. It goes up with every change to the
+/// lists, so the verdicts of a filter can be traced back to the lists it
+/// ran with, with `Verdict::special_purpose_version`.
+pub const SPECIAL_PURPOSE_VERSION: u32 = 1;
+
+/// The IPv4 prefixes that can't be the destination of a route at all.
+const MARTIANS_V4: &[(Ipv4Addr, u8)] = &[
+    // "This network", RFC 791
+    (Ipv4Addr::new(0, 0, 0, 0), 8),
+    // Loopback, RFC 1122
+    (Ipv4Addr::new(127, 0, 0, 0), 8),
+    // Link local, RFC 3927
+    (Ipv4Addr::new(169, 254, 0, 0), 16),
+    // Multicast, RFC 5771
+    (Ipv4Addr::new(224, 0, 0, 0), 4),
+    // Reserved and limited broadcast, RFC 1112 and RFC 919
+    (Ipv4Addr::new(240, 0, 0, 0), 4),
+];
+
+/// The IPv6 prefixes that can't be the destination of a route at all.
+const MARTIANS_V6: &[(Ipv6Addr, u8)] = &[
+    // Unspecified address, RFC 4291
+    (Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 0), 128),
+    // Loopback, RFC 4291
+    (Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 1), 128),
+    // IPv4-mapped addresses, RFC 4291
+    (Ipv6Addr::new(0, 0, 0, 0, 0, 0xffff, 0, 0), 96),
+    // Link local, RFC 4291
+    (Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 0), 10),
+    // Multicast, RFC 4291
+    (Ipv6Addr::new(0xff00, 0, 0, 0, 0, 0, 0, 0), 8),
+];
+
+/// The IPv4 prefixes, on top of the martians, that don't belong in the
+/// global routing table.
+const BOGONS_V4: &[(Ipv4Addr, u8)] = &[
+    // Private use, RFC 1918
+    (Ipv4Addr::new(10, 0, 0, 0), 8),
+    // Shared address space, RFC 6598
+    (Ipv4Addr::new(100, 64, 0, 0), 10),
+    // Private use, RFC 1918
+    (Ipv4Addr::new(172, 16, 0, 0), 12),
+    // IETF protocol assignments, RFC 6890
+    (Ipv4Addr::new(192, 0, 0, 0), 24),
+    // Documentation, RFC 5737
+    (Ipv4Addr::new(192, 0, 2, 0), 24),
+    // 6to4 relay anycast, RFC 7526
+    (Ipv4Addr::new(192, 88, 99, 0), 24),
+    // Private use, RFC 1918
+    (Ipv4Addr::new(192, 168, 0, 0), 16),
+    // Benchmarking, RFC 2544
+    (Ipv4Addr::new(198, 18, 0, 0), 15),
+    // Documentation, RFC 5737
+    (Ipv4Addr::new(198, 51, 100, 0), 24),
+    // Documentation, RFC 5737
+    (Ipv4Addr::new(203, 0, 113, 0), 24),
+];
+
+/// The IPv6 prefixes, on top of the martians, that don't belong in the
+/// global routing table.
+const BOGONS_V6: &[(Ipv6Addr, u8)] = &[
+    // Reserved by the IETF, RFC 4291
+    (Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 0), 8),
+    // Discard only, RFC 6666
+    (Ipv6Addr::new(0x100, 0, 0, 0, 0, 0, 0, 0), 64),
+    // Benchmarking, RFC 5180
+    (Ipv6Addr::new(0x2001, 0x2, 0, 0, 0, 0, 0, 0), 48),
+    // ORCHID, RFC 4843
+    (Ipv6Addr::new(0x2001, 0x10, 0, 0, 0, 0, 0, 0), 28),
+    // Documentation, RFC 3849
+    (Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 0), 32),
+    // 6to4, RFC 7526
+    (Ipv6Addr::new(0x2002, 0, 0, 0, 0, 0, 0, 0), 16),
+    // 6bone, RFC 3701
+    (Ipv6Addr::new(0x3ffe, 0, 0, 0, 0, 0, 0, 0), 16),
+    // Documentation, RFC 9637
+    (Ipv6Addr::new(0x3fff, 0, 0, 0, 0, 0, 0, 0), 20),
+    // Unique local, RFC 4193
+    (Ipv6Addr::new(0xfc00, 0, 0, 0, 0, 0, 0, 0), 7),
+    // Site local, RFC 3879
+    (Ipv6Addr::new(0xfec0, 0, 0, 0, 0, 0, 0, 0), 10),
+];
+
+/// Whether `prefix` is covered by a prefix that can't be the destination
+/// of a route, like loopback, link local or multicast addresses.
+fn is_martian(prefix: &Prefix) -> bool {
+    is_covered_by_any(prefix, MARTIANS_V4, MARTIANS_V6)
+}
+
+/// Whether `prefix` is a martian, or is covered by another prefix that
+/// doesn't belong in the global routing table, like private use or
+/// documentation addresses.
+fn is_bogon(prefix: &Prefix) -> bool {
+    is_martian(prefix) || is_covered_by_any(prefix, BOGONS_V4, BOGONS_V6)
+}
+
+fn is_covered_by_any(
+    prefix: &Prefix,
+    v4: &[(Ipv4Addr, u8)],
+    v6: &[(Ipv6Addr, u8)],
+) -> bool {
+    match prefix.addr() {
+        IpAddr::V4(addr) => v4.iter().any(|(net, len)| {
+            let mask =
+                u32::MAX.checked_shl(32 - u32::from(*len)).unwrap_or(0);
+            prefix.len() >= *len
+                && (u32::from(addr) & mask) == u32::from(*net)
+        }),
+        IpAddr::V6(addr) => v6.iter().any(|(net, len)| {
+            let mask =
+                u128::MAX.checked_shl(128 - u32::from(*len)).unwrap_or(0);
+            prefix.len() >= *len
+                && (u128::from(addr) & mask) == u128::from(*net)
+        }),
+    }
+}
+
+/// Whether `asn` is for private use, RFC 6996.
+fn is_private_asn(asn: Asn) -> bool {
+    matches!(asn.into_u32(), 64512..=65534 | 4_200_000_000..=4_294_967_294)
+}
+
+/// Whether `asn` is for use in documentation, RFC 5398.
+fn is_documentation_asn(asn: Asn) -> bool {
+    matches!(asn.into_u32(), 64496..=64511 | 65536..=65551)
+}
+
+/// Whether `asn` is reserved, and can't be used at all: AS 0 (RFC 7607),
+/// AS_TRANS (RFC 6793), the last ASNs of the 16 and 32 bit ranges (RFC
+/// 7300) and the block reserved by IANA.
+fn is_reserved_asn(asn: Asn) -> bool {
+    matches!(
+        asn.into_u32(),
+        0 | 23456 | 65535 | 65552..=131071 | 4_294_967_295
+    )
+}
+
 //------------ PrefixLength type ---------------------------------------------
 
 createtoken!(PrefixLength; Set = 0);
@@ -1563,10 +1718,104 @@ impl From<&'_ ast::IpAddress> for IpAddr {
 
 // ----------- Asn type -----------------------------------------------------
 
-createtoken!(Asn; Set = 0);
+createtoken!(Asn;
+    Set = 0
+    IsPrivate = 1
+    IsReserved = 2
+    IsDocumentation = 3
+);
 
 impl RotoType for inetnum::asn::Asn {
-    setmethodonly!(Asn);
+    fn get_props_for_method(
+        _ty: TypeDef,
+        method_name: &crate::ast::Identifier,
+    ) -> Result<MethodProps, CompileError>
+    where
+        Self: std::marker::Sized,
+    {
+        match method_name.ident.as_str() {
+            "set" => Ok(MethodProps::new(
+                TypeDef::Unknown,
+                AsnToken::Set.into(),
+                vec![TypeDef::Asn],
+            )
+            .consume_value()),
+            "is_private" => Ok(MethodProps::new(
+                TypeDef::Bool,
+                AsnToken::IsPrivate.into(),
+                vec![],
+            )),
+            "is_reserved" => Ok(MethodProps::new(
+                TypeDef::Bool,
+                AsnToken::IsReserved.into(),
+                vec![],
+            )),
+            "is_documentation" => Ok(MethodProps::new(
+                TypeDef::Bool,
+                AsnToken::IsDocumentation.into(),
+                vec![],
+            )),
+            _ => Err(format!(
+                "Unknown method: '{}' for type Asn",
+                method_name.ident
+            )
+            .into()),
+        }
+    }
+
+    fn exec_value_method(
+        &self,
+        method_token: usize,
+        args: &[StackValue],
+        _res_type: TypeDef,
+    ) -> Result<TypeValue, VmError> {
+        match method_token.try_into()? {
+            AsnToken::Set => {
+                if let TypeValue::Builtin(BuiltinTypeValue::Asn(value)) = args
+                    .first()
+                    .ok_or(VmError::InvalidMethodCall)?
+                    .as_ref()
+                {
+                    Ok(TypeValue::from(*value))
+                } else {
+                    Err(VmError::AnonymousArgumentNotFound)
+                }
+            }
+            AsnToken::IsPrivate => Ok(is_private_asn(*self).into()),
+            AsnToken::IsReserved => Ok(is_reserved_asn(*self).into()),
+            AsnToken::IsDocumentation => {
+                Ok(is_documentation_asn(*self).into())
+            }
+        }
+    }
+
+    fn exec_consume_value_method(
+        self,
+        method_token: usize,
+        mut args: Vec<TypeValue>,
+        _res_type: TypeDef,
+    ) -> Result<TypeValue, VmError> {
+        match method_token.try_into()? {
+            AsnToken::Set => {
+                if let Ok(TypeValue::Builtin(BuiltinTypeValue::Asn(value))) =
+                    args.remove(0).into_type(&TypeDef::Asn)
+                {
+                    Ok(TypeValue::Builtin(BuiltinTypeValue::Asn(value)))
+                } else {
+                    Err(VmError::InvalidValueType)
+                }
+            }
+            _ => Err(VmError::InvalidMethodCall),
+        }
+    }
+
+    fn exec_type_method(
+        _method_token: usize,
+        _args: &[StackValue],
+        _res_type: TypeDef,
+    ) -> Result<TypeValue, VmError> {
+        Err(VmError::InvalidMethodCall)
+    }
 
     fn into_type(
         self,
@@ -3,6 +3,8 @@
 pub enum DataSource {
     Table(Table),
     Rib(Arc<dyn RotoRib>),
+    /// A data source that is implemented outside of roto, with its name.
+    Custom(ShortString, Arc<dyn RotoDataSource>),
 }
 
 impl DataSource {
@@ -13,17 +15,35 @@ impl DataSource {
         match records.first() {
             Some(rec) => {
                 let ty = TypeDef::from(&TypeValue::Record(rec.clone()));
-                Ok(Self::Table(Table {
-                    name: name.into(),
-                    ty,
-                    records,
-                }))
+                Ok(Self::Table(Table::new(name.into(), ty, records, None)))
             }
             None => Err(VmError::DataSourceEmpty(name.into())),
         }
     }
 
-    pub fn rib_from_prefix_store<M: Meta + 'static>(
+    /// Create a table with records of type `ty`, read from CSV. See the
+    /// [`loaders`](super::loaders) module for the format.
+    pub fn table_from_csv(
+        name: &str,
+        ty: &TypeDef,
+        reader: impl BufRead,
+    ) -> Result<Self, LoadError> {
+        let records = loaders::records_from_csv(ty, reader)?;
+        Ok(Self::Table(Table::new(name.into(), ty.clone(), records, None)))
+    }
+
+    /// Create a table with records of type `ty`, read from JSON lines. See
+    /// the [`loaders`](super::loaders) module for the format.
+    pub fn table_from_json_lines(
+        name: &str,
+        ty: &TypeDef,
+        reader: impl BufRead,
+    ) -> Result<Self, LoadError> {
+        let records = loaders::records_from_json_lines(ty, reader)?;
+        Ok(Self::Table(Table::new(name.into(), ty.clone(), records, None)))
+    }
+
+    pub fn rib_from_prefix_store<M: Meta + Into<TypeValue> + 'static>(
         name: &str,
         ty: TypeDef,
         store: rotonda_store::MultiThreadedStore<M>,
@@ -32,6 +52,10 @@ impl DataSource {
         Ok(DataSource::Rib(Arc::new(rib)))
     }
 
+    pub fn custom(name: &str, source: Arc<dyn RotoDataSource>) -> Self {
+        DataSource::Custom(name.into(), source)
+    }
+
     pub fn get_at_field_index(
         &self,
         index: usize,
@@ -44,25 +68,38 @@ impl DataSource {
             DataSource::Rib(ref _r) => {
                 todo!()
             }
+            DataSource::Custom(..) => None,
         }
     }
 
     /// Methods on a data source can indicate whether they are returning a
     /// value created by the method or a reference to a value in the data
     /// source itself, through the [`DataSourceMethodValue`] enum.
+    ///
+    /// `ds_token` is the token of this data source in the filter, references
+    /// to the records of a table carry it.
     pub(crate) fn exec_method(
         &self,
+        ds_token: usize,
         method_token: usize,
         args: &[StackValue],
         res_type: TypeDef,
     ) -> Result<DataSourceMethodValue, VmError> {
         match self {
-            DataSource::Table(t) => {
-                Ok(t.exec_ref_value_method(method_token, args, res_type)?())
-            }
+            DataSource::Table(t) => Ok(t.exec_ref_value_method(
+                ds_token,
+                method_token,
+                args,
+                res_type,
+            )?()),
             DataSource::Rib(ref r) => {
                 r.exec_ref_value_method(method_token, args, res_type)
             }
+            DataSource::Custom(_, c) => {
+                let args: Vec<_> = args.iter().map(|a| a.as_ref()).collect();
+                c.exec_method(method_token, &args)
+                    .map(DataSourceMethodValue::TypeValue)
+            }
         }
     }
 
@@ -70,6 +107,7 @@ impl DataSource {
         match &self {
             DataSource::Table(t) => t.name.clone(),
             DataSource::Rib(r) => (**r).get_name(),
+            DataSource::Custom(name, _) => name.clone(),
         }
     }
 
@@ -77,18 +115,22 @@ impl DataSource {
         match &self {
             DataSource::Table(t) => t.ty.clone(),
             DataSource::Rib(r) => r.get_type(),
+            DataSource::Custom(name, c) => {
+                TypeDef::Custom((name.clone(), c.methods()))
+            }
         }
     }
 
     pub fn is_empty(&self) -> bool {
         match &self {
-            DataSource::Table(t) => t.records.is_empty(),
+            DataSource::Table(t) => t.is_empty(),
             DataSource::Rib(r) => r.is_empty(),
+            DataSource::Custom(..) => false,
         }
     }
 }
 
-impl<M: Meta + 'static> From<Rib<M>> for DataSource {
+impl<M: Meta + Into<TypeValue> + 'static> From<Rib<M>> for DataSource {
     fn from(rib: Rib<M>) -> Self {
         DataSource::Rib(Arc::new(rib))
     }
@@ -96,25 +138,41 @@ impl<M: Meta + 'static> From<Rib<M>> for DataSource {
 
 // ----------- Rib Type ----------------------------------------------------
 
-use std::sync::Arc;
+use std::{io::BufRead, sync::Arc};
 
 use log::{error, trace};
+use inetnum::addr::Prefix;
 use rotonda_store::{epoch, prelude::Meta, MatchOptions, MatchType};
+use serde::Serialize;
 
 use crate::{
     ast::ShortString,
     compiler::compile::CompileError,
-    traits::{RotoRib, Token},
+    traits::{RotoDataSource, RotoRib, Token},
     vm::{StackRefPos, StackValue, VmError, FieldIndex}, first_into_vm_err,
 };
 
 use super::{
     builtin::BuiltinTypeValue,
-    collections::Record,
+    collections::{ElementTypeValue, List, Record},
+    enum_types::GlobalEnumTypeDef,
+    loaders::{self, LoadError},
+    rpki::{self, Aspa, Vrps},
+    shards::ShardedMap,
     typedef::{MethodProps, TypeDef},
     typevalue::TypeValue,
 };
 
+/// The record types of the built-in data sources, that can be used for a
+/// rib or table without a body, e.g. `rib vrps contains Vrp`.
+pub(crate) fn builtin_record_type(name: &str) -> Option<TypeDef> {
+    match name {
+        "Vrp" => Some(Vrps::record_type()),
+        "Aspa" => Some(Aspa::record_type()),
+        _ => None,
+    }
+}
+
 /// This data-structure only exists to populate the static methods for the type
 /// `Rib`, e.g. the methods `Rib::method_name()` and their properties.
 #[derive(Debug)]
@@ -127,9 +185,18 @@ impl RibType {
         ty: &TypeDef,
         method_name: &crate::ast::Identifier,
     ) -> Result<MethodProps, CompileError> {
+        // The methods return the records stored in the Rib.
+        let ty = match ty {
+            TypeDef::Rib((rec, _)) => rec.as_ref(),
+            ty => ty,
+        };
+
         match method_name.ident.as_str() {
+            // All the matches for the prefix, i.e. the records of the prefix
+            // and of its less-specifics, where `longest_match` returns only
+            // the most specific one and `get` only an exact match.
             "match" => Ok(MethodProps::new(
-                ty.clone(),
+                TypeDef::List(Box::new(ty.clone())),
                 RibToken::Match.into(),
                 vec![TypeDef::Prefix],
             )),
@@ -138,11 +205,42 @@ impl RibType {
                 RibToken::LongestMatch.into(),
                 vec![TypeDef::Prefix],
             )),
+            "get" => Ok(MethodProps::new(
+                ty.clone(),
+                RibToken::Get.into(),
+                vec![TypeDef::Prefix],
+            )),
             "contains" => Ok(MethodProps::new(
                 TypeDef::Bool,
                 RibToken::Contains.into(),
                 vec![TypeDef::Prefix],
             )),
+            "more_specifics" => Ok(MethodProps::new(
+                TypeDef::List(Box::new(ty.clone())),
+                RibToken::MoreSpecifics.into(),
+                vec![TypeDef::Prefix],
+            )),
+            "less_specifics" => Ok(MethodProps::new(
+                TypeDef::List(Box::new(ty.clone())),
+                RibToken::LessSpecifics.into(),
+                vec![TypeDef::Prefix],
+            )),
+            // Only for ribs of VRPs, see [`Vrps`].
+            "validate" if *ty == Vrps::record_type() => {
+                Ok(MethodProps::new(
+                    TypeDef::ConstEnumVariant(
+                        GlobalEnumTypeDef::RovStatus.into(),
+                    ),
+                    RibToken::Validate.into(),
+                    vec![TypeDef::Prefix, TypeDef::Asn],
+                ))
+            }
+            // Executed by the VM, see [`DataSourceHandle`].
+            "version" => Ok(MethodProps::new(
+                TypeDef::U32,
+                RibToken::Version.into(),
+                vec![],
+            )),
             _ => Err(format!(
                 "Unknown method '{}' for data source of type Rib",
                 method_name.ident
@@ -166,6 +264,10 @@ pub enum RibToken {
     LongestMatch,
     Get,
     Contains,
+    MoreSpecifics,
+    LessSpecifics,
+    Validate,
+    Version,
 }
 
 impl TryFrom<usize> for RibToken {
@@ -177,6 +279,10 @@ impl TryFrom<usize> for RibToken {
             1 => Ok(RibToken::LongestMatch),
             2 => Ok(RibToken::Get),
             3 => Ok(RibToken::Contains),
+            4 => Ok(RibToken::MoreSpecifics),
+            5 => Ok(RibToken::LessSpecifics),
+            6 => Ok(RibToken::Validate),
+            7 => Ok(RibToken::Version),
             _ => Err(VmError::InvalidDataSource)
         }
     }
@@ -214,7 +320,92 @@ impl<M: Meta> Rib<M> {
     }
 }
 
-impl<M: Meta> RotoRib for Rib<M> {
+impl<M: Meta + Into<TypeValue>> Rib<M> {
+    /// Look up `prefix` in the store. Returns the record of the matching
+    /// prefix, if there is one.
+    fn match_prefix(
+        &self,
+        prefix: &Prefix,
+        match_type: MatchType,
+    ) -> Option<TypeValue> {
+        let exact = matches!(match_type, MatchType::ExactMatch);
+        let guard = epoch::pin();
+        let res = self.store.match_prefix(
+            prefix,
+            &MatchOptions {
+                match_type,
+                include_all_records: false,
+                include_less_specifics: false,
+                include_more_specifics: false,
+            },
+            &guard,
+        );
+
+        match (res.prefix, res.prefix_meta) {
+            (Some(p), _) if exact && p != *prefix => None,
+            (Some(_), Some(meta)) => Some(meta.into()),
+            _ => None,
+        }
+    }
+
+    /// The records of the more-specifics or the less-specifics of `prefix`
+    /// in the store, not including `prefix` itself.
+    fn match_specifics(
+        &self,
+        prefix: &Prefix,
+        more_specifics: bool,
+    ) -> Vec<ElementTypeValue> {
+        let guard = epoch::pin();
+        let res = self.store.match_prefix(
+            prefix,
+            &MatchOptions {
+                match_type: MatchType::ExactMatch,
+                include_all_records: false,
+                include_less_specifics: !more_specifics,
+                include_more_specifics: more_specifics,
+            },
+            &guard,
+        );
+
+        let records = if more_specifics {
+            res.more_specifics
+        } else {
+            res.less_specifics
+        };
+
+        records
+            .map(|records| {
+                records
+                    .iter()
+                    .map(|rec| {
+                        ElementTypeValue::Primitive(rec.meta.clone().into())
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// The prefix that is the first argument of a method call on a Rib. Returns
+/// `None` if the argument isn't a prefix.
+pub(super) fn prefix_arg(
+    args: &[StackValue],
+) -> Result<Option<Prefix>, VmError> {
+    let arg = first_into_vm_err!(args, InvalidMethodCall)?;
+    match Prefix::try_from(arg.as_ref()) {
+        Ok(prefix) => Ok(Some(prefix)),
+        Err(_) => {
+            error!(
+                "Cannot convert Argument '{:?}' to Prefix while executing \
+                Data Source method.",
+                arg
+            );
+            Ok(None)
+        }
+    }
+}
+
+impl<M: Meta + Into<TypeValue>> RotoRib for Rib<M> {
     fn exec_value_method<'a>(
         &'a self,
         _method_token: usize,
@@ -230,60 +421,60 @@ impl<M: Meta> RotoRib for Rib<M> {
         args: &'a [StackValue],
         _res_type: TypeDef,
     ) -> Result<DataSourceMethodValue, VmError> {
-        match RibToken::try_from(method)? {
+        let token = RibToken::try_from(method)?;
+        trace!("{:?} on rib with args {:?}", token, args);
+
+        if args.len() != 1 {
+            return Err(VmError::InvalidMethodCall);
+        }
+
+        let prefix = if let Some(prefix) = prefix_arg(args)? {
+            prefix
+        } else {
+            return Ok(DataSourceMethodValue::TypeValue(TypeValue::Unknown));
+        };
+
+        let res = match token {
             RibToken::Match => {
-                todo!()
-            }
-            RibToken::LongestMatch if args.len() == 1 => {
-                trace!("longest match on rib");
-                trace!("args {:?}", args);
-                let guard = epoch::pin();
-                let prefix = if let Ok(prefix) =
-                    inetnum::addr::Prefix::try_from(first_into_vm_err!(args, InvalidMethodCall)?.as_ref())
-                {
-                    prefix
-                } else {
-                    error!("Cannot convert Argument '{:?}' to Prefix while executing Data Source method.", args[0]);
-                    return Ok(DataSourceMethodValue::TypeValue(
-                        TypeValue::Unknown,
-                    ));
-                };
-                Ok(self.store
-                    .match_prefix(
-                        &prefix,
-                        &MatchOptions {
-                            match_type: MatchType::LongestMatch,
-                            include_all_records: false,
-                            include_less_specifics: false,
-                            include_more_specifics: false,
-                        },
-                        &guard,
-                    )
-                    .prefix
-                    .map(|v| DataSourceMethodValue::TypeValue(v.into()))
-                    .unwrap_or_else(|| {
-                        DataSourceMethodValue::TypeValue(TypeValue::Unknown)
-                    })
-                )
+                let mut records: Vec<_> = self
+                    .match_prefix(&prefix, MatchType::ExactMatch)
+                    .into_iter()
+                    .map(ElementTypeValue::Primitive)
+                    .collect();
+                records.extend(self.match_specifics(&prefix, false));
+                TypeValue::List(List::new(records))
             }
-            RibToken::Contains => {
-                trace!("contains on rib");
-                todo!()
-            }
-            RibToken::Get => {
-                trace!("get on rib");
-                todo!()
+            RibToken::LongestMatch => self
+                .match_prefix(&prefix, MatchType::LongestMatch)
+                .unwrap_or(TypeValue::Unknown),
+            RibToken::Get => self
+                .match_prefix(&prefix, MatchType::ExactMatch)
+                .unwrap_or(TypeValue::Unknown),
+            RibToken::Contains => TypeValue::Builtin(BuiltinTypeValue::Bool(
+                self.match_prefix(&prefix, MatchType::ExactMatch).is_some(),
+            )),
+            RibToken::MoreSpecifics => TypeValue::List(List::new(
+                self.match_specifics(&prefix, true),
+            )),
+            RibToken::LessSpecifics => TypeValue::List(List::new(
+                self.match_specifics(&prefix, false),
+            )),
+            // Validation needs VRPs, that are stored in a `Vrps`.
+            RibToken::Validate | RibToken::Version => {
+                return Err(VmError::InvalidMethodCall)
             }
-            _ => Err(VmError::InvalidMethodCall)
-        }
+        };
+
+        Ok(DataSourceMethodValue::TypeValue(res))
     }
 
-    fn get_by_key<'a>(&'a self, _key: &str) -> Option<&'a Record> {
-        todo!()
+    fn get_by_key(&self, key: &str) -> Option<TypeValue> {
+        let prefix = key.parse::<Prefix>().ok()?;
+        self.match_prefix(&prefix, MatchType::ExactMatch)
     }
 
     fn len(&self) -> usize {
-        todo!()
+        self.store.prefixes_count()
     }
 
     fn is_empty(&self) -> bool {
@@ -305,6 +496,31 @@ impl std::fmt::Display for RibType {
     }
 }
 
+// ----------- Custom Data Sources -----------------------------------------
+
+/// The signature of a method of a data source that is implemented outside
+/// of roto, see [`RotoDataSource`].
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Serialize, Hash)]
+pub struct DataSourceMethod {
+    pub name: ShortString,
+    pub arg_types: Vec<TypeDef>,
+    pub return_type: TypeDef,
+}
+
+impl DataSourceMethod {
+    pub fn new(
+        name: &str,
+        arg_types: Vec<TypeDef>,
+        return_type: TypeDef,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            arg_types,
+            return_type,
+        }
+    }
+}
+
 // ----------- Table Type --------------------------------------------------
 
 pub enum DataSourceMethodValue {
@@ -313,26 +529,196 @@ pub enum DataSourceMethodValue {
     Empty(TypeDef),
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Clone, Debug)]
 pub struct Table {
     pub(crate) name: ShortString,
     pub(crate) ty: TypeDef,
-    pub(crate) records: Vec<Record>,
+    /// The field that the records are indexed on, if the table declares a
+    /// key. Otherwise they are indexed on their first field.
+    key: Option<ShortString>,
+    /// The records by their id, which is their position in the records
+    /// that the table was created with. Records that are added later get
+    /// the next ids. References to the records of the table hold the id.
+    records: ShardedMap<usize, Record>,
+    /// The ids of the records for each value of the indexed field, in
+    /// ascending order.
+    index: ShardedMap<TypeValue, Vec<usize>>,
+    /// The id of the next record that is added.
+    next_id: usize,
 }
 
 impl Table {
+    /// Create a table, with an index on the field `key` if it's given, or
+    /// else on the first field of the records.
+    pub(crate) fn new(
+        name: ShortString,
+        ty: TypeDef,
+        records: Vec<Record>,
+        key: Option<&ShortString>,
+    ) -> Self {
+        let mut table = Self {
+            name,
+            ty,
+            key: key.cloned(),
+            records: ShardedMap::new(),
+            index: ShardedMap::new(),
+            next_id: 0,
+        };
+        for rec in records {
+            table.push(rec);
+        }
+        table
+    }
+
+    /// A copy of this table under `name`, indexed on `key`. The records
+    /// are shared with this table, and so is the index if the key is the
+    /// same.
+    pub(crate) fn rekeyed(
+        &self,
+        name: ShortString,
+        key: Option<&ShortString>,
+    ) -> Self {
+        let mut table = Self {
+            name,
+            key: key.cloned(),
+            ..self.clone()
+        };
+        if table.key != self.key {
+            table.index = ShardedMap::new();
+            let mut ids: Vec<usize> =
+                self.records.iter().map(|(id, _)| *id).collect();
+            ids.sort_unstable();
+            for id in ids {
+                let value = self
+                    .records
+                    .get(&id)
+                    .and_then(|rec| indexed_value(key, rec));
+                if let Some(value) = value {
+                    table.index.entry(value.clone()).push(id);
+                }
+            }
+        }
+        table
+    }
+
+    /// A copy of this table with `records` added.
+    pub(crate) fn with_records(
+        &self,
+        records: impl IntoIterator<Item = Record>,
+    ) -> Self {
+        let mut table = self.clone();
+        for rec in records {
+            table.push(rec);
+        }
+        table
+    }
+
+    /// A copy of this table without the records for which `remove`
+    /// returns true.
+    pub(crate) fn without_records(
+        &self,
+        mut remove: impl FnMut(&Record) -> bool,
+    ) -> Self {
+        let ids: Vec<usize> = self
+            .records
+            .iter()
+            .filter(|(_, rec)| remove(*rec))
+            .map(|(id, _)| *id)
+            .collect();
+        let mut table = self.clone();
+        for id in ids {
+            table.remove(id);
+        }
+        table
+    }
+
+    /// A copy of this table in which the records for which `replace`
+    /// returns a record are replaced by that record. The records are
+    /// passed to `replace` in no particular order.
+    pub(crate) fn with_replaced_records(
+        &self,
+        mut replace: impl FnMut(&Record) -> Option<Record>,
+    ) -> Self {
+        let replaced: Vec<(usize, Record)> = self
+            .records
+            .iter()
+            .filter_map(|(id, rec)| replace(rec).map(|rec| (*id, rec)))
+            .collect();
+        let mut table = self.clone();
+        for (id, rec) in replaced {
+            table.remove(id);
+            table.insert_at(id, rec);
+        }
+        table
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Add `rec` with the next id.
+    fn push(&mut self, rec: Record) {
+        self.insert_at(self.next_id, rec);
+        self.next_id += 1;
+    }
+
+    /// Add `rec` with `id`, which must not be in use.
+    fn insert_at(&mut self, id: usize, rec: Record) {
+        if let Some(value) = indexed_value(self.key.as_ref(), &rec) {
+            let ids = self.index.entry(value.clone());
+            if let Err(pos) = ids.binary_search(&id) {
+                ids.insert(pos, id);
+            }
+        }
+        self.records.insert(id, rec);
+    }
+
+    /// Remove the record with `id`.
+    fn remove(&mut self, id: usize) {
+        let Some(rec) = self.records.remove(&id) else {
+            return;
+        };
+        if let Some(value) = indexed_value(self.key.as_ref(), &rec) {
+            let empty = self.index.get_mut(value).map(|ids| {
+                ids.retain(|i| *i != id);
+                ids.is_empty()
+            });
+            if empty == Some(true) {
+                self.index.remove(value);
+            }
+        }
+    }
+
+    /// The ids of the records with `key` as the value of their indexed
+    /// field, in ascending order.
+    fn ids<'a>(
+        &'a self,
+        key: &StackValue,
+    ) -> impl Iterator<Item = usize> + 'a {
+        self.index.get(key.as_ref()).into_iter().flatten().copied()
+    }
+
+    /// The records with `key` as the value of their key field, or of their
+    /// first field if the table doesn't have a key.
+    pub(crate) fn records_for<'a>(
+        &'a self,
+        key: &StackValue,
+    ) -> impl Iterator<Item = &'a Record> + 'a {
+        self.ids(key).filter_map(|id| self.records.get(&id))
+    }
+
     pub fn get_at_field_index(
         &self,
         index: usize,
         field_index: FieldIndex,
     ) -> Result<&TypeValue, VmError> {
         match field_index {
-            fi if fi.is_empty() => if let Some(r) = self.records.get(index) {
+            fi if fi.is_empty() => if let Some(r) = self.records.get(&index) {
                 if let Some((_, ref v)) = r.get_field_by_single_index(index) {
                     v.try_into()
                 } else { Err(VmError::InvalidFieldAccess) }
             } else { Err(VmError::InvalidFieldAccess) },
-            field_index => match self.records.get(index) {
+            field_index => match self.records.get(&index) {
                 Some(r) => {
                     if let Some(v) = r.get_field_by_index(&field_index) {
                         v.try_into()
@@ -343,30 +729,24 @@ impl Table {
         }
     }
 
+    /// `ds_token` is the token of this table in the filter, the reference to
+    /// the record that `find` returns holds it next to the position of the
+    /// record.
     pub(crate) fn exec_ref_value_method<'a>(
         &'a self,
+        ds_token: usize,
         method_token: usize,
         args: &'a [StackValue],
         _res_type: TypeDef,
     ) -> Result<Box<dyn FnOnce() -> DataSourceMethodValue + 'a>, VmError> {
         match method_token.try_into()? {
             TableToken::Find if args.len() == 1 => Ok(Box::new(|| {
-                self.records
-                    .iter()
-                    .enumerate()
-                    .find(|v| {
-                        if let Some(val) =
-                            v.1.get_field_by_index(&FieldIndex::from(vec![0]))
-                        {
-                            val == args[0]
-                        } else {
-                            false
-                        }
-                    })
-                    .map(|v| {
+                self.ids(&args[0])
+                    .next()
+                    .map(|id| {
                         DataSourceMethodValue::Ref(StackRefPos::TablePos(
-                            Token::Table(v.0),
-                            0,
+                            Token::Table(ds_token),
+                            id,
                         ))
                     })
                     .unwrap_or_else(|| {
@@ -374,28 +754,30 @@ impl Table {
                     })
             })),
             TableToken::Contains if args.len() == 1 => Ok(Box::new(|| {
-                self.records
-                    .iter()
-                    .enumerate()
-                    .find(|v| {
-                        if let Some(val) =
-                            v.1.get_field_by_index(&FieldIndex::from(vec![0]))
-                        {
-                            val == args[0]
-                        } else {
-                            false
-                        }
-                    })
-                    .map(|_v| {
-                        DataSourceMethodValue::TypeValue(TypeValue::Builtin(
-                            BuiltinTypeValue::Bool(true),
-                        ))
-                    })
-                    .unwrap_or_else(|| {
-                        DataSourceMethodValue::TypeValue(TypeValue::Builtin(
-                            BuiltinTypeValue::Bool(false),
+                DataSourceMethodValue::TypeValue(TypeValue::Builtin(
+                    BuiltinTypeValue::Bool(
+                        self.ids(&args[0]).next().is_some(),
+                    ),
+                ))
+            })),
+            TableToken::FindAll if args.len() == 1 => Ok(Box::new(|| {
+                let records = self
+                    .records_for(&args[0])
+                    .map(|rec| {
+                        ElementTypeValue::Primitive(TypeValue::Record(
+                            rec.clone(),
                         ))
                     })
+                    .collect();
+                DataSourceMethodValue::TypeValue(TypeValue::List(List::new(
+                    records,
+                )))
+            })),
+            // Only for tables of ASPA records, see [`Aspas`].
+            TableToken::Validate => Ok(Box::new(|| {
+                DataSourceMethodValue::TypeValue(rpki::validate_aspa_table(
+                    self, args,
+                ))
             })),
             _ => Err(VmError::InvalidMethodCall)
         }
@@ -408,16 +790,54 @@ impl Table {
     where
         Self: std::marker::Sized,
     {
+        // The records are looked up by the key of the table, or by their
+        // first field, an Asn, if the table doesn't have a key.
+        let (rec_ty, key_ty) = match &ty {
+            TypeDef::Table((rec, key)) => (
+                *rec.clone(),
+                key.as_ref()
+                    .and_then(|key| rec.get_field(key))
+                    .unwrap_or(TypeDef::Asn),
+            ),
+            _ => (ty.clone(), TypeDef::Asn),
+        };
+
         match method_name.ident.as_str() {
             "find" => Ok(MethodProps::new(
                 ty,
                 TableToken::Find.into(),
-                vec![TypeDef::Asn],
+                vec![key_ty],
             )),
             "contains" => Ok(MethodProps::new(
                 TypeDef::Bool,
                 TableToken::Contains.into(),
-                vec![TypeDef::Asn],
+                vec![key_ty],
+            )),
+            "find_all" => Ok(MethodProps::new(
+                TypeDef::List(Box::new(rec_ty)),
+                TableToken::FindAll.into(),
+                vec![key_ty],
+            )),
+            // Only for tables of ASPA records, see [`Aspas`].
+            "validate" if rec_ty == Aspa::record_type() => {
+                Ok(MethodProps::new(
+                    TypeDef::ConstEnumVariant(
+                        GlobalEnumTypeDef::AspaStatus.into(),
+                    ),
+                    TableToken::Validate.into(),
+                    vec![
+                        TypeDef::AsPath,
+                        TypeDef::ConstEnumVariant(
+                            GlobalEnumTypeDef::PeerRole.into(),
+                        ),
+                    ],
+                ))
+            }
+            // Executed by the VM, see [`DataSourceHandle`].
+            "version" => Ok(MethodProps::new(
+                TypeDef::U32,
+                TableToken::Version.into(),
+                vec![],
             )),
             _ => Err(format!(
                 "Unknown method '{}' for table",
@@ -436,10 +856,26 @@ impl Table {
     }
 }
 
+/// The value of the field of `rec` that a table is indexed on: `key`, or
+/// the first field if the table doesn't have a key.
+fn indexed_value<'a>(
+    key: Option<&ShortString>,
+    rec: &'a Record,
+) -> Option<&'a TypeValue> {
+    let value = match key {
+        Some(key) => rec.get_value_for_field(key),
+        None => rec.get_field_by_single_index(0).map(|(_, v)| v),
+    };
+    value.and_then(|v| <&TypeValue>::try_from(v).ok())
+}
+
 #[derive(Debug)]
 pub enum TableToken {
     Find,
     Contains,
+    FindAll,
+    Validate,
+    Version,
 }
 
 impl TryFrom<usize> for TableToken {
@@ -449,6 +885,9 @@ impl TryFrom<usize> for TableToken {
         match token {
             0 => Ok(TableToken::Find),
             1 => Ok(TableToken::Contains),
+            2 => Ok(TableToken::FindAll),
+            3 => Ok(TableToken::Validate),
+            4 => Ok(TableToken::Version),
             t => {
                 error!("Cannot find method on Table for token: {}", t);
                 Err(VmError::InvalidDataSourceAccess)
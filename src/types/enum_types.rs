@@ -10,6 +10,7 @@ use serde::Serialize;
 use crate::compiler::compile::CompileError;
 use crate::types::builtin::BytesRecord;
 use crate::types::lazyrecord_types::BmpMessage;
+use crate::types::rpki::{AspaStatus, PeerRole, RovStatus};
 use crate::vm::VmError;
 use crate::{
     ast::ShortString,
@@ -154,6 +155,9 @@ pub enum GlobalEnumTypeDef {
     Safi,
     WellKnownCommunities,
     BmpMessageType,
+    RovStatus,
+    AspaStatus,
+    PeerRole,
 }
 
 impl GlobalEnumTypeDef {
@@ -163,6 +167,9 @@ impl GlobalEnumTypeDef {
             GlobalEnumTypeDef::Safi,
             GlobalEnumTypeDef::WellKnownCommunities,
             GlobalEnumTypeDef::BmpMessageType,
+            GlobalEnumTypeDef::RovStatus,
+            GlobalEnumTypeDef::AspaStatus,
+            GlobalEnumTypeDef::PeerRole,
         ])
         .iter()
     }
@@ -298,6 +305,26 @@ impl GlobalEnumTypeDef {
                 }
                 _ => Err(AccessReceiverError::Global),
             },
+            GlobalEnumTypeDef::RovStatus => match variant {
+                "ROV_VALID" => Ok(RovStatus::Valid.into()),
+                "ROV_INVALID" => Ok(RovStatus::Invalid.into()),
+                "ROV_NOT_FOUND" => Ok(RovStatus::NotFound.into()),
+                _ => Err(AccessReceiverError::Global),
+            },
+            GlobalEnumTypeDef::AspaStatus => match variant {
+                "ASPA_VALID" => Ok(AspaStatus::Valid.into()),
+                "ASPA_INVALID" => Ok(AspaStatus::Invalid.into()),
+                "ASPA_UNKNOWN" => Ok(AspaStatus::Unknown.into()),
+                _ => Err(AccessReceiverError::Global),
+            },
+            GlobalEnumTypeDef::PeerRole => match variant {
+                "ROLE_CUSTOMER" => Ok(PeerRole::Customer.into()),
+                "ROLE_PEER" => Ok(PeerRole::Peer.into()),
+                "ROLE_PROVIDER" => Ok(PeerRole::Provider.into()),
+                "ROLE_ROUTE_SERVER" => Ok(PeerRole::RouteServer.into()),
+                "ROLE_RS_CLIENT" => Ok(PeerRole::RsClient.into()),
+                _ => Err(AccessReceiverError::Global),
+            },
         }
     }
 
@@ -376,6 +403,9 @@ impl std::fmt::Display for GlobalEnumTypeDef {
             GlobalEnumTypeDef::BmpMessageType => {
                 write!(f, "BMP_MESSAGE_TYPE")
             }
+            GlobalEnumTypeDef::RovStatus => write!(f, "ROV_STATUS"),
+            GlobalEnumTypeDef::AspaStatus => write!(f, "ASPA_STATUS"),
+            GlobalEnumTypeDef::PeerRole => write!(f, "PEER_ROLE"),
         }
     }
 }
@@ -391,6 +421,11 @@ impl From<&GlobalEnumTypeDef> for ShortString {
             GlobalEnumTypeDef::BmpMessageType => {
                 ShortString::from("BMP_MESSAGE_TYPE")
             }
+            GlobalEnumTypeDef::RovStatus => ShortString::from("ROV_STATUS"),
+            GlobalEnumTypeDef::AspaStatus => {
+                ShortString::from("ASPA_STATUS")
+            }
+            GlobalEnumTypeDef::PeerRole => ShortString::from("PEER_ROLE"),
         }
     }
 }
@@ -406,6 +441,11 @@ impl From<GlobalEnumTypeDef> for ShortString {
             GlobalEnumTypeDef::BmpMessageType => {
                 ShortString::from("BMP_MESSAGE_TYPE")
             }
+            GlobalEnumTypeDef::RovStatus => ShortString::from("ROV_STATUS"),
+            GlobalEnumTypeDef::AspaStatus => {
+                ShortString::from("ASPA_STATUS")
+            }
+            GlobalEnumTypeDef::PeerRole => ShortString::from("PEER_ROLE"),
         }
     }
 }
@@ -420,6 +460,9 @@ impl TryFrom<&ShortString> for GlobalEnumTypeDef {
                 Ok(GlobalEnumTypeDef::WellKnownCommunities)
             }
             "BMP_MESSAGE_TYPE" => Ok(GlobalEnumTypeDef::BmpMessageType),
+            "ROV_STATUS" => Ok(GlobalEnumTypeDef::RovStatus),
+            "ASPA_STATUS" => Ok(GlobalEnumTypeDef::AspaStatus),
+            "PEER_ROLE" => Ok(GlobalEnumTypeDef::PeerRole),
             _ => Err(CompileError::from(format!(
                 "Unknown variant name {} in global enums",
                 value
@@ -439,6 +482,9 @@ impl FromStr for GlobalEnumTypeDef {
                 Ok(GlobalEnumTypeDef::WellKnownCommunities)
             }
             "BMP_MESSAGE_TYPE" => Ok(GlobalEnumTypeDef::BmpMessageType),
+            "ROV_STATUS" => Ok(GlobalEnumTypeDef::RovStatus),
+            "ASPA_STATUS" => Ok(GlobalEnumTypeDef::AspaStatus),
+            "PEER_ROLE" => Ok(GlobalEnumTypeDef::PeerRole),
             _ => Err(CompileError::from(format!(
                 "Unknown variant name {} in global enums",
                 s
@@ -457,6 +503,9 @@ impl TryFrom<&str> for GlobalEnumTypeDef {
                 Ok(GlobalEnumTypeDef::WellKnownCommunities)
             }
             "BMP_MESSAGE_TYPE" => Ok(GlobalEnumTypeDef::BmpMessageType),
+            "ROV_STATUS" => Ok(GlobalEnumTypeDef::RovStatus),
+            "ASPA_STATUS" => Ok(GlobalEnumTypeDef::AspaStatus),
+            "PEER_ROLE" => Ok(GlobalEnumTypeDef::PeerRole),
             _ => Err(CompileError::from(format!(
                 "Unknown variant name {} in global enums",
                 value
@@ -474,6 +523,9 @@ impl From<GlobalEnumTypeDef> for TypeDef {
             GlobalEnumTypeDef::BmpMessageType => TypeDef::LazyRecord(
                 super::lazyrecord_types::LazyRecordTypeDef::RouteMonitoring,
             ),
+            GlobalEnumTypeDef::RovStatus => TypeDef::U8,
+            GlobalEnumTypeDef::AspaStatus => TypeDef::U8,
+            GlobalEnumTypeDef::PeerRole => TypeDef::U8,
         }
     }
 }
@@ -0,0 +1,631 @@
+//! Built-in data sources for IRR data.
+//!
+//! [`Irr`] holds the as-set, route-set, route and route6 objects of one or
+//! more local RPSL dump files, from which the sets used for customer
+//! filtering are expanded. An [`AsSet`] contains the ASNs of an as-set and
+//! of the as-sets in it, recursively. A [`RouteSet`] contains the prefixes
+//! of a route-set, including those of the route-sets, as-sets and ASNs in
+//! it, the latter through the route and route6 objects of their origins.
+//!
+//! Both are custom data sources, that are registered under a name with a
+//! [`DataSourceRegistry`](crate::pipeline::DataSourceRegistry), and that
+//! have a `contains` method:
+//!
+//! ```roto
+//! filter-map customer {
+//!     define {
+//!         rx route: Route;
+//!     }
+//!
+//!     term from-customer {
+//!         match {
+//!             customer-asns.contains(route.as-path.origin());
+//!             customer-routes.contains(route.prefix);
+//!         }
+//!     }
+//!     ...
+//! }
+//! ```
+//!
+//! The range operators of the route-set members are mapped onto a
+//! [`PrefixMatchType`]: `^-` is `Longer`, `^+` is `OrLonger`, `^n` and
+//! `^n-m` are a `PrefixLengthRange`, and a member without an operator is
+//! `Exact`. An operator on a member that is a set, or an ASN, applies to
+//! all the prefixes of the member, and limits the lengths of the prefixes
+//! that have an operator of their own, as described in RFC 2622.
+
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use inetnum::addr::Prefix;
+use inetnum::asn::Asn;
+use log::debug;
+
+use crate::ast::{PrefixLength, PrefixLengthRange, PrefixMatchType};
+use crate::traits::RotoDataSource;
+use crate::vm::VmError;
+
+use super::builtin::BuiltinTypeValue;
+use super::datasources::DataSourceMethod;
+use super::loaders::LoadError;
+use super::rpki::truncate;
+use super::typedef::TypeDef;
+use super::typevalue::TypeValue;
+
+//------------ Irr ----------------------------------------------------------
+
+/// The objects of one or more RPSL dumps, that sets are expanded from.
+///
+/// Only the as-set, route-set, route and route6 objects are kept, objects
+/// of other classes are skipped. The names of the sets are case
+/// insensitive.
+#[derive(Clone, Debug, Default)]
+pub struct Irr {
+    /// The members of the as-sets, by the upper case name of the set.
+    as_sets: HashMap<String, Vec<String>>,
+    /// The members and mp-members of the route-sets, by the upper case name
+    /// of the set.
+    route_sets: HashMap<String, Vec<String>>,
+    /// The prefixes of the route and route6 objects, by their origin.
+    routes: HashMap<Asn, Vec<Prefix>>,
+}
+
+impl Irr {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read the objects of a single RPSL dump.
+    pub fn from_rpsl(reader: impl BufRead) -> Result<Self, LoadError> {
+        let mut irr = Self::new();
+        irr.add_rpsl(reader)?;
+        Ok(irr)
+    }
+
+    /// Read the objects of the RPSL dump files at `paths`. The message of
+    /// an error starts with the path of the file it happened in.
+    pub fn from_files(
+        paths: impl IntoIterator<Item = impl AsRef<Path>>,
+    ) -> Result<Self, LoadError> {
+        let mut irr = Self::new();
+        for path in paths {
+            let path = path.as_ref();
+            let file = File::open(path).map_err(|e| {
+                LoadError::new(0, format!("{}: {}", path.display(), e))
+            })?;
+            irr.add_rpsl(BufReader::new(file)).map_err(|e| LoadError {
+                message: format!("{}: {}", path.display(), e.message),
+                ..e
+            })?;
+        }
+        Ok(irr)
+    }
+
+    /// Add the objects of an RPSL dump. An object with the same class and
+    /// name as one that was added before replaces it, except for routes,
+    /// which are added to the ones of their origin. The row of an error is
+    /// the line it happened on.
+    pub fn add_rpsl(
+        &mut self,
+        reader: impl BufRead,
+    ) -> Result<(), LoadError> {
+        let mut object: Vec<(usize, String, String)> = vec![];
+
+        for (i, line) in reader.lines().enumerate() {
+            let row = i + 1;
+            let line = line.map_err(|e| LoadError::new(row, e.to_string()))?;
+            let line = line.trim_end();
+
+            // Objects are separated by blank lines.
+            if line.is_empty() {
+                self.add_object(&object)?;
+                object.clear();
+                continue;
+            }
+            if line.starts_with('%') || line.starts_with('#') {
+                continue;
+            }
+
+            // A line that starts with white space or a `+` continues the
+            // value of the attribute before it.
+            let continued = match line.strip_prefix('+') {
+                Some(rest) => Some(rest),
+                None if line.starts_with(|c: char| c == ' ' || c == '\t') => {
+                    Some(line)
+                }
+                None => None,
+            };
+            if let Some(value) = continued {
+                let Some((_, _, prev)) = object.last_mut() else {
+                    return Err(LoadError::new(
+                        row,
+                        "continuation line without an attribute",
+                    ));
+                };
+                prev.push(' ');
+                prev.push_str(strip_comment(value));
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once(':') else {
+                return Err(LoadError::new(
+                    row,
+                    format!("expected an attribute, found '{}'", line),
+                ));
+            };
+            object.push((
+                row,
+                key.trim().to_ascii_lowercase(),
+                strip_comment(value).to_string(),
+            ));
+        }
+
+        self.add_object(&object)
+    }
+
+    fn add_object(
+        &mut self,
+        object: &[(usize, String, String)],
+    ) -> Result<(), LoadError> {
+        let Some((row, class, name)) = object.first() else {
+            return Ok(());
+        };
+
+        match class.as_str() {
+            "as-set" => {
+                self.as_sets.insert(
+                    name.to_ascii_uppercase(),
+                    members(object, &["members"]),
+                );
+            }
+            "route-set" => {
+                self.route_sets.insert(
+                    name.to_ascii_uppercase(),
+                    members(object, &["members", "mp-members"]),
+                );
+            }
+            "route" | "route6" => {
+                let prefix = name.parse::<Prefix>().map_err(|_| {
+                    LoadError::new(*row, format!("invalid prefix '{}'", name))
+                })?;
+                let (row, _, origin) = object
+                    .iter()
+                    .find(|(_, key, _)| key == "origin")
+                    .ok_or_else(|| {
+                        LoadError::new(
+                            *row,
+                            format!("{} {} has no origin", class, name),
+                        )
+                    })?;
+                let origin = parse_asn(&origin.to_ascii_uppercase())
+                    .ok_or_else(|| {
+                        LoadError::new(
+                            *row,
+                            format!("invalid origin '{}'", origin),
+                        )
+                    })?;
+                self.routes.entry(origin).or_default().push(prefix);
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Expand the as-set `name` into its ASNs.
+    ///
+    /// As-sets in it that aren't in the dumps are skipped, since sets often
+    /// refer to the sets of other registries. An as-set `name` that isn't
+    /// in the dumps is an error.
+    pub fn as_set(&self, name: &str) -> Result<AsSet, LoadError> {
+        let name = name.to_ascii_uppercase();
+        if !self.as_sets.contains_key(&name) {
+            return Err(LoadError::new(0, format!("unknown as-set {}", name)));
+        }
+
+        let mut asns = HashSet::new();
+        self.expand_as_set(&name, &mut HashSet::new(), &mut asns);
+        Ok(AsSet { asns })
+    }
+
+    fn expand_as_set(
+        &self,
+        name: &str,
+        seen: &mut HashSet<String>,
+        asns: &mut HashSet<Asn>,
+    ) {
+        // Sets can contain each other.
+        if !seen.insert(name.to_string()) {
+            return;
+        }
+        let Some(members) = self.as_sets.get(name) else {
+            debug!("skipping unknown as-set {}", name);
+            return;
+        };
+
+        for member in members {
+            match parse_asn(member) {
+                Some(asn) => {
+                    asns.insert(asn);
+                }
+                None => self.expand_as_set(member, seen, asns),
+            }
+        }
+    }
+
+    /// Expand the route-set `name` into its prefixes, with the lengths
+    /// they match.
+    ///
+    /// Sets in it that aren't in the dumps are skipped, like for
+    /// [`Irr::as_set`]. A route-set `name` that isn't in the dumps, and
+    /// members that aren't valid, are errors.
+    pub fn route_set(&self, name: &str) -> Result<RouteSet, LoadError> {
+        let name = name.to_ascii_uppercase();
+        if !self.route_sets.contains_key(&name) {
+            return Err(LoadError::new(
+                0,
+                format!("unknown route-set {}", name),
+            ));
+        }
+
+        let mut expansion = Expansion::default();
+        let (routes, _) = self.expand_route_set(&name, &mut expansion)?;
+        Ok(RouteSet::new(routes))
+    }
+
+    /// The prefixes of the route-set `name`, and the position on the stack
+    /// of the lowest set that was cut off because it contains itself.
+    ///
+    /// The sets that are being expanded are on the stack of `expansion`, a
+    /// set that contains itself adds no prefixes the second time around.
+    /// The prefixes of a set are memoized, so that a set that is in more
+    /// than one other set is only expanded once, unless a set below it on
+    /// the stack was cut off, since then its prefixes depend on where the
+    /// expansion started.
+    fn expand_route_set(
+        &self,
+        name: &str,
+        expansion: &mut Expansion,
+    ) -> Result<(Vec<(Prefix, PrefixMatchType)>, Option<usize>), LoadError>
+    {
+        if let Some(routes) = expansion.route_sets.get(name) {
+            return Ok((routes.clone(), None));
+        }
+        if let Some(pos) = expansion.stack.iter().position(|s| s == name) {
+            return Ok((vec![], Some(pos)));
+        }
+        let Some(members) = self.route_sets.get(name) else {
+            debug!("skipping unknown route-set {}", name);
+            return Ok((vec![], None));
+        };
+        let depth = expansion.stack.len();
+        expansion.stack.push(name.to_string());
+        let mut cut = None;
+
+        let mut routes = vec![];
+        for member in members {
+            let invalid = || {
+                LoadError::new(
+                    0,
+                    format!(
+                        "invalid member '{}' of route-set {}",
+                        member, name
+                    ),
+                )
+            };
+            let (base, operator) = match member.split_once('^') {
+                Some((base, op)) => {
+                    (base, Some(parse_operator(op).ok_or_else(invalid)?))
+                }
+                None => (member.as_str(), None),
+            };
+
+            let prefixes = if base.contains('/') {
+                let prefix = base.parse::<Prefix>().map_err(|_| invalid())?;
+                vec![(prefix, PrefixMatchType::Exact)]
+            } else if let Some(asn) = parse_asn(base) {
+                self.origin_routes(asn)
+            } else if self.route_sets.contains_key(base) {
+                let (set_routes, set_cut) =
+                    self.expand_route_set(base, expansion)?;
+                cut = match (cut, set_cut) {
+                    (Some(a), Some(b)) => Some(a.min(b)),
+                    (a, b) => a.or(b),
+                };
+                set_routes
+            } else {
+                self.as_set_routes(base, expansion)
+            };
+
+            routes.extend(prefixes.into_iter().filter_map(|(prefix, ty)| {
+                match &operator {
+                    Some(op) => {
+                        Some((prefix, with_operator(&prefix, &ty, op)?))
+                    }
+                    None => Some((prefix, ty)),
+                }
+            }));
+        }
+
+        expansion.stack.pop();
+
+        // Cut offs of this set itself don't matter once it's done.
+        let cut = cut.filter(|pos| *pos < depth);
+        if cut.is_none() {
+            expansion.route_sets.insert(name.to_string(), routes.clone());
+        }
+        Ok((routes, cut))
+    }
+
+    /// The prefixes of the origins of the ASNs in the as-set `name`,
+    /// memoized like the prefixes of route-sets.
+    fn as_set_routes(
+        &self,
+        name: &str,
+        expansion: &mut Expansion,
+    ) -> Vec<(Prefix, PrefixMatchType)> {
+        if let Some(routes) = expansion.as_sets.get(name) {
+            return routes.clone();
+        }
+
+        let mut asns = HashSet::new();
+        self.expand_as_set(name, &mut HashSet::new(), &mut asns);
+        let routes: Vec<_> = asns
+            .into_iter()
+            .flat_map(|asn| self.origin_routes(asn))
+            .collect();
+        expansion.as_sets.insert(name.to_string(), routes.clone());
+        routes
+    }
+
+    /// The prefixes of the route and route6 objects with origin `asn`.
+    fn origin_routes(&self, asn: Asn) -> Vec<(Prefix, PrefixMatchType)> {
+        self.routes
+            .get(&asn)
+            .into_iter()
+            .flatten()
+            .map(|prefix| (*prefix, PrefixMatchType::Exact))
+            .collect()
+    }
+}
+
+/// The state of the expansion of a route-set.
+#[derive(Default)]
+struct Expansion {
+    /// The route-sets that are being expanded.
+    stack: Vec<String>,
+    /// The prefixes of the route-sets that were expanded.
+    route_sets: HashMap<String, Vec<(Prefix, PrefixMatchType)>>,
+    /// The prefixes of the as-sets that were expanded.
+    as_sets: HashMap<String, Vec<(Prefix, PrefixMatchType)>>,
+}
+
+//------------ AsSet --------------------------------------------------------
+
+/// The ASNs of an expanded as-set.
+///
+/// In roto it has a `contains` method that takes an `Asn`.
+#[derive(Clone, Debug, Default)]
+pub struct AsSet {
+    asns: HashSet<Asn>,
+}
+
+impl AsSet {
+    pub fn new(asns: impl IntoIterator<Item = Asn>) -> Self {
+        Self {
+            asns: asns.into_iter().collect(),
+        }
+    }
+
+    pub fn contains(&self, asn: Asn) -> bool {
+        self.asns.contains(&asn)
+    }
+
+    pub fn len(&self) -> usize {
+        self.asns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.asns.is_empty()
+    }
+}
+
+impl RotoDataSource for AsSet {
+    fn methods(&self) -> Vec<DataSourceMethod> {
+        vec![DataSourceMethod::new(
+            "contains",
+            vec![TypeDef::Asn],
+            TypeDef::Bool,
+        )]
+    }
+
+    fn exec_method(
+        &self,
+        method: usize,
+        args: &[&TypeValue],
+    ) -> Result<TypeValue, VmError> {
+        match (method, args) {
+            (0, [TypeValue::Builtin(BuiltinTypeValue::Asn(asn))]) => {
+                Ok(self.contains(*asn).into())
+            }
+            _ => Err(VmError::InvalidMethodCall),
+        }
+    }
+}
+
+//------------ RouteSet -----------------------------------------------------
+
+/// The prefixes of an expanded route-set, with the lengths of the more
+/// specific prefixes they match.
+///
+/// In roto it has a `contains` method that takes a `Prefix`.
+#[derive(Clone, Debug, Default)]
+pub struct RouteSet {
+    /// The match types of the prefixes, by prefix.
+    routes: HashMap<Prefix, Vec<PrefixMatchType>>,
+    len: usize,
+}
+
+impl RouteSet {
+    pub fn new(
+        routes: impl IntoIterator<Item = (Prefix, PrefixMatchType)>,
+    ) -> Self {
+        let mut map: HashMap<Prefix, Vec<PrefixMatchType>> = HashMap::new();
+        let mut len = 0;
+        for (prefix, ty) in routes {
+            map.entry(prefix).or_default().push(ty);
+            len += 1;
+        }
+        Self { routes: map, len }
+    }
+
+    /// Whether one of the prefixes of the set covers `prefix`, and matches
+    /// its length.
+    ///
+    /// Only the prefixes that cover `prefix` are looked up, one for every
+    /// length up to the length of `prefix`.
+    pub fn contains(&self, prefix: &Prefix) -> bool {
+        (0..=prefix.len())
+            .filter_map(|len| truncate(prefix, len))
+            .filter_map(|route| Some((route, self.routes.get(&route)?)))
+            .any(|(route, types)| {
+                types.iter().any(|ty| {
+                    length_range(&route, ty).is_some_and(|(start, end)| {
+                        (start..=end).contains(&prefix.len())
+                    })
+                })
+            })
+    }
+
+    /// The prefixes of the set, with the lengths of the more specific
+    /// prefixes they match.
+    pub fn routes(
+        &self,
+    ) -> impl Iterator<Item = (&Prefix, &PrefixMatchType)> + '_ {
+        self.routes.iter().flat_map(|(prefix, types)| {
+            types.iter().map(move |ty| (prefix, ty))
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl RotoDataSource for RouteSet {
+    fn methods(&self) -> Vec<DataSourceMethod> {
+        vec![DataSourceMethod::new(
+            "contains",
+            vec![TypeDef::Prefix],
+            TypeDef::Bool,
+        )]
+    }
+
+    fn exec_method(
+        &self,
+        method: usize,
+        args: &[&TypeValue],
+    ) -> Result<TypeValue, VmError> {
+        match (method, args) {
+            (0, [TypeValue::Builtin(BuiltinTypeValue::Prefix(prefix))]) => {
+                Ok(self.contains(prefix).into())
+            }
+            _ => Err(VmError::InvalidMethodCall),
+        }
+    }
+}
+
+//------------ Helpers ------------------------------------------------------
+
+/// The value of an attribute, without the comment at the end.
+fn strip_comment(value: &str) -> &str {
+    value.split('#').next().unwrap_or_default().trim()
+}
+
+/// The upper case members in the values of the attributes `keys`.
+fn members(object: &[(usize, String, String)], keys: &[&str]) -> Vec<String> {
+    object
+        .iter()
+        .filter(|(_, key, _)| keys.contains(&key.as_str()))
+        .flat_map(|(_, _, value)| {
+            value.split(|c: char| c == ',' || c.is_whitespace())
+        })
+        .filter(|member| !member.is_empty())
+        .map(|member| member.to_ascii_uppercase())
+        .collect()
+}
+
+/// An upper case `AS<number>`.
+fn parse_asn(s: &str) -> Option<Asn> {
+    let number = s.strip_prefix("AS")?;
+    if number.is_empty() || !number.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    number.parse().ok().map(Asn::from_u32)
+}
+
+/// The match type of a range operator, without the `^`.
+fn parse_operator(op: &str) -> Option<PrefixMatchType> {
+    match op {
+        "-" => Some(PrefixMatchType::Longer),
+        "+" => Some(PrefixMatchType::OrLonger),
+        _ => {
+            let (start, end) = op.split_once('-').unwrap_or((op, op));
+            Some(PrefixMatchType::PrefixLengthRange(PrefixLengthRange {
+                start: PrefixLength(start.parse().ok()?),
+                end: PrefixLength(end.parse().ok()?),
+            }))
+        }
+    }
+}
+
+/// The lengths of the prefixes `prefix` with match type `ty` matches, or
+/// `None` if it matches none.
+fn length_range(prefix: &Prefix, ty: &PrefixMatchType) -> Option<(u8, u8)> {
+    let len = prefix.len();
+    let max = if prefix.is_v4() { 32 } else { 128 };
+
+    let (start, end) = match ty {
+        PrefixMatchType::Exact => (len, len),
+        PrefixMatchType::Longer => (len.checked_add(1)?, max),
+        PrefixMatchType::OrLonger => (len, max),
+        PrefixMatchType::PrefixLengthRange(range) => {
+            (range.start.0, range.end.0)
+        }
+        PrefixMatchType::UpTo(end) | PrefixMatchType::Through(end) => {
+            (len, end.0)
+        }
+        PrefixMatchType::NetMask(_) => return None,
+    };
+
+    let (start, end) = (start.max(len), end.min(max));
+    (start <= end).then_some((start, end))
+}
+
+/// The match type of `prefix` with match type `ty`, with the range
+/// operator `op` of the set it is in applied.
+fn with_operator(
+    prefix: &Prefix,
+    ty: &PrefixMatchType,
+    op: &PrefixMatchType,
+) -> Option<PrefixMatchType> {
+    if let PrefixMatchType::Exact = ty {
+        return Some(op.clone());
+    }
+    let (start, end) = length_range(prefix, ty)?;
+    let (op_start, op_end) = length_range(prefix, op)?;
+    let (start, end) = (start.max(op_start), end.min(op_end));
+
+    (start <= end).then_some(PrefixMatchType::PrefixLengthRange(
+        PrefixLengthRange {
+            start: PrefixLength(start),
+            end: PrefixLength(end),
+        },
+    ))
+}
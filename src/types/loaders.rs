@@ -0,0 +1,446 @@
+//! Loaders that read the records for a table data source from a file.
+//!
+//! Two formats are supported:
+//!
+//! - CSV, with a header line that names the fields of the record type. The
+//!   columns may come in any order. Fields can be quoted with `"`, a quote
+//!   inside a quoted field is written as `""`. Quoted fields can span
+//!   multiple lines.
+//! - JSON lines, with one JSON object per line, that has the fields of the
+//!   record type as its keys.
+//!
+//! In both formats empty lines, and lines starting with `#`, are skipped,
+//! as is a byte order mark at the start of the file.
+//!
+//! Every record is validated against the record type that was declared in
+//! the roto source code: all fields have to be present, no other fields are
+//! allowed, and every value has to parse as the type of its field. The first
+//! failure is returned as a [`LoadError`], with the line number and the name
+//! of the column it happened in.
+
+use std::io::{BufRead, Read};
+use std::iter::Peekable;
+use std::net::IpAddr;
+use std::str::Chars;
+
+use inetnum::addr::Prefix;
+use inetnum::asn::Asn;
+
+use crate::ast::ShortString;
+
+use super::builtin::{
+    BuiltinTypeValue, IntegerLiteral, PrefixLength, StringLiteral,
+};
+use super::collections::Record;
+use super::typedef::TypeDef;
+use super::typevalue::TypeValue;
+
+//------------ LoadError ----------------------------------------------------
+
+/// A failure to load a record from a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadError {
+    /// The line, or the entry, the error happened on, starting at 1, or 0
+    /// if the error is not about a line in the file. For a CSV record that
+    /// spans multiple lines, this is the line it starts on.
+    pub row: usize,
+    /// The field the error happened in, if any.
+    pub column: Option<ShortString>,
+    pub message: String,
+}
+
+impl LoadError {
+    pub(crate) fn new(row: usize, message: impl Into<String>) -> Self {
+        Self {
+            row,
+            column: None,
+            message: message.into(),
+        }
+    }
+
+    pub(crate) fn in_column(
+        row: usize,
+        column: &ShortString,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            row,
+            column: Some(column.clone()),
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.row, &self.column) {
+            (0, _) => write!(f, "{}", self.message),
+            (row, None) => write!(f, "row {}: {}", row, self.message),
+            (row, Some(column)) => write!(
+                f,
+                "row {}, column '{}': {}",
+                row, column, self.message
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+//------------ CSV ----------------------------------------------------------
+
+/// Read the records of type `ty` from CSV.
+pub fn records_from_csv(
+    ty: &TypeDef,
+    mut reader: impl BufRead,
+) -> Result<Vec<Record>, LoadError> {
+    let fields = record_fields(ty)?;
+
+    // Quoted fields can contain line breaks, so the text is split into
+    // records as a whole.
+    let mut text = String::new();
+    reader
+        .read_to_string(&mut text)
+        .map_err(|e| LoadError::new(0, e.to_string()))?;
+    let mut rows = CsvRows {
+        chars: text.strip_prefix(BOM).unwrap_or(&text).chars().peekable(),
+        line: 0,
+    };
+
+    let (header_row, columns) = match rows.next() {
+        Some(row) => row?,
+        None => return Ok(vec![]),
+    };
+
+    for column in &columns {
+        if !fields.iter().any(|(name, _)| name.as_str() == column) {
+            return Err(LoadError::new(
+                header_row,
+                format!("unknown column '{}'", column),
+            ));
+        }
+    }
+
+    // For every field of the record type, the index of its column.
+    let indexes = fields
+        .iter()
+        .map(|(name, _)| {
+            columns.iter().position(|c| c == name.as_str()).ok_or_else(
+                || LoadError::in_column(header_row, name, "missing column"),
+            )
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut records = vec![];
+    for row in rows {
+        let (row, values) = row?;
+        if values.len() != columns.len() {
+            return Err(LoadError::new(
+                row,
+                format!(
+                    "expected {} columns, but found {}",
+                    columns.len(),
+                    values.len()
+                ),
+            ));
+        }
+
+        let kvs = fields
+            .iter()
+            .zip(&indexes)
+            .map(|((name, ty), i)| {
+                parse_value(ty, &values[*i])
+                    .map(|v| (name.as_str(), v))
+                    .map_err(|e| LoadError::in_column(row, name, e))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        records.push(create_record(ty, row, kvs)?);
+    }
+
+    Ok(records)
+}
+
+/// The records of a CSV text, split into their (unquoted) fields, with the
+/// line they start on. Empty lines and comments are skipped.
+struct CsvRows<'a> {
+    chars: Peekable<Chars<'a>>,
+    /// The number of the line that was read last.
+    line: usize,
+}
+
+impl CsvRows<'_> {
+    /// Whether the line that starts at the current position is empty or a
+    /// comment.
+    fn at_skipped_line(&self) -> bool {
+        let mut chars = self.chars.clone();
+        while chars.next_if(|c| c.is_whitespace() && *c != '\n').is_some() {}
+        matches!(chars.peek(), None | Some('\n') | Some('#'))
+    }
+
+    fn skip_line(&mut self) {
+        while self.chars.next_if(|c| *c != '\n').is_some() {}
+        self.chars.next();
+    }
+
+    fn skip_blanks(&mut self) {
+        while self
+            .chars
+            .next_if(|c| *c == ' ' || *c == '\t' || *c == '\r')
+            .is_some()
+        {}
+    }
+
+    /// Read the record that starts at the current position, on line `row`.
+    fn record(&mut self, row: usize) -> Result<Vec<String>, LoadError> {
+        let mut fields = vec![];
+
+        loop {
+            let mut field = String::new();
+            self.skip_blanks();
+
+            if self.chars.next_if_eq(&'"').is_some() {
+                loop {
+                    match self.chars.next() {
+                        Some('"')
+                            if self.chars.next_if_eq(&'"').is_some() =>
+                        {
+                            field.push('"')
+                        }
+                        Some('"') => break,
+                        Some(c) => {
+                            if c == '\n' {
+                                self.line += 1;
+                            }
+                            field.push(c)
+                        }
+                        None => {
+                            return Err(LoadError::new(
+                                row,
+                                "unterminated quote",
+                            ))
+                        }
+                    }
+                }
+                self.skip_blanks();
+                if !matches!(self.chars.peek(), None | Some(',' | '\n')) {
+                    return Err(LoadError::new(
+                        row,
+                        "unexpected character after quoted field",
+                    ));
+                }
+            } else {
+                while let Some(c) =
+                    self.chars.next_if(|c| *c != ',' && *c != '\n')
+                {
+                    field.push(c);
+                }
+                field.truncate(field.trim_end().len());
+            }
+
+            fields.push(field);
+            if self.chars.next() != Some(',') {
+                return Ok(fields);
+            }
+        }
+    }
+}
+
+impl Iterator for CsvRows<'_> {
+    type Item = Result<(usize, Vec<String>), LoadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.chars.peek()?;
+            self.line += 1;
+            if self.at_skipped_line() {
+                self.skip_line();
+                continue;
+            }
+            let row = self.line;
+            return Some(self.record(row).map(|fields| (row, fields)));
+        }
+    }
+}
+
+//------------ JSON lines ---------------------------------------------------
+
+/// Read the records of type `ty` from JSON lines.
+pub fn records_from_json_lines(
+    ty: &TypeDef,
+    reader: impl BufRead,
+) -> Result<Vec<Record>, LoadError> {
+    let fields = record_fields(ty)?;
+
+    let mut records = vec![];
+    for line in data_lines(reader) {
+        let (row, line) = line?;
+        let mut object = match serde_json::from_str(&line) {
+            Ok(serde_json::Value::Object(object)) => object,
+            Ok(_) => {
+                return Err(LoadError::new(row, "expected a JSON object"))
+            }
+            Err(e) => return Err(LoadError::new(row, e.to_string())),
+        };
+
+        let kvs = fields
+            .iter()
+            .map(|(name, ty)| {
+                let value = object.remove(name.as_str()).ok_or_else(|| {
+                    LoadError::in_column(row, name, "missing field")
+                })?;
+                json_value(ty, value)
+                    .map(|v| (name.as_str(), v))
+                    .map_err(|e| LoadError::in_column(row, name, e))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if let Some(key) = object.keys().next() {
+            return Err(LoadError::new(
+                row,
+                format!("unknown field '{}'", key),
+            ));
+        }
+        records.push(create_record(ty, row, kvs)?);
+    }
+
+    Ok(records)
+}
+
+/// Convert a JSON value into a value of type `ty`. Strings are parsed, so
+/// e.g. `"AS65534"` is a valid Asn, numbers and booleans are only accepted
+/// for fields of a matching type.
+fn json_value(
+    ty: &TypeDef,
+    value: serde_json::Value,
+) -> Result<TypeValue, String> {
+    match (ty, value) {
+        (_, serde_json::Value::String(s)) => parse_value(ty, &s),
+        (
+            TypeDef::U32
+            | TypeDef::U16
+            | TypeDef::U8
+            | TypeDef::Asn
+            | TypeDef::PrefixLength
+            | TypeDef::IntegerLiteral,
+            serde_json::Value::Number(n),
+        ) => parse_value(ty, &n.to_string()),
+        (TypeDef::Bool, serde_json::Value::Bool(b)) => {
+            Ok(TypeValue::Builtin(BuiltinTypeValue::Bool(b)))
+        }
+        (ty, value) => {
+            Err(format!("cannot convert '{}' into type {}", value, ty))
+        }
+    }
+}
+
+//------------ Values -------------------------------------------------------
+
+/// Parse the textual representation of a value of type `ty`.
+fn parse_value(ty: &TypeDef, s: &str) -> Result<TypeValue, String> {
+    let invalid = || format!("invalid value '{}' for type {}", s, ty);
+
+    let value = match ty {
+        TypeDef::U32 => {
+            BuiltinTypeValue::U32(s.parse().map_err(|_| invalid())?)
+        }
+        TypeDef::U16 => {
+            BuiltinTypeValue::U16(s.parse().map_err(|_| invalid())?)
+        }
+        TypeDef::U8 => {
+            BuiltinTypeValue::U8(s.parse().map_err(|_| invalid())?)
+        }
+        TypeDef::IntegerLiteral => BuiltinTypeValue::IntegerLiteral(
+            IntegerLiteral::new(s.parse().map_err(|_| invalid())?),
+        ),
+        TypeDef::Bool => match s {
+            "true" => BuiltinTypeValue::Bool(true),
+            "false" => BuiltinTypeValue::Bool(false),
+            _ => return Err(invalid()),
+        },
+        TypeDef::StringLiteral => {
+            BuiltinTypeValue::StringLiteral(StringLiteral::new(s.into()))
+        }
+        TypeDef::Asn => {
+            let n = match s.get(..2) {
+                Some(p) if p.eq_ignore_ascii_case("as") => &s[2..],
+                _ => s,
+            };
+            BuiltinTypeValue::Asn(Asn::from_u32(
+                n.parse().map_err(|_| invalid())?,
+            ))
+        }
+        TypeDef::Prefix => BuiltinTypeValue::Prefix(
+            s.parse::<Prefix>().map_err(|_| invalid())?,
+        ),
+        TypeDef::IpAddr => BuiltinTypeValue::IpAddr(
+            s.parse::<IpAddr>().map_err(|_| invalid())?,
+        ),
+        TypeDef::PrefixLength => {
+            let len: u8 = s
+                .strip_prefix('/')
+                .unwrap_or(s)
+                .parse()
+                .map_err(|_| invalid())?;
+            if len > 128 {
+                return Err(invalid());
+            }
+            BuiltinTypeValue::PrefixLength(PrefixLength::new(len))
+        }
+        _ => return Err(format!("fields of type {} can't be loaded", ty)),
+    };
+
+    Ok(TypeValue::Builtin(value))
+}
+
+//------------ Helpers ------------------------------------------------------
+
+/// The (sorted) fields of the record type `ty`.
+fn record_fields(
+    ty: &TypeDef,
+) -> Result<Vec<(ShortString, TypeDef)>, LoadError> {
+    match ty {
+        TypeDef::Record(rec_type) => Ok(rec_type
+            .iter()
+            .map(|(name, ty)| (name.clone(), *ty.clone()))
+            .collect()),
+        _ => Err(LoadError::new(0, format!("{} is not a record type", ty))),
+    }
+}
+
+/// The byte order mark that some editors put at the start of a file.
+const BOM: char = '\u{feff}';
+
+/// The numbered lines of `reader`, without the byte order mark, the empty
+/// lines and comments.
+fn data_lines(
+    reader: impl BufRead,
+) -> impl Iterator<Item = Result<(usize, String), LoadError>> {
+    reader
+        .lines()
+        .enumerate()
+        .map(|(i, line)| {
+            line.map(|mut line| {
+                if i == 0 && line.starts_with(BOM) {
+                    line.remove(0);
+                }
+                (i + 1, line)
+            })
+            .map_err(|e| LoadError::new(i + 1, e.to_string()))
+        })
+        .filter(|line| match line {
+            Ok((_, line)) => {
+                let line = line.trim();
+                !line.is_empty() && !line.starts_with('#')
+            }
+            Err(_) => true,
+        })
+}
+
+fn create_record(
+    ty: &TypeDef,
+    row: usize,
+    kvs: Vec<(&str, TypeValue)>,
+) -> Result<Record, LoadError> {
+    Record::create_instance_with_ordered_fields(ty, kvs)
+        .map_err(|e| LoadError::new(row, e.to_string()))
+}
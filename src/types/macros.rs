@@ -293,7 +293,7 @@ macro_rules! setmethodonly {
     
         fn exec_type_method(
             _method_token: usize,
-            _args: &[$crate::vm::StackValue],
+            _args: &[$crate::unstable::StackValue],
             _res_type: $crate::types::typedef::TypeDef,
         ) -> Result<TypeValue, $crate::vm::VmError> {
             Err(VmError::InvalidMethodCall)
@@ -2,8 +2,12 @@ pub mod builtin;
 pub mod collections;
 pub mod enum_types;
 pub mod datasources;
+pub mod irr;
 pub mod lazyrecord_types;
+pub mod loaders;
 pub mod outputs;
+pub mod rpki;
+pub(crate) mod shards;
 pub mod typedef;
 pub mod typevalue;
 
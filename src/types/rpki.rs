@@ -0,0 +1,881 @@
+//! Built-in data sources for RPKI data.
+//!
+//! [`Vrps`] holds the Validated ROA Payloads that are used for route origin
+//! validation. It is declared in roto as a rib that contains the built-in
+//! `Vrp` record type, and it has the `validate` method on top of the
+//! methods of a rib:
+//!
+//! ```roto
+//! rib vrps contains Vrp
+//!
+//! filter-map rov {
+//!     define {
+//!         rx route: Route;
+//!     }
+//!
+//!     term rov-invalid {
+//!         match {
+//!             vrps.validate(route.prefix, route.as-path.origin())
+//!                 == ROV_INVALID;
+//!         }
+//!     }
+//!     ...
+//! }
+//! ```
+//!
+//! [`Aspas`] holds the ASPA records that are used for AS path verification.
+//! It is declared as a table that contains the built-in `Aspa` record type,
+//! which is looked up by the customer AS, and it has a `validate` method
+//! that takes the role of the neighbor the route was received from:
+//!
+//! ```roto
+//! table aspas contains Aspa
+//!
+//! filter-map aspa {
+//!     define {
+//!         rx route: Route;
+//!     }
+//!
+//!     term aspa-invalid {
+//!         match {
+//!             aspas.validate(route.as-path, ROLE_PROVIDER) == ASPA_INVALID;
+//!         }
+//!     }
+//!     ...
+//! }
+//! ```
+
+use std::collections::HashMap;
+use std::io::Read;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use inetnum::addr::Prefix;
+use inetnum::asn::Asn;
+use log::trace;
+use routecore::bgp::aspath::HopPath;
+
+use crate::ast::ShortString;
+use crate::traits::RotoRib;
+use crate::vm::{StackValue, VmError};
+
+use super::builtin::{BuiltinTypeValue, PrefixLength};
+use super::collections::{ElementTypeValue, List, Record};
+use super::datasources::{
+    prefix_arg, DataSource, DataSourceMethodValue, RibToken, Table,
+};
+use super::enum_types::{EnumVariant, GlobalEnumTypeDef};
+use super::loaders::LoadError;
+use super::shards::ShardedMap;
+use super::typedef::{RecordTypeDef, TypeDef};
+use super::typevalue::TypeValue;
+
+//------------ RovStatus ----------------------------------------------------
+
+/// The outcome of route origin validation, as defined in RFC 6811.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RovStatus {
+    /// A VRP covers the prefix and matches the origin and the length.
+    Valid,
+    /// VRPs cover the prefix, but none of them matches.
+    Invalid,
+    /// No VRP covers the prefix.
+    NotFound,
+}
+
+impl From<RovStatus> for BuiltinTypeValue {
+    fn from(status: RovStatus) -> Self {
+        BuiltinTypeValue::ConstU8EnumVariant(EnumVariant::new((
+            GlobalEnumTypeDef::RovStatus.into(),
+            status as u8,
+        )))
+    }
+}
+
+impl From<RovStatus> for TypeValue {
+    fn from(status: RovStatus) -> Self {
+        TypeValue::Builtin(status.into())
+    }
+}
+
+//------------ Vrp ----------------------------------------------------------
+
+/// A Validated ROA Payload: the prefixes up to `max_length` that `asn` is
+/// allowed to originate.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Vrp {
+    pub prefix: Prefix,
+    pub max_length: u8,
+    pub asn: Asn,
+}
+
+impl Vrp {
+    fn matches(&self, prefix: &Prefix, origin: Asn) -> bool {
+        // A VRP for AS0 means that the prefix should not be routed at all.
+        self.asn == origin
+            && self.asn != Asn::from_u32(0)
+            && prefix.len() <= self.max_length
+    }
+}
+
+impl From<&Vrp> for TypeValue {
+    fn from(vrp: &Vrp) -> Self {
+        let field = |name: &str, value: BuiltinTypeValue| {
+            let value = TypeValue::Builtin(value);
+            (ShortString::from(name), ElementTypeValue::Primitive(value))
+        };
+
+        TypeValue::Record(Record::new(vec![
+            field("asn", BuiltinTypeValue::Asn(vrp.asn)),
+            field(
+                "max_length",
+                BuiltinTypeValue::PrefixLength(PrefixLength::new(
+                    vrp.max_length,
+                )),
+            ),
+            field("prefix", BuiltinTypeValue::Prefix(vrp.prefix)),
+        ]))
+    }
+}
+
+impl TryFrom<&TypeValue> for Vrp {
+    type Error = VmError;
+
+    fn try_from(value: &TypeValue) -> Result<Self, VmError> {
+        let rec = match value {
+            TypeValue::Record(rec) => rec,
+            _ => return Err(VmError::InvalidValueType),
+        };
+        let field = |name: &str| {
+            rec.get_value_for_field(name)
+                .and_then(|v| <&TypeValue>::try_from(v).ok())
+        };
+
+        match (field("prefix"), field("max_length"), field("asn")) {
+            (
+                Some(TypeValue::Builtin(BuiltinTypeValue::Prefix(prefix))),
+                Some(TypeValue::Builtin(BuiltinTypeValue::PrefixLength(
+                    max_length,
+                ))),
+                Some(TypeValue::Builtin(BuiltinTypeValue::Asn(asn))),
+            ) => Ok(Vrp {
+                prefix: *prefix,
+                max_length: max_length.0,
+                asn: *asn,
+            }),
+            _ => Err(VmError::InvalidValueType),
+        }
+    }
+}
+
+//------------ Vrps ---------------------------------------------------------
+
+/// A set of VRPs that routes can be validated against.
+#[derive(Clone, Debug)]
+pub struct Vrps {
+    name: ShortString,
+    /// The VRPs by their prefix. An update of a prefix only copies the
+    /// shard of the prefix.
+    vrps: ShardedMap<Prefix, Vec<Vrp>>,
+}
+
+impl Vrps {
+    pub fn new(name: &str, vrps: impl IntoIterator<Item = Vrp>) -> Self {
+        let mut map = ShardedMap::new();
+        for vrp in vrps {
+            map.entry(vrp.prefix).push(vrp);
+        }
+
+        Self {
+            name: name.into(),
+            vrps: map,
+        }
+    }
+
+    /// Read the VRPs from JSON, in the format that is exported by the
+    /// common RPKI validators:
+    ///
+    /// ```json
+    /// { "roas": [
+    ///     { "asn": "AS65534", "prefix": "192.0.2.0/24", "maxLength": 24 }
+    /// ] }
+    /// ```
+    ///
+    /// The asn can be a number or a string, the `maxLength` defaults to the
+    /// length of the prefix. Other fields are ignored. The row of an error
+    /// is the position of the ROA in the list, starting at 1.
+    pub fn from_json(
+        name: &str,
+        reader: impl Read,
+    ) -> Result<Self, LoadError> {
+        let json: serde_json::Value = serde_json::from_reader(reader)
+            .map_err(|e| LoadError::new(e.line(), e.to_string()))?;
+
+        let roas = json
+            .get("roas")
+            .and_then(|roas| roas.as_array())
+            .ok_or_else(|| {
+                LoadError::new(0, "expected an object with a 'roas' list")
+            })?;
+
+        let vrps = roas
+            .iter()
+            .enumerate()
+            .map(|(i, roa)| vrp_from_json(i + 1, roa))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self::new(name, vrps))
+    }
+
+    /// The record type of the VRPs as exposed to roto, the built-in `Vrp`
+    /// type.
+    pub fn record_type() -> TypeDef {
+        TypeDef::Record(RecordTypeDef::new(vec![
+            ("asn".into(), Box::new(TypeDef::Asn)),
+            ("max_length".into(), Box::new(TypeDef::PrefixLength)),
+            ("prefix".into(), Box::new(TypeDef::Prefix)),
+        ]))
+    }
+
+    /// Validate the origin of a route for `prefix`, as described in RFC
+    /// 6811.
+    pub fn validate(&self, prefix: &Prefix, origin: Asn) -> RovStatus {
+        let mut status = RovStatus::NotFound;
+        for vrp in self.covering(prefix) {
+            if vrp.matches(prefix, origin) {
+                return RovStatus::Valid;
+            }
+            status = RovStatus::Invalid;
+        }
+        status
+    }
+
+    /// The VRPs for `prefix` and all its less-specifics, from the least
+    /// specific to `prefix` itself.
+    fn covering(&self, prefix: &Prefix) -> impl Iterator<Item = &Vrp> + '_ {
+        let prefix = *prefix;
+        (0..=prefix.len())
+            .filter_map(move |len| truncate(&prefix, len))
+            .filter_map(|p| self.vrps.get(&p))
+            .flatten()
+    }
+
+    /// The VRPs for the more-specifics of `prefix`.
+    fn more_specifics(
+        &self,
+        prefix: &Prefix,
+    ) -> impl Iterator<Item = &Vrp> + '_ {
+        let prefix = *prefix;
+        self.vrps
+            .iter()
+            .filter(move |(p, _)| {
+                p.len() > prefix.len()
+                    && truncate(p, prefix.len()) == Some(prefix)
+            })
+            .flat_map(|(_, vrps)| vrps)
+    }
+}
+
+/// The VRPs as a roto list of records.
+fn list<'a>(vrps: impl Iterator<Item = &'a Vrp>) -> TypeValue {
+    TypeValue::List(List::new(
+        vrps.map(|vrp| ElementTypeValue::Primitive(vrp.into()))
+            .collect(),
+    ))
+}
+
+impl RotoRib for Vrps {
+    fn exec_value_method<'a>(
+        &'a self,
+        _method_token: usize,
+        _args: &'a [StackValue],
+        _res_type: TypeDef,
+    ) -> Result<TypeValue, VmError> {
+        Err(VmError::InvalidMethodCall)
+    }
+
+    fn exec_ref_value_method(
+        &self,
+        method: usize,
+        args: &[StackValue],
+        _res_type: TypeDef,
+    ) -> Result<DataSourceMethodValue, VmError> {
+        let token = RibToken::try_from(method)?;
+        trace!("{:?} on vrps with args {:?}", token, args);
+
+        if let RibToken::Validate = token {
+            let mut prefix = None;
+            let mut origin = None;
+            for arg in args {
+                match arg.as_ref() {
+                    TypeValue::Builtin(BuiltinTypeValue::Prefix(p)) => {
+                        prefix = Some(*p)
+                    }
+                    TypeValue::Builtin(BuiltinTypeValue::Asn(asn)) => {
+                        origin = Some(*asn)
+                    }
+                    _ => {}
+                }
+            }
+
+            let res = match (prefix, origin) {
+                (Some(prefix), Some(origin)) => {
+                    self.validate(&prefix, origin).into()
+                }
+                _ => TypeValue::Unknown,
+            };
+            return Ok(DataSourceMethodValue::TypeValue(res));
+        }
+
+        let prefix = if let Some(prefix) = prefix_arg(args)? {
+            prefix
+        } else {
+            return Ok(DataSourceMethodValue::TypeValue(TypeValue::Unknown));
+        };
+        let exact = self.vrps.get(&prefix).into_iter().flatten();
+
+        let res = match token {
+            RibToken::Match => {
+                let covering: Vec<_> = self.covering(&prefix).collect();
+                list(covering.into_iter().rev())
+            }
+            RibToken::Get => exact
+                .map(TypeValue::from)
+                .next()
+                .unwrap_or(TypeValue::Unknown),
+            RibToken::LongestMatch => self
+                .covering(&prefix)
+                .last()
+                .map(TypeValue::from)
+                .unwrap_or(TypeValue::Unknown),
+            RibToken::Contains => TypeValue::Builtin(BuiltinTypeValue::Bool(
+                self.vrps.contains_key(&prefix),
+            )),
+            RibToken::MoreSpecifics => list(self.more_specifics(&prefix)),
+            RibToken::LessSpecifics => list(
+                self.covering(&prefix).filter(|vrp| vrp.prefix != prefix),
+            ),
+            RibToken::Validate => unreachable!(),
+            RibToken::Version => return Err(VmError::InvalidMethodCall),
+        };
+
+        Ok(DataSourceMethodValue::TypeValue(res))
+    }
+
+    fn get_by_key(&self, key: &str) -> Option<TypeValue> {
+        let prefix = key.parse::<Prefix>().ok()?;
+        self.vrps.get(&prefix)?.first().map(TypeValue::from)
+    }
+
+    fn len(&self) -> usize {
+        self.vrps.values().map(Vec::len).sum()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.vrps.is_empty()
+    }
+
+    fn get_name(&self) -> ShortString {
+        self.name.clone()
+    }
+
+    fn get_type(&self) -> TypeDef {
+        Self::record_type()
+    }
+
+    fn with_prefix_records(
+        &self,
+        prefix: Prefix,
+        records: Vec<TypeValue>,
+    ) -> Result<Arc<dyn RotoRib>, VmError> {
+        let vrps = records
+            .iter()
+            .map(Vrp::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+        if vrps.iter().any(|vrp| vrp.prefix != prefix) {
+            return Err(VmError::InvalidValueType);
+        }
+
+        let mut new = self.clone();
+        if vrps.is_empty() {
+            new.vrps.remove(&prefix);
+        } else {
+            new.vrps.insert(prefix, vrps);
+        }
+        Ok(Arc::new(new))
+    }
+}
+
+impl From<Vrps> for DataSource {
+    fn from(vrps: Vrps) -> Self {
+        DataSource::Rib(Arc::new(vrps))
+    }
+}
+
+//------------ AspaStatus ---------------------------------------------------
+
+/// The outcome of AS path verification with ASPA records.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AspaStatus {
+    /// The ASPA records attest every hop of the AS path that has to be
+    /// attested.
+    Valid,
+    /// The ASPA records show that the AS path contains a route leak, or the
+    /// path has an AS_SET.
+    Invalid,
+    /// Too few ASes in the path have an ASPA record to decide.
+    Unknown,
+}
+
+impl From<AspaStatus> for BuiltinTypeValue {
+    fn from(status: AspaStatus) -> Self {
+        BuiltinTypeValue::ConstU8EnumVariant(EnumVariant::new((
+            GlobalEnumTypeDef::AspaStatus.into(),
+            status as u8,
+        )))
+    }
+}
+
+impl From<AspaStatus> for TypeValue {
+    fn from(status: AspaStatus) -> Self {
+        TypeValue::Builtin(status.into())
+    }
+}
+
+//------------ PeerRole -----------------------------------------------------
+
+/// The role of the neighbor a route was received from, as in RFC 9234.
+///
+/// The role is configured per BGP session, it can't be derived from the
+/// route or its context, so it is passed to `validate` explicitly. Routes
+/// from a provider are verified downstream, all other routes upstream.
+///
+/// In roto the roles are the constants `ROLE_CUSTOMER`, `ROLE_PEER`,
+/// `ROLE_PROVIDER`, `ROLE_ROUTE_SERVER` and `ROLE_RS_CLIENT`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PeerRole {
+    Customer,
+    Peer,
+    Provider,
+    RouteServer,
+    RsClient,
+}
+
+impl PeerRole {
+    fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(PeerRole::Customer),
+            1 => Some(PeerRole::Peer),
+            2 => Some(PeerRole::Provider),
+            3 => Some(PeerRole::RouteServer),
+            4 => Some(PeerRole::RsClient),
+            _ => None,
+        }
+    }
+}
+
+impl From<PeerRole> for BuiltinTypeValue {
+    fn from(role: PeerRole) -> Self {
+        BuiltinTypeValue::ConstU8EnumVariant(EnumVariant::new((
+            GlobalEnumTypeDef::PeerRole.into(),
+            role as u8,
+        )))
+    }
+}
+
+impl From<PeerRole> for TypeValue {
+    fn from(role: PeerRole) -> Self {
+        TypeValue::Builtin(role.into())
+    }
+}
+
+//------------ Aspa ---------------------------------------------------------
+
+/// An ASPA record: the ASes that `customer` uses as its providers.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Aspa {
+    pub customer: Asn,
+    pub providers: Vec<Asn>,
+}
+
+impl Aspa {
+    /// The record type of the ASPA records as exposed to roto, the
+    /// built-in `Aspa` type.
+    pub fn record_type() -> TypeDef {
+        TypeDef::Record(RecordTypeDef::new(vec![
+            ("customer".into(), Box::new(TypeDef::Asn)),
+            (
+                "providers".into(),
+                Box::new(TypeDef::List(Box::new(TypeDef::Asn))),
+            ),
+        ]))
+    }
+}
+
+impl From<&Aspa> for Record {
+    fn from(aspa: &Aspa) -> Self {
+        let asn = |asn: Asn| {
+            ElementTypeValue::Primitive(TypeValue::Builtin(
+                BuiltinTypeValue::Asn(asn),
+            ))
+        };
+
+        Record::new(vec![
+            ("customer".into(), asn(aspa.customer)),
+            (
+                "providers".into(),
+                ElementTypeValue::Primitive(TypeValue::List(List::new(
+                    aspa.providers.iter().copied().map(asn).collect(),
+                ))),
+            ),
+        ])
+    }
+}
+
+//------------ Aspas --------------------------------------------------------
+
+/// A set of ASPA records that AS paths can be verified against.
+#[derive(Debug)]
+pub struct Aspas {
+    name: ShortString,
+    /// The providers of every customer AS that has an ASPA record.
+    providers: HashMap<Asn, Vec<Asn>>,
+}
+
+impl Aspas {
+    pub fn new(name: &str, aspas: impl IntoIterator<Item = Aspa>) -> Self {
+        let mut providers: HashMap<Asn, Vec<Asn>> = HashMap::new();
+        for aspa in aspas {
+            providers
+                .entry(aspa.customer)
+                .or_default()
+                .extend(aspa.providers);
+        }
+
+        Self {
+            name: name.into(),
+            providers,
+        }
+    }
+
+    /// Read the ASPA records from JSON, in the format that is exported by
+    /// the common RPKI validators:
+    ///
+    /// ```json
+    /// { "aspas": [
+    ///     { "customer": "AS65534", "providers": ["AS65535", 65536] }
+    /// ] }
+    /// ```
+    ///
+    /// The customer can also be given as `customer_asid`. Other fields are
+    /// ignored. The row of an error is the position of the record in the
+    /// list, starting at 1.
+    pub fn from_json(
+        name: &str,
+        reader: impl Read,
+    ) -> Result<Self, LoadError> {
+        let json: serde_json::Value = serde_json::from_reader(reader)
+            .map_err(|e| LoadError::new(e.line(), e.to_string()))?;
+
+        let aspas = json
+            .get("aspas")
+            .and_then(|aspas| aspas.as_array())
+            .ok_or_else(|| {
+                LoadError::new(0, "expected an object with an 'aspas' list")
+            })?;
+
+        let aspas = aspas
+            .iter()
+            .enumerate()
+            .map(|(i, aspa)| aspa_from_json(i + 1, aspa))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self::new(name, aspas))
+    }
+
+    pub fn len(&self) -> usize {
+        self.providers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.providers.is_empty()
+    }
+
+    /// Verify `path` of a route that was received from a neighbor with
+    /// `role`.
+    pub fn validate(&self, path: &HopPath, role: PeerRole) -> AspaStatus {
+        verify_path(path, role, |customer, provider| {
+            self.providers
+                .get(&customer)
+                .map(|providers| providers.contains(&provider))
+        })
+    }
+}
+
+/// The ASPA records are stored in a table, so that they can also be looked
+/// up with the methods of a table.
+impl From<Aspas> for DataSource {
+    fn from(aspas: Aspas) -> Self {
+        let records = aspas
+            .providers
+            .into_iter()
+            .map(|(customer, providers)| {
+                Record::from(&Aspa {
+                    customer,
+                    providers,
+                })
+            })
+            .collect();
+
+        DataSource::Table(Table::new(
+            aspas.name,
+            Aspa::record_type(),
+            records,
+            Some(&"customer".into()),
+        ))
+    }
+}
+
+/// Execute the `validate` method on a table of ASPA records, with the AS
+/// path and the role of the neighbor in `args`.
+pub(super) fn validate_aspa_table(
+    table: &Table,
+    args: &[StackValue],
+) -> TypeValue {
+    let role_enum = ShortString::from(GlobalEnumTypeDef::PeerRole);
+    let mut path = None;
+    let mut role = None;
+    for arg in args {
+        match arg.as_ref() {
+            TypeValue::Builtin(BuiltinTypeValue::AsPath(p)) => path = Some(p),
+            TypeValue::Builtin(BuiltinTypeValue::ConstU8EnumVariant(v))
+                if v.enum_name == role_enum =>
+            {
+                role = PeerRole::from_u8(v.value)
+            }
+            _ => {}
+        }
+    }
+    trace!("validate {:?} from a {:?} with aspas", path, role);
+
+    let (Some(path), Some(role)) = (path, role) else {
+        return TypeValue::Unknown;
+    };
+
+    verify_path(path, role, |customer, provider| {
+        let mut found = false;
+        let mut attested = false;
+        let key = StackValue::Owned(TypeValue::from(customer));
+        for rec in table.records_for(&key) {
+            found = true;
+            if let Some(ElementTypeValue::Primitive(TypeValue::List(list))) =
+                rec.get_value_for_field("providers")
+            {
+                attested |= list.iter().any(|p| {
+                    matches!(
+                        p,
+                        ElementTypeValue::Primitive(TypeValue::Builtin(
+                            BuiltinTypeValue::Asn(asn)
+                        )) if *asn == provider
+                    )
+                });
+            }
+        }
+        found.then_some(attested)
+    })
+}
+
+/// Verify `path` with the procedures of the ASPA verification draft.
+///
+/// `is_provider(customer, provider)` checks one hop of the path: it returns
+/// `None` if `customer` has no ASPA record, and otherwise whether
+/// `provider` is one of its providers.
+fn verify_path(
+    path: &HopPath,
+    role: PeerRole,
+    is_provider: impl Fn(Asn, Asn) -> Option<bool>,
+) -> AspaStatus {
+    // The ASes from the origin to the neighbor, without the prepends. An
+    // AS_SET makes the path invalid.
+    let mut asns: Vec<Asn> = vec![];
+    for hop in path.iter() {
+        let Ok(asn) = hop.clone().try_into_asn() else {
+            return AspaStatus::Invalid;
+        };
+        if asns.last() != Some(&asn) {
+            asns.push(asn);
+        }
+    }
+    asns.reverse();
+
+    let n = asns.len();
+    if n == 0 {
+        return AspaStatus::Invalid;
+    }
+
+    // The hop from asns[i] up to asns[i + 1], and down from asns[i + 1] to
+    // asns[i].
+    let up = |i: usize| is_provider(asns[i], asns[i + 1]);
+    let down = |i: usize| is_provider(asns[i + 1], asns[i]);
+
+    if role != PeerRole::Provider {
+        // Upstream: every hop has to go up to a provider.
+        let mut status = AspaStatus::Valid;
+        for i in 0..n - 1 {
+            match up(i) {
+                Some(false) => return AspaStatus::Invalid,
+                None => status = AspaStatus::Unknown,
+                Some(true) => {}
+            }
+        }
+        return status;
+    }
+
+    // Downstream: the path has to go up from the origin, and then down to
+    // us, with at most one lateral hop in between.
+    if n <= 2 {
+        return AspaStatus::Valid;
+    }
+
+    // The first AS that is not a provider of the AS before it, and the
+    // last AS that is not a provider of the AS after it. If the path goes
+    // down before it goes up, it has a valley.
+    let u_min = (0..n - 1).find(|i| up(*i) == Some(false)).map(|i| i + 1);
+    let v_max = (0..n - 1).rev().find(|i| down(*i) == Some(false));
+    if let (Some(u_min), Some(v_max)) = (u_min, v_max) {
+        if u_min <= v_max {
+            return AspaStatus::Invalid;
+        }
+    }
+
+    // The top of the attested up-ramp and the bottom of the attested
+    // down-ramp.
+    let k = (0..n - 1).find(|i| up(*i) != Some(true)).unwrap_or(n - 1);
+    let l = (0..n - 1)
+        .rev()
+        .find(|i| down(*i) != Some(true))
+        .map_or(0, |i| i + 1);
+    if l <= k + 1 {
+        AspaStatus::Valid
+    } else {
+        AspaStatus::Unknown
+    }
+}
+
+//------------ Helpers ------------------------------------------------------
+
+/// The less-specific of `prefix` with length `len`.
+pub(crate) fn truncate(prefix: &Prefix, len: u8) -> Option<Prefix> {
+    let addr = match prefix.addr() {
+        IpAddr::V4(addr) => {
+            let mask = u32::MAX.checked_shl(32 - u32::from(len)).unwrap_or(0);
+            IpAddr::V4((u32::from(addr) & mask).into())
+        }
+        IpAddr::V6(addr) => {
+            let mask =
+                u128::MAX.checked_shl(128 - u32::from(len)).unwrap_or(0);
+            IpAddr::V6((u128::from(addr) & mask).into())
+        }
+    };
+    Prefix::new(addr, len).ok()
+}
+
+fn vrp_from_json(
+    row: usize,
+    roa: &serde_json::Value,
+) -> Result<Vrp, LoadError> {
+    let field = |name: &str| {
+        roa.get(name).ok_or_else(|| {
+            LoadError::in_column(row, &name.into(), "missing field")
+        })
+    };
+    let invalid = |name: &str, value: &serde_json::Value| {
+        LoadError::in_column(
+            row,
+            &name.into(),
+            format!("invalid value '{}'", value),
+        )
+    };
+
+    let asn = field("asn")?;
+    let asn = asn_from_json(asn).ok_or_else(|| invalid("asn", asn))?;
+
+    let prefix = field("prefix")?;
+    let prefix = prefix
+        .as_str()
+        .and_then(|p| p.parse::<Prefix>().ok())
+        .ok_or_else(|| invalid("prefix", prefix))?;
+
+    let max_length = match roa.get("maxLength") {
+        None => prefix.len(),
+        Some(value) => value
+            .as_u64()
+            .and_then(|len| u8::try_from(len).ok())
+            .filter(|len| {
+                let bits = match prefix.addr() {
+                    IpAddr::V4(_) => 32,
+                    IpAddr::V6(_) => 128,
+                };
+                (prefix.len()..=bits).contains(len)
+            })
+            .ok_or_else(|| invalid("maxLength", value))?,
+    };
+
+    Ok(Vrp {
+        prefix,
+        max_length,
+        asn,
+    })
+}
+
+fn aspa_from_json(
+    row: usize,
+    aspa: &serde_json::Value,
+) -> Result<Aspa, LoadError> {
+    let invalid = |name: &str, value: &serde_json::Value| {
+        LoadError::in_column(
+            row,
+            &name.into(),
+            format!("invalid value '{}'", value),
+        )
+    };
+
+    let (name, customer) = ["customer", "customer_asid"]
+        .into_iter()
+        .find_map(|name| aspa.get(name).map(|value| (name, value)))
+        .ok_or_else(|| {
+            LoadError::in_column(row, &"customer".into(), "missing field")
+        })?;
+    let customer =
+        asn_from_json(customer).ok_or_else(|| invalid(name, customer))?;
+
+    let providers = aspa.get("providers").ok_or_else(|| {
+        LoadError::in_column(row, &"providers".into(), "missing field")
+    })?;
+    let providers: Vec<Asn> = providers
+        .as_array()
+        .and_then(|list| list.iter().map(asn_from_json).collect())
+        .ok_or_else(|| invalid("providers", providers))?;
+
+    Ok(Aspa {
+        customer,
+        providers,
+    })
+}
+
+/// An AS number in JSON, either a number or a string like `"AS65534"`.
+fn asn_from_json(value: &serde_json::Value) -> Option<Asn> {
+    match value {
+        serde_json::Value::Number(n) => n.as_u64(),
+        serde_json::Value::String(s) => {
+            let n = match s.get(..2) {
+                Some(p) if p.eq_ignore_ascii_case("as") => &s[2..],
+                _ => s,
+            };
+            n.parse().ok()
+        }
+        _ => None,
+    }
+    .and_then(|n| u32::try_from(n).ok())
+    .map(Asn::from_u32)
+}
@@ -0,0 +1,122 @@
+//! A hash map that is cheap to copy on write.
+//!
+//! The built-in data sources are immutable once they are published to the
+//! filters, an update makes a new version of a data source next to the one
+//! that is in use. [`ShardedMap`] spreads its entries over a fixed number
+//! of shards that are shared between the versions, so that a new version
+//! only copies the shards that are changed by an update instead of all the
+//! entries.
+
+use std::borrow::Borrow;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash};
+use std::sync::Arc;
+
+/// The number of shards of a [`ShardedMap`].
+const SHARDS: usize = 64;
+
+//------------ ShardedMap ----------------------------------------------------
+
+/// A hash map of which clones share their unchanged shards.
+#[derive(Clone, Debug)]
+pub(crate) struct ShardedMap<K, V> {
+    hasher: RandomState,
+    shards: Vec<Arc<HashMap<K, V>>>,
+}
+
+impl<K: Clone + Eq + Hash, V: Clone> ShardedMap<K, V> {
+    pub(crate) fn new() -> Self {
+        Self {
+            hasher: RandomState::new(),
+            shards: (0..SHARDS).map(|_| Arc::default()).collect(),
+        }
+    }
+
+    /// The position of the shard of `key`.
+    fn shard<Q>(&self, key: &Q) -> usize
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        (self.hasher.hash_one(key) % SHARDS as u64) as usize
+    }
+
+    pub(crate) fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.shards[self.shard(key)].get(key)
+    }
+
+    pub(crate) fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.shards[self.shard(key)].contains_key(key)
+    }
+
+    /// The value for `key`, to change it in place. The shard of `key` is
+    /// copied if it is shared with another map.
+    pub(crate) fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let shard = self.shard(key);
+        if !self.shards[shard].contains_key(key) {
+            return None;
+        }
+        Arc::make_mut(&mut self.shards[shard]).get_mut(key)
+    }
+
+    /// The value for `key`, inserting the default value if there is none.
+    /// The shard of `key` is copied if it is shared with another map.
+    pub(crate) fn entry(&mut self, key: K) -> &mut V
+    where
+        V: Default,
+    {
+        let shard = self.shard(&key);
+        Arc::make_mut(&mut self.shards[shard]).entry(key).or_default()
+    }
+
+    pub(crate) fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let shard = self.shard(&key);
+        Arc::make_mut(&mut self.shards[shard]).insert(key, value)
+    }
+
+    pub(crate) fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let shard = self.shard(key);
+        // Don't copy a shared shard that doesn't have the key.
+        if !self.shards[shard].contains_key(key) {
+            return None;
+        }
+        Arc::make_mut(&mut self.shards[shard]).remove(key)
+    }
+
+    /// The entries, in no particular order.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&K, &V)> + '_ {
+        self.shards.iter().flat_map(|shard| shard.iter())
+    }
+
+    /// The values, in no particular order.
+    pub(crate) fn values(&self) -> impl Iterator<Item = &V> + '_ {
+        self.shards.iter().flat_map(|shard| shard.values())
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.shards.iter().all(|shard| shard.is_empty())
+    }
+}
+
+impl<K: Clone + Eq + Hash, V: Clone> Default for ShardedMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -37,7 +37,7 @@ use super::builtin::{
     FlowSpecRoute, HexLiteral, IntegerLiteral, NlriStatus, PrefixLength, PrefixRoute, RouteContext, StringLiteral, Unknown
 };
 use super::collections::{LazyElementTypeValue, Record};
-use super::datasources::{RibType, Table};
+use super::datasources::{DataSourceMethod, RibType, Table};
 use super::enum_types::{EnumVariant, GlobalEnumTypeDef};
 use super::lazyrecord_types::LazyRecordTypeDef;
 use super::outputs::OutputStreamMessage;
@@ -49,6 +49,12 @@ use super::{
 /// vec of field_indexes that are used in the hash to calculate
 /// uniqueness for an entry.
 pub type RibTypeDef = (Box<TypeDef>, Option<Vec<FieldIndex>>);
+/// the type definition of the type that's stored in the table and the name
+/// of the field that the records are indexed on, if the table has a key.
+pub type TableTypeDef = (Box<TypeDef>, Option<ShortString>);
+/// the name of a data source that is implemented outside of roto and the
+/// methods it declares.
+pub type CustomTypeDef = (ShortString, Vec<DataSourceMethod>);
 pub type NamedTypeDef = (ShortString, Box<TypeDef>);
 pub type LazyNamedTypeDef<'a, T> =
     Vec<(ShortString, LazyElementTypeValue<'a, T>)>;
@@ -164,8 +170,11 @@ pub enum TypeDef {
     // Data Sources, the data field in the enum represents the contained
     // type.
     Rib(RibTypeDef),
-    Table(Box<TypeDef>),
+    Table(TableTypeDef),
     OutputStream(Box<TypeDef>),
+    // A data source that is implemented outside of roto, see
+    // [`RotoDataSource`](crate::traits::RotoDataSource).
+    Custom(CustomTypeDef),
     // Collection Types
     List(Box<TypeDef>),
     // Record with sorted named fields
@@ -295,6 +304,7 @@ impl TypeDef {
         GlobalEnum,
         Rib,
         Table,
+        Custom,
         OutputStream
     );
 
@@ -333,6 +343,7 @@ impl TypeDef {
             self,
             TypeDef::Rib(_)
                 | TypeDef::Table(_)
+                | TypeDef::Custom(_)
                 | TypeDef::List(_)
                 | TypeDef::Record(_)
         )
@@ -370,7 +381,7 @@ impl TypeDef {
         // contained type. They don't have field access.
         trace!("has_fields_chain for {:?} with {:?}", self, check_fields);
         let mut parent_type: (TypeDef, Token) = (
-            if let TypeDef::Table(rec)
+            if let TypeDef::Table((rec, _))
             | TypeDef::Rib((rec, None))
             | TypeDef::OutputStream(rec) = self
             {
@@ -670,6 +681,23 @@ impl TypeDef {
             TypeDef::Table(_) => {
                 Table::get_props_for_method(self.clone(), method_name)
             }
+            // The token of a method is its position in the list.
+            TypeDef::Custom((name, methods)) => methods
+                .iter()
+                .position(|m| m.name == method_name.ident)
+                .map(|i| {
+                    MethodProps::new(
+                        methods[i].return_type.clone(),
+                        i,
+                        methods[i].arg_types.clone(),
+                    )
+                })
+                .ok_or_else(|| {
+                    CompileError::from(format!(
+                        "Unknown method '{}' for data source {}",
+                        method_name.ident, name
+                    ))
+                }),
             TypeDef::OutputStream(_) => {
                 OutputStreamMessage::get_props_for_method(
                     self.clone(),
@@ -944,7 +972,8 @@ impl std::fmt::Display for TypeDef {
                 write!(f, "Lazy Record {}", lazy_type_def.type_def())
             }
             TypeDef::Rib(rib) => write!(f, "Rib of {}", rib.0),
-            TypeDef::Table(table) => write!(f, "Table of {}", table),
+            TypeDef::Table(table) => write!(f, "Table of {}", table.0),
+            TypeDef::Custom((name, _)) => write!(f, "Data Source {}", name),
             TypeDef::OutputStream(stream) => {
                 write!(f, "Output Stream of {}", stream)
             }
@@ -460,9 +460,10 @@ impl RotoType for TypeValue {
             TypeDef::StringLiteral => {
                 StringLiteral::get_props_for_method(ty, method_name)
             }
-            TypeDef::Table(ty) => {
+            TypeDef::Table((ty, _)) => {
                 Self::get_props_for_method(*ty, method_name)
             }
+            TypeDef::Custom(_) => ty.get_props_for_method(method_name),
             TypeDef::U32 => u32::get_props_for_method(ty, method_name),
             TypeDef::U16 => u16::get_props_for_method(ty, method_name),
             TypeDef::U8 => u8::get_props_for_method(ty, method_name),
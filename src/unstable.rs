@@ -0,0 +1,32 @@
+//! Types that appear in the public traits of this crate, but that are
+//! internals of the VM.
+//!
+//! They are only public so that [`RotoType`] and [`RotoRib`] can be
+//! implemented outside of this crate. They may change with any release,
+//! without notice.
+//!
+//! [`RotoType`]: crate::traits::RotoType
+//! [`RotoRib`]: crate::traits::RotoRib
+
+use std::sync::Arc;
+
+use crate::types::typevalue::TypeValue;
+
+/// A value on the stack of the VM, as it is handed to the methods of a
+/// type.
+#[derive(Debug, Eq, PartialEq)]
+pub enum StackValue<'a> {
+    Ref(&'a TypeValue),
+    Arc(Arc<TypeValue>),
+    Owned(TypeValue),
+}
+
+impl<'a> AsRef<TypeValue> for StackValue<'a> {
+    fn as_ref(&self) -> &TypeValue {
+        match self {
+            StackValue::Ref(r) => r,
+            StackValue::Arc(r) => r,
+            StackValue::Owned(r) => r,
+        }
+    }
+}
@@ -4,23 +4,33 @@ use std::{
     fmt::{Display, Formatter},
     hash::{Hash, Hasher},
     ops::{Index, IndexMut},
-    sync::Arc,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
 };
 
 use crate::{
     ast::{self, AcceptReject, CompareOp, ShortString},
-    compiler::compile::{CompileError, MirBlock},
+    blocks::Scope,
+    compiler::compile::{
+        prepare_source, CompileError, CompiledFilter, MirBlock,
+    },
     first_into_vm_err,
-    traits::{RotoType, Token},
+    parser::span::Span,
+    traits::{RotoRib, RotoType, Token},
     types::{
         builtin::{BuiltinTypeValue, RouteContext},
         collections::{
             BytesRecord, ElementTypeValue, EnumBytesRecord, LazyRecord, List,
             Record,
         },
-        datasources::{DataSource, DataSourceMethodValue},
+        datasources::{
+            DataSource, DataSourceMethodValue, RibToken, Table, TableToken,
+        },
         lazyrecord_types::{
-            BgpUpdateMessage, InitiationMessage, LazyRecordTypeDef, PeerDownNotification, PeerUpNotification, RouteMonitoring, StatisticsReport, TerminationMessage
+            BgpUpdateMessage, InitiationMessage, PeerDownNotification, PeerUpNotification, RouteMonitoring, StatisticsReport, TerminationMessage
         },
         outputs::OutputStreamMessage,
         typedef::TypeDef,
@@ -28,11 +38,17 @@ use crate::{
     },
 };
 
+pub(crate) use crate::unstable::StackValue;
+
 use arc_swap::ArcSwapOption;
+use inetnum::addr::Prefix;
 use log::{debug, error, log_enabled, trace, Level};
 use serde::Serialize;
 use smallvec::SmallVec;
 
+#[cfg(test)]
+mod tests;
+
 //------------ FieldIndex ---------------------------------------------------
 
 // This is a compound index that hop from sub-field to sub-field into a
@@ -99,7 +115,7 @@ impl From<&Vec<u8>> for FieldIndex {
 pub enum StackRefPos {
     // index into LinearMemory
     MemPos(u32),
-    // index into a Table (which is a vec of shared Records)
+    // The token of a table and the id of one of its records
     TablePos(Token, usize),
     // CompareResult, which is not a Ref at all, but hey,
     // it's smaller than a ref, so who cares
@@ -194,35 +210,34 @@ impl<'a> Stack {
     }
 }
 
-#[derive(Debug, Eq, PartialEq)]
-pub enum StackValue<'a> {
-    Ref(&'a TypeValue),
-    Arc(Arc<TypeValue>),
-    Owned(TypeValue),
-}
-
-impl<'a> AsRef<TypeValue> for StackValue<'a> {
-    fn as_ref(&self) -> &TypeValue {
-        match self {
-            StackValue::Ref(r) => r,
-            StackValue::Arc(r) => r,
-            StackValue::Owned(r) => r,
-        }
-    }
-}
-
 // #[derive(Debug, Eq, PartialEq, PartialOrd, Ord)]
 // pub struct StackValueRef<SV>(SV);
 
 //------------ LinearMemory -------------------------------------------------
 
-#[derive(Debug)]
-pub struct LinearMemory([TypeValue; 512]);
+/// The memory a VM uses to store the rx, tx and context values, the
+/// variables and the intermediary results of a filter-map run in.
+///
+/// The compiler records the number of memory positions a filter-map needs
+/// in its RotoPack, the VM grows the memory to that size at the start of
+/// each run, if it's smaller.
+///
+/// Each ExecutionContext holds the memory for the runs of its filter-map.
+#[derive(Debug, Default)]
+pub(crate) struct LinearMemory(Vec<TypeValue>);
 
 impl LinearMemory {
-    pub fn uninit() -> Self {
-        const V: TypeValue = TypeValue::UnInit;
-        LinearMemory([V; 512])
+    /// Create a LinearMemory with `size` uninitialized memory positions,
+    /// e.g. the `mem_size` of a RotoPack.
+    pub fn with_size(size: usize) -> Self {
+        LinearMemory(vec![TypeValue::UnInit; size])
+    }
+
+    /// Make sure at least `size` memory positions are available.
+    pub fn grow(&mut self, size: usize) {
+        if self.0.len() < size {
+            self.0.resize(size, TypeValue::UnInit);
+        }
     }
 
     pub fn get_mem_pos(&self, index: usize) -> Option<&TypeValue> {
@@ -605,20 +620,6 @@ impl LinearMemory {
         }
     }
 
-    pub fn get_as_lazy_record_type(
-        &self,
-        mem_pos: usize,
-    ) -> Result<LazyRecordTypeDef, VmError> {
-        match self.get_mem_pos(mem_pos) {
-            // We only know how to deal with BmpMessages currently.
-            Some(TypeValue::Builtin(BuiltinTypeValue::BmpMessage(
-                bytes_rec,
-            ))) => Ok(bytes_rec.get_variant()),
-            Some(_) => Err(VmError::InvalidRecord),
-            _ => Err(VmError::InvalidMemoryAccess(mem_pos)),
-        }
-    }
-
     // Return the contents of the memory position as a TypeValue only if it is
     // a LazyRecord, or a field on a LazyRecord (a
     // LazyRecordElementTypeValue), otherwise return a VmError.
@@ -781,9 +782,8 @@ impl LinearMemory {
     }
 
     fn set_mem_pos(&mut self, index: usize, value: TypeValue) {
-        if let Some(mp) = self.0.get_mut(index) {
-            *mp = value;
-        }
+        self.grow(index + 1);
+        self.0[index] = value;
     }
 
     // When returning the value on exiting the VM, the rx value should be
@@ -830,31 +830,40 @@ impl Display for LinearMemory {
 //------------ Command Arguments Stack --------------------------------------
 
 /// A stack especially for the arguments to a Command. The Vec that forms the
-/// stack is immutable, so that the VM doesn't need to clone the MIR code for
-/// each run. A counter keeps track of the current position in the stack.
+/// stack is immutable, so that the VM doesn't need to clone the MIR code, or
+/// the arguments of each command, for each run. A counter keeps track of the
+/// current position in the stack, another one of the items that were taken
+/// from the front of it.
 #[derive(Debug)]
 pub(crate) struct CommandArgsStack<'a> {
-    args: &'a mut VecDeque<CommandArg>,
+    args: &'a VecDeque<CommandArg>,
     args_counter: usize,
+    front_counter: usize,
 }
 
 impl<'a> CommandArgsStack<'a> {
     // The counter counts down from the last element + 1.
-    fn new(args: &'a mut VecDeque<CommandArg>) -> Self {
+    fn new(args: &'a VecDeque<CommandArg>) -> Self {
         let args_counter = args.len();
-        Self { args, args_counter }
+        Self {
+            args,
+            args_counter,
+            front_counter: 0,
+        }
     }
 
-    fn first(&self) -> Option<&CommandArg> {
-        self.args.front()
+    fn first(&self) -> Option<&'a CommandArg> {
+        self.args.get(self.front_counter)
     }
 
-    /// Remove and return the first item, decrement the counter
+    /// Return the first item that wasn't taken yet and move past it.
     ///
     /// This returns `None` if the stack has under flowed.
-    fn pop_front(&mut self) -> Option<CommandArg> {
+    fn pop_front(&mut self) -> Option<&'a CommandArg> {
         self.args_counter.checked_sub(1)?;
-        self.args.pop_front()
+        let arg = self.args.get(self.front_counter)?;
+        self.front_counter += 1;
+        Some(arg)
     }
 
     /// Return the last item and decrement the counter.
@@ -871,7 +880,17 @@ impl<'a> CommandArgsStack<'a> {
     }
 
     fn is_empty(&self) -> bool {
-        self.args.is_empty()
+        self.args.len() <= self.front_counter
+    }
+
+    /// Take the name of the label if the first argument is a Label.
+    fn take_label(&mut self) -> Option<ShortString> {
+        if let Some(CommandArg::Label(_)) = self.first() {
+            if let Some(CommandArg::Label(label)) = self.pop_front() {
+                return Some(label.clone());
+            }
+        }
+        None
     }
 
     /// Interpret the last stack entry as a constant value.
@@ -953,11 +972,12 @@ impl<'a> Index<usize> for CommandArgsStack<'a> {
     }
 }
 
-impl<'a> From<&'a mut VecDeque<CommandArg>> for CommandArgsStack<'a> {
-    fn from(value: &'a mut VecDeque<CommandArg>) -> Self {
+impl<'a> From<&'a VecDeque<CommandArg>> for CommandArgsStack<'a> {
+    fn from(value: &'a VecDeque<CommandArg>) -> Self {
         Self {
             args: value,
             args_counter: 0,
+            front_counter: 0,
         }
     }
 }
@@ -1011,82 +1031,103 @@ impl FilterMapArg {
     }
 }
 
+/// Convert the value supplied for argument `name` into its declared type
+/// `ty`, if it doesn't have that type already.
+pub(crate) fn coerce_argument(
+    name: &str,
+    ty: &TypeDef,
+    value: TypeValue,
+) -> Result<TypeValue, CompileError> {
+    if *ty == value {
+        return Ok(value);
+    }
+
+    let value_ty = TypeDef::from(&value);
+    value.into_type(ty).map_err(|_| {
+        format!(
+            "Argument '{}' has the wrong type, expected '{}', got '{}'",
+            name, ty, value_ty
+        )
+        .into()
+    })
+}
+
 #[derive(Default, Debug, Clone)]
 pub struct FilterMapArgs(Vec<FilterMapArg>);
 
 impl FilterMapArgs {
+    /// Create the complete set of arguments for running a filter-map, from
+    /// the arguments declared in the source code (`self`) and the values
+    /// supplied in `args`.
+    ///
+    /// A supplied value that doesn't have the declared type is converted
+    /// into it, if possible, e.g. an U32 into an Asn. The values that were
+    /// compiled into the filter-map are taken over, they can't be supplied
+    /// again. All the other arguments have to be supplied.
     pub fn compile_arguments(
         &self,
         args: Vec<(&str, TypeValue)>,
     ) -> Result<FilterMapArgs, CompileError> {
         // Walk over all the filter_map arguments that were supplied and see
-        // if they match up with the ones in the source code.
-        let mut arguments_map = FilterMapArgs::new();
-        let len = args.len();
-        for supplied_arg in args {
+        // if they match up with the ones in the source code. All the
+        // problems are collected, so that they can be reported at once.
+        let mut errors = vec![];
+        for supplied_arg in &args {
             match self.iter().find(|a| supplied_arg.0 == a.get_name()) {
-                // The argument is in the source code
-                Some(found_arg) => {
-                    // nice, but do the types match?
-                    if found_arg.get_type() == supplied_arg.1 {
-                        // yes, they match
-                        arguments_map.insert(
-                            found_arg.get_name(),
-                            found_arg.get_index(),
-                            found_arg.get_type(),
-                            supplied_arg.1,
-                        )
-                    } else {
-                        // Ok, but maybe we can convert into the type we
-                        // need? Note that we can only try to convert if
-                        // it's a builtin type.
-                        match supplied_arg
-                            .1
-                            .into_builtin()
-                            .and_then(|t| t.into_type(&found_arg.get_type()))
-                        {
-                            Ok(arg) => arguments_map.insert(
-                                found_arg.get_name(),
-                                found_arg.get_index(),
-                                found_arg.get_type(),
-                                arg,
-                            ),
-                            Err(_) => {
-                                return Err(format!(
-                                    "An invalid type was \
-                                specified for argument: {}",
-                                    supplied_arg.0
-                                )
-                                .into());
-                            }
-                        };
-                    }
-                }
-                // The supplied argument is not in the source code.
-                None => {
-                    return Err(format!(
-                        "Can't find argument in source: {}",
+                Some(found_arg) if !found_arg.value.is_unitialized() => {
+                    errors.push(format!(
+                        "Argument '{}' was already set at compile time",
                         supplied_arg.0
-                    )
-                    .into())
+                    ));
                 }
+                Some(_) => {}
+                // The supplied argument is not in the source code.
+                None => errors.push(format!(
+                    "Can't find argument '{}' in source",
+                    supplied_arg.0
+                )),
             }
         }
 
-        // See if we got all the required arguments in the source code
-        // covered.
-        if arguments_map.len() != len {
-            let missing_args = self
+        let mut arguments_map = FilterMapArgs::new();
+        for declared_arg in self.iter() {
+            let value = match args
                 .iter()
-                .filter(|a| {
-                    arguments_map.get_by_token_value(a.get_index()).is_none()
-                })
-                .map(|a| a.get_name())
-                .collect::<Vec<&str>>();
+                .find(|a| a.0 == declared_arg.get_name())
+            {
+                Some((name, value)) => match coerce_argument(
+                    name,
+                    &declared_arg.ty,
+                    value.clone(),
+                ) {
+                    Ok(value) => value,
+                    Err(err) => {
+                        errors.push(err.to_string());
+                        continue;
+                    }
+                },
+                None if declared_arg.value.is_unitialized() => {
+                    errors.push(format!(
+                        "Argument '{}' of type '{}' is missing",
+                        declared_arg.get_name(),
+                        declared_arg.ty
+                    ));
+                    continue;
+                }
+                None => declared_arg.value.clone(),
+            };
+            arguments_map.insert(
+                declared_arg.get_name(),
+                declared_arg.get_index(),
+                declared_arg.get_type(),
+                value,
+            );
+        }
 
+        if !errors.is_empty() {
             return Err(format!(
-                "Some arguments are missing: {:?}",
-                missing_args
+                "Invalid arguments for filter-map: {}",
+                errors.join("; ")
             )
             .into());
         }
@@ -1098,6 +1139,25 @@ impl FilterMapArgs {
         Ok(arguments_map)
     }
 
+    /// The names of the arguments that don't have a value (yet).
+    pub fn missing_arguments(&self) -> Vec<ShortString> {
+        self.iter()
+            .filter(|a| a.value.is_unitialized())
+            .map(|a| a.name.clone())
+            .collect()
+    }
+
+    /// Check that all the arguments have a value, so that a filter-map can
+    /// run with them.
+    pub fn check_complete(&self) -> Result<(), VmError> {
+        let missing_args = self.missing_arguments();
+        if missing_args.is_empty() {
+            Ok(())
+        } else {
+            Err(VmError::ArgumentsMissing(missing_args))
+        }
+    }
+
     pub fn inspect_arguments(&self) -> Vec<(&str, TypeDef)> {
         self.iter()
             .map(|a| (a.get_name(), a.get_type()))
@@ -1190,11 +1250,11 @@ impl std::hash::Hash for FilterMapArgs {
 }
 
 /// Computes the has over the mir code and the data sources, that is stored in
-/// the built VM.
+/// the RotoPack.
 ///
 /// This hash serves the purpose to figure out if a new (mir
 /// code,data sources) tuple actually contains meaningful changes.
-pub fn compute_hash(
+pub(crate) fn compute_hash(
     mir_code: &[MirBlock],
     data_sources: &[ExtDataSource],
 ) -> u64 {
@@ -1204,6 +1264,22 @@ pub fn compute_hash(
     hasher.finish()
 }
 
+/// Compute the number of memory positions the VM needs to run `mir_code`,
+/// i.e. the highest memory position that is referenced in it plus one. The
+/// first three positions are always reserved for the rx, tx and context
+/// values.
+pub(crate) fn compute_mem_size(mir_code: &[MirBlock]) -> usize {
+    mir_code
+        .iter()
+        .flat_map(|mb| mb.iter())
+        .flat_map(|c| c.args.iter())
+        .filter_map(|arg| match arg {
+            CommandArg::MemPos(pos) => Some(*pos as usize + 1),
+            _ => None,
+        })
+        .fold(3, usize::max)
+}
+
 //------------ CompiledPrimitiveField ----------------------------------------
 
 /// Variable Assignments can be of any these types:
@@ -1433,9 +1509,66 @@ impl VariablesRefTable {
     }
 }
 
+//------------ ExecLocation -------------------------------------------------
+
+/// Keeps track of where the VM is in the MIR code, so that an error can be
+/// reported with its location. Only indexes are stored while executing, the
+/// command and the trace point are looked up when an error happened.
+#[derive(Debug, Default)]
+struct ExecLocation {
+    /// The (block, pc) of the command that is being executed.
+    command: Option<(usize, usize)>,
+    /// The (block, pc) of the last trace point that was passed.
+    trace_point: Option<(usize, usize)>,
+}
+
+impl ExecLocation {
+    fn into_error(self, mir_code: &[MirBlock], error: VmError) -> VmError {
+        let Some((block, pc)) = self.command else {
+            return error;
+        };
+        let Some(command) =
+            mir_code.get(block).and_then(|mb| mb.iter().nth(pc))
+        else {
+            return error;
+        };
+
+        let point = self.trace_point.and_then(|(block, pc)| {
+            match mir_code.get(block)?.iter().nth(pc)?.args.front() {
+                Some(CommandArg::TracePoint(point)) => Some(point),
+                _ => None,
+            }
+        });
+        let (section, span) = match point {
+            Some(TracePoint::TermStart { term, span })
+            | Some(TracePoint::Term { term, span, .. })
+            | Some(TracePoint::MatchAction { term, span, .. }) => {
+                (Some(term.clone()), *span)
+            }
+            Some(TracePoint::ActionSection { name, span }) => {
+                (Some(name.clone()), *span)
+            }
+            Some(TracePoint::Action { section, span }) => {
+                (Some(section.clone()), *span)
+            }
+            Some(TracePoint::ActionEnd) | None => (None, None),
+        };
+
+        VmError::InCommand(Box::new(VmErrorContext {
+            error,
+            op: command.op,
+            block,
+            pc,
+            name: command.symbol.clone(),
+            section,
+            span,
+        }))
+    }
+}
+
 //------------ Virtual Machine ----------------------------------------------
 
-pub struct VirtualMachine<MB: AsRef<[MirBlock]>, C: AsRef<RouteContext>, EDS: AsRef<[ExtDataSource]>>
+pub(crate) struct VirtualMachine<MB: AsRef<[MirBlock]>, C: AsRef<RouteContext>, EDS: AsRef<[ExtDataSource]>>
 {
     // _rx_type: TypeDef,
     // _tx_type: Option<TypeDef>,
@@ -1445,7 +1578,13 @@ pub struct VirtualMachine<MB: AsRef<[MirBlock]>, C: AsRef<RouteContext>, EDS: As
     data_sources: EDS,
     arguments: FilterMapArgs,
     stack: RefCell<Stack>,
-    hash_id: u64,
+    mem_size: usize,
+    trace: bool,
+    metrics: Option<Arc<FilterMetrics>>,
+    /// The sources of the data sources, with their versions, as they were
+    /// when the current execution started, so that an execution sees the
+    /// same sources from start to end, even if they are updated meanwhile.
+    snapshot: Vec<(Option<Arc<DataSource>>, u64)>,
 }
 
 impl<
@@ -1492,12 +1631,7 @@ impl<
                     unwind_stack.push(v);
                 }
                 StackRefPos::TablePos(token, pos) => {
-                    let ds =
-                        get_data_source(self.data_sources.as_ref(), token)?;
-                    match ds
-                        .get_at_field_index(pos, sr.field_index)
-                        .map_err(|_| VmError::InvalidDataSourceAccess)?
-                    {
+                    match self.get_table_field(token, pos, sr.field_index)? {
                         Some(v) => {
                             unwind_stack.push(StackValue::Arc(v.into()))
                         }
@@ -1511,9 +1645,9 @@ impl<
                 StackRefPos::ConstantIndex(c) => {
                     unwind_stack.push(StackValue::Owned(c.into()))
                 }
-                StackRefPos::ConstantValue(v) => {
-                    unwind_stack.push(StackValue::Owned(v))
-                }
+                StackRefPos::ConstantValue(v) => unwind_stack.push(
+                    StackValue::Owned(constant_field(v, &sr.field_index)?),
+                ),
             }
         }
         Ok(unwind_stack)
@@ -1541,11 +1675,11 @@ impl<
                     )?)
                 }
                 StackRefPos::TablePos(token, pos) => {
-                    let ds =
-                        get_data_source(self.data_sources.as_ref(), token)?;
-                    let v = ds
-                        .get_at_field_index(pos, sr.field_index.clone())
-                        .map_err(|_| VmError::InvalidDataSourceAccess)?;
+                    let v = self.get_table_field(
+                        token,
+                        pos,
+                        sr.field_index.clone(),
+                    )?;
                     if let Some(v) = v {
                         take_vec.push(StackValue::Arc(v.into()))
                     } else {
@@ -1558,9 +1692,9 @@ impl<
                 StackRefPos::ConstantIndex(c) => {
                     take_vec.push(StackValue::Owned(c.into()))
                 }
-                StackRefPos::ConstantValue(v) => {
-                    take_vec.push(StackValue::Owned(v))
-                }
+                StackRefPos::ConstantValue(v) => take_vec.push(
+                    StackValue::Owned(constant_field(v, &sr.field_index)?),
+                ),
             };
         }
 
@@ -1599,11 +1733,11 @@ impl<
                     )?);
                 }
                 StackRefPos::TablePos(token, pos) => {
-                    let ds =
-                        get_data_source(self.data_sources.as_ref(), token)?;
-                    let v = ds
-                        .get_at_field_index(pos, sr.field_index.clone())
-                        .map_err(|_| VmError::InvalidDataSourceAccess)?;
+                    let v = self.get_table_field(
+                        token,
+                        pos,
+                        sr.field_index.clone(),
+                    )?;
                     if let Some(v) = v {
                         take_vec.push(StackValue::Arc(v.into()));
                     } else {
@@ -1616,9 +1750,9 @@ impl<
                 StackRefPos::ConstantIndex(c) => {
                     take_vec.push(StackValue::Owned(c.into()));
                 }
-                StackRefPos::ConstantValue(v) => {
-                    take_vec.push(StackValue::Owned(v))
-                }
+                StackRefPos::ConstantValue(v) => take_vec.push(
+                    StackValue::Owned(constant_field(v, &sr.field_index)?),
+                ),
             };
         }
 
@@ -1677,88 +1811,170 @@ impl<
     fn get_data_source(
         &self,
         token: usize,
-    ) -> Result<Arc<DataSource>, VmError> {
+    ) -> Result<(&DataSource, u64), VmError> {
         self.data_sources
             .as_ref()
             .iter()
-            .find(|ds| ds.token == token)
-            .and_then(|ds| ds.source.load_full().clone())
+            .position(|ds| ds.token == token)
+            .and_then(|pos| self.snapshot.get(pos))
+            .and_then(|(ds, version)| Some((ds.as_deref()?, *version)))
             .ok_or(VmError::DataSourceTokenNotFound(token))
     }
 
-    pub fn get_hash_id(&self) -> u64 {
-        self.hash_id
+    /// The value of a field of a record in a table, as referenced on the
+    /// stack.
+    fn get_table_field(
+        &self,
+        token: Token,
+        pos: usize,
+        field_index: FieldIndex,
+    ) -> Result<Option<TypeValue>, VmError> {
+        let ds = match token {
+            Token::Table(token) => self.get_data_source(token)?.0,
+            _ => return Err(VmError::InvalidDataSource),
+        };
+        field_at_index(ds, pos, field_index)
+            .map_err(|_| VmError::InvalidDataSourceAccess)
     }
 
-    /// Re-populate the VM with different intermediate code and different data
-    /// sources.
-    ///
-    /// Note that the MIR and the data sources MUST come from the same
-    /// [`RotoPack`]. If they are not, then the VM will crash or return arbitrary
-    /// results, a.k.a. Undefined Behaviour! THIS IS NOT CHECKED BY THIS
-    /// METHOD, ITS ON THE CONSUMER OF THIS METHOD TO GUARANTEE THIS.
+    /// Execute the filter-map for one rx (and optionally tx) payload.
     ///
-    /// Returns a bool that indicates whether the MIR and the data sources
-    /// where actually replaced.
-    ///
-    /// [`RotoPack`]: crate::compiler::RotoPack
-    pub fn replace_mir_code_and_data_sources(
+    /// The filter-map level arguments that were given when creating this VM
+    /// can be replaced for this execution only with `arguments`, e.g. as
+    /// created by `Rotolo::compile_arguments`. The data sources that are in
+    /// `pinned` are used as they were pinned instead of as they are now.
+    pub fn exec(
         &'a mut self,
-        mir_code: MB,
-        data_sources: EDS,
-    ) -> bool {
-        let new_hash = compute_hash(mir_code.as_ref(), data_sources.as_ref());
+        rx: impl RotoType,
+        tx: Option<impl RotoType>,
+        arguments: Option<FilterMapArgs>,
+        pinned: Option<&PinnedSources>,
+        mem: &mut LinearMemory,
+    ) -> Result<VmResult, VmError> {
+        let start = self.metrics.as_ref().map(|_| Instant::now());
+
+        self.snapshot.clear();
+        self.snapshot.extend(self.data_sources.as_ref().iter().map(|ds| {
+            pinned
+                .and_then(|pinned| pinned.get(ds))
+                .unwrap_or_else(|| ds.snapshot())
+        }));
+
+        // Missing arguments count as a failed execution in the metrics.
+        let res = match arguments {
+            Some(arguments) => match arguments.check_complete() {
+                Ok(()) => {
+                    let build_args =
+                        std::mem::replace(&mut self.arguments, arguments);
+                    let res = self.exec_mir(rx, tx, mem);
+                    self.arguments = build_args;
+                    res
+                }
+                Err(err) => Err(err),
+            },
+            None => self
+                .arguments
+                .check_complete()
+                .and_then(|_| self.exec_mir(rx, tx, mem)),
+        };
 
-        // If the hash is the same the MIR code has *not* changed, return
-        // without replacing anything.
-        if new_hash == self.hash_id {
-            return false;
+        if let (Some(metrics), Some(start)) = (&self.metrics, start) {
+            metrics.record_exec(&res, start.elapsed());
         }
 
-        // This is different from the *mir_cde, data_sources) tuple, so store
-        // that.
-        self.data_sources = data_sources;
-        self.mir_code = mir_code;
-        self.hash_id = new_hash;
+        // Don't keep sources alive that may have been replaced.
+        self.snapshot.clear();
 
-        true
+        res
     }
 
-    pub fn exec(
-        &'a mut self,
+    fn exec_mir(
+        &mut self,
+        rx: impl RotoType,
+        tx: Option<impl RotoType>,
+        mem: &mut LinearMemory,
+    ) -> Result<VmResult, VmError> {
+        let mut loc = ExecLocation::default();
+        self.exec_commands(rx, tx, mem, &mut loc)
+            .map_err(|err| loc.into_error(self.mir_code.as_ref(), err))
+    }
+
+    fn exec_commands(
+        &mut self,
         rx: impl RotoType,
         tx: Option<impl RotoType>,
-        // TODO: define filter-map-level arguments, not used yet!
-        mut _arguments: Option<FilterMapArgs>,
         mem: &mut LinearMemory,
+        loc: &mut ExecLocation,
     ) -> Result<VmResult, VmError> {
         trace!("\nstart executing vm...");
 
         let mut commands_num: usize = 0;
 
+        mem.grow(self.mem_size);
         self._move_rx_tx_to_mem(rx, tx, mem);
         self.copy_context_to_mem(mem);
 
         let mut output_stream_queue: OutputStreamQueue =
             OutputStreamQueue::new();
 
-        for mir_block in self.mir_code.as_ref() {
+        let mut tracer = self.trace.then(Tracer::default);
+
+        for (block, mir_block) in self.mir_code.as_ref().iter().enumerate() {
             trace!("\n\n--mirblock------------------");
             trace!("stack: {:?}", self.stack);
             let mut skip_label = false;
-
-            for (pc, Command { op, args }) in mir_block.iter().enumerate() {
+            // The label to skip to, if the skip was targeted. An untargeted
+            // skip stops at the first label it encounters.
+            let mut skip_to: Option<ShortString> = None;
+            // A trace point of an earlier block doesn't locate the
+            // commands of this one.
+            loc.trace_point = None;
+
+            for (pc, Command { op, args, .. }) in mir_block.iter().enumerate()
+            {
                 if skip_label {
                     if let OpCode::Label = op {
-                        trace!("stop skip");
-                        skip_label = false;
+                        match (&skip_to, args.front()) {
+                            (
+                                Some(target),
+                                Some(CommandArg::Label(label)),
+                            ) if label != target => {
+                                continue;
+                            }
+                            _ => {
+                                trace!("stop skip");
+                                skip_label = false;
+                                skip_to = None;
+                            }
+                        }
                     } else {
                         continue;
                     }
                 }
 
+                // Trace points leave the stack and the memory alone, so
+                // they are handled here, without cloning their arguments.
+                if let OpCode::Trace = op {
+                    loc.trace_point = Some((block, pc));
+                    if let Some(tracer) = tracer.as_mut() {
+                        tracer.record(
+                            args.front(),
+                            &self.stack.borrow(),
+                            mem,
+                        );
+                    }
+                    if let Some(metrics) = &self.metrics {
+                        metrics.record_point(
+                            args.front(),
+                            &self.stack.borrow(),
+                            mem,
+                        );
+                    }
+                    continue;
+                }
+
                 commands_num += 1;
-                let args = &mut args.clone();
+                loc.command = Some((block, pc));
                 let mut args = CommandArgsStack::new(args);
                 trace!("\n{:3} -> {:?} {:?} ", pc, op, args);
                 match op {
@@ -1958,18 +2174,11 @@ impl<
                                         )
                                     })?,
                                 StackRefPos::TablePos(token, pos) => {
-                                    let ds = get_data_source(
-                                        self.data_sources.as_ref(),
+                                    let v = self.get_table_field(
                                         token,
+                                        pos,
+                                        sr.field_index,
                                     )?;
-                                    let v = ds
-                                        .get_at_field_index(
-                                            pos,
-                                            sr.field_index,
-                                        )
-                                        .map_err(|_| {
-                                            VmError::InvalidDataSourceAccess
-                                        })?;
                                     if let Some(v) = v {
                                         StackValue::Arc(v.into())
                                     } else {
@@ -1983,7 +2192,10 @@ impl<
                                     StackValue::Owned(c.into())
                                 }
                                 StackRefPos::ConstantValue(v) => {
-                                    StackValue::Owned(v)
+                                    StackValue::Owned(constant_field(
+                                        v,
+                                        &sr.field_index,
+                                    )?)
                                 }
                             };
 
@@ -2341,41 +2553,67 @@ impl<
                         let (method_token, data_source_token) =
                             args.pop_2()?;
 
-                        match data_source_token {
-                            CommandArg::DataSourceTable(ds_s)
-                            | CommandArg::DataSourceRib(ds_s) => {
-                                let ds = self.get_data_source(*ds_s)?;
-                                let stack_args = self
-                                    ._unwind_resolved_stack_into_vec(mem)?;
+                        let (CommandArg::DataSourceTable(ds_s)
+                        | CommandArg::DataSourceRib(ds_s)) =
+                            data_source_token
+                        else {
+                            return Err(VmError::InvalidDataSource);
+                        };
+                        let (ds, version) = self.get_data_source(*ds_s)?;
+                        let stack_args =
+                            self._unwind_resolved_stack_into_vec(mem)?;
+                        let method_token = usize::try_from(method_token)?;
+
+                        // Custom sources declare their own methods, they
+                        // don't have a version method.
+                        let version_token = match ds {
+                            DataSource::Table(_) => {
+                                Some(usize::from(TableToken::Version))
+                            }
+                            DataSource::Rib(_) => {
+                                Some(usize::from(RibToken::Version))
+                            }
+                            DataSource::Custom(..) => None,
+                        };
+
+                        // The version is the one of the snapshot for this
+                        // execution. Roto has no 64-bit integers, so it
+                        // saturates at the maximum of a U32.
+                        if version_token == Some(method_token) {
+                            let version =
+                                u32::try_from(version).unwrap_or(u32::MAX);
+                            self.stack.borrow_mut().push(
+                                StackRefPos::ConstantValue(TypeValue::from(
+                                    version,
+                                )),
+                            )?;
+                            continue;
+                        }
 
-                                let v = ds.exec_method(
-                                    method_token.try_into()?,
-                                    &stack_args[..],
-                                    TypeDef::Unknown,
-                                )?;
-                                let mut s = self.stack.borrow_mut();
-                                match v {
-                                    DataSourceMethodValue::Ref(sr_pos) => {
-                                        s.push(sr_pos)?;
-                                    }
-                                    DataSourceMethodValue::TypeValue(tv) => {
-                                        // mem.set_mem_pos(mem_pos, tv);
-                                        s.push(StackRefPos::ConstantValue(
-                                            tv,
-                                        ))?;
-                                    }
-                                    DataSourceMethodValue::Empty(_ty) => {
-                                        // mem.set_mem_pos(
-                                        //     mem_pos,
-                                        //     TypeValue::Unknown,
-                                        // );
-                                        s.push(StackRefPos::ConstantValue(
-                                            TypeValue::Unknown,
-                                        ))?;
-                                    }
-                                }
+                        let v = ds.exec_method(
+                            *ds_s,
+                            method_token,
+                            &stack_args[..],
+                            TypeDef::Unknown,
+                        )?;
+                        let mut s = self.stack.borrow_mut();
+                        match v {
+                            DataSourceMethodValue::Ref(sr_pos) => {
+                                s.push(sr_pos)?;
+                            }
+                            DataSourceMethodValue::TypeValue(tv) => {
+                                // mem.set_mem_pos(mem_pos, tv);
+                                s.push(StackRefPos::ConstantValue(tv))?;
+                            }
+                            DataSourceMethodValue::Empty(_ty) => {
+                                // mem.set_mem_pos(
+                                //     mem_pos,
+                                //     TypeValue::Unknown,
+                                // );
+                                s.push(StackRefPos::ConstantValue(
+                                    TypeValue::Unknown,
+                                ))?;
                             }
-                            _ => return Err(VmError::InvalidDataSource),
                         }
                     }
                     // stack args: [mem_pos | constant_value]
@@ -2384,17 +2622,17 @@ impl<
                             if log_enabled!(Level::Trace) {
                                 trace!(
                                     " mem_pos content: {:?}",
-                                    mem.get_mem_pos(pos as usize)
+                                    mem.get_mem_pos(*pos as usize)
                                 );
                             }
 
                             let mut s = self.stack.borrow_mut();
-                            s.push(StackRefPos::MemPos(pos))?;
+                            s.push(StackRefPos::MemPos(*pos))?;
                             if log_enabled!(Level::Trace) {
                                 trace!(" stack {:?}", s);
                             }
                         }
-                        Some(CommandArg::ConstantIndex(ref c)) => {
+                        Some(CommandArg::ConstantIndex(c)) => {
                             if log_enabled!(Level::Trace) {
                                 trace!(" constant index content: {:?}", c);
                             }
@@ -2411,7 +2649,7 @@ impl<
                                 trace!(" constant value content: {:?}", v);
                             }
                             let mut s = self.stack.borrow_mut();
-                            s.push(StackRefPos::ConstantValue(v))?;
+                            s.push(StackRefPos::ConstantValue(v.clone()))?;
                             if log_enabled!(Level::Trace) {
                                 trace!(" stack {:?}", s);
                             }
@@ -2421,7 +2659,9 @@ impl<
                                 trace!(" list value content: {:?}", l);
                             }
                             let mut s = self.stack.borrow_mut();
-                            s.push(StackRefPos::ConstantValue(l.into()))?;
+                            s.push(StackRefPos::ConstantValue(
+                                l.clone().into(),
+                            ))?;
                             if log_enabled!(Level::Trace) {
                                 trace!(" stack {:?}", s);
                             }
@@ -2457,7 +2697,7 @@ impl<
                     }
                     // stack args: [field_index]
                     OpCode::StackOffset => {
-                        let mut args = args.args.iter_mut();
+                        let mut args = args.args.iter();
                         match args.next() {
                             Some(CommandArg::FieldAccess(_field)) => {
                                 for arg in args {
@@ -2475,9 +2715,7 @@ impl<
                             }
                             Some(CommandArg::FieldIndex(field_index)) => {
                                 let mut s = self.stack.borrow_mut();
-                                s.push_with_field_index(std::mem::take(
-                                    field_index,
-                                ))?;
+                                s.push_with_field_index(field_index.clone())?;
                             }
                             _ => {
                                 return Err(VmError::InvalidValueType);
@@ -2564,6 +2802,8 @@ impl<
                     OpCode::Label => {
                         // NOOP
                     }
+                    // Handled before the arguments are cloned.
+                    OpCode::Trace => {}
                     // Term procedures
                     // stack args ignored
                     OpCode::SkipToEOB => {
@@ -2626,12 +2866,30 @@ impl<
                             continue;
                         } else {
                             if log_enabled!(Level::Trace) {
-                                trace!(" skip to next label");
+                                trace!(" skip to label {:?}", args.first());
+                            }
+                            skip_to = args.take_label();
+                            skip_label = true;
+                        }
+                    }
+                    // stack args ignored
+                    OpCode::CondTrueSkipToLabel => {
+                        let s = self.stack.borrow();
+                        let stack_ref = s.get_top_value()?;
+                        if !mem.get_mp_field_as_bool(stack_ref)? {
+                            if log_enabled!(Level::Trace) {
+                                trace!(" continue");
+                            }
+                            continue;
+                        } else {
+                            if log_enabled!(Level::Trace) {
+                                trace!(" skip to label {:?}", args.first());
                             }
+                            skip_to = args.take_label();
                             skip_label = true;
                         }
                     }
-                    // stack args: [exit value]
+                    // command args: [reason?]
                     OpCode::Exit(accept_reject) => {
                         // Make sure to TAKE the rx and optionally the tx
                         // value, so the references in LinearMemory are
@@ -2664,11 +2922,20 @@ impl<
                                 commands_num
                             );
 
+                            let reason = match args.pop_front() {
+                                Some(CommandArg::ConstantValue(reason)) => {
+                                    Some(reason.clone())
+                                }
+                                _ => None,
+                            };
+
                             return Ok(VmResult {
                                 accept_reject: *accept_reject,
+                                reason,
                                 rx,
                                 tx,
                                 output_stream_queue,
+                                trace: tracer.map(|t| t.trace),
                             });
                         }
                     }
@@ -2690,13 +2957,30 @@ impl<
                                 .ok_or(VmError::StackUnderflow)?,
                         )?;
 
+                        if let Some(tracer) = tracer.as_mut() {
+                            tracer.set_field(0, mem, &val);
+                        }
+
                         // save the value in memory position 0 (rx instance
                         // by definition).
                         mem.set_mem_pos(0, val);
                     }
-                    // stack args: [tx type instance field, new value]
+                    // stack args: [new value]
                     OpCode::SetTxField => {
-                        todo!();
+                        let stack_args = self.as_vec();
+
+                        let val = mem.get_mp_field_by_stack_ref_owned(
+                            stack_args
+                                .last()
+                                .ok_or(VmError::StackUnderflow)?,
+                        )?;
+
+                        if let Some(tracer) = tracer.as_mut() {
+                            tracer.set_field(1, mem, &val);
+                        }
+
+                        // memory position 1 holds the tx instance.
+                        mem.set_mem_pos(1, val);
                     }
                     // stack args: [ordered_field_values..]
                     OpCode::PushOutputStreamQueue => {
@@ -2753,8 +3037,40 @@ impl<
         // we'll wipe the rx and tx value, so that the LinearMemory instance
         // can still be reused by another VM run.
         mem.reset();
+        loc.command = None;
         Err(VmError::UnexpectedTermination)
     }
+
+    /// Execute the filter-map for every rx payload in `rxs`, e.g. the routes
+    /// that `explode_announcements` returns for one BGP UPDATE message.
+    ///
+    /// One LinearMemory is created for the whole batch and it's reused for
+    /// every payload, as is the stack of this VM, that gets cleared in
+    /// between runs. The results are returned in the order of the payloads.
+    /// Execution stops at the first payload that returns an error.
+    pub fn exec_batch<R: RotoType>(
+        &mut self,
+        rxs: impl IntoIterator<Item = R>,
+    ) -> Result<Vec<VmResult>, VmError> {
+        let rxs = rxs.into_iter();
+        let mut res = Vec::with_capacity(rxs.size_hint().0);
+        let mem = &mut LinearMemory::with_size(self.mem_size);
+
+        for rx in rxs {
+            self.reset_stack();
+            match self.exec(rx, None::<TypeValue>, None, None, mem) {
+                Ok(vm_res) => res.push(vm_res),
+                Err(err) => {
+                    mem.reset();
+                    self.reset_stack();
+                    return Err(err);
+                }
+            }
+        }
+
+        self.reset_stack();
+        Ok(res)
+    }
 }
 
 impl<
@@ -2770,114 +3086,103 @@ impl<
     }
 }
 
-pub struct VmBuilder<MB, C, EDS> {
-    rx_type: TypeDef,
-    tx_type: Option<TypeDef>,
-    context: Option<C>,
-    mir_code: Option<MB>,
-    arguments: FilterMapArgs,
-    data_sources: Option<EDS>,
-}
+//------------ ExecutionContext ----------------------------------------------
 
-impl<MB, C: AsRef<RouteContext>, EDS> VmBuilder<MB, C, EDS> {
-    pub fn with_context(mut self, context: C) -> Self {
-        self.context = Some(context);
-        self
-    }
+/// The per-thread state needed to execute a CompiledFilter.
+///
+/// It holds a VirtualMachine that shares the MIR code and the data sources
+/// with the CompiledFilter it was created from, and the LinearMemory that
+/// gets reused for every run. Creating one is cheap, so each worker thread
+/// can create its own from a shared CompiledFilter.
+pub struct ExecutionContext<C: AsRef<RouteContext> + Hash> {
+    vm: VirtualMachine<Arc<[MirBlock]>, C, Arc<[ExtDataSource]>>,
+    mem: LinearMemory,
 }
 
-impl<MB, C, EDS: AsRef<[ExtDataSource]>> VmBuilder<MB, C, EDS> {
-    pub fn with_data_sources(mut self, data_sources: EDS) -> Self {
-        self.data_sources = Some(data_sources);
-        self
-    }
-}
+impl<C: AsRef<RouteContext> + Hash> ExecutionContext<C> {
+    pub(crate) fn new(
+        filter: &CompiledFilter,
+        context: C,
+    ) -> Result<Self, VmError> {
+        for ds in filter.data_sources.iter() {
+            if let ExistsAndEmpty(None) = ds.exists_and_is_empty() {
+                return Err(VmError::DataSourceNotInBuild(ds.get_name()));
+            }
+        }
 
-impl<MB: AsRef<[MirBlock]>, C, EDS> VmBuilder<MB, C, EDS> {
-    pub fn with_mir_code(mut self, mir_code: MB) -> Self {
-        self.mir_code = Some(mir_code);
-        self
+        Ok(Self {
+            vm: VirtualMachine {
+                mir_code: Arc::clone(&filter.mir),
+                data_sources: Arc::clone(&filter.data_sources),
+                context,
+                arguments: filter.arguments.clone(),
+                stack: RefCell::new(Stack::new()),
+                mem_size: filter.mem_size,
+                trace: filter.trace,
+                metrics: filter.metrics.clone(),
+                snapshot: vec![],
+            },
+            mem: LinearMemory::with_size(filter.mem_size),
+        })
     }
-}
 
-impl<MB: AsRef<[MirBlock]>, C: AsRef<RouteContext>, EDS: AsRef<[ExtDataSource]>> VmBuilder<MB, C, EDS> {
-    pub fn new() -> Self {
-        Self {
-            rx_type: TypeDef::default(),
-            tx_type: None,
-            mir_code: None,
-            context: None,
-            arguments: FilterMapArgs::default(),
-            data_sources: None,
-        }
+    /// Execute the filter for one rx (and optionally tx) payload.
+    pub fn exec(
+        &mut self,
+        rx: impl RotoType,
+        tx: Option<impl RotoType>,
+    ) -> Result<VmResult, VmError> {
+        self.exec_inner(rx, tx, None, None)
     }
 
-    pub fn with_arguments(mut self, args: FilterMapArgs) -> Self {
-        self.arguments = args;
-        self
+    /// Execute the filter for one rx (and optionally tx) payload, with
+    /// the filter-map level arguments replaced by `arguments` for this
+    /// execution only, see `CompiledFilter::compile_arguments`.
+    pub fn exec_with_arguments(
+        &mut self,
+        rx: impl RotoType,
+        tx: Option<impl RotoType>,
+        arguments: FilterMapArgs,
+    ) -> Result<VmResult, VmError> {
+        self.exec_inner(rx, tx, Some(arguments), None)
     }
 
-
-    pub fn with_rx(mut self, rx_type: TypeDef) -> Self {
-        self.rx_type = rx_type;
-        self
+    /// Execute the filter for one rx payload, with the data sources in
+    /// `pinned` as they were pinned, see [`PinnedSources`].
+    pub(crate) fn exec_pinned(
+        &mut self,
+        rx: impl RotoType,
+        pinned: &PinnedSources,
+    ) -> Result<VmResult, VmError> {
+        self.exec_inner(rx, None::<TypeValue>, None, Some(pinned))
     }
 
-    pub fn with_tx(mut self, tx_type: TypeDef) -> Self {
-        self.tx_type = Some(tx_type);
-        self
+    fn exec_inner(
+        &mut self,
+        rx: impl RotoType,
+        tx: Option<impl RotoType>,
+        arguments: Option<FilterMapArgs>,
+        pinned: Option<&PinnedSources>,
+    ) -> Result<VmResult, VmError> {
+        self.vm.reset_stack();
+        let res = self.vm.exec(rx, tx, arguments, pinned, &mut self.mem);
+        if res.is_err() {
+            self.mem.reset();
+        }
+        res
     }
 
-    pub fn build(self) -> Result<VirtualMachine<MB, C, EDS>, VmError> {
-        // data sources need to be complete. Check that.
-        trace!("data sources in builder");
-        let data_sources = if let Some(data_sources) = self.data_sources {
-            for ds in data_sources.as_ref().iter() {
-                trace!("{}", ds.exists_and_is_empty());
-                match ds.exists_and_is_empty() {
-                    ExistsAndEmpty(Some(_)) => {}
-                    ExistsAndEmpty(None) => {
-                        return Err(VmError::DataSourceNotInBuild(
-                            ds.get_name(),
-                        ))
-                    }
-                };
-            }
-            data_sources
-        } else {
-            return Err(VmError::DataSourcesNotReady);
-        };
+    /// Execute the filter for all the rx payloads in `rxs`, see
+    /// `VirtualMachine::exec_batch`.
+    pub fn exec_batch<R: RotoType>(
+        &mut self,
+        rxs: impl IntoIterator<Item = R>,
+    ) -> Result<Vec<VmResult>, VmError> {
+        self.vm.exec_batch(rxs)
+    }
 
-        if let Some(mir_code) = self.mir_code {
-            let hash_id =
-                compute_hash(mir_code.as_ref(), data_sources.as_ref());
-
-            if let Some(context) = self.context {
-                    Ok(VirtualMachine { 
-                        mir_code,
-                        data_sources,
-                        context,
-                        arguments: self.arguments,
-                        stack: RefCell::new(Stack::new()),
-                        hash_id,
-                    })
-                } else {
-                    Err(VmError::IncompleteContext)
-                }
-        } else {
-            Err(VmError::NoMir)
-        }
-    }
-}
-
-impl<
-        MB: AsRef<[MirBlock]> + std::hash::Hash,
-        C: AsRef<RouteContext> + std::hash::Hash,
-        EDS: AsRef<[ExtDataSource]> + std::hash::Hash,
-    > Default for VmBuilder<MB, C, EDS>
-{
-    fn default() -> Self {
-        Self::new()
+    pub fn update_context(&mut self, context: C) {
+        self.vm.update_context(context);
     }
 }
 
@@ -2886,9 +3191,435 @@ impl<
 #[derive(Debug, Clone)]
 pub struct VmResult {
     pub accept_reject: AcceptReject,
+    /// The reason given with the verdict in the apply section, e.g. the
+    /// "rpki-invalid" in `return reject with "rpki-invalid";`.
+    pub reason: Option<TypeValue>,
     pub rx: TypeValue,
     pub tx: Option<TypeValue>,
     pub output_stream_queue: OutputStreamQueue,
+    /// The execution trace, only present if tracing is turned on for the
+    /// filter, see `CompiledFilter::with_trace`.
+    pub trace: Option<Trace>,
+}
+
+//------------ Trace --------------------------------------------------------
+
+/// A point in the MIR code that is recorded in the execution trace. The
+/// compiler inserts these as the argument of an `OpCode::Trace` command.
+#[derive(Debug, Clone, Hash)]
+pub enum TracePoint {
+    /// The start of a line of a term section. It isn't recorded in the
+    /// trace, but it locates the errors of the line.
+    TermStart {
+        term: ShortString,
+        span: Option<Span>,
+    },
+    /// A line of a term section, the result of the line is on the top of
+    /// the stack.
+    Term {
+        term: ShortString,
+        index: usize,
+        span: Option<Span>,
+    },
+    /// A filter match arm in the apply section, the result of the term
+    /// section it refers to is on the top of the stack.
+    MatchAction {
+        term: ShortString,
+        negate: bool,
+        span: Option<Span>,
+    },
+    /// The start of an action section.
+    ActionSection {
+        name: ShortString,
+        span: Option<Span>,
+    },
+    /// The start of one expression in an action section.
+    Action {
+        section: ShortString,
+        span: Option<Span>,
+    },
+    /// The end of one expression in an action section.
+    ActionEnd,
+}
+
+/// Something that happened while executing a filter-map.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TraceEvent {
+    /// A line of a term section was evaluated.
+    Term {
+        term: ShortString,
+        index: usize,
+        result: bool,
+        span: Option<Span>,
+    },
+    /// A filter match arm in the apply section was reached. `fired` is
+    /// true if its actions were executed.
+    MatchAction {
+        term: ShortString,
+        negate: bool,
+        term_result: bool,
+        fired: bool,
+        span: Option<Span>,
+    },
+    /// An action section was executed.
+    ActionSection {
+        name: ShortString,
+        span: Option<Span>,
+    },
+    /// An expression in an action section was executed.
+    Action {
+        section: ShortString,
+        span: Option<Span>,
+    },
+    /// The rx value was changed by the action with `span`.
+    RxChanged {
+        old: TypeValue,
+        new: TypeValue,
+        span: Option<Span>,
+    },
+    /// The tx value was changed by the action with `span`.
+    TxChanged {
+        old: TypeValue,
+        new: TypeValue,
+        span: Option<Span>,
+    },
+}
+
+/// The trace of one execution of a filter-map, in the order the events
+/// happened. Only the code that was actually executed shows up, e.g. the
+/// term lines after the first line that evaluated to false are missing.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Trace(Vec<TraceEvent>);
+
+impl Trace {
+    pub fn events(&self) -> &[TraceEvent] {
+        &self.0
+    }
+
+    pub fn into_events(self) -> Vec<TraceEvent> {
+        self.0
+    }
+
+    /// The names of the terms of the match arms whose actions were
+    /// executed.
+    pub fn fired_match_actions(&self) -> impl Iterator<Item = &str> + '_ {
+        self.0.iter().filter_map(|e| match e {
+            TraceEvent::MatchAction {
+                term, fired: true, ..
+            } => Some(term.as_str()),
+            _ => None,
+        })
+    }
+
+    /// The names of the action sections that were executed.
+    pub fn executed_actions(&self) -> impl Iterator<Item = &str> + '_ {
+        self.0.iter().filter_map(|e| match e {
+            TraceEvent::ActionSection { name, .. } => Some(name.as_str()),
+            _ => None,
+        })
+    }
+}
+
+// Read the boolean the top of the stack refers to, without popping it.
+fn top_of_stack_as_bool(stack: &Stack, mem: &LinearMemory) -> Option<bool> {
+    stack
+        .get_top_value()
+        .and_then(|stack_ref| mem.get_mp_field_as_bool(stack_ref))
+        .ok()
+}
+
+// The state needed to build a Trace during execution: the rx and tx values
+// as they were at the start of the action that is currently running, so
+// that changes to them can be attributed to that action.
+#[derive(Debug, Default)]
+struct Tracer {
+    trace: Trace,
+    action: Option<(Option<Span>, TypeValue, TypeValue)>,
+}
+
+impl Tracer {
+    fn record(
+        &mut self,
+        point: Option<&CommandArg>,
+        stack: &Stack,
+        mem: &LinearMemory,
+    ) {
+        // A trace point should never make the execution fail, so a value
+        // that can't be read is simply not recorded.
+        let top_as_bool = || top_of_stack_as_bool(stack, mem);
+
+        let event = match point {
+            Some(CommandArg::TracePoint(TracePoint::Term {
+                term,
+                index,
+                span,
+            })) => match top_as_bool() {
+                Some(result) => TraceEvent::Term {
+                    term: term.clone(),
+                    index: *index,
+                    result,
+                    span: *span,
+                },
+                None => return,
+            },
+            Some(CommandArg::TracePoint(TracePoint::MatchAction {
+                term,
+                negate,
+                span,
+            })) => match top_as_bool() {
+                Some(term_result) => TraceEvent::MatchAction {
+                    term: term.clone(),
+                    negate: *negate,
+                    term_result,
+                    fired: term_result != *negate,
+                    span: *span,
+                },
+                None => return,
+            },
+            Some(CommandArg::TracePoint(TracePoint::ActionSection {
+                name,
+                span,
+            })) => TraceEvent::ActionSection {
+                name: name.clone(),
+                span: *span,
+            },
+            Some(CommandArg::TracePoint(TracePoint::Action {
+                section,
+                span,
+            })) => {
+                self.action = Some((
+                    *span,
+                    mem.get_mem_pos(0).cloned().unwrap_or_default(),
+                    mem.get_mem_pos(1).cloned().unwrap_or_default(),
+                ));
+                TraceEvent::Action {
+                    section: section.clone(),
+                    span: *span,
+                }
+            }
+            Some(CommandArg::TracePoint(TracePoint::ActionEnd)) => {
+                if let Some((span, rx, tx)) = self.action.take() {
+                    self.changed(0, rx, mem, span);
+                    self.changed(1, tx, mem, span);
+                }
+                return;
+            }
+            _ => return,
+        };
+
+        self.trace.0.push(event);
+    }
+
+    // Record that the value in memory position `pos`, the rx or the tx
+    // value, is about to be replaced with `new` by a SetRxField or
+    // SetTxField command.
+    fn set_field(&mut self, pos: usize, mem: &LinearMemory, new: &TypeValue) {
+        let old = mem.get_mem_pos(pos).cloned().unwrap_or_default();
+        let span = self.action.as_ref().and_then(|a| a.0);
+
+        // Move the snapshot of the running action forward, so that the
+        // change doesn't get recorded twice at the end of the action.
+        if let Some(action) = self.action.as_mut() {
+            match pos {
+                0 => action.1 = new.clone(),
+                _ => action.2 = new.clone(),
+            }
+        }
+
+        self.push_change(pos, old, new.clone(), span);
+    }
+
+    fn changed(
+        &mut self,
+        pos: usize,
+        old: TypeValue,
+        mem: &LinearMemory,
+        span: Option<Span>,
+    ) {
+        if let Some(new) = mem.get_mem_pos(pos) {
+            if new != &old {
+                self.push_change(pos, old, new.clone(), span);
+            }
+        }
+    }
+
+    fn push_change(
+        &mut self,
+        pos: usize,
+        old: TypeValue,
+        new: TypeValue,
+        span: Option<Span>,
+    ) {
+        self.trace.0.push(match pos {
+            0 => TraceEvent::RxChanged { old, new, span },
+            _ => TraceEvent::TxChanged { old, new, span },
+        });
+    }
+}
+
+//------------ Metrics ------------------------------------------------------
+
+/// Runtime counters for one filter-map: how often it accepted, rejected,
+/// continued or failed, how long its executions took, and how often each
+/// of its terms was evaluated and matched.
+///
+/// The counters are atomics, so one FilterMetrics can be shared by all the
+/// VMs (or execution contexts) that run the same filter-map, on any number
+/// of threads. The terms are collected from the MIR code up front, so that
+/// counting never allocates.
+#[derive(Debug)]
+pub struct FilterMetrics {
+    filter_map: Scope,
+    executions: AtomicU64,
+    accepted: AtomicU64,
+    rejected: AtomicU64,
+    continued: AtomicU64,
+    errors: AtomicU64,
+    exec_time_ns: AtomicU64,
+    terms: Vec<TermMetrics>,
+}
+
+#[derive(Debug)]
+struct TermMetrics {
+    name: ShortString,
+    // The index of the last line of the term section. The term matched if
+    // this line evaluated to true.
+    last_index: usize,
+    evaluated: AtomicU64,
+    matched: AtomicU64,
+}
+
+impl FilterMetrics {
+    pub(crate) fn new(filter_map: Scope, mir: &[MirBlock]) -> Self {
+        let mut terms: Vec<TermMetrics> = vec![];
+
+        for command in mir.iter().flat_map(|mb| mb.iter()) {
+            let (term, index) = match command.args.front() {
+                Some(CommandArg::TracePoint(TracePoint::Term {
+                    term,
+                    index,
+                    ..
+                })) => (term, *index),
+                _ => continue,
+            };
+
+            match terms.iter_mut().find(|t| &t.name == term) {
+                Some(t) => t.last_index = t.last_index.max(index),
+                None => terms.push(TermMetrics {
+                    name: term.clone(),
+                    last_index: index,
+                    evaluated: AtomicU64::new(0),
+                    matched: AtomicU64::new(0),
+                }),
+            }
+        }
+
+        Self {
+            filter_map,
+            executions: AtomicU64::new(0),
+            accepted: AtomicU64::new(0),
+            rejected: AtomicU64::new(0),
+            continued: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+            exec_time_ns: AtomicU64::new(0),
+            terms,
+        }
+    }
+
+    pub fn snapshot(&self) -> FilterMetricsSnapshot {
+        FilterMetricsSnapshot {
+            filter_map: self.filter_map.clone(),
+            executions: self.executions.load(Ordering::Relaxed),
+            accepted: self.accepted.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+            continued: self.continued.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            exec_time: Duration::from_nanos(
+                self.exec_time_ns.load(Ordering::Relaxed),
+            ),
+            terms: self
+                .terms
+                .iter()
+                .map(|t| TermMetricsSnapshot {
+                    name: t.name.clone(),
+                    evaluated: t.evaluated.load(Ordering::Relaxed),
+                    matched: t.matched.load(Ordering::Relaxed),
+                })
+                .collect(),
+        }
+    }
+
+    fn record_exec(
+        &self,
+        res: &Result<VmResult, VmError>,
+        elapsed: Duration,
+    ) {
+        self.executions.fetch_add(1, Ordering::Relaxed);
+        self.exec_time_ns
+            .fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+        let counter = match res {
+            Ok(VmResult { accept_reject, .. }) => match accept_reject {
+                AcceptReject::Accept => &self.accepted,
+                AcceptReject::Reject => &self.rejected,
+                AcceptReject::Continue | AcceptReject::NoReturn => {
+                    &self.continued
+                }
+            },
+            Err(_) => &self.errors,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_point(
+        &self,
+        point: Option<&CommandArg>,
+        stack: &Stack,
+        mem: &LinearMemory,
+    ) {
+        if let Some(CommandArg::TracePoint(TracePoint::Term {
+            term,
+            index,
+            ..
+        })) = point
+        {
+            if let Some(t) = self.terms.iter().find(|t| &t.name == term) {
+                if *index == 0 {
+                    t.evaluated.fetch_add(1, Ordering::Relaxed);
+                }
+                if *index == t.last_index
+                    && top_of_stack_as_bool(stack, mem) == Some(true)
+                {
+                    t.matched.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+    }
+}
+
+/// The values of the counters of a FilterMetrics at one point in time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilterMetricsSnapshot {
+    pub filter_map: Scope,
+    pub executions: u64,
+    pub accepted: u64,
+    pub rejected: u64,
+    /// Executions that neither accepted nor rejected, i.e. that returned
+    /// `continue` or didn't return at all.
+    pub continued: u64,
+    /// Executions that returned an error.
+    pub errors: u64,
+    /// The total time spent in all executions.
+    pub exec_time: Duration,
+    pub terms: Vec<TermMetricsSnapshot>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TermMetricsSnapshot {
+    pub name: ShortString,
+    /// The number of times the term section was evaluated.
+    pub evaluated: u64,
+    /// The number of times all the lines of the term section were true.
+    pub matched: u64,
 }
 
 //------------ StreamOutputQueue --------------------------------------------
@@ -2950,6 +3681,9 @@ impl IntoIterator for OutputStreamQueue {
 
 #[derive(Debug)]
 pub enum VmError {
+    /// An error that happened while executing a command of the MIR code,
+    /// with the location of that command.
+    InCommand(Box<VmErrorContext>),
     StackUnderflow,
     StackOverflow,
     MemOutOfBounds,
@@ -2987,20 +3721,50 @@ pub enum VmError {
     ParseError(routecore::bgp::ParseError),
 }
 
+impl VmError {
+    /// The error without the location it happened at.
+    pub fn kind(&self) -> &VmError {
+        match self {
+            VmError::InCommand(ctx) => ctx.error.kind(),
+            _ => self,
+        }
+    }
+
+    /// The location in the MIR code this error happened at, if it happened
+    /// while executing a command.
+    pub fn context(&self) -> Option<&VmErrorContext> {
+        match self {
+            VmError::InCommand(ctx) => Some(ctx),
+            _ => None,
+        }
+    }
+
+    /// The span in the source code of the term or action that was being
+    /// executed when this error happened, if known.
+    pub fn span(&self) -> Option<Span> {
+        self.context().and_then(|ctx| ctx.span)
+    }
+}
+
 impl Display for VmError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
+            VmError::InCommand(ctx) => write!(f, "{}", ctx),
             VmError::StackUnderflow => f.write_str("StackUnderflow"),
             VmError::StackOverflow => f.write_str("StackOverflow"),
             VmError::MemOutOfBounds => f.write_str("MemOutOfBounds"),
-            VmError::InvalidMemoryAccess(_) => {
-                f.write_str("InvalidMemoryAccess")
+            VmError::InvalidMemoryAccess(pos) => {
+                write!(f, "InvalidMemoryAccess at memory position {}", pos)
             }
             VmError::AnonymousArgumentNotFound => {
                 f.write_str("AnonymousArgumentNotFound")
             }
-            VmError::ArgumentNotFound(_) => f.write_str("ArgumentNotFound"),
-            VmError::ArgumentsMissing(_) => f.write_str("ArgumentsMissing"),
+            VmError::ArgumentNotFound(name) => {
+                write!(f, "ArgumentNotFound '{}'", name)
+            }
+            VmError::ArgumentsMissing(names) => {
+                write!(f, "ArgumentsMissing {:?}", names)
+            }
             VmError::InvalidValueType => f.write_str("InvalidValueType"),
             VmError::InvalidPayload => f.write_str("InvalidPayload"),
             VmError::InvalidVariableAccess => {
@@ -3012,8 +3776,8 @@ impl Display for VmError {
             VmError::InvalidDataSourceAccess => {
                 f.write_str("InvalidDataSourceAccess")
             }
-            VmError::DataSourceTokenNotFound(_) => {
-                f.write_str("DataSourceTokenNotFound")
+            VmError::DataSourceTokenNotFound(token) => {
+                write!(f, "DataSourceTokenNotFound {}", token)
             }
             VmError::DataSourceNotInBuild(ds_name) => {
                 write!(
@@ -3036,7 +3800,9 @@ impl Display for VmError {
             VmError::InvalidCommandArg => f.write_str("InvalidCommandArg"),
             VmError::InvalidDataSource => f.write_str("InvalidDataSource"),
             VmError::InvalidConversion => f.write_str("InvalidConversion"),
-            VmError::InvalidCompareOp(_) => f.write_str("InvalidCompareOp"),
+            VmError::InvalidCompareOp(op) => {
+                write!(f, "InvalidCompareOp {}", op)
+            }
             VmError::InvalidPathAttribute => f.write_str("InvalidPathAttribute"),
             VmError::UnexpectedTermination => {
                 f.write_str("UnexpectedTermination")
@@ -3056,6 +3822,40 @@ impl Display for VmError {
     }
 }
 
+/// The location of the command in the MIR code that failed.
+#[derive(Debug)]
+pub struct VmErrorContext {
+    pub error: VmError,
+    pub op: OpCode,
+    /// The index of the MIR block.
+    pub block: usize,
+    /// The index of the command in the MIR block.
+    pub pc: usize,
+    /// The name of the variable, field or method that failed.
+    pub name: Option<ShortString>,
+    /// The name of the term or action section that was being executed.
+    pub section: Option<ShortString>,
+    /// The span of the term line or the action that was being executed.
+    pub span: Option<Span>,
+}
+
+impl Display for VmErrorContext {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} in {:?} (block {}, pc {})",
+            self.error, self.op, self.block, self.pc
+        )?;
+        if let Some(name) = &self.name {
+            write!(f, " at '{}'", name)?;
+        }
+        if let Some(section) = &self.section {
+            write!(f, " in '{}'", section)?;
+        }
+        Ok(())
+    }
+}
+
 impl From<VmError> for Box<dyn std::error::Error> {
     fn from(value: VmError) -> Self {
         format!("A fatal VM Error occured: {}", value).into()
@@ -3068,28 +3868,32 @@ impl From<routecore::bgp::ParseError> for VmError {
     }
 }
 
+/// One VM command, an internal of the compiler and the VM.
 #[derive(Debug, Clone)]
-pub struct Command {
+pub(crate) struct Command {
     pub(crate) op: OpCode,
     pub(crate) args: VecDeque<CommandArg>,
+    /// The name of the variable, field or method in the source code this
+    /// command was compiled from, to report a failing command with.
+    pub(crate) symbol: Option<ShortString>,
 }
 
 impl Command {
-    pub fn new(op: OpCode, args: Vec<CommandArg>) -> Self {
+    pub(crate) fn new(op: OpCode, args: Vec<CommandArg>) -> Self {
         Command {
             op,
             args: args.into(),
+            symbol: None,
         }
     }
 
-    pub fn try_clone(self) -> Result<Self, CompileError> {
-        Ok(Self {
-            op: self.op,
-            args: self.args.clone(),
-        })
+    pub(crate) fn with_symbol(mut self, symbol: Option<ShortString>) -> Self {
+        self.symbol = symbol;
+        self
     }
 }
 
+// The symbol is left out, it doesn't change what the command does.
 impl std::hash::Hash for Command {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.op.hash(state);
@@ -3132,6 +3936,7 @@ impl Display for Command {
             OpCode::CondFalseSkipToEOB => "-->",
             OpCode::CondTrueSkipToEOB => "-->",
             OpCode::CondFalseSkipToLabel => "-->",
+            OpCode::CondTrueSkipToLabel => "-->",
             OpCode::CondUnknownSkipToLabel => "-->",
             OpCode::Label => {
                 return write!(
@@ -3150,6 +3955,7 @@ impl Display for Command {
             OpCode::PushOutputStreamQueue => "(o)=>",
             OpCode::SetRxField => "->",
             OpCode::SetTxField => "->",
+            OpCode::Trace => "..",
         };
         write!(f, "{:?}{}{:?}", self.op, arrow, self.args)
     }
@@ -3205,6 +4011,8 @@ pub enum CommandArg {
     AcceptReject(AcceptReject),
     /// the index of a variant of an enum
     Variant(usize),
+    /// a point in the code that gets recorded in the execution trace
+    TracePoint(TracePoint),
 }
 
 impl CommandArg {
@@ -3337,9 +4145,20 @@ pub enum OpCode {
     /// reference to a boolean value false.
     CondTrueSkipToEOB,
     /// Skip to the next label in a MIR block if the top of the stack holds a
-    /// reference to a TypeValue::Boolean that is false.
+    /// reference to a TypeValue::Boolean that is false. If the command has a
+    /// Label argument, skip to the label with that name instead. The value
+    /// on the stack is left in place.
     CondFalseSkipToLabel,
     /// Skip to the next label in a MIR block if the top of the stack holds a
+    /// reference to a TypeValue::Boolean that is true. If the command has a
+    /// Label argument, skip to the label with that name instead. The value
+    /// on the stack is left in place.
+    ///
+    /// Used for `||` and `in`, that are decided by the first true value.
+    /// CondTrueSkipToEOB can't be used for that, since it skips the rest of
+    /// the term block, including the other lines of the `match` block.
+    CondTrueSkipToLabel,
+    /// Skip to the next label in a MIR block if the top of the stack holds a
     /// reference to a TypeValue::Unknown. Used to match expression variants.
     CondUnknownSkipToLabel,
     /// Debug Label for terms
@@ -3349,6 +4168,10 @@ pub enum OpCode {
     /// The output stream stack holds indexes to memory positions that contain
     /// messages to be send out.
     PushOutputStreamQueue,
+    /// Record the TracePoint argument in the execution trace, if the VM was
+    /// built with tracing enabled, otherwise a no-op. Reads the top of the
+    /// stack, but leaves it in place.
+    Trace,
     Exit(AcceptReject),
 }
 
@@ -3356,7 +4179,63 @@ pub struct ExtDataSource {
     name: ShortString,
     token: usize,
     ty: TypeDef,
+    /// Shared with the filters that take this data source over, see
+    /// [`ExtDataSource::sharing`].
+    slot: Arc<SourceSlot>,
+}
+
+/// The source of an [`ExtDataSource`] together with its version.
+struct SourceSlot {
     source: ArcSwapOption<DataSource>,
+    /// Twice the number of times a source was stored, and odd while a
+    /// source is being stored, so that a source can be read together with
+    /// its version without taking the lock.
+    version: AtomicU64,
+    /// Serializes the updates, so that an update that is based on the
+    /// current source doesn't get lost.
+    update_lock: Mutex<()>,
+}
+
+impl SourceSlot {
+    fn new(source: Option<Arc<DataSource>>, version: u64) -> Arc<Self> {
+        Arc::new(Self {
+            source: ArcSwapOption::from(source),
+            version: AtomicU64::new(version * 2),
+            update_lock: Mutex::new(()),
+        })
+    }
+}
+
+/// The sources of data sources, with their versions, as they were at one
+/// moment, e.g. when a route entered a pipeline. All the stages of the
+/// pipeline run with these, so that a route sees one version of every
+/// source on its way through the pipeline.
+#[derive(Default)]
+pub(crate) struct PinnedSources(
+    Vec<(Arc<SourceSlot>, Option<Arc<DataSource>>, u64)>,
+);
+
+impl PinnedSources {
+    /// Pin the current sources of `data_sources` that aren't pinned yet.
+    pub(crate) fn pin(&mut self, data_sources: &[ExtDataSource]) {
+        for ds in data_sources {
+            if self.get(ds).is_none() {
+                let (source, version) = ds.snapshot();
+                self.0.push((Arc::clone(&ds.slot), source, version));
+            }
+        }
+    }
+
+    /// The pinned source of `ds` with its version, if it is pinned.
+    fn get(
+        &self,
+        ds: &ExtDataSource,
+    ) -> Option<(Option<Arc<DataSource>>, u64)> {
+        self.0
+            .iter()
+            .find(|(slot, ..)| Arc::ptr_eq(slot, &ds.slot))
+            .map(|(_, source, version)| (source.clone(), *version))
+    }
 }
 
 impl std::fmt::Debug for ExtDataSource {
@@ -3388,15 +4267,12 @@ impl Display for ExistsAndEmpty {
 
 impl Clone for ExtDataSource {
     fn clone(&self) -> Self {
+        let (source, version) = self.snapshot();
         Self {
             name: self.name.clone(),
             token: self.token,
             ty: self.ty.clone(),
-            source: if let Some(s) = self.source.load().as_ref() {
-                ArcSwapOption::from(Some(Arc::clone(s)))
-            } else {
-                ArcSwapOption::from(None)
-            },
+            slot: SourceSlot::new(source, version),
         }
     }
 }
@@ -3410,8 +4286,8 @@ impl ExtDataSource {
         Ok(ExtDataSource {
             name: name.into(),
             token: token.try_into()?,
-            source: ArcSwapOption::from(None),
             ty,
+            slot: SourceSlot::new(None, 0),
         })
     }
 
@@ -3419,17 +4295,86 @@ impl ExtDataSource {
         self.name.clone()
     }
 
-    pub(crate) fn get_source(&self) -> &ArcSwapOption<DataSource> {
-        &self.source
+    /// The version of the source, that goes up by one every time the
+    /// source is (re)placed or updated.
+    pub fn version(&self) -> u64 {
+        self.snapshot().1
+    }
+
+    /// Replace the source with the one that `update` creates from the
+    /// current source, and return the new version.
+    ///
+    /// The updates are serialized, but the executions that are running
+    /// keep the source they started with, see [`DataSourceHandle`].
+    pub(crate) fn update_source<E>(
+        &self,
+        update: impl FnOnce(
+            Option<&DataSource>,
+        ) -> Result<Arc<DataSource>, E>,
+    ) -> Result<u64, E> {
+        let _guard = self
+            .slot
+            .update_lock
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let source = update(self.slot.source.load().as_deref())?;
+        Ok(self.store(Some(source)))
+    }
+
+    /// This data source, sharing the source of `other`, e.g. when a filter
+    /// is reloaded. From then on, every update of the one is an update of
+    /// the other.
+    pub(crate) fn sharing(&self, other: &ExtDataSource) -> Self {
+        Self {
+            name: self.name.clone(),
+            token: self.token,
+            ty: self.ty.clone(),
+            slot: Arc::clone(&other.slot),
+        }
+    }
+
+    /// Store `source` with the next version. Must be called with the
+    /// update lock held.
+    fn store(&self, source: Option<Arc<DataSource>>) -> u64 {
+        let cur = self.slot.version.fetch_add(1, Ordering::AcqRel) + 1;
+        self.slot.source.store(source);
+        let version = cur / 2 + 1;
+        self.slot.version.store(version * 2, Ordering::Release);
+        version
+    }
+
+    /// The current source together with its version.
+    fn snapshot(&self) -> (Option<Arc<DataSource>>, u64) {
+        loop {
+            let before = self.slot.version.load(Ordering::Acquire);
+            if before % 2 == 1 {
+                std::hint::spin_loop();
+                continue;
+            }
+            let source = self.slot.source.load_full();
+            std::sync::atomic::fence(Ordering::Acquire);
+            if self.slot.version.load(Ordering::Relaxed) == before {
+                return (source, before / 2);
+            }
+        }
     }
 
     pub fn get_type(&self) -> &TypeDef {
         &self.ty
     }
 
+    /// The key the table is indexed on, if this is a table that declares
+    /// one in the source code.
+    pub(crate) fn get_table_key(&self) -> Option<&ShortString> {
+        match &self.ty {
+            TypeDef::Table((_, key)) => key.as_ref(),
+            _ => None,
+        }
+    }
+
     pub fn get_value_type(&self) -> TypeDef {
         match &self.ty {
-            TypeDef::Table(t) => *t.clone(),
+            TypeDef::Table((t, _)) => *t.clone(),
             TypeDef::Rib((rec, _)) => *rec.clone(),
             TypeDef::OutputStream(s) => *s.clone(),
             _ => self.ty.clone(),
@@ -3437,7 +4382,7 @@ impl ExtDataSource {
     }
 
     pub fn exists_and_is_empty(&self) -> ExistsAndEmpty {
-        if let Some(source) = self.source.load().as_ref() {
+        if let Some(source) = self.slot.source.load().as_ref() {
             ExistsAndEmpty(Some(source.as_ref().is_empty()))
         } else {
             ExistsAndEmpty(None)
@@ -3449,20 +4394,272 @@ impl ExtDataSource {
         pos: usize,
         field_index: FieldIndex,
     ) -> Result<Option<TypeValue>, VmError> {
-        self.source
+        self.slot.source
             .load()
             .as_ref()
-            .map(|ds| match ds.get_at_field_index(pos, field_index) {
-                Some(TypeValue::SharedValue(sv)) => {
-                    Ok(Some(TypeValue::SharedValue(Arc::clone(sv))))
-                }
-                Some(_) => Err(VmError::InvalidFieldAccess),
-                None => Ok(Some(TypeValue::Unknown)),
-            })
+            .map(|ds| field_at_index(ds, pos, field_index))
             .ok_or(VmError::InvalidFieldAccess)?
     }
 }
 
+/// The value of the field at `field_index` of the record at `pos` in `ds`.
+fn field_at_index(
+    ds: &DataSource,
+    pos: usize,
+    field_index: FieldIndex,
+) -> Result<Option<TypeValue>, VmError> {
+    match ds.get_at_field_index(pos, field_index) {
+        Some(TypeValue::SharedValue(sv)) => {
+            Ok(Some(TypeValue::SharedValue(Arc::clone(sv))))
+        }
+        Some(v) => Ok(Some(v.clone())),
+        None => Ok(Some(TypeValue::Unknown)),
+    }
+}
+
+/// The value of the field at `field_index` of a value on the stack, e.g.
+/// of the record that a method returned.
+fn constant_field(
+    value: TypeValue,
+    field_index: &FieldIndex,
+) -> Result<TypeValue, VmError> {
+    if field_index.is_empty() {
+        return Ok(value);
+    }
+    match &value {
+        TypeValue::Record(rec) => rec
+            .get_field_by_index(field_index)
+            .ok_or(VmError::InvalidFieldAccess)
+            .and_then(<&TypeValue>::try_from)
+            .cloned(),
+        TypeValue::Unknown => Ok(TypeValue::Unknown),
+        _ => Err(VmError::InvalidFieldAccess),
+    }
+}
+
+//------------ DataSourceHandle ---------------------------------------------
+
+/// A handle to update a data source of a filter, while the filter runs.
+///
+/// Every update creates a new version of the source and swaps it in. The
+/// new version shares the records of the table or the prefixes of the rib
+/// that the update doesn't touch with the old one, so an update only copies
+/// the part of the source that it changes. An execution of the filter uses
+/// the sources as they were when it started, so a route never sees part of
+/// an update. The filter can check the
+/// version of a data source with its `version()` method:
+///
+/// ```roto
+/// term customers-loaded {
+///     match {
+///         customers.version() > 0;
+///     }
+/// }
+/// ```
+///
+/// The version goes up by one with every update, and with every source that
+/// is bound with `bind_source`.
+///
+/// A handle is created with `Filter::data_source` or `Pipeline::data_source`,
+/// and can be cloned and sent to other threads. The stages of a pipeline
+/// share their data sources, so an update of a pipeline source is a single
+/// new version for all of them. It keeps working after a
+/// [`ReloadableFilter`] is reloaded, since the new version of the filter
+/// shares its sources with the old one.
+///
+/// [`ReloadableFilter`]: crate::pipeline::ReloadableFilter
+#[derive(Debug)]
+pub struct DataSourceHandle {
+    name: ShortString,
+    /// Shares its source with the data source of the filters.
+    source: ExtDataSource,
+}
+
+impl DataSourceHandle {
+    /// A handle to the first data source with `name` in `data_sources`.
+    pub(crate) fn new<'a>(
+        name: &str,
+        data_sources: impl IntoIterator<Item = &'a Arc<[ExtDataSource]>>,
+    ) -> Option<Self> {
+        let source = data_sources
+            .into_iter()
+            .find_map(|ds| ds.iter().find(|d| d.get_name() == name))?;
+
+        Some(Self {
+            name: name.into(),
+            source: source.sharing(source),
+        })
+    }
+
+    pub fn get_name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    /// The current version of the source, 0 if it hasn't been bound yet.
+    /// The `version()` method in roto returns a U32, that stays at
+    /// `u32::MAX` once this version gets past it.
+    pub fn version(&self) -> u64 {
+        self.source.version()
+    }
+
+    /// Replace the whole source. Returns the new version.
+    pub fn replace(&self, source: DataSource) -> Result<u64, CompileError> {
+        if source.get_name() != self.name {
+            return Err(CompileError::from(format!(
+                "Cannot replace data source {} with a source with name: {}",
+                self.name,
+                source.get_name()
+            )));
+        }
+        self.update(|ds, _| prepare_source(ds, &source))
+    }
+
+    /// Add `records` to the table. Returns the new version.
+    pub fn insert_records(
+        &self,
+        records: Vec<Record>,
+    ) -> Result<u64, CompileError> {
+        let ty = self.source.get_value_type();
+        for rec in &records {
+            let rec_ty = TypeDef::from(&TypeValue::Record(rec.clone()));
+            if rec_ty != ty {
+                return Err(CompileError::from(format!(
+                    "Cannot insert a record of type {} into table {}, \
+                    expected {}",
+                    rec_ty, self.name, ty
+                )));
+            }
+        }
+        self.update_table(|table| Ok(table.with_records(records)))
+    }
+
+    /// Remove the records of the table for which `remove` returns true.
+    /// Returns the new version.
+    pub fn remove_records(
+        &self,
+        remove: impl FnMut(&Record) -> bool,
+    ) -> Result<u64, CompileError> {
+        self.update_table(|table| Ok(table.without_records(remove)))
+    }
+
+    /// Replace the records of the table for which `replace` returns a
+    /// record with that record, which has to be of the record type of the
+    /// table. The records are passed to `replace` in no particular order.
+    /// Returns the new version.
+    pub fn update_records(
+        &self,
+        mut replace: impl FnMut(&Record) -> Option<Record>,
+    ) -> Result<u64, CompileError> {
+        let ty = self.source.get_value_type();
+        self.update_table(|table| {
+            let mut wrong_ty = None;
+            let table = table.with_replaced_records(|rec| {
+                let new = replace(rec)?;
+                let new_ty = TypeDef::from(&TypeValue::Record(new.clone()));
+                if new_ty != ty {
+                    wrong_ty.get_or_insert(new_ty);
+                }
+                Some(new)
+            });
+            match wrong_ty {
+                Some(rec_ty) => Err(CompileError::from(format!(
+                    "Cannot update a record of table {} to type {}, \
+                    expected {}",
+                    self.name, rec_ty, ty
+                ))),
+                None => Ok(table),
+            }
+        })
+    }
+
+    /// Store the table that `update` creates from the current table, or
+    /// from an empty table if the table hasn't been bound yet.
+    fn update_table(
+        &self,
+        update: impl FnOnce(&Table) -> Result<Table, CompileError>,
+    ) -> Result<u64, CompileError> {
+        self.update(|ds, cur| {
+            let table = match cur {
+                Some(DataSource::Table(t)) => update(t)?,
+                None if matches!(ds.get_type(), TypeDef::Table(_)) => {
+                    update(&Table::new(
+                        ds.get_name(),
+                        ds.get_value_type(),
+                        vec![],
+                        ds.get_table_key(),
+                    ))?
+                }
+                _ => {
+                    return Err(CompileError::from(format!(
+                        "Data source {} is not a table",
+                        self.name
+                    )))
+                }
+            };
+            Ok(Arc::new(DataSource::Table(table)))
+        })
+    }
+
+    /// Set the records for `prefix` in the rib, replacing the records it
+    /// had. Returns the new version.
+    pub fn insert_prefix(
+        &self,
+        prefix: Prefix,
+        records: Vec<TypeValue>,
+    ) -> Result<u64, CompileError> {
+        self.update(|_, cur| match cur {
+            Some(DataSource::Rib(rib)) => rib
+                .with_prefix_records(prefix, records)
+                .map(|rib| Arc::new(DataSource::Rib(rib)))
+                .map_err(|e| {
+                    CompileError::from(format!(
+                        "Cannot update prefix {} in rib {}: {}",
+                        prefix, self.name, e
+                    ))
+                }),
+            Some(DataSource::Table(_) | DataSource::Custom(..)) => {
+                Err(CompileError::from(format!(
+                    "Data source {} is not a rib",
+                    self.name
+                )))
+            }
+            None => Err(CompileError::from(format!(
+                "Rib {} has no source to update",
+                self.name
+            ))),
+        })
+    }
+
+    /// Remove `prefix` with its records from the rib. Returns the new
+    /// version.
+    pub fn remove_prefix(
+        &self,
+        prefix: Prefix,
+    ) -> Result<u64, CompileError> {
+        self.insert_prefix(prefix, vec![])
+    }
+
+    /// Store the source that `update` creates from the current source.
+    fn update(
+        &self,
+        update: impl FnOnce(
+            &ExtDataSource,
+            Option<&DataSource>,
+        ) -> Result<Arc<DataSource>, CompileError>,
+    ) -> Result<u64, CompileError> {
+        self.source.update_source(|cur| update(&self.source, cur))
+    }
+}
+
+impl Clone for DataSourceHandle {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            source: self.source.sharing(&self.source),
+        }
+    }
+}
+
 // I'd rather implement SliceIndex on [ExtDataSource], so you could just do
 // [..].get() etc, but that's experimental still.
 pub fn get_data_source(
@@ -0,0 +1,88 @@
+use inetnum::asn::Asn;
+
+use crate::ast::AcceptReject;
+use crate::blocks::Scope;
+use crate::pipeline;
+use crate::types::builtin::{
+    NlriStatus, PeerId, PeerRibType, Provenance, RouteContext,
+};
+use crate::types::collections::Record;
+use crate::types::typedef::TypeDef;
+
+use super::LinearMemory;
+
+const SOURCE_CODE: &str = r###"
+    filter-map my-filter-map {
+        define {
+            rx_tx msg: BmpMsg;
+            v0 = AS1;
+            v1 = AS2;
+            v2 = AS3;
+            v3 = AS4;
+            v4 = AS5;
+        }
+
+        term asn-not-in-vars {
+            match {
+                msg.asn != v0;
+                msg.asn != v1;
+                msg.asn != v2;
+                msg.asn != v3;
+                msg.asn != v4;
+            }
+        }
+
+        apply {
+            filter match asn-not-in-vars matching { return accept; };
+            return reject;
+        }
+    }
+
+    type BmpMsg {
+        asn: Asn
+    }
+"###;
+
+fn context() -> RouteContext {
+    let provenance = Provenance {
+        timestamp: chrono::Utc::now(),
+        connection_id: "192.0.2.10:178".parse().unwrap(),
+        peer_id: PeerId {
+            addr: "192.0.2.10".parse().unwrap(),
+            asn: Asn::from(65534),
+        },
+        peer_bgp_id: [0; 4].into(),
+        peer_distuingisher: [0; 8],
+        peer_rib_type: PeerRibType::OutPost,
+    };
+
+    RouteContext::new(None, NlriStatus::InConvergence, provenance)
+}
+
+fn payload(asn: u32) -> Record {
+    let ty = TypeDef::new_record_type(vec![("asn", Box::new(TypeDef::Asn))])
+        .unwrap();
+
+    Record::create_instance_with_ordered_fields(
+        &ty,
+        vec![("asn", Asn::from_u32(asn).into())],
+    )
+    .unwrap()
+}
+
+// A LinearMemory that is too small for the filter is grown by the VM.
+#[test]
+fn linear_memory_grows() {
+    let filter = pipeline::run_test(SOURCE_CODE, None)
+        .unwrap()
+        .retrieve_compiled_filter(&Scope::FilterMap("my-filter-map".into()))
+        .unwrap();
+    assert!(filter.get_mem_size() > 3);
+
+    let mut ctx = filter.new_execution_context(context()).unwrap();
+    ctx.mem = LinearMemory::with_size(3);
+
+    let res = ctx.exec(payload(65534), None::<Record>).unwrap();
+    assert_eq!(res.accept_reject, AcceptReject::Accept);
+    assert!(ctx.mem.0.len() >= filter.get_mem_size());
+}
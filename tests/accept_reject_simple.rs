@@ -1,26 +1,24 @@
 use log::{info, trace};
 use roto::ast::AcceptReject;
 
-use roto::blocks::Scope::{self, FilterMap};
+use roto::runtime::{Runtime, Verdict};
 use roto::types::builtin::{NlriStatus, PeerId, PeerRibType, Provenance, RouteContext};
-use roto::pipeline;
 use roto::types::collections::Record;
 use roto::types::typedef::TypeDef;
-use roto::vm::{self, VmResult};
 
 use inetnum::asn::Asn;
 
 mod common;
 
 fn test_data(
-    name: Scope,
+    name: &str,
     source_code: &str,
-) -> Result<VmResult, Box<dyn std::error::Error>> {
+) -> Result<Verdict, Box<dyn std::error::Error>> {
     info!("Evaluate filter-map {}...", name);
 
     // Compile the source code in this example
-    let rotolo = pipeline::run_test(source_code, None)?;
-    let roto_pack = rotolo.retrieve_pack_as_refs(&name)?;
+    let runtime = Runtime::from_string(source_code)?;
+    let mut filter = runtime.filter(name)?;
 
     let payload_type =
         TypeDef::new_record_type(vec![("asn", Box::new(TypeDef::Asn))])?;
@@ -30,10 +28,6 @@ fn test_data(
         vec![("asn", Asn::from(65534_u32).into())],
     )?;
 
-    for mb in roto_pack.get_mir().iter() {
-        println!("{}", mb);
-    }
-
     let peer_ip = "192.0.2.0".parse().unwrap();
     
     let provenance = Provenance {
@@ -49,14 +43,8 @@ fn test_data(
         None, NlriStatus::Empty, provenance
     );
 
-    let mut vm = vm::VmBuilder::new()
-        .with_data_sources(roto_pack.data_sources)
-        .with_context(&context)
-        .with_mir_code(roto_pack.mir)
-        .build()?;
-
-    let mem = &mut vm::LinearMemory::uninit();
-    let res = vm.exec(payload, None::<Record>, None, mem)?;
+    filter.bind_context(context);
+    let res = filter.run(payload)?;
 
     trace!("\nRESULT");
     trace!("action: {}", res.accept_reject);
@@ -116,14 +104,14 @@ fn test_filter_map_10() {
         "AS65534",
         "reject",
     );
-    let test_run = test_data(FilterMap("my-filter-map".into()), src_line);
+    let test_run = test_data("my-filter-map", src_line);
 
     if let Err(e) = &test_run {
         println!("{}", e);
         unreachable!();
     }
 
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Accept);
 }
 
@@ -135,11 +123,11 @@ fn test_filter_map_11() {
         "AS0",
         "reject",
     );
-    let test_run = test_data(FilterMap("my-filter-map".into()), src_line);
+    let test_run = test_data("my-filter-map", src_line);
 
     assert!(test_run.is_ok());
 
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Reject);
 }
 
@@ -151,11 +139,11 @@ fn test_filter_map_12() {
         "AS0",
         "accept",
     );
-    let test_run = test_data(FilterMap("my-filter-map".into()), src_line);
+    let test_run = test_data("my-filter-map", src_line);
 
     assert!(test_run.is_ok());
 
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Accept);
 }
 
@@ -167,11 +155,11 @@ fn test_filter_map_20() {
         "AS65534",
         "reject",
     );
-    let test_run = test_data(FilterMap("my-filter-map".into()), src_line);
+    let test_run = test_data("my-filter-map", src_line);
 
     assert!(test_run.is_ok());
 
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Reject);
 }
 
@@ -182,9 +170,9 @@ fn test_filter_map_21() {
         "AS0",
         "reject",
     );
-    let test_run = test_data(FilterMap("my-filter-map".into()), src_line);
+    let test_run = test_data("my-filter-map", src_line);
 
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Reject);
 }
 
@@ -195,11 +183,11 @@ fn test_filter_map_22() {
         "AS0",
         "accept",
     );
-    let test_run = test_data(FilterMap("my-filter-map".into()), src_line);
+    let test_run = test_data("my-filter-map", src_line);
 
     assert!(test_run.is_ok());
 
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Accept);
 }
 
@@ -210,11 +198,11 @@ fn test_filter_map_30() {
         "AS65534",
         "reject",
     );
-    let test_run = test_data(FilterMap("my-filter-map".into()), src_line);
+    let test_run = test_data("my-filter-map", src_line);
 
     assert!(test_run.is_ok());
 
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Reject);
 }
 
@@ -225,11 +213,11 @@ fn test_filter_map_31() {
         "AS0",
         "reject",
     );
-    let test_run = test_data(FilterMap("my-filter-map".into()), src_line);
+    let test_run = test_data("my-filter-map", src_line);
 
     assert!(test_run.is_ok());
 
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Reject);
 }
 
@@ -240,11 +228,11 @@ fn test_filter_map_32() {
         "AS0",
         "accept",
     );
-    let test_run = test_data(FilterMap("my-filter-map".into()), src_line);
+    let test_run = test_data("my-filter-map", src_line);
 
     assert!(test_run.is_ok());
 
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Accept);
 }
 
@@ -255,11 +243,11 @@ fn test_filter_map_40() {
         "AS65534",
         "reject",
     );
-    let test_run = test_data(FilterMap("my-filter-map".into()), src_line);
+    let test_run = test_data("my-filter-map", src_line);
 
     assert!(test_run.is_ok());
 
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Accept);
 }
 
@@ -272,11 +260,11 @@ fn test_filter_map_41() {
         "AS0",
         "reject",
     );
-    let test_run = test_data(FilterMap("my-filter-map".into()), src_line);
+    let test_run = test_data("my-filter-map", src_line);
 
     assert!(test_run.is_ok());
 
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Reject);
 }
 
@@ -287,11 +275,11 @@ fn test_filter_map_42() {
         "AS0",
         "accept",
     );
-    let test_run = test_data(FilterMap("my-filter-map".into()), src_line);
+    let test_run = test_data("my-filter-map", src_line);
 
     assert!(test_run.is_ok());
 
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Accept);
 }
 
@@ -302,11 +290,11 @@ fn test_filter_map_50() {
         "AS65534",
         "reject",
     );
-    let test_run = test_data(FilterMap("my-filter-map".into()), src_line);
+    let test_run = test_data("my-filter-map", src_line);
 
     assert!(test_run.is_ok());
 
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Reject);
 }
 
@@ -317,11 +305,11 @@ fn test_filter_map_51() {
         "AS0",
         "reject",
     );
-    let test_run = test_data(FilterMap("my-filter-map".into()), src_line);
+    let test_run = test_data("my-filter-map", src_line);
 
     assert!(test_run.is_ok());
 
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Reject);
 }
 
@@ -332,11 +320,11 @@ fn test_filter_map_52() {
         "AS0",
         "accept",
     );
-    let test_run = test_data(FilterMap("my-filter-map".into()), src_line);
+    let test_run = test_data("my-filter-map", src_line);
 
     assert!(test_run.is_ok());
 
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Accept);
 }
 
@@ -347,11 +335,11 @@ fn test_filter_map_60() {
         "AS65534",
         "reject",
     );
-    let test_run = test_data(FilterMap("my-filter-map".into()), src_line);
+    let test_run = test_data("my-filter-map", src_line);
 
     assert!(test_run.is_ok());
 
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Reject);
 }
 
@@ -362,11 +350,11 @@ fn test_filter_map_61() {
         "AS0",
         "reject",
     );
-    let test_run = test_data(FilterMap("my-filter-map".into()), src_line);
+    let test_run = test_data("my-filter-map", src_line);
 
     assert!(test_run.is_ok());
 
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Reject);
 }
 
@@ -377,11 +365,11 @@ fn test_filter_map_62() {
         "AS0",
         "accept",
     );
-    let test_run = test_data(FilterMap("my-filter-map".into()), src_line);
+    let test_run = test_data("my-filter-map", src_line);
 
     assert!(test_run.is_ok());
 
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Accept);
 }
 
@@ -396,11 +384,11 @@ fn test_filter_map_70() {
         "AS65534",
         "reject",
     );
-    let test_run = test_data(FilterMap("my-filter-map".into()), src_line);
+    let test_run = test_data("my-filter-map", src_line);
 
     assert!(test_run.is_ok());
 
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Accept);
 }
 
@@ -415,11 +403,11 @@ fn test_filter_map_71() {
         "AS0",
         "reject",
     );
-    let test_run = test_data(FilterMap("my-filter-map".into()), src_line);
+    let test_run = test_data("my-filter-map", src_line);
 
     assert!(test_run.is_ok());
 
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Reject);
 }
 
@@ -436,12 +424,12 @@ fn test_filter_map_72() {
         "AS0",
         "accept",
     );
-    let test_run = test_data(FilterMap("my-filter-map".into()), src_line);
+    let test_run = test_data("my-filter-map", src_line);
 
     trace!("{:?}", test_run);
     assert!(test_run.is_ok());
 
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Accept);
 }
 
@@ -456,11 +444,11 @@ fn test_filter_map_80() {
         "AS65534",
         "reject",
     );
-    let test_run = test_data(FilterMap("my-filter-map".into()), src_line);
+    let test_run = test_data("my-filter-map", src_line);
 
     assert!(test_run.is_ok());
 
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Reject);
 }
 
@@ -476,12 +464,12 @@ fn test_filter_map_81() {
         "AS65534",
         "reject",
     );
-    let test_run = test_data(FilterMap("my-filter-map".into()), src_line);
+    let test_run = test_data("my-filter-map", src_line);
 
     trace!("test run {:?}", test_run);
     assert!(test_run.is_ok());
 
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Reject);
 }
 
@@ -497,10 +485,10 @@ fn test_filter_map_82() {
         "AS65534",
         "accept",
     );
-    let test_run = test_data(FilterMap("my-filter-map".into()), src_line);
+    let test_run = test_data("my-filter-map", src_line);
 
     assert!(test_run.is_ok());
 
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Reject);
 }
@@ -0,0 +1,248 @@
+use roto::ast::AcceptReject;
+
+use roto::blocks::Scope::FilterMap;
+use roto::compiler::CompiledFilter;
+use roto::pipeline;
+use roto::types::collections::Record;
+use roto::types::typedef::TypeDef;
+use roto::types::typevalue::TypeValue;
+use roto::vm::VmError;
+
+use inetnum::asn::Asn;
+
+mod common;
+
+use common::context;
+
+const SOURCE_CODE: &str = r###"
+    filter-map my-filter-map with my_asn: Asn {
+        define {
+            rx_tx msg: BmpMsg;
+        }
+
+        term asn-matches {
+            match {
+                msg.asn == my_asn;
+            }
+        }
+
+        apply {
+            filter match asn-matches matching { return accept; };
+            return reject;
+        }
+    }
+
+    type BmpMsg {
+        asn: Asn
+    }
+"###;
+
+fn payload(asn: u32) -> Record {
+    let ty = TypeDef::new_record_type(vec![("asn", Box::new(TypeDef::Asn))])
+        .unwrap();
+
+    Record::create_instance_with_ordered_fields(
+        &ty,
+        vec![("asn", Asn::from_u32(asn).into())],
+    )
+    .unwrap()
+}
+
+fn compiled_filter(
+    arguments: Option<Vec<(&str, TypeValue)>>,
+) -> CompiledFilter {
+    let name = FilterMap("my-filter-map".into());
+    let rotolo =
+        pipeline::run_test(SOURCE_CODE, arguments.map(|args| (&name, args)))
+            .unwrap();
+    rotolo.retrieve_compiled_filter(&name).unwrap()
+}
+
+// Arguments supplied at compile time, a U32 is coerced into an Asn.
+#[test]
+fn test_arguments_1() {
+    common::init();
+
+    let filter =
+        compiled_filter(Some(vec![("my_asn", TypeValue::from(65534_u32))]));
+    let mut ctx = filter.new_execution_context(context()).unwrap();
+
+    let res = ctx.exec(payload(65534), None::<Record>).unwrap();
+    assert_eq!(res.accept_reject, AcceptReject::Accept);
+
+    let res = ctx.exec(payload(65000), None::<Record>).unwrap();
+    assert_eq!(res.accept_reject, AcceptReject::Reject);
+}
+
+// Arguments supplied at runtime, for the filter or per execution.
+#[test]
+fn test_arguments_2() {
+    common::init();
+
+    let filter = compiled_filter(None);
+
+    // Without a value for my_asn the filter can't run.
+    let mut ctx = filter.new_execution_context(context()).unwrap();
+    let res = ctx.exec(payload(65534), None::<Record>);
+    assert!(matches!(
+        res,
+        Err(VmError::ArgumentsMissing(names))
+            if names.len() == 1 && names[0].as_str() == "my_asn"
+    ));
+
+    let args = filter
+        .compile_arguments(vec![("my_asn", TypeValue::from(65534_u32))])
+        .unwrap();
+    let override_args = filter
+        .compile_arguments(vec![("my_asn", Asn::from_u32(65000).into())])
+        .unwrap();
+    let filter = filter.with_arguments(args);
+    let mut ctx = filter.new_execution_context(context()).unwrap();
+
+    let res = ctx.exec(payload(65534), None::<Record>).unwrap();
+    assert_eq!(res.accept_reject, AcceptReject::Accept);
+
+    // Override the value for one execution only.
+    let res = ctx
+        .exec_with_arguments(payload(65000), None::<Record>, override_args)
+        .unwrap();
+    assert_eq!(res.accept_reject, AcceptReject::Accept);
+
+    let res = ctx.exec(payload(65000), None::<Record>).unwrap();
+    assert_eq!(res.accept_reject, AcceptReject::Reject);
+}
+
+// Executions that fail on missing arguments are counted as errors.
+#[test]
+fn test_arguments_missing_metrics() {
+    common::init();
+
+    let filter = compiled_filter(None).with_metrics(true);
+    let mut ctx = filter.new_execution_context(context()).unwrap();
+    assert!(ctx.exec(payload(65534), None::<Record>).is_err());
+
+    let metrics = filter.metrics().unwrap();
+    assert_eq!(metrics.executions, 1);
+    assert_eq!(metrics.accepted, 0);
+    assert_eq!(metrics.errors, 1);
+}
+
+// Arguments that can't be used for the filter.
+#[test]
+fn test_arguments_3() {
+    common::init();
+
+    let filter = compiled_filter(None);
+
+    // Wrong type.
+    assert!(filter
+        .compile_arguments(vec![("my_asn", TypeValue::from(true))])
+        .is_err());
+
+    // Unknown argument.
+    assert!(filter
+        .compile_arguments(vec![
+            ("my_asn", TypeValue::from(65534_u32)),
+            ("your_asn", TypeValue::from(65534_u32)),
+        ])
+        .is_err());
+
+    // Missing argument.
+    assert!(filter.compile_arguments(vec![]).is_err());
+}
+
+struct MyArgs {
+    my_asn: u32,
+}
+
+roto::impl_into_roto_args!(MyArgs { my_asn });
+
+struct OtherArgs {
+    your_asn: Asn,
+}
+
+roto::impl_into_roto_args!(OtherArgs { your_asn });
+
+// Arguments from a Rust struct.
+#[test]
+fn test_arguments_4() {
+    common::init();
+
+    let filter = compiled_filter(None);
+
+    let args = filter.compile_args(MyArgs { my_asn: 65534 }).unwrap();
+    let filter = filter.with_arguments(args);
+    let mut ctx = filter.new_execution_context(context()).unwrap();
+
+    let res = ctx.exec(payload(65534), None::<Record>).unwrap();
+    assert_eq!(res.accept_reject, AcceptReject::Accept);
+
+    // All the problems are reported in one error.
+    let err = compiled_filter(None)
+        .compile_args(OtherArgs {
+            your_asn: Asn::from_u32(65534),
+        })
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("'your_asn'"));
+    assert!(err.contains("'my_asn' of type 'Asn' is missing"));
+
+    let err = compiled_filter(None)
+        .compile_arguments(vec![
+            ("my_asn", TypeValue::from(true)),
+            ("your_asn", TypeValue::from(65534_u32)),
+        ])
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("'my_asn' has the wrong type"));
+    assert!(err.contains("'your_asn'"));
+}
+
+const HYPHENATED: &str = r###"
+    filter-map my-filter-map with peer-asn: Asn {
+        define {
+            rx_tx msg: BmpMsg;
+        }
+
+        term asn-matches {
+            match {
+                msg.asn == peer-asn;
+            }
+        }
+
+        apply {
+            filter match asn-matches matching { return accept; };
+            return reject;
+        }
+    }
+
+    type BmpMsg {
+        asn: Asn
+    }
+"###;
+
+struct PeerArgs {
+    peer_asn: u32,
+}
+
+roto::impl_into_roto_args!(PeerArgs { peer_asn => "peer-asn" });
+
+// Arguments from a Rust struct, with a name that isn't a Rust identifier.
+#[test]
+fn test_arguments_5() {
+    common::init();
+
+    let name = FilterMap("my-filter-map".into());
+    let rotolo = pipeline::run_test(HYPHENATED, None).unwrap();
+    let filter = rotolo.retrieve_compiled_filter(&name).unwrap();
+
+    let args = filter.compile_args(PeerArgs { peer_asn: 65534 }).unwrap();
+    let filter = filter.with_arguments(args);
+    let mut ctx = filter.new_execution_context(context()).unwrap();
+
+    let res = ctx.exec(payload(65534), None::<Record>).unwrap();
+    assert_eq!(res.accept_reject, AcceptReject::Accept);
+
+    let res = ctx.exec(payload(65000), None::<Record>).unwrap();
+    assert_eq!(res.accept_reject, AcceptReject::Reject);
+}
@@ -2,11 +2,11 @@ use std::{collections::BTreeSet, str::FromStr};
 
 use log::trace;
 use roto::{
-    ast::AcceptReject, blocks::Scope, pipeline, types::{
+    ast::AcceptReject, blocks::Scope, runtime::{Runtime, Verdict}, types::{
         builtin::{
             basic_route::{PeerId, PeerRibType, Provenance}, explode_announcements, NlriStatus, PrefixRoute, RouteContext
-        }, collections::{BytesRecord, Record}, lazyrecord_types::BgpUpdateMessage,
-    }, vm::{self, VmResult}
+        }, collections::BytesRecord, lazyrecord_types::BgpUpdateMessage,
+    }
 };
 use inetnum::asn::Asn;
 use routecore::bgp::message::SessionConfig;
@@ -21,13 +21,13 @@ fn test_data(
     name: Scope,
     source_code: &str,
     announce_str: &str,
-) -> Result<(VmResult, PrefixRoute), Box<dyn std::error::Error>> {
+) -> Result<(Verdict, PrefixRoute), Box<dyn std::error::Error>> {
     common::init();
     println!("Evaluate filter-map {}...", name);
 
     // Compile the source code in this example
-    let rotolo = pipeline::run_test(source_code, None)?;
-    let roto_pack = rotolo.retrieve_pack_as_refs(&name)?;
+    let runtime = Runtime::from_string(source_code)?;
+    let mut filter = runtime.filter(name.as_str())?;
 
     // Create a BGP packet
     let prefix_str = "192.0.2.1";
@@ -74,36 +74,8 @@ fn test_data(
     //     &bgp_msg.into_inner()
     // )?;
 
-    // Create the VM
-    trace!("Used Arguments");
-    trace!("{:#?}", &roto_pack.get_arguments());
-    trace!("Used Data Sources");
-    trace!("{:#?}", &roto_pack.get_data_sources());
-
-    let ds_ref = roto_pack.get_data_sources();
-
-    for mb in roto_pack.get_mir().iter() {
-        println!("{}", mb);
-    }
-
-    let mut vm = vm::VmBuilder::new()
-        // .with_arguments(args)
-        .with_data_sources(ds_ref)
-        .with_context(&context)
-        .with_mir_code(roto_pack.get_mir())
-        .build()?;
-    
-    let mem = &mut vm::LinearMemory::uninit();
-    let res = vm
-        .exec(
-            // roto::types::builtin::BasicRoute::new(payload.clone()),
-            rws.clone(),
-            None::<Record>,
-            // Some(filter_map_arguments),
-            None,
-            mem,
-        )
-        .unwrap();
+    filter.bind_context(context);
+    let res = filter.run(rws.clone())?;
 
     trace!("\nRESULT");
     trace!("action: {}", res.accept_reject);
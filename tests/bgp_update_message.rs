@@ -1,27 +1,25 @@
 use log::trace;
 use roto::ast::AcceptReject;
 
-use roto::blocks::Scope::{self, FilterMap};
-use roto::pipeline;
+use roto::runtime::{Runtime, Verdict};
 use roto::types::builtin::{NlriStatus, PeerId, PeerRibType, Provenance, RouteContext};
-use roto::types::collections::{BytesRecord, Record};
+use roto::types::collections::BytesRecord;
 use roto::types::lazyrecord_types::BgpUpdateMessage;
 use roto::types::typevalue::TypeValue;
-use roto::vm::{self, VmResult};
 use inetnum::asn::Asn;
 use routecore::bgp::message::SessionConfig;
 
 mod common;
 
 fn test_data(
-    name: Scope,
+    name: &str,
     source_code: &str,
-) -> Result<(VmResult, BytesRecord<BgpUpdateMessage>), Box<dyn std::error::Error>> {
+) -> Result<(Verdict, BytesRecord<BgpUpdateMessage>), Box<dyn std::error::Error>> {
     println!("Evaluate filter-map {}...", name);
 
     // Compile the source code in this example
-    let rotolo = pipeline::run_test(source_code, None)?;
-    let roto_pack = rotolo.retrieve_pack_as_refs(&name)?;
+    let runtime = Runtime::from_string(source_code)?;
+    let mut filter = runtime.filter(name)?;
 
     // BGP UPDATE message containing MP_REACH_NLRI path attribute,
     // comprising 5 IPv6 NLRIs
@@ -65,17 +63,10 @@ fn test_data(
 
     // assert!(roto_pack.check_rx_payload_type(&payload2));
 
-    // Create the VM
     trace!("Used Arguments");
-    trace!("{:#?}", &roto_pack.get_arguments());
+    trace!("{:#?}", filter.compiled().get_arguments());
     trace!("Used Data Sources");
-    trace!("{:#?}", &roto_pack.get_data_sources());
-
-    let ds_ref = roto_pack.get_data_sources();
-
-    for mb in roto_pack.get_mir().iter() {
-        println!("{}", mb);
-    }
+    trace!("{:#?}", filter.compiled().get_data_sources());
 
     let prov = Provenance {
         timestamp: chrono::Utc::now(),
@@ -86,25 +77,10 @@ fn test_data(
         peer_rib_type: PeerRibType::OutPost,
     };
 
-    let context = &RouteContext::new(None, NlriStatus::Empty, prov);
+    let context = RouteContext::new(None, NlriStatus::Empty, prov);
+    filter.bind_context(context);
 
-    let mut vm = vm::VmBuilder::new()
-        // .with_arguments(args)
-        .with_data_sources(ds_ref)
-        .with_context(context)
-        .with_mir_code(roto_pack.get_mir())
-        .build()?;
-
-    let mem = &mut vm::LinearMemory::uninit();
-    let res = vm
-        .exec(
-            TypeValue::from(payload.clone()),
-            None::<Record>,
-            // Some(filter_map_arguments),
-            None,
-            mem,
-        )
-        .unwrap();
+    let res = filter.run(TypeValue::from(payload.clone())).unwrap();
 
     trace!("\nRESULT");
     trace!("action: {}", res.accept_reject);
@@ -119,7 +95,7 @@ fn test_bgp_update_1() {
     common::init();
 
     let (res, _payload) = test_data(
-        Scope::FilterMap("filter-unicast-v4-v6-only".into()),
+        "filter-unicast-v4-v6-only",
         r###"
         filter-map filter-unicast-v4-v6-only {
             define {
@@ -169,7 +145,7 @@ fn test_bgp_update_2() {
     common::init();
 
     let res = test_data(
-        FilterMap("filter-unicast-v4-v6-only".into()),
+        "filter-unicast-v4-v6-only",
         r###"
         filter-map filter-unicast-v4-v6-only {
             define {
@@ -201,7 +177,7 @@ fn test_bgp_update_2() {
 fn test_bgp_update_3() {
     common::init();
     let (res, payload) = test_data(
-        Scope::FilterMap("bgp-update-filter-map-3".into()),
+        "bgp-update-filter-map-3",
         r#"
         filter-map bgp-update-filter-map-3 {
             define {
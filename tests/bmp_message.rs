@@ -2,22 +2,19 @@ use log::trace;
 
 use roto::{
     ast::AcceptReject,
-    blocks::Scope::{self, Filter, FilterMap},
     compiler::CompileError,
-    pipeline,
+    runtime::{Runtime, Verdict},
     types::{
         builtin::{
             BuiltinTypeValue, BytesRecord, NlriStatus, PeerId, PeerRibType,
             Provenance, RouteContext,
         },
-        collections::Record,
         lazyrecord_types::{
             BmpMessage, InitiationMessage, LazyRecordTypeDef, RouteMonitoring,
         },
         typedef::TypeDef,
         typevalue::TypeValue,
     },
-    vm::{self, VmResult},
 };
 use inetnum::asn::Asn;
 use routes::bmp::encode::{
@@ -27,15 +24,15 @@ use routes::bmp::encode::{
 mod common;
 
 fn test_data(
-    name: Scope,
+    name: &str,
     source_code: &'static str,
     buf: Vec<u8>,
-) -> Result<VmResult, Box<dyn std::error::Error>> {
+) -> Result<Verdict, Box<dyn std::error::Error>> {
     println!("Evaluate filter-map {}...", name);
 
     // Compile the source code in this example
-    let rotolo = pipeline::run_test(source_code, None)?;
-    let roto_pack = rotolo.retrieve_pack_as_refs(&name)?;
+    let runtime = Runtime::from_string(source_code)?;
+    let mut filter = runtime.filter(name)?;
 
     let rm_msg = BytesRecord::<RouteMonitoring>::new(buf.clone().into());
     assert!(rm_msg.is_ok());
@@ -51,15 +48,9 @@ fn test_data(
         routecore::bmp::message::Message::from_octets(buf.clone()).unwrap()
     );
     trace!("Used Arguments");
-    trace!("{:#?}", &roto_pack.get_arguments());
+    trace!("{:#?}", filter.compiled().get_arguments());
     trace!("Used Data Sources");
-    trace!("{:#?}", &roto_pack.get_data_sources());
-
-    let ds_ref = roto_pack.get_data_sources();
-
-    for mb in roto_pack.get_mir().iter() {
-        println!("{}", mb);
-    }
+    trace!("{:#?}", filter.compiled().get_data_sources());
 
     let peer_ip = "192.0.2.0".parse().unwrap();
 
@@ -78,23 +69,9 @@ fn test_data(
     let context =
         RouteContext::new(None, NlriStatus::InConvergence, provenance);
 
-    let mut vm = vm::VmBuilder::new()
-        // .with_arguments(args)
-        .with_context(context)
-        .with_data_sources(ds_ref)
-        .with_mir_code(roto_pack.get_mir())
-        .build()?;
-
-    let mem = &mut vm::LinearMemory::uninit();
-    let res = vm
-        .exec(
-            payload,
-            None::<Record>,
-            // Some(filter_map_arguments),
-            None,
-            mem,
-        )
-        .unwrap();
+    filter.bind_context(context);
+
+    let res = filter.run(payload).unwrap();
 
     trace!("\nRESULT");
     trace!("action: {}", res.accept_reject);
@@ -105,14 +82,14 @@ fn test_data(
 }
 
 fn test_data_2(
-    name: Scope,
+    name: &str,
     source_code: &'static str,
-) -> Result<VmResult, Box<dyn std::error::Error>> {
+) -> Result<Verdict, Box<dyn std::error::Error>> {
     println!("Evaluate filter-map {}...", name);
 
     // Compile the source code in this example
-    let rotolo = pipeline::run_test(source_code, None)?;
-    let roto_pack = rotolo.retrieve_pack_as_refs(&name)?;
+    let runtime = Runtime::from_string(source_code)?;
+    let mut filter = runtime.filter(name)?;
 
     let buf = vec![
         0x03, 0x00, 0x00, 0x00, 0x67, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
@@ -136,15 +113,9 @@ fn test_data_2(
     );
 
     trace!("Used Arguments");
-    trace!("{:#?}", &roto_pack.get_arguments());
+    trace!("{:#?}", filter.compiled().get_arguments());
     trace!("Used Data Sources");
-    trace!("{:#?}", &roto_pack.get_data_sources());
-
-    let ds_ref = roto_pack.get_data_sources();
-
-    for mb in roto_pack.get_mir().iter() {
-        println!("{}", mb);
-    }
+    trace!("{:#?}", filter.compiled().get_data_sources());
 
     let peer_ip = "192.0.2.0".parse().unwrap();
 
@@ -163,23 +134,9 @@ fn test_data_2(
     let context =
         RouteContext::new(None, NlriStatus::InConvergence, provenance);
 
-    let mut vm = vm::VmBuilder::new()
-        // .with_arguments(args)
-        .with_context(context)
-        .with_data_sources(ds_ref)
-        .with_mir_code(roto_pack.get_mir())
-        .build()?;
-
-    let mem = &mut vm::LinearMemory::uninit();
-    let res = vm
-        .exec(
-            payload,
-            None::<Record>,
-            // Some(filter_map_arguments),
-            None,
-            mem,
-        )
-        .unwrap();
+    filter.bind_context(context);
+
+    let res = filter.run(payload).unwrap();
 
     trace!("\nRESULT");
     trace!("action: {}", res.accept_reject);
@@ -190,14 +147,14 @@ fn test_data_2(
 }
 
 fn test_data_3(
-    name: Scope,
+    name: &str,
     source_code: &'static str,
-) -> Result<VmResult, Box<dyn std::error::Error>> {
+) -> Result<Verdict, Box<dyn std::error::Error>> {
     println!("Evaluate filter-map {}...", name);
 
     // Compile the source code in this example
-    let rotolo = pipeline::run_test(source_code, None)?;
-    let roto_pack = rotolo.retrieve_pack_as_refs(&name)?;
+    let runtime = Runtime::from_string(source_code)?;
+    let mut filter = runtime.filter(name)?;
 
     // BMP PeerDownNotification type 3, containing a BGP NOTIFICATION.
     let buf = vec![
@@ -219,15 +176,9 @@ fn test_data_3(
     );
 
     trace!("Used Arguments");
-    trace!("{:#?}", &roto_pack.get_arguments());
+    trace!("{:#?}", filter.compiled().get_arguments());
     trace!("Used Data Sources");
-    trace!("{:#?}", &roto_pack.get_data_sources());
-
-    let ds_ref = roto_pack.get_data_sources();
-
-    for mb in roto_pack.get_mir().iter() {
-        println!("{}", mb);
-    }
+    trace!("{:#?}", filter.compiled().get_data_sources());
 
     let peer_ip = "192.0.2.0".parse().unwrap();
 
@@ -246,23 +197,9 @@ fn test_data_3(
     let context =
         RouteContext::new(None, NlriStatus::InConvergence, provenance);
 
-    let mut vm = vm::VmBuilder::new()
-        // .with_arguments(args)
-        .with_context(context)
-        .with_data_sources(ds_ref)
-        .with_mir_code(roto_pack.get_mir())
-        .build()?;
-
-    let mem = &mut vm::LinearMemory::uninit();
-    let res = vm
-        .exec(
-            payload,
-            None::<Record>,
-            // Some(filter_map_arguments),
-            None,
-            mem,
-        )
-        .unwrap();
+    filter.bind_context(context);
+
+    let res = filter.run(payload).unwrap();
 
     trace!("\nRESULT");
     trace!("action: {}", res.accept_reject);
@@ -273,26 +210,20 @@ fn test_data_3(
 }
 
 fn test_data_4(
-    name: Scope,
+    name: &str,
     payload: TypeValue,
     source_code: &'static str,
-) -> Result<VmResult, Box<dyn std::error::Error>> {
+) -> Result<Verdict, Box<dyn std::error::Error>> {
     println!("Evaluate filter-map {}...", name);
     
         // Compile the source code in this example
-        let rotolo = pipeline::run_test(source_code, None)?;
-        let roto_pack = rotolo.retrieve_pack_as_refs(&name)?;
+        let runtime = Runtime::from_string(source_code)?;
+        let mut filter = runtime.filter(name)?;
     
         trace!("Used Arguments");
-        trace!("{:#?}", &roto_pack.get_arguments());
+        trace!("{:#?}", filter.compiled().get_arguments());
         trace!("Used Data Sources");
-        trace!("{:#?}", &roto_pack.get_data_sources());
-    
-        let ds_ref = roto_pack.get_data_sources();
-    
-        for mb in roto_pack.get_mir().iter() {
-            println!("{}", mb);
-        }
+        trace!("{:#?}", filter.compiled().get_data_sources());
  
         let peer_ip = "192.0.2.0".parse().unwrap();
 
@@ -311,23 +242,9 @@ fn test_data_4(
         let context =
             RouteContext::new(None, NlriStatus::InConvergence, provenance);
 
-        let mut vm = vm::VmBuilder::new()
-            // .with_arguments(args)
-            .with_context(context)
-            .with_data_sources(ds_ref)
-            .with_mir_code(roto_pack.get_mir())
-            .build()?;
-    
-        let mem = &mut vm::LinearMemory::uninit();
-        let res = vm
-            .exec(
-                payload,
-                None::<Record>,
-                // Some(filter_map_arguments),
-                None,
-                mem,
-            )
-            .unwrap();
+        filter.bind_context(context);
+
+        let res = filter.run(payload).unwrap();
     
         trace!("\nRESULT");
         trace!("action: {}", res.accept_reject);
@@ -348,15 +265,15 @@ fn initiation_payload_example() -> Vec<u8> {
 }
 
 fn compile_initiation_payload(
-    name: Scope,
+    name: &str,
     source_code: &'static str,
     buf: routecore::bmp::message::Message<bytes::Bytes>,
-) -> Result<VmResult, Box<dyn std::error::Error>> {
+) -> Result<Verdict, Box<dyn std::error::Error>> {
     println!("Evaluate filter-map {}...", name);
 
     // Compile the source code in this example
-    let rotolo = pipeline::run_test(source_code, None)?;
-    let roto_pack = rotolo.retrieve_pack_as_refs(&name)?;
+    let runtime = Runtime::from_string(source_code)?;
+    let mut filter = runtime.filter(name)?;
 
     // assert!(i_msg.is_ok());
 
@@ -366,15 +283,9 @@ fn compile_initiation_payload(
     );
 
     trace!("Used Arguments");
-    trace!("{:#?}", &roto_pack.get_arguments());
+    trace!("{:#?}", filter.compiled().get_arguments());
     trace!("Used Data Sources");
-    trace!("{:#?}", &roto_pack.get_data_sources());
-
-    let ds_ref = roto_pack.get_data_sources();
-
-    for mb in roto_pack.get_mir().iter() {
-        println!("{}", mb);
-    }
+    trace!("{:#?}", filter.compiled().get_data_sources());
 
     let peer_ip = "192.0.2.0".parse().unwrap();
 
@@ -393,23 +304,9 @@ fn compile_initiation_payload(
     let context =
         RouteContext::new(None, NlriStatus::InConvergence, provenance);
 
-    let mut vm = vm::VmBuilder::new()
-        // .with_arguments(args)
-        .with_context(context)
-        .with_data_sources(ds_ref)
-        .with_mir_code(roto_pack.get_mir())
-        .build()?;
-
-    let mem = &mut vm::LinearMemory::uninit();
-    let res = vm
-        .exec(
-            payload,
-            None::<Record>,
-            // Some(filter_map_arguments),
-            None,
-            mem,
-        )
-        .unwrap();
+    filter.bind_context(context);
+
+    let res = filter.run(payload).unwrap();
 
     trace!("\nRESULT");
     trace!("action: {}", res.accept_reject);
@@ -451,7 +348,7 @@ fn initiation_msg_test_1() {
     ));
 
     let res = compile_initiation_payload(
-        Filter("bmp-in-filter".into()),
+        "bmp-in-filter",
         r#"
         filter bmp-in-filter {
             define {
@@ -503,7 +400,7 @@ fn initiation_msg_test_2() {
     ));
 
     let res = compile_initiation_payload(
-        Filter("bmp-in-filter".into()),
+        "bmp-in-filter",
         r#"filter bmp-in-filter {
         define {
             rx msg: BmpMessage;
@@ -556,7 +453,7 @@ fn initiation_msg_test_3() {
     ));
 
     let res = compile_initiation_payload(
-        Filter("bmp-in-filter".into()),
+        "bmp-in-filter",
         r#"filter bmp-in-filter {
         define {
             rx msg: BmpMessage;
@@ -607,7 +504,7 @@ fn bmp_route_monitoring_1() {
     ));
 
     let res = compile_initiation_payload(
-        Filter("bmp-in-filter".into()),
+        "bmp-in-filter",
         r#"filter bmp-in-filter {
         define {
             rx msg: BmpMessage;
@@ -649,7 +546,7 @@ fn bmp_message_1() {
     common::init();
 
     let res = test_data(
-        FilterMap("filter-v4-only".into()),
+        "filter-v4-only",
         r###"
         filter-map filter-v4-only {
             define {
@@ -682,7 +579,7 @@ fn bmp_message_2() {
     common::init();
 
     let res = test_data(
-        FilterMap("filter-v6-only".into()),
+        "filter-v6-only",
         r###"
         filter-map filter-v6-only {
             define {
@@ -715,7 +612,7 @@ fn bmp_message_3() {
     common::init();
 
     let res = test_data(
-        FilterMap("filter-v6-only".into()),
+        "filter-v6-only",
         r###"
         filter-map filter-v6-only {
             define {
@@ -747,7 +644,7 @@ fn bmp_message_4() {
     common::init();
 
     let res = test_data_2(
-        Filter("filter-v6-only".into()),
+        "filter-v6-only",
         r###"
         filter filter-v6-only {
             define {
@@ -790,7 +687,7 @@ fn bmp_message_5() {
     common::init();
 
     let res = test_data_3(
-        Filter("filter-v6-only".into()),
+        "filter-v6-only",
         r###"
         filter filter-v6-only {
             define {
@@ -832,7 +729,7 @@ fn bmp_message_6() {
     common::init();
 
     let res = test_data_3(
-        Filter("is-rm-ipv4".into()),
+        "is-rm-ipv4",
         r#"
         filter is-rm-ipv4 {
             define {
@@ -888,7 +785,7 @@ fn bmp_message_7() {
     common::init();
 
     let res = test_data_3(
-        Filter("is-rm-ipv4".into()),
+        "is-rm-ipv4",
         r#"
         filter is-rm-ipv4 {
             define {
@@ -939,7 +836,7 @@ fn bmp_message_8() {
     common::init();
 
     let res = test_data_3(
-        Filter("is-rm-ipv4".into()),
+        "is-rm-ipv4",
         r#"
         filter is-rm-ipv4 {
             define {
@@ -1011,7 +908,7 @@ fn bmp_message_9() {
     );
 
     let res = test_data_4(
-        Filter("my-module".into()),
+        "my-module",
         payload,
         r#"
         filter my-module {
@@ -1067,7 +964,7 @@ fn bmp_message_10() {
     );
 
     let res = test_data_4(
-        Filter("my-module".into()),
+        "my-module",
         payload.clone(),
         r#"
         filter my-module {
@@ -1117,7 +1014,7 @@ fn initiation_message() {
     );
 
     let res = test_data_4(
-        Filter("my-module".into()),
+        "my-module",
         payload,
         r#"
         filter my-module {
@@ -1162,7 +1059,7 @@ fn peer_down_notification_1() {
     println!("payload {:?}", payload);
 
     let res = test_data_4(
-        Filter("my-module".into()),
+        "my-module",
         payload,
         r#"
         filter my-module {
@@ -1207,7 +1104,7 @@ fn peer_down_notification_2() {
     println!("payload {:?}", payload);
 
     let res = test_data_4(
-        Filter("my-module".into()),
+        "my-module",
         payload,
         r#"
         filter my-module {
@@ -1260,7 +1157,7 @@ fn termination_message_1() {
     println!("payload {:?}", payload);
 
     let res = test_data_4(
-        Filter("my-module".into()),
+        "my-module",
         payload,
         r#"
         filter my-module {
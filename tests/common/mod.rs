@@ -1,8 +1,71 @@
+// Every test file includes this module, but none of them uses all of it.
+#![allow(dead_code)]
+
 use std::io::Write;
 
+use roto::runtime::Filter;
+use roto::types::builtin::{
+    NlriStatus, PeerId, PeerRibType, Provenance, RouteContext,
+};
+use roto::types::collections::Record;
+use roto::types::typedef::TypeDef;
+
+use inetnum::addr::Prefix;
+use inetnum::asn::Asn;
+
 pub fn init() {
     let _ = env_logger::builder()
         .format(|buf, record| writeln!(buf, "{}", record.args()))
         .is_test(true)
         .try_init();
 }
+
+/// The context of a route received from peer 192.0.2.10 in AS65534.
+pub fn context() -> RouteContext {
+    let peer_ip = "192.0.2.10".parse().unwrap();
+
+    let provenance = Provenance {
+        timestamp: chrono::Utc::now(),
+        connection_id: "192.0.2.10:178".parse().unwrap(),
+        peer_id: PeerId {
+            addr: peer_ip,
+            asn: Asn::from(65534),
+        },
+        peer_bgp_id: [0; 4].into(),
+        peer_distuingisher: [0; 8],
+        peer_rib_type: PeerRibType::OutPost,
+    };
+
+    RouteContext::new(None, NlriStatus::InConvergence, provenance)
+}
+
+pub fn prefix(s: &str) -> Prefix {
+    s.parse().unwrap()
+}
+
+/// The type of [`announcement`], declared in the test scripts as
+/// `type Announcement { prefix: Prefix, origin: Asn }`.
+pub fn announcement_type() -> TypeDef {
+    TypeDef::new_record_type(vec![
+        ("prefix", Box::new(TypeDef::Prefix)),
+        ("origin", Box::new(TypeDef::Asn)),
+    ])
+    .unwrap()
+}
+
+/// An announcement of `p` by AS `origin`.
+pub fn announcement(p: &str, origin: u32) -> Record {
+    Record::create_instance_with_ordered_fields(
+        &announcement_type(),
+        vec![
+            ("prefix", prefix(p).into()),
+            ("origin", Asn::from_u32(origin).into()),
+        ],
+    )
+    .unwrap()
+}
+
+/// Whether `filter` accepts the announcement of `p` by AS `origin`.
+pub fn is_accepted(filter: &mut Filter, p: &str, origin: u32) -> bool {
+    filter.run(announcement(p, origin)).unwrap().is_accepted()
+}
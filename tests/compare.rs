@@ -1,12 +1,10 @@
 use roto::ast::AcceptReject;
 
-use roto::blocks::Scope::{self, FilterMap};
 use roto::types::builtin::{NlriStatus, PeerId, PeerRibType, Provenance, RouteContext};
-use roto::pipeline;
 use roto::types::collections::Record;
 use roto::types::typedef::TypeDef;
 use roto::types::typevalue::TypeValue;
-use roto::vm::{self, VmResult};
+use roto::runtime::{Runtime, Verdict};
 use rotonda_store::prelude::MergeUpdate;
 
 use inetnum::asn::Asn;
@@ -81,13 +79,13 @@ fn src_code(code_line: &str, end_accept_reject: &str) -> String {
 }
 
 fn test_data(
-    name: Scope,
+    name: &str,
     source_code: &str,
-) -> Result<VmResult, Box<dyn std::error::Error>> {
+) -> Result<Verdict, Box<dyn std::error::Error>> {
     println!("Evaluate filter-map {}...", name);
 
-    let rotolo = pipeline::run_test(source_code, None)?;
-    let roto_pack = rotolo.retrieve_pack_as_refs(&name)?;
+    let runtime = Runtime::from_string(source_code)?;
+    let mut filter = runtime.filter(name)?;
     let asn: TypeValue = Asn::from_u32(211321).into();
 
     println!("ASN {:?}", asn);
@@ -104,16 +102,10 @@ fn test_data(
     )
     .unwrap();
 
-    let mem = &mut vm::LinearMemory::uninit();
-
     println!("Used Arguments");
-    println!("{:#?}", &roto_pack.arguments);
+    println!("{:#?}", filter.compiled().get_arguments());
     println!("Used Data Sources");
-    println!("{:#?}", &roto_pack.data_sources);
-
-    for mb in roto_pack.get_mir().iter() {
-        println!("{}", mb);
-    }
+    println!("{:#?}", filter.compiled().get_data_sources());
 
     let peer_ip = "192.0.2.10".parse().unwrap();
 
@@ -128,13 +120,9 @@ fn test_data(
 
     let context = RouteContext::new(None, NlriStatus::InConvergence, provenance);
 
-    let mut vm = vm::VmBuilder::new()
-        .with_context(context)
-        .with_data_sources(roto_pack.data_sources)
-        .with_mir_code(roto_pack.mir)
-        .build()?;
+    filter.bind_context(context);
 
-    let res = vm.exec(my_payload, None::<Record>, None, mem).unwrap();
+    let res = filter.run(my_payload).unwrap();
 
     println!("\nRESULT");
     println!("action: {}", res.accept_reject);
@@ -148,9 +136,9 @@ fn test_data(
 fn test_compare_1() {
     common::init();
     let src_line = src_code("msg.type == 2; // Peer Down", "reject");
-    let test_run = test_data(FilterMap("in-filter-map".into()), &src_line);
+    let test_run = test_data("in-filter-map", &src_line);
 
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Accept);
 }
 
@@ -158,9 +146,9 @@ fn test_compare_1() {
 fn test_compare_2() {
     common::init();
     let src_line = src_code("msg.type == 2; // Peer Down", "accept");
-    let test_run = test_data(FilterMap("in-filter-map".into()), &src_line);
+    let test_run = test_data("in-filter-map", &src_line);
 
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Accept);
 }
 
@@ -168,9 +156,9 @@ fn test_compare_2() {
 fn test_compare_3() {
     common::init();
     let src_line = src_code("msg.type == 1; // Peer Down", "reject");
-    let test_run = test_data(FilterMap("in-filter-map".into()), &src_line);
+    let test_run = test_data("in-filter-map", &src_line);
 
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Reject);
 }
 
@@ -179,9 +167,9 @@ fn test_compare_4() {
     common::init();
     let src_line =
         src_code("msg.type == 1 && msg.type == 2; // Peer Down", "accept");
-    let test_run = test_data(FilterMap("in-filter-map".into()), &src_line);
+    let test_run = test_data("in-filter-map", &src_line);
 
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Accept);
 }
 
@@ -190,9 +178,9 @@ fn test_compare_5() {
     common::init();
     let src_line =
         src_code("msg.type == 2 && msg.type == 2; // Peer Down", "reject");
-    let test_run = test_data(FilterMap("in-filter-map".into()), &src_line);
+    let test_run = test_data("in-filter-map", &src_line);
 
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Accept);
 }
 
@@ -203,9 +191,9 @@ fn test_compare_6() {
         "(msg.type == 2) || (msg.type == 2); // Peer Down",
         "reject",
     );
-    let test_run = test_data(FilterMap("in-filter-map".into()), &src_line);
+    let test_run = test_data("in-filter-map", &src_line);
 
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Accept);
 }
 
@@ -216,9 +204,9 @@ fn test_compare_7() {
         "( (msg.type == 2) || (msg.type == 2) ) && ( msg.type == 2 ); // Peer Down",
         "reject",
     );
-    let test_run = test_data(FilterMap("in-filter-map".into()), &src_line);
+    let test_run = test_data("in-filter-map", &src_line);
 
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Accept);
 }
 
@@ -229,9 +217,9 @@ fn test_compare_8() {
         "( (msg.type == 2) || (msg.type == 2) ) || ( msg.type == 1 ); // Peer Down",
         "reject",
     );
-    let test_run = test_data(FilterMap("in-filter-map".into()), &src_line);
+    let test_run = test_data("in-filter-map", &src_line);
 
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Reject);
 }
 
@@ -239,9 +227,9 @@ fn test_compare_8() {
 fn test_compare_9() {
     common::init();
     let src_line = src_code("msg.type in [2,3,4,5]; // Peer Down", "accept");
-    let test_run = test_data(FilterMap("in-filter-map".into()), &src_line);
+    let test_run = test_data("in-filter-map", &src_line);
 
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Accept);
 }
 
@@ -250,8 +238,69 @@ fn test_compare_10() {
     common::init();
     let src_line =
         src_code("msg.type in [20,30,40,50]; // Peer Down", "reject");
-    let test_run = test_data(FilterMap("in-filter-map".into()), &src_line);
+    let test_run = test_data("in-filter-map", &src_line);
+
+    let Verdict { accept_reject, .. } = test_run.unwrap();
+    assert_eq!(accept_reject, AcceptReject::Accept);
+}
+
+#[test]
+fn test_compare_11() {
+    common::init();
+    let src_line =
+        src_code("msg.type not in [2,3,4,5]; // Peer Down", "reject");
+    let test_run = test_data("in-filter-map", &src_line);
+
+    let Verdict { accept_reject, .. } = test_run.unwrap();
+    assert_eq!(accept_reject, AcceptReject::Reject);
+}
+
+#[test]
+fn test_compare_12() {
+    common::init();
+    let src_line =
+        src_code("msg.type not in [2,1,4,5]; // Peer Down", "reject");
+    let test_run = test_data("in-filter-map", &src_line);
+
+    let Verdict { accept_reject, .. } = test_run.unwrap();
+    assert_eq!(accept_reject, AcceptReject::Accept);
+}
+
+#[test]
+fn test_compare_13() {
+    common::init();
+    let src_line = src_code(
+        "msg.type in [1,3,4,5]; msg.type == 2; // Peer Down",
+        "reject",
+    );
+    let test_run = test_data("in-filter-map", &src_line);
 
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Accept);
 }
+
+#[test]
+fn test_compare_14() {
+    common::init();
+    let src_line = src_code(
+        "(msg.type == 1) || (msg.type in [2,3]); msg.type == 1; // Peer Down",
+        "reject",
+    );
+    let test_run = test_data("in-filter-map", &src_line);
+
+    let Verdict { accept_reject, .. } = test_run.unwrap();
+    assert_eq!(accept_reject, AcceptReject::Reject);
+}
+
+#[test]
+fn test_compare_15() {
+    common::init();
+    let src_line = src_code(
+        "((msg.type == 2) && (msg.type == 1)) || (msg.type in [3,1]); // Peer Down",
+        "reject",
+    );
+    let test_run = test_data("in-filter-map", &src_line);
+
+    let Verdict { accept_reject, .. } = test_run.unwrap();
+    assert_eq!(accept_reject, AcceptReject::Reject);
+}
@@ -0,0 +1,223 @@
+use roto::ast::AcceptReject;
+
+use roto::blocks::Scope::FilterMap;
+use roto::compiler::CompiledFilter;
+use roto::pipeline;
+use roto::types::collections::Record;
+use roto::types::datasources::DataSource;
+use roto::types::typedef::TypeDef;
+use roto::types::typevalue::TypeValue;
+
+use inetnum::asn::Asn;
+
+mod common;
+
+use common::context;
+
+const SOURCE_CODE: &str = r###"
+    filter-map in-filter-map {
+        define {
+            rx_tx msg: BmpMsg;
+        }
+
+        term in-source-asns {
+            match {
+                source_asns.contains(msg.asn);
+            }
+        }
+
+        apply {
+            filter match in-source-asns matching { return accept; };
+            return reject;
+        }
+    }
+
+    table source_asns contains AsnLines {
+        asn: Asn
+    }
+
+    type BmpMsg {
+        type: U8,
+        asn: Asn
+    }
+"###;
+
+fn source_asns(asns: &[u32]) -> DataSource {
+    let ty = TypeDef::new_record_type(vec![("asn", Box::new(TypeDef::Asn))])
+        .unwrap();
+
+    let records = asns
+        .iter()
+        .map(|asn| {
+            Record::create_instance_with_ordered_fields(
+                &ty,
+                vec![("asn", Asn::from_u32(*asn).into())],
+            )
+            .unwrap()
+        })
+        .collect::<Vec<_>>();
+
+    DataSource::table_from_records("source_asns", records).unwrap()
+}
+
+fn payload(asn: u32) -> Record {
+    let ty = TypeDef::new_record_type(vec![
+        ("type", Box::new(TypeDef::U8)),
+        ("asn", Box::new(TypeDef::Asn)),
+    ])
+    .unwrap();
+
+    Record::create_instance_with_ordered_fields(
+        &ty,
+        vec![
+            ("type", TypeValue::from(1_u8)),
+            ("asn", Asn::from_u32(asn).into()),
+        ],
+    )
+    .unwrap()
+}
+
+fn compiled_filter() -> CompiledFilter {
+    let rotolo = pipeline::run_test(SOURCE_CODE, None).unwrap();
+    rotolo
+        .retrieve_compiled_filter(&FilterMap("in-filter-map".into()))
+        .unwrap()
+}
+
+#[test]
+fn test_compiled_filter_1() {
+    common::init();
+
+    fn is_send_sync<T: Send + Sync>(_: &T) {}
+
+    let filter = compiled_filter();
+    is_send_sync(&filter);
+    filter.set_source(source_asns(&[300, 211321])).unwrap();
+
+    std::thread::scope(|s| {
+        for asn in [211321_u32, 65000, 300, 65001] {
+            let filter = &filter;
+            s.spawn(move || {
+                let mut ctx =
+                    filter.new_execution_context(context()).unwrap();
+                for _ in 0..10 {
+                    let res =
+                        ctx.exec(payload(asn), None::<Record>).unwrap();
+                    let expected = match asn {
+                        211321 | 300 => AcceptReject::Accept,
+                        _ => AcceptReject::Reject,
+                    };
+                    assert_eq!(res.accept_reject, expected);
+                }
+            });
+        }
+    });
+}
+
+#[test]
+fn test_compiled_filter_2() {
+    common::init();
+
+    let filter = compiled_filter();
+
+    // All data sources need to be filled before a context can be created.
+    assert!(filter.new_execution_context(context()).is_err());
+
+    filter.set_source(source_asns(&[300])).unwrap();
+    let mut ctx = filter.new_execution_context(context()).unwrap();
+
+    let res = ctx.exec(payload(211321), None::<Record>).unwrap();
+    assert_eq!(res.accept_reject, AcceptReject::Reject);
+
+    // Swapping the source of the filter is picked up by the existing
+    // execution context.
+    filter.set_source(source_asns(&[300, 211321])).unwrap();
+
+    let res = ctx.exec(payload(211321), None::<Record>).unwrap();
+    assert_eq!(res.accept_reject, AcceptReject::Accept);
+}
+
+#[test]
+fn test_compiled_filter_3() {
+    common::init();
+
+    let filter = compiled_filter();
+    assert!(filter.metrics().is_none());
+
+    let filter = filter.with_metrics(true);
+    filter.set_source(source_asns(&[300, 211321])).unwrap();
+
+    std::thread::scope(|s| {
+        for asn in [211321_u32, 65000] {
+            let filter = &filter;
+            s.spawn(move || {
+                let mut ctx =
+                    filter.new_execution_context(context()).unwrap();
+                for _ in 0..10 {
+                    ctx.exec(payload(asn), None::<Record>).unwrap();
+                }
+            });
+        }
+    });
+
+    let metrics = filter.metrics().unwrap();
+    assert_eq!(metrics.filter_map, FilterMap("in-filter-map".into()));
+    assert_eq!(metrics.executions, 20);
+    assert_eq!(metrics.accepted, 10);
+    assert_eq!(metrics.rejected, 10);
+    assert_eq!(metrics.errors, 0);
+
+    assert_eq!(metrics.terms.len(), 1);
+    assert_eq!(metrics.terms[0].name, "in-source-asns");
+    assert_eq!(metrics.terms[0].evaluated, 20);
+    assert_eq!(metrics.terms[0].matched, 10);
+}
+
+#[test]
+fn test_compiled_filter_continue_metrics() {
+    common::init();
+
+    let source = SOURCE_CODE.replace("return reject;", "return continue;");
+    let rotolo = pipeline::run_test(&source, None).unwrap();
+    let filter = rotolo
+        .retrieve_compiled_filter(&FilterMap("in-filter-map".into()))
+        .unwrap()
+        .with_metrics(true);
+    filter.set_source(source_asns(&[300])).unwrap();
+
+    let mut ctx = filter.new_execution_context(context()).unwrap();
+    for asn in [300_u32, 65000, 65001] {
+        ctx.exec(payload(asn), None::<Record>).unwrap();
+    }
+
+    // A `continue` is neither an accept nor a reject.
+    let metrics = filter.metrics().unwrap();
+    assert_eq!(metrics.executions, 3);
+    assert_eq!(metrics.accepted, 1);
+    assert_eq!(metrics.rejected, 0);
+    assert_eq!(metrics.continued, 2);
+    assert_eq!(metrics.errors, 0);
+}
+
+#[test]
+fn test_compiled_filter_exec_batch() {
+    common::init();
+
+    let filter = compiled_filter();
+    filter.set_source(source_asns(&[300])).unwrap();
+
+    let mut ctx = filter.new_execution_context(context()).unwrap();
+    let res = ctx
+        .exec_batch([300_u32, 65000, 300, 65001].into_iter().map(payload))
+        .unwrap();
+
+    assert_eq!(
+        res.iter().map(|r| r.accept_reject).collect::<Vec<_>>(),
+        vec![
+            AcceptReject::Accept,
+            AcceptReject::Reject,
+            AcceptReject::Accept,
+            AcceptReject::Reject
+        ]
+    );
+}
@@ -0,0 +1,244 @@
+use std::sync::Arc;
+
+use roto::pipeline::DataSourceRegistry;
+use roto::runtime::Runtime;
+use roto::traits::RotoDataSource;
+use roto::types::builtin::BuiltinTypeValue;
+use roto::types::datasources::{DataSource, DataSourceMethod};
+use roto::types::typedef::TypeDef;
+use roto::types::typevalue::TypeValue;
+use roto::vm::VmError;
+
+use inetnum::addr::Prefix;
+use inetnum::asn::Asn;
+
+mod common;
+
+use common::{context, is_accepted, prefix};
+
+/// A set of bogon prefixes, that contains a prefix if one of the bogons
+/// covers it.
+struct Bogons {
+    prefixes: Vec<Prefix>,
+}
+
+impl Bogons {
+    fn new(prefixes: &[&str]) -> Self {
+        Self {
+            prefixes: prefixes.iter().map(|p| prefix(p)).collect(),
+        }
+    }
+
+    fn contains(&self, p: &Prefix) -> bool {
+        self.prefixes.iter().any(|b| b.covers(*p))
+    }
+}
+
+impl RotoDataSource for Bogons {
+    fn methods(&self) -> Vec<DataSourceMethod> {
+        vec![
+            DataSourceMethod::new(
+                "contains",
+                vec![TypeDef::Prefix],
+                TypeDef::Bool,
+            ),
+            DataSourceMethod::new(
+                "contains_from",
+                vec![TypeDef::Prefix, TypeDef::Asn],
+                TypeDef::Bool,
+            ),
+        ]
+    }
+
+    fn exec_method(
+        &self,
+        method: usize,
+        args: &[&TypeValue],
+    ) -> Result<TypeValue, VmError> {
+        match (method, args) {
+            (0, [TypeValue::Builtin(BuiltinTypeValue::Prefix(p))]) => {
+                Ok(self.contains(p).into())
+            }
+            // Bogons from private ASNs are fine.
+            (
+                1,
+                [
+                    TypeValue::Builtin(BuiltinTypeValue::Prefix(p)),
+                    TypeValue::Builtin(BuiltinTypeValue::Asn(asn)),
+                ],
+            ) => Ok((asn.into_u32() < 64512 && self.contains(p)).into()),
+            _ => Err(VmError::InvalidMethodCall),
+        }
+    }
+}
+
+const SOURCE_CODE: &str = r###"
+    filter-map bogon-filter {
+        define {
+            rx_tx msg: Announcement;
+        }
+
+        term is-bogon {
+            match {
+                bogons.contains(msg.prefix);
+            }
+        }
+
+        apply {
+            filter match is-bogon matching {
+                return reject;
+            };
+            return accept;
+        }
+    }
+
+    filter-map bogon-origin-filter {
+        define {
+            rx_tx msg: Announcement;
+        }
+
+        term is-bogon-from-origin {
+            match {
+                bogons.contains_from(msg.prefix, msg.origin);
+            }
+        }
+
+        apply {
+            filter match is-bogon-from-origin matching {
+                return reject;
+            };
+            return accept;
+        }
+    }
+
+    type Announcement {
+        prefix: Prefix,
+        origin: Asn
+    }
+"###;
+
+fn registry(bogons: Bogons) -> DataSourceRegistry {
+    let mut sources = DataSourceRegistry::new();
+    sources.register("bogons", Arc::new(bogons)).unwrap();
+    sources
+}
+
+#[test]
+fn test_custom_data_source() {
+    common::init();
+
+    let sources = registry(Bogons::new(&["10.0.0.0/8", "192.168.0.0/16"]));
+    let runtime =
+        Runtime::from_string_with_sources(SOURCE_CODE, sources).unwrap();
+    let mut filter = runtime.filter("bogon-filter").unwrap();
+    filter.bind_context(context());
+
+    assert!(!is_accepted(&mut filter, "10.1.0.0/16", 65534));
+    assert!(!is_accepted(&mut filter, "192.168.1.0/24", 65534));
+    assert!(is_accepted(&mut filter, "192.0.2.0/24", 65534));
+}
+
+#[test]
+fn test_custom_data_source_with_arguments() {
+    common::init();
+
+    let sources = registry(Bogons::new(&["10.0.0.0/8"]));
+    let runtime =
+        Runtime::from_string_with_sources(SOURCE_CODE, sources).unwrap();
+    let mut filter = runtime.filter("bogon-origin-filter").unwrap();
+    filter.bind_context(context());
+
+    assert!(!is_accepted(&mut filter, "10.1.0.0/16", 3333));
+    assert!(is_accepted(&mut filter, "10.1.0.0/16", 65534));
+    assert!(is_accepted(&mut filter, "192.0.2.0/24", 3333));
+}
+
+#[test]
+fn test_replace_custom_data_source() {
+    common::init();
+
+    let sources = registry(Bogons::new(&["10.0.0.0/8"]));
+    let runtime =
+        Runtime::from_string_with_sources(SOURCE_CODE, sources).unwrap();
+    let mut filter = runtime.filter("bogon-filter").unwrap();
+    filter.bind_context(context());
+
+    let bogons = filter.data_source("bogons").unwrap();
+    assert_eq!(bogons.version(), 1);
+    assert!(is_accepted(&mut filter, "192.168.1.0/24", 65534));
+
+    bogons
+        .replace(DataSource::custom(
+            "bogons",
+            Arc::new(Bogons::new(&["192.168.0.0/16"])),
+        ))
+        .unwrap();
+    assert_eq!(bogons.version(), 2);
+    assert!(is_accepted(&mut filter, "10.1.0.0/16", 65534));
+    assert!(!is_accepted(&mut filter, "192.168.1.0/24", 65534));
+
+    // Custom sources aren't tables.
+    assert!(bogons.insert_records(vec![]).is_err());
+    assert!(bogons.remove_records(|_| true).is_err());
+}
+
+#[test]
+fn test_unknown_custom_data_source() {
+    common::init();
+
+    // Without the registry, `bogons` is unknown.
+    assert!(Runtime::from_string(SOURCE_CODE).is_err());
+
+    let wrong_method = SOURCE_CODE.replace("contains(", "covers(");
+    let sources = registry(Bogons::new(&[]));
+    assert!(
+        Runtime::from_string_with_sources(wrong_method, sources).is_err()
+    );
+
+    let wrong_argument = SOURCE_CODE.replace("(msg.prefix)", "(msg.origin)");
+    let sources = registry(Bogons::new(&[]));
+    assert!(
+        Runtime::from_string_with_sources(wrong_argument, sources).is_err()
+    );
+
+    // A name can only be registered once.
+    let mut sources = registry(Bogons::new(&[]));
+    assert!(sources
+        .register("bogons", Arc::new(Bogons::new(&[])))
+        .is_err());
+}
+
+/// A source with a method that returns a type roto can't use.
+struct Unusable;
+
+impl RotoDataSource for Unusable {
+    fn methods(&self) -> Vec<DataSourceMethod> {
+        vec![DataSourceMethod::new(
+            "lookup",
+            vec![TypeDef::Prefix],
+            TypeDef::Unknown,
+        )]
+    }
+
+    fn exec_method(
+        &self,
+        _method: usize,
+        _args: &[&TypeValue],
+    ) -> Result<TypeValue, VmError> {
+        Ok(TypeValue::Unknown)
+    }
+}
+
+#[test]
+fn test_unusable_custom_data_source() {
+    common::init();
+
+    let mut sources = DataSourceRegistry::new();
+    let err = sources.register("unusable", Arc::new(Unusable)).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Method lookup of data source unusable has a type that can't be \
+        used in roto"
+    );
+    assert!(sources.get("unusable").is_none());
+}
@@ -0,0 +1,300 @@
+use roto::runtime::{Filter, Runtime};
+use roto::types::builtin::BuiltinTypeValue;
+use roto::types::collections::Record;
+use roto::types::datasources::DataSource;
+use roto::types::rpki::{Vrp, Vrps};
+use roto::types::typedef::TypeDef;
+use roto::types::typevalue::TypeValue;
+
+use inetnum::asn::Asn;
+
+mod common;
+
+use common::{announcement, context, prefix};
+
+const SOURCE_CODE: &str = r###"
+    filter-map customer-filter-map {
+        define {
+            rx_tx msg: Msg;
+        }
+
+        term is-customer {
+            match {
+                customers.contains(msg.id);
+            }
+        }
+
+        apply {
+            filter match is-customer matching { return accept; };
+            return reject;
+        }
+    }
+
+    table customers contains Customer key id {
+        asn: Asn,
+        id: U32
+    }
+
+    type Msg {
+        asn: Asn,
+        id: U32
+    }
+"###;
+
+fn customer_type() -> TypeDef {
+    TypeDef::new_record_type(vec![
+        ("asn", Box::new(TypeDef::Asn)),
+        ("id", Box::new(TypeDef::U32)),
+    ])
+    .unwrap()
+}
+
+fn record(asn: u32, id: u32) -> Record {
+    Record::create_instance_with_ordered_fields(
+        &customer_type(),
+        vec![
+            ("asn", Asn::from_u32(asn).into()),
+            ("id", TypeValue::from(id)),
+        ],
+    )
+    .unwrap()
+}
+
+fn id_of(rec: &Record) -> Option<u32> {
+    match <&TypeValue>::try_from(rec.get_value_for_field("id")?) {
+        Ok(&TypeValue::Builtin(BuiltinTypeValue::U32(id))) => Some(id),
+        _ => None,
+    }
+}
+
+#[test]
+fn test_update_table() {
+    common::init();
+
+    let runtime = Runtime::from_string(SOURCE_CODE).unwrap();
+    let mut filter = runtime.filter("customer-filter-map").unwrap();
+    filter.bind_context(context());
+
+    let customers = filter.data_source("customers").unwrap();
+    assert_eq!(customers.get_name(), "customers");
+    assert_eq!(customers.version(), 0);
+    assert!(filter.data_source("suppliers").is_err());
+
+    // A table can be filled through the handle, without binding it first.
+    assert_eq!(customers.insert_records(vec![record(65534, 1)]).unwrap(), 1);
+    assert!(filter.run(record(1, 1)).unwrap().is_accepted());
+    assert!(filter.run(record(1, 2)).unwrap().is_rejected());
+
+    // The index on the key is kept up to date.
+    assert_eq!(
+        customers
+            .insert_records(vec![record(65535, 2), record(65536, 3)])
+            .unwrap(),
+        2
+    );
+    assert!(filter.run(record(1, 2)).unwrap().is_accepted());
+    assert!(filter.run(record(1, 3)).unwrap().is_accepted());
+
+    assert_eq!(
+        customers.remove_records(|rec| id_of(rec) == Some(2)).unwrap(),
+        3
+    );
+    assert!(filter.run(record(1, 1)).unwrap().is_accepted());
+    assert!(filter.run(record(1, 2)).unwrap().is_rejected());
+    assert!(filter.run(record(1, 3)).unwrap().is_accepted());
+
+    // Replace a record.
+    customers
+        .update_records(|rec| {
+            (id_of(rec) == Some(3)).then(|| record(65536, 4))
+        })
+        .unwrap();
+    assert!(filter.run(record(1, 3)).unwrap().is_rejected());
+    assert!(filter.run(record(1, 4)).unwrap().is_accepted());
+
+    // Binding a source counts as an update as well.
+    filter.bind_table("customers", vec![record(1, 5)]).unwrap();
+    assert_eq!(customers.version(), 5);
+    assert!(filter.run(record(1, 1)).unwrap().is_rejected());
+    assert!(filter.run(record(1, 5)).unwrap().is_accepted());
+
+    // Records of another type are refused, and don't change the version.
+    let other_ty =
+        TypeDef::new_record_type(vec![("asn", Box::new(TypeDef::Asn))])
+            .unwrap();
+    let other = Record::create_instance_with_ordered_fields(
+        &other_ty,
+        vec![("asn", Asn::from_u32(1).into())],
+    )
+    .unwrap();
+    assert!(customers.insert_records(vec![other]).is_err());
+    assert!(customers
+        .insert_prefix(prefix("192.0.2.0/24"), vec![])
+        .is_err());
+    assert_eq!(customers.version(), 5);
+}
+
+const VERSION_SOURCE_CODE: &str = r###"
+    filter-map version-filter-map {
+        define {
+            rx_tx msg: Msg;
+        }
+
+        term first-version {
+            match {
+                customers.version() == 1;
+            }
+        }
+
+        apply {
+            filter match first-version matching {
+                return accept with "first-version";
+            };
+            return accept;
+        }
+    }
+
+    table customers contains Customer key id {
+        asn: Asn,
+        id: U32
+    }
+
+    type Msg {
+        asn: Asn,
+        id: U32
+    }
+"###;
+
+#[test]
+fn test_version_in_filter() {
+    common::init();
+
+    let runtime = Runtime::from_string(VERSION_SOURCE_CODE).unwrap();
+    let mut filter = runtime.filter("version-filter-map").unwrap();
+    filter.bind_context(context());
+
+    // The filter can't run before the table is loaded.
+    assert!(filter.run(record(1, 1)).is_err());
+
+    let customers = filter.data_source("customers").unwrap();
+    customers.insert_records(vec![record(1, 1)]).unwrap();
+    let verdict = filter.run(record(1, 1)).unwrap();
+    assert!(verdict.reason.is_some());
+
+    customers.insert_records(vec![record(1, 2)]).unwrap();
+    let verdict = filter.run(record(1, 1)).unwrap();
+    assert_eq!(verdict.reason, None);
+}
+
+#[test]
+fn test_update_from_other_thread() {
+    common::init();
+
+    let runtime = Runtime::from_string(SOURCE_CODE).unwrap();
+    let mut filter = runtime.filter("customer-filter-map").unwrap();
+    filter.bind_context(context());
+    filter.bind_table("customers", vec![record(1, 1)]).unwrap();
+
+    let customers = filter.data_source("customers").unwrap();
+    let updater = {
+        let customers = customers.clone();
+        std::thread::spawn(move || {
+            for id in 2..100 {
+                customers.insert_records(vec![record(1, id)]).unwrap();
+            }
+        })
+    };
+
+    // Every run sees a complete version of the table.
+    for _ in 0..100 {
+        assert!(filter.run(record(1, 1)).unwrap().is_accepted());
+    }
+    updater.join().unwrap();
+
+    assert_eq!(customers.version(), 99);
+    assert!(filter.run(record(1, 99)).unwrap().is_accepted());
+}
+
+const VRPS_SOURCE_CODE: &str = r###"
+    filter-map rov-filter-map {
+        define {
+            rx_tx msg: Announcement;
+        }
+
+        term rov-valid {
+            match {
+                vrps.validate(msg.prefix, msg.origin) == ROV_VALID;
+            }
+        }
+
+        apply {
+            filter match rov-valid matching { return accept; };
+            return reject;
+        }
+    }
+
+    rib vrps contains Vrp
+
+    type Announcement {
+        prefix: Prefix,
+        origin: Asn
+    }
+"###;
+
+fn vrp(p: &str, max_length: u8, asn: u32) -> TypeValue {
+    TypeValue::from(&Vrp {
+        prefix: prefix(p),
+        max_length,
+        asn: Asn::from_u32(asn),
+    })
+}
+
+fn is_valid(filter: &mut Filter, p: &str, origin: u32) -> bool {
+    filter.run(announcement(p, origin)).unwrap().is_accepted()
+}
+
+#[test]
+fn test_update_rib() {
+    common::init();
+
+    let runtime = Runtime::from_string(VRPS_SOURCE_CODE).unwrap();
+    let mut filter = runtime.filter("rov-filter-map").unwrap();
+    filter.bind_context(context());
+    filter
+        .bind_source(DataSource::from(Vrps::new("vrps", Vec::<Vrp>::new())))
+        .unwrap();
+
+    let vrps = filter.data_source("vrps").unwrap();
+    assert_eq!(vrps.version(), 1);
+    assert!(!is_valid(&mut filter, "192.0.2.0/24", 65534));
+
+    vrps.insert_prefix(
+        prefix("192.0.2.0/24"),
+        vec![vrp("192.0.2.0/24", 24, 65534), vrp("192.0.2.0/24", 24, 65535)],
+    )
+    .unwrap();
+    assert!(is_valid(&mut filter, "192.0.2.0/24", 65534));
+    assert!(is_valid(&mut filter, "192.0.2.0/24", 65535));
+
+    // The records replace the ones the prefix had.
+    vrps.insert_prefix(
+        prefix("192.0.2.0/24"),
+        vec![vrp("192.0.2.0/24", 24, 65535)],
+    )
+    .unwrap();
+    assert!(!is_valid(&mut filter, "192.0.2.0/24", 65534));
+    assert!(is_valid(&mut filter, "192.0.2.0/24", 65535));
+
+    assert_eq!(vrps.remove_prefix(prefix("192.0.2.0/24")).unwrap(), 4);
+    assert!(!is_valid(&mut filter, "192.0.2.0/24", 65535));
+
+    // A VRP has to be for the prefix it is stored under.
+    assert!(vrps
+        .insert_prefix(
+            prefix("192.0.2.0/24"),
+            vec![vrp("198.51.100.0/24", 24, 65535)]
+        )
+        .is_err());
+    assert!(vrps.insert_records(vec![]).is_err());
+    assert_eq!(vrps.version(), 4);
+}
@@ -6,7 +6,7 @@ use roto::types::collections::{ElementTypeValue, List, Record};
 use roto::types::datasources::DataSource;
 use roto::types::typedef::TypeDef;
 use roto::types::typevalue::TypeValue;
-use roto::{pipeline, vm};
+use roto::pipeline;
 
 use inetnum::asn::Asn;
 use routecore::bgp::communities::HumanReadableCommunity as Community;
@@ -19,14 +19,13 @@ fn test_data(
 ) -> Result<(), Box<dyn std::error::Error>> {
     println!("Evaluate filter-map {}...", name);
 
-    // Type coercion doesn't work here...
     let filter_map_arguments =
-        vec![("extra_asn", TypeValue::from(Asn::from(65534_u32)))];
+        vec![("extra_asn", TypeValue::from(65534_u32))];
 
     let rotolo =
         pipeline::run_test(source_code, Some((&name, filter_map_arguments)))?;
 
-    let mut roto_pack = rotolo.retrieve_pack_as_refs(&name)?;
+    let filter = rotolo.retrieve_compiled_filter(&name)?;
     let _count: TypeValue = 1_u32.into();
     let prefix: TypeValue =
         inetnum::addr::Prefix::new("193.0.0.0".parse().unwrap(), 24)?
@@ -93,23 +92,17 @@ fn test_data(
         vec![("asn", Asn::from_u32(300).into())],
     )?;
 
-    let mem = &mut vm::LinearMemory::uninit();
-
     println!("Used Arguments");
-    println!("{:#?}", &roto_pack.arguments);
+    println!("{:#?}", filter.get_arguments());
     println!("Used Data Sources");
-    println!("{:#?}", &roto_pack.data_sources);
+    println!("{:#?}", filter.get_data_sources());
 
     // table source_asns contains AsnLines {
     //     asn: Asn
     // }
     let sources_asns =
         DataSource::table_from_records("source_asns", vec![new_sa_rec])?;
-    roto_pack.set_source(sources_asns)?;
-
-    for mb in roto_pack.get_mir().iter() {
-        println!("{}", mb);
-    }
+    filter.set_source(sources_asns)?;
 
     let peer_ip = "192.0.2.11".parse().unwrap();
     let provenance = Provenance {
@@ -123,14 +116,9 @@ fn test_data(
 
     let context = RouteContext::new(None, NlriStatus::InConvergence, provenance);
 
-    let mut vm = vm::VmBuilder::new()
-        // .with_arguments(args)
-        .with_context(context)
-        .with_data_sources(roto_pack.data_sources)
-        .with_mir_code(roto_pack.mir)
-        .build()?;
+    let mut exec_ctx = filter.new_execution_context(context)?;
 
-    let res = vm.exec(my_payload, None::<Record>, None, mem).unwrap();
+    let res = exec_ctx.exec(my_payload, None::<Record>).unwrap();
 
     println!("\nRESULT");
     println!("action: {}", res.accept_reject);
@@ -0,0 +1,526 @@
+//! Differential tests between the evaluation of literals at compile time
+//! and at runtime.
+//!
+//! Randomly generated expressions, nested `&&`, `||` and `!` over
+//! comparisons of literals with literals or fields of the rx message of
+//! compatible types, are compiled in two ways: once with the literals on
+//! the left-hand sides of the comparisons assigned to variables in the
+//! `define` section, which are evaluated into constants at compile time,
+//! and once with all the literals directly in the `term` section, which are
+//! evaluated by the VM. Both filter-maps should come to the verdict that is
+//! computed for the expression in Rust, or should both fail. Enough of the
+//! cases should succeed, so that a compiler that rejects everything doesn't
+//! pass.
+//!
+//! The random generator is seeded, so a failing case can be reproduced by
+//! setting the `ROTO_DIFF_SEED` environment variable to the seed that is
+//! printed for it.
+
+use std::net::{IpAddr, Ipv4Addr};
+
+use roto::ast::AcceptReject;
+
+use roto::blocks::Scope::FilterMap;
+use roto::pipeline;
+use roto::types::builtin::PrefixLength;
+use roto::types::collections::Record;
+use roto::types::typedef::TypeDef;
+use roto::types::typevalue::TypeValue;
+
+use inetnum::asn::Asn;
+
+mod common;
+
+use common::context;
+
+const CASES_PER_SEED: usize = 64;
+// The minimum number of cases per seed that should compile and run. A case
+// with a comparison that doesn't type check fails as a whole, so with the
+// nesting this is lower than half.
+const MIN_SUCCESSES_PER_SEED: usize = CASES_PER_SEED / 4;
+const SEEDS: [u64; 4] = [1, 0x5eed, 0xdead_beef, 0x0123_4567_89ab_cdef];
+// The deepest nesting of logical expressions.
+const MAX_DEPTH: u64 = 3;
+
+//------------ Random generation --------------------------------------------
+
+/// A xorshift64* generator, good enough to generate test cases with.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // xorshift can't start from zero.
+        Rng(seed.max(1))
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len() as u64) as usize]
+    }
+}
+
+const INT_OPS: [&str; 6] = ["==", "!=", "<", "<=", ">", ">="];
+const EQ_OPS: [&str; 2] = ["==", "!="];
+
+/// A literal, the number is its value for integers, ASNs and prefix
+/// lengths, its suffix for strings, and its last octet for IP addresses.
+#[derive(Debug, Clone, Copy)]
+enum Literal {
+    Integer(u64),
+    Asn(u64),
+    String(u64),
+    IpAddress(u64),
+    PrefixLength(u64),
+}
+
+impl std::fmt::Display for Literal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Literal::Integer(n) => write!(f, "{}", n),
+            Literal::Asn(n) => write!(f, "AS{}", n),
+            Literal::String(n) => write!(f, "\"roto-{}\"", n),
+            Literal::IpAddress(n) => write!(f, "192.0.2.{}", n),
+            Literal::PrefixLength(n) => write!(f, "/{}", n),
+        }
+    }
+}
+
+impl Literal {
+    fn value(self) -> u64 {
+        match self {
+            Literal::Integer(n)
+            | Literal::Asn(n)
+            | Literal::String(n)
+            | Literal::IpAddress(n)
+            | Literal::PrefixLength(n) => n,
+        }
+    }
+}
+
+/// A field of the payload, see [`payload`] for their values.
+#[derive(Debug, Clone, Copy)]
+enum Field {
+    Asn,
+    Kind,
+    Len,
+    Addr,
+    Name,
+}
+
+impl Field {
+    /// The value of the field in the payload, in the same terms as
+    /// [`Literal::value`].
+    fn value(self) -> u64 {
+        match self {
+            Field::Asn => 65531,
+            Field::Kind => 254,
+            Field::Len => 24,
+            Field::Addr => 1,
+            Field::Name => 1,
+        }
+    }
+}
+
+impl std::fmt::Display for Field {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Field::Asn => write!(f, "msg.asn"),
+            Field::Kind => write!(f, "msg.kind"),
+            Field::Len => write!(f, "msg.len"),
+            Field::Addr => write!(f, "msg.addr"),
+            Field::Name => write!(f, "msg.name"),
+        }
+    }
+}
+
+/// The right-hand side of a comparison.
+#[derive(Debug, Clone, Copy)]
+enum Operand {
+    Literal(Literal),
+    Field(Field),
+}
+
+impl std::fmt::Display for Operand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Operand::Literal(lit) => lit.fmt(f),
+            Operand::Field(field) => field.fmt(f),
+        }
+    }
+}
+
+impl Operand {
+    fn value(self) -> u64 {
+        match self {
+            Operand::Literal(lit) => lit.value(),
+            Operand::Field(field) => field.value(),
+        }
+    }
+}
+
+/// One comparison of a literal with a literal or a field of a type it
+/// converts into. The literal is always on the left-hand side, so that it
+/// can be moved into a variable, but it's written on the right if
+/// `flipped` is set.
+#[derive(Debug)]
+struct Comparison {
+    left: Literal,
+    op: &'static str,
+    right: Operand,
+    flipped: bool,
+}
+
+impl Comparison {
+    fn random(rng: &mut Rng) -> Self {
+        // Pick the values from a small range, so that they're equal
+        // regularly.
+        let (left, ops, right) = match rng.below(7) {
+            0 => {
+                // Integers around the boundaries of U8 and U16.
+                let base = *rng.pick(&[0, 255, 65535]);
+                let left = base + rng.below(3);
+                let right = base + rng.below(3);
+                (
+                    Literal::Integer(left),
+                    &INT_OPS[..],
+                    Operand::Literal(Literal::Integer(right)),
+                )
+            }
+            1 => (
+                Literal::Integer(253 + rng.below(3)),
+                &INT_OPS[..],
+                Operand::Field(Field::Kind),
+            ),
+            2 => (
+                Literal::Integer(65530 + rng.below(3)),
+                &EQ_OPS[..],
+                Operand::Field(Field::Asn),
+            ),
+            3 => {
+                let right = if rng.below(2) == 0 {
+                    Operand::Literal(Literal::Asn(65530 + rng.below(3)))
+                } else {
+                    Operand::Field(Field::Asn)
+                };
+                (Literal::Asn(65530 + rng.below(3)), &EQ_OPS[..], right)
+            }
+            4 => {
+                let left = if rng.below(2) == 0 {
+                    Literal::Integer(23 + rng.below(3))
+                } else {
+                    Literal::PrefixLength(23 + rng.below(3))
+                };
+                (left, &EQ_OPS[..], Operand::Field(Field::Len))
+            }
+            5 => {
+                let right = if rng.below(2) == 0 {
+                    Operand::Literal(Literal::String(rng.below(3)))
+                } else {
+                    Operand::Field(Field::Name)
+                };
+                (Literal::String(rng.below(3)), &EQ_OPS[..], right)
+            }
+            _ => {
+                let right = if rng.below(2) == 0 {
+                    Operand::Literal(Literal::IpAddress(1 + rng.below(3)))
+                } else {
+                    Operand::Field(Field::Addr)
+                };
+                (Literal::IpAddress(1 + rng.below(3)), &EQ_OPS[..], right)
+            }
+        };
+
+        Comparison {
+            left,
+            op: *rng.pick(ops),
+            right,
+            flipped: rng.below(2) == 0,
+        }
+    }
+
+    /// The outcome of the comparison, computed in Rust. The operands of a
+    /// comparison always convert into the same type, so comparing their
+    /// numbers is enough.
+    fn holds(&self) -> bool {
+        let (left, right) = (self.left.value(), self.right.value());
+        match self.op {
+            "==" => left == right,
+            "!=" => left != right,
+            "<" => left < right,
+            "<=" => left <= right,
+            ">" => left > right,
+            ">=" => left >= right,
+            op => panic!("unknown operator {}", op),
+        }
+    }
+
+    /// The comparison in source code, with `left` in place of the literal
+    /// on the left-hand side.
+    fn source(&self, left: &str) -> String {
+        if self.flipped {
+            let op = match self.op {
+                "<" => ">",
+                "<=" => ">=",
+                ">" => "<",
+                ">=" => "<=",
+                op => op,
+            };
+            format!("{} {} {}", self.right, op, left)
+        } else {
+            format!("{} {} {}", left, self.op, self.right)
+        }
+    }
+}
+
+/// A logical expression over comparisons.
+#[derive(Debug)]
+enum Expr {
+    Compare(Comparison),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+}
+
+impl Expr {
+    fn random(rng: &mut Rng, depth: u64) -> Self {
+        if depth == 0 {
+            return Expr::Compare(Comparison::random(rng));
+        }
+
+        match rng.below(7) {
+            0 | 1 => Expr::Compare(Comparison::random(rng)),
+            2 | 3 => Expr::And(
+                Box::new(Expr::random(rng, depth - 1)),
+                Box::new(Expr::random(rng, depth - 1)),
+            ),
+            4 | 5 => Expr::Or(
+                Box::new(Expr::random(rng, depth - 1)),
+                Box::new(Expr::random(rng, depth - 1)),
+            ),
+            _ => Expr::Not(Box::new(Expr::random(rng, depth - 1))),
+        }
+    }
+
+    /// The outcome of the expression, computed in Rust.
+    fn holds(&self) -> bool {
+        match self {
+            Expr::Compare(c) => c.holds(),
+            Expr::And(left, right) => left.holds() && right.holds(),
+            Expr::Or(left, right) => left.holds() || right.holds(),
+            Expr::Not(expr) => !expr.holds(),
+        }
+    }
+
+    /// The verdict the filter-maps should come to, computed in Rust.
+    fn expected(&self) -> AcceptReject {
+        if self.holds() {
+            AcceptReject::Accept
+        } else {
+            AcceptReject::Reject
+        }
+    }
+
+    /// The expression in source code. If `defines` is given, the literals
+    /// on the left-hand sides of the comparisons are assigned to variables
+    /// that are added to it.
+    fn source(&self, defines: &mut Option<Vec<String>>) -> String {
+        match self {
+            Expr::Compare(c) => match defines {
+                Some(defines) => {
+                    let var = format!("v{}", defines.len());
+                    defines.push(format!("{} = {};", var, c.left));
+                    c.source(&var)
+                }
+                None => c.source(&c.left.to_string()),
+            },
+            Expr::And(left, right) => format!(
+                "{} && {}",
+                left.operand(defines),
+                right.operand(defines)
+            ),
+            Expr::Or(left, right) => format!(
+                "{} || {}",
+                left.operand(defines),
+                right.operand(defines)
+            ),
+            Expr::Not(expr) => format!("!{}", expr.operand(defines)),
+        }
+    }
+
+    /// The expression as an operand of a logical operator, the grammar
+    /// only allows comparisons and grouped expressions there.
+    fn operand(&self, defines: &mut Option<Vec<String>>) -> String {
+        match self {
+            Expr::Compare(_) => self.source(defines),
+            _ => format!("({})", self.source(defines)),
+        }
+    }
+
+    /// The expression with all the literals in place.
+    fn runtime_source(&self) -> (String, String) {
+        (String::new(), self.source(&mut None))
+    }
+
+    /// The expression with the left-hand side literals moved into
+    /// variables in the define section.
+    fn compile_time_source(&self) -> (String, String) {
+        let mut defines = Some(vec![]);
+        let expr = self.source(&mut defines);
+
+        (defines.unwrap_or_default().join("\n"), expr)
+    }
+}
+
+//------------ Running the filter-maps --------------------------------------
+
+fn src_code(defines: &str, expr: &str) -> String {
+    format!(
+        r###"
+        filter-map diff-filter-map {{
+            define {{
+                rx_tx msg: BmpMsg;
+                {}
+            }}
+
+            term expr-holds {{
+                match {{
+                    {};
+                }}
+            }}
+
+            apply {{
+                filter match expr-holds matching {{ return accept; }};
+                return reject;
+            }}
+        }}
+
+        type BmpMsg {{
+            asn: Asn,
+            kind: U8,
+            len: PrefixLength,
+            addr: IpAddress,
+            name: String
+        }}
+    "###,
+        defines, expr
+    )
+}
+
+/// The payload with the values of [`Field::value`].
+fn payload() -> Record {
+    let ty = TypeDef::new_record_type(vec![
+        ("asn", Box::new(TypeDef::Asn)),
+        ("kind", Box::new(TypeDef::U8)),
+        ("len", Box::new(TypeDef::PrefixLength)),
+        ("addr", Box::new(TypeDef::IpAddr)),
+        ("name", Box::new(TypeDef::StringLiteral)),
+    ])
+    .unwrap();
+
+    Record::create_instance_with_ordered_fields(
+        &ty,
+        vec![
+            ("asn", Asn::from_u32(65531).into()),
+            ("kind", TypeValue::from(254_u8)),
+            ("len", PrefixLength::new(24).into()),
+            ("addr", IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)).into()),
+            ("name", "roto-1".into()),
+        ],
+    )
+    .unwrap()
+}
+
+/// Compile and run the filter-map, the error is turned into a string, since
+/// only the fact that it failed is compared.
+fn run((defines, expr): (String, String)) -> Result<AcceptReject, String> {
+    let source_code = src_code(&defines, &expr);
+    let name = FilterMap("diff-filter-map".into());
+
+    let rotolo =
+        pipeline::run_test(&source_code, None).map_err(|e| e.to_string())?;
+    let filter = rotolo
+        .retrieve_compiled_filter(&name)
+        .map_err(|e| e.to_string())?;
+    let mut ctx = filter
+        .new_execution_context(context())
+        .map_err(|e| e.to_string())?;
+
+    ctx.exec(payload(), None::<Record>)
+        .map(|res| res.accept_reject)
+        .map_err(|e| e.to_string())
+}
+
+fn check_seed(seed: u64) {
+    let mut rng = Rng::new(seed);
+    let mut successes = 0;
+
+    for case in 0..CASES_PER_SEED {
+        let expr = Expr::random(&mut rng, MAX_DEPTH);
+
+        let runtime = run(expr.runtime_source());
+        let compile_time = run(expr.compile_time_source());
+
+        assert_eq!(
+            runtime.is_ok(),
+            compile_time.is_ok(),
+            "seed {} case {}: {:?}\nruntime: {:?}\ncompile time: {:?}",
+            seed,
+            case,
+            expr,
+            runtime,
+            compile_time
+        );
+
+        if let (Ok(runtime), Ok(compile_time)) = (&runtime, &compile_time) {
+            assert_eq!(
+                runtime, compile_time,
+                "seed {} case {}: {:?}",
+                seed, case, expr
+            );
+            assert_eq!(
+                *runtime,
+                expr.expected(),
+                "seed {} case {}: {:?}",
+                seed,
+                case,
+                expr
+            );
+            successes += 1;
+        }
+    }
+
+    assert!(
+        successes >= MIN_SUCCESSES_PER_SEED,
+        "seed {}: only {} of {} cases ran",
+        seed,
+        successes,
+        CASES_PER_SEED
+    );
+}
+
+#[test]
+fn test_differential_literals() {
+    common::init();
+
+    if let Some(seed) = std::env::var("ROTO_DIFF_SEED")
+        .ok()
+        .and_then(|s| s.parse().ok())
+    {
+        check_seed(seed);
+        return;
+    }
+
+    for seed in SEEDS {
+        check_seed(seed);
+    }
+}
@@ -8,7 +8,7 @@ use roto::types::collections::{ElementTypeValue, List, Record};
 use roto::types::datasources::{DataSource, Rib};
 use roto::types::typedef::TypeDef;
 use roto::types::typevalue::TypeValue;
-use roto::vm::{self, VmResult};
+use roto::vm::VmResult;
 use rotonda_store::prelude::MergeUpdate;
 use routecore::bgp::communities::HumanReadableCommunity as Community;
 use inetnum::asn::Asn;
@@ -45,6 +45,14 @@ impl MergeUpdate for RibValue {
     }
 }
 
+impl From<RibValue> for TypeValue {
+    fn from(value: RibValue) -> Self {
+        TypeValue::List(List::new(
+            value.0.into_iter().map(ElementTypeValue::Primitive).collect(),
+        ))
+    }
+}
+
 impl std::fmt::Display for RibValue {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{:?}", self.0)
@@ -61,7 +69,7 @@ fn test_data(
 
     let rotolo =
         pipeline::run_test(source_code, Some((&name, filter_args)))?;
-    let mut roto_pack = rotolo.retrieve_pack_as_refs(&name)?;
+    let filter = rotolo.retrieve_compiled_filter(&name)?;
     let _count: TypeValue = 1_u32.into();
     let prefix: TypeValue =
         inetnum::addr::Prefix::new("193.0.0.0".parse().unwrap(), 24)?
@@ -126,20 +134,13 @@ fn test_data(
     )
     .unwrap();
 
-    // Turn it into a DataSource.
-    let mem = &mut vm::LinearMemory::uninit();
-
     trace!("Used Arguments");
-    trace!("{:#?}", &roto_pack.arguments);
+    trace!("{:#?}", filter.get_arguments());
     trace!("Used Data Sources");
-    trace!("{:#?}", &roto_pack.data_sources);
-
-    for mb in roto_pack.get_mir().iter() {
-        trace!("{}", mb);
-    }
+    trace!("{:#?}", filter.get_data_sources());
 
     for data_source in data_sources {
-        roto_pack.set_source(data_source)?;
+        filter.set_source(data_source)?;
     }
 
     let peer_ip = "192.0.2.0".parse().unwrap();
@@ -154,14 +155,9 @@ fn test_data(
     };
 
     let context = RouteContext::new(None, NlriStatus::InConvergence, provenance);
-    let mut vm = vm::VmBuilder::new()
-        // .with_arguments(args)
-        .with_context(context)
-        .with_data_sources(roto_pack.data_sources)
-        .with_mir_code(roto_pack.mir)
-        .build()?;
+    let mut exec_ctx = filter.new_execution_context(context)?;
 
-    let res = vm.exec(my_payload, None::<Record>, None, mem).unwrap();
+    let res = exec_ctx.exec(my_payload, None::<Record>).unwrap();
 
     trace!("\nRESULT");
     trace!("action: {}", res.accept_reject);
@@ -176,9 +172,8 @@ fn test_data(
 fn test_filter_map_1() {
     common::init();
 
-    // Type coercion doesn't work here...
     let filter_map_arguments =
-        vec![("extra_asn", TypeValue::from(Asn::from(65534_u32)))];
+        vec![("extra_asn", TypeValue::from(65534_u32))];
 
     let source_asns_type =
         TypeDef::new_record_type(vec![("asn", Box::new(TypeDef::Asn))])
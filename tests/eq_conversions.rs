@@ -1,11 +1,9 @@
 use roto::ast::AcceptReject;
-use roto::blocks::Scope::{self, FilterMap};
+use roto::runtime::{Runtime, Verdict};
 use roto::types::builtin::{NlriStatus, PeerId, PeerRibType, Provenance, RouteContext};
-use roto::pipeline;
 use roto::types::collections::Record;
 use roto::types::typedef::TypeDef;
 use roto::types::typevalue::TypeValue;
-use roto::vm::{self, VmResult};
 
 use rotonda_store::prelude::MergeUpdate;
 
@@ -82,13 +80,13 @@ fn src_code(code_line: &str, end_accept_reject: &str) -> String {
 }
 
 fn test_data(
-    name: Scope,
+    name: &str,
     source_code: &str,
-) -> Result<VmResult, Box<dyn std::error::Error>> {
+) -> Result<Verdict, Box<dyn std::error::Error>> {
     println!("Evaluate filter-map {}...", name);
 
-    let rotolo = pipeline::run_test(source_code, None)?;
-    let roto_pack = rotolo.retrieve_pack_as_refs(&name)?;
+    let runtime = Runtime::from_string(source_code)?;
+    let mut filter = runtime.filter(name)?;
     let asn: TypeValue = Asn::from_u32(211321).into();
 
     println!("ASN {:?}", asn);
@@ -105,16 +103,10 @@ fn test_data(
     )
     .unwrap();
 
-    let mem = &mut vm::LinearMemory::uninit();
-
     println!("Used Arguments");
-    println!("{:#?}", &roto_pack.arguments);
+    println!("{:#?}", filter.compiled().get_arguments());
     println!("Used Data Sources");
-    println!("{:#?}", &roto_pack.data_sources);
-
-    for mb in roto_pack.get_mir().iter() {
-        println!("{}", mb);
-    }
+    println!("{:#?}", filter.compiled().get_data_sources());
     
     let peer_ip = "192.0.2.0".parse().unwrap();
 
@@ -129,13 +121,9 @@ fn test_data(
 
     let context = RouteContext::new(None, NlriStatus::InConvergence, provenance);
 
-    let mut vm = vm::VmBuilder::new()
-        .with_context(context)
-        .with_data_sources(roto_pack.data_sources)
-        .with_mir_code(roto_pack.mir)
-        .build()?;
+    filter.bind_context(context);
 
-    let res = vm.exec(my_payload, None::<Record>, None, mem).unwrap();
+    let res = filter.run(my_payload).unwrap();
 
     println!("\nRESULT");
     println!("action: {}", res.accept_reject);
@@ -149,9 +137,9 @@ fn test_data(
 fn test_eq_conversion_1() {
     common::init();
     let src_line = src_code(r#"1 in a;"#, "reject");
-    let test_run = test_data(FilterMap("in-filter-map".into()), &src_line);
+    let test_run = test_data("in-filter-map", &src_line);
 
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Reject);
 }
 
@@ -159,7 +147,7 @@ fn test_eq_conversion_1() {
 fn test_eq_conversion_2() {
     common::init();
     let src_line = src_code(r#""b" in a;"#, "reject");
-    let test_run = test_data(FilterMap("in-filter-map".into()), &src_line);
+    let test_run = test_data("in-filter-map", &src_line);
 
     test_run.unwrap_err();
 }
@@ -168,8 +156,8 @@ fn test_eq_conversion_2() {
 fn test_eq_conversion_3() {
     common::init();
     let src_line = src_code(r#"32768 in a;"#, "reject");
-    let test_run = test_data(FilterMap("in-filter-map".into()), &src_line);
+    let test_run = test_data("in-filter-map", &src_line);
 
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Accept);
 }
@@ -0,0 +1,245 @@
+use std::sync::Arc;
+
+use roto::pipeline::DataSourceRegistry;
+use roto::runtime::Runtime;
+use roto::types::irr::Irr;
+
+use inetnum::asn::Asn;
+
+mod common;
+
+use common::{context, is_accepted, prefix};
+
+const RPSL: &str = r###"
+% A comment from the registry.
+
+as-set:         AS-CUSTOMERS
+descr:          The customers of AS65534
+members:        AS65535, AS65536,
+                AS-RESELLER # a nested set
++               AS-UPSTREAM
+source:         TEST
+
+as-set:         as-reseller
+members:        AS65537 AS-CUSTOMERS
+source:         TEST
+
+aut-num:        AS65535
+as-name:        CUSTOMER
+
+route:          192.0.2.0/24
+origin:         AS65535
+source:         TEST
+
+route6:         2001:db8::/32
+origin:         AS65537
+source:         TEST
+
+route:          198.51.100.0/24
+origin:         AS65537
+source:         TEST
+
+route-set:      RS-CUSTOMERS
+members:        203.0.113.0/24^+, 10.0.0.0/8^-,
+                172.16.0.0/12^16-20, 100.64.0.0/10^16
+mp-members:     2001:db8:1::/48
+source:         TEST
+
+route-set:      RS-NESTED
+members:        RS-CUSTOMERS^24, AS65535^+, AS-RESELLER, RS-NESTED
+source:         TEST
+"###;
+
+fn irr() -> Irr {
+    Irr::from_rpsl(RPSL.as_bytes()).unwrap()
+}
+
+#[test]
+fn test_as_set() {
+    common::init();
+
+    let customers = irr().as_set("AS-CUSTOMERS").unwrap();
+    assert_eq!(customers.len(), 3);
+    for asn in [65535, 65536, 65537] {
+        assert!(customers.contains(Asn::from_u32(asn)));
+    }
+    assert!(!customers.contains(Asn::from_u32(65534)));
+
+    // Names are case insensitive, and nested sets are expanded once.
+    let reseller = irr().as_set("as-Reseller").unwrap();
+    assert_eq!(reseller.len(), 3);
+
+    assert!(irr().as_set("AS-UPSTREAM").is_err());
+    assert!(irr().as_set("RS-CUSTOMERS").is_err());
+}
+
+#[test]
+fn test_route_set() {
+    common::init();
+
+    let customers = irr().route_set("RS-CUSTOMERS").unwrap();
+    assert_eq!(customers.len(), 5);
+
+    // ^+
+    assert!(customers.contains(&prefix("203.0.113.0/24")));
+    assert!(customers.contains(&prefix("203.0.113.128/25")));
+    // ^-
+    assert!(!customers.contains(&prefix("10.0.0.0/8")));
+    assert!(customers.contains(&prefix("10.1.0.0/16")));
+    // ^n-m
+    assert!(!customers.contains(&prefix("172.16.0.0/12")));
+    assert!(customers.contains(&prefix("172.16.0.0/16")));
+    assert!(customers.contains(&prefix("172.17.16.0/20")));
+    assert!(!customers.contains(&prefix("172.17.16.0/21")));
+    // ^n
+    assert!(customers.contains(&prefix("100.64.0.0/16")));
+    assert!(!customers.contains(&prefix("100.64.0.0/17")));
+    // No operator
+    assert!(customers.contains(&prefix("2001:db8:1::/48")));
+    assert!(!customers.contains(&prefix("2001:db8:1::/64")));
+    assert!(!customers.contains(&prefix("192.0.2.0/24")));
+}
+
+#[test]
+fn test_nested_route_set() {
+    common::init();
+
+    let nested = irr().route_set("rs-nested").unwrap();
+
+    // The operator of a set limits the lengths its members allow.
+    assert!(nested.contains(&prefix("203.0.113.0/24")));
+    assert!(!nested.contains(&prefix("203.0.113.0/25")));
+    assert!(nested.contains(&prefix("10.1.2.0/24")));
+    assert!(!nested.contains(&prefix("10.1.0.0/16")));
+    assert!(!nested.contains(&prefix("172.16.0.0/16")));
+    assert!(!nested.contains(&prefix("100.64.0.0/16")));
+    assert!(!nested.contains(&prefix("2001:db8:1::/48")));
+
+    // ASNs and as-sets are expanded into the routes of their origins, and
+    // the operator of an ASN applies to its routes.
+    assert!(nested.contains(&prefix("192.0.2.0/24")));
+    assert!(nested.contains(&prefix("192.0.2.0/25")));
+    assert!(nested.contains(&prefix("198.51.100.0/24")));
+    assert!(!nested.contains(&prefix("198.51.100.0/25")));
+    assert!(nested.contains(&prefix("2001:db8::/32")));
+}
+
+#[test]
+fn test_route_set_diamonds_and_cycles() {
+    common::init();
+
+    let rpsl = "\
+route-set: RS-TOP
+members: RS-LEFT, RS-RIGHT, RS-CYCLE-B
+
+route-set: RS-LEFT
+members: RS-BOTTOM^+
+
+route-set: RS-RIGHT
+members: RS-BOTTOM
+
+route-set: RS-BOTTOM
+members: 192.0.2.0/24
+
+route-set: RS-CYCLE-A
+members: RS-CYCLE-B, 10.0.0.0/8
+
+route-set: RS-CYCLE-B
+members: RS-CYCLE-A, 172.16.0.0/12
+";
+    let irr = Irr::from_rpsl(rpsl.as_bytes()).unwrap();
+
+    // A set that is in two sets is expanded with the operator of each.
+    let top = irr.route_set("RS-TOP").unwrap();
+    assert!(top.contains(&prefix("192.0.2.0/24")));
+    assert!(top.contains(&prefix("192.0.2.0/25")));
+
+    // The sets of a cycle contain each other, wherever the expansion
+    // starts.
+    assert!(top.contains(&prefix("10.0.0.0/8")));
+    assert!(top.contains(&prefix("172.16.0.0/12")));
+    for name in ["RS-CYCLE-A", "RS-CYCLE-B"] {
+        let set = irr.route_set(name).unwrap();
+        assert_eq!(set.len(), 2, "{name}");
+        assert!(set.contains(&prefix("10.0.0.0/8")), "{name}");
+        assert!(set.contains(&prefix("172.16.0.0/12")), "{name}");
+    }
+}
+
+#[test]
+fn test_invalid_rpsl() {
+    common::init();
+
+    let err = Irr::from_rpsl("route: 192.0.2.0/24\n".as_bytes()).unwrap_err();
+    assert_eq!(err.row, 1);
+
+    let rpsl = "route: 192.0.2.0/24\norigin: 65535\n";
+    let err = Irr::from_rpsl(rpsl.as_bytes()).unwrap_err();
+    assert_eq!(err.row, 2);
+
+    let err = Irr::from_rpsl("not an attribute\n".as_bytes()).unwrap_err();
+    assert_eq!(err.row, 1);
+
+    let rpsl = "route-set: RS-FOO\nmembers: 192.0.2.0/24^x\n";
+    let irr = Irr::from_rpsl(rpsl.as_bytes()).unwrap();
+    assert!(irr.route_set("RS-FOO").is_err());
+
+    assert!(Irr::from_files(["/nonexistent/irr.db"]).is_err());
+}
+
+const SOURCE_CODE: &str = r###"
+    filter-map customer-filter {
+        define {
+            rx_tx msg: Announcement;
+        }
+
+        term from-customer {
+            match {
+                customer-asns.contains(msg.origin);
+                customer-routes.contains(msg.prefix);
+            }
+        }
+
+        apply {
+            filter match from-customer matching {
+                return accept;
+            };
+            return reject;
+        }
+    }
+
+    type Announcement {
+        prefix: Prefix,
+        origin: Asn
+    }
+"###;
+
+#[test]
+fn test_irr_in_filter() {
+    common::init();
+
+    let irr = irr();
+    let mut sources = DataSourceRegistry::new();
+    sources
+        .register(
+            "customer-asns",
+            Arc::new(irr.as_set("AS-CUSTOMERS").unwrap()),
+        )
+        .unwrap();
+    sources
+        .register(
+            "customer-routes",
+            Arc::new(irr.route_set("RS-NESTED").unwrap()),
+        )
+        .unwrap();
+
+    let runtime =
+        Runtime::from_string_with_sources(SOURCE_CODE, sources).unwrap();
+    let mut filter = runtime.filter("customer-filter").unwrap();
+    filter.bind_context(context());
+
+    assert!(is_accepted(&mut filter, "192.0.2.0/25", 65535));
+    assert!(is_accepted(&mut filter, "2001:db8::/32", 65537));
+    assert!(!is_accepted(&mut filter, "192.0.2.0/24", 65534));
+    assert!(!is_accepted(&mut filter, "198.51.100.0/25", 65537));
+}
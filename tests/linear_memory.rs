@@ -0,0 +1,100 @@
+use roto::ast::AcceptReject;
+
+use roto::blocks::Scope::FilterMap;
+use roto::compiler::CompiledFilter;
+use roto::pipeline;
+use roto::types::collections::Record;
+use roto::types::typedef::TypeDef;
+
+use inetnum::asn::Asn;
+
+mod common;
+
+use common::context;
+
+fn src_code(vars: usize) -> String {
+    let defines = (0..vars)
+        .map(|i| format!("v{} = AS{};", i, i + 1))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let matches = (0..vars)
+        .map(|i| format!("msg.asn != v{};", i))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!(
+        r###"
+        filter-map my-filter-map {{
+            define {{
+                rx_tx msg: BmpMsg;
+                {}
+            }}
+
+            term asn-not-in-vars {{
+                match {{
+                    {}
+                }}
+            }}
+
+            apply {{
+                filter match asn-not-in-vars matching {{ return accept; }};
+                return reject;
+            }}
+        }}
+
+        type BmpMsg {{
+            asn: Asn
+        }}
+    "###,
+        defines, matches
+    )
+}
+
+fn payload(asn: u32) -> Record {
+    let ty = TypeDef::new_record_type(vec![("asn", Box::new(TypeDef::Asn))])
+        .unwrap();
+
+    Record::create_instance_with_ordered_fields(
+        &ty,
+        vec![("asn", Asn::from_u32(asn).into())],
+    )
+    .unwrap()
+}
+
+fn compiled_filter(vars: usize) -> CompiledFilter {
+    let name = FilterMap("my-filter-map".into());
+    let rotolo = pipeline::run_test(&src_code(vars), None).unwrap();
+    rotolo.retrieve_compiled_filter(&name).unwrap()
+}
+
+// A tiny filter doesn't need a lot of memory.
+#[test]
+fn test_mem_size_1() {
+    common::init();
+
+    let filter = compiled_filter(1);
+    assert!(filter.get_mem_size() >= 3);
+    assert!(filter.get_mem_size() < 512);
+
+    let mut ctx = filter.new_execution_context(context()).unwrap();
+    let res = ctx.exec(payload(65534), None::<Record>).unwrap();
+    assert_eq!(res.accept_reject, AcceptReject::Accept);
+
+    let res = ctx.exec(payload(1), None::<Record>).unwrap();
+    assert_eq!(res.accept_reject, AcceptReject::Reject);
+}
+
+// A filter with more variables than the old fixed size of the memory.
+#[test]
+fn test_mem_size_2() {
+    common::init();
+
+    let filter = compiled_filter(600);
+
+    let mut ctx = filter.new_execution_context(context()).unwrap();
+    let res = ctx.exec(payload(65534), None::<Record>).unwrap();
+    assert_eq!(res.accept_reject, AcceptReject::Accept);
+
+    let res = ctx.exec(payload(600), None::<Record>).unwrap();
+    assert_eq!(res.accept_reject, AcceptReject::Reject);
+}
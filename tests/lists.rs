@@ -1,13 +1,11 @@
 use log::trace;
 use roto::ast::AcceptReject;
 
-use roto::blocks::Scope::{self, FilterMap};
 use roto::types::builtin::{NlriStatus, PeerId, PeerRibType, Provenance, RouteContext};
-use roto::pipeline;
 use roto::types::collections::Record;
 use roto::types::typedef::TypeDef;
 use roto::types::typevalue::TypeValue;
-use roto::vm::{self, VmResult};
+use roto::runtime::{Runtime, Verdict};
 
 use rotonda_store::prelude::MergeUpdate;
 
@@ -86,13 +84,13 @@ fn src_code(code_line: &str, end_accept_reject: &str) -> String {
 }
 
 fn test_data(
-    name: Scope,
+    name: &str,
     source_code: &str,
-) -> Result<VmResult, Box<dyn std::error::Error>> {
+) -> Result<Verdict, Box<dyn std::error::Error>> {
     trace!("Evaluate filter-map {}...", name);
 
-    let rotolo = pipeline::run_test(source_code, None)?;
-    let roto_pack = rotolo.retrieve_pack_as_refs(&name)?;
+    let runtime = Runtime::from_string(source_code)?;
+    let mut filter = runtime.filter(name)?;
     let asn: TypeValue = Asn::from_u32(211321).into();
 
     println!("ASN {:?}", asn);
@@ -109,16 +107,10 @@ fn test_data(
     )
     .unwrap();
 
-    let mem = &mut vm::LinearMemory::uninit();
-
     println!("Used Arguments");
-    println!("{:#?}", &roto_pack.arguments);
+    println!("{:#?}", filter.compiled().get_arguments());
     println!("Used Data Sources");
-    println!("{:#?}", &roto_pack.data_sources);
-
-    for mb in roto_pack.get_mir().iter() {
-        println!("{}", mb);
-    }
+    println!("{:#?}", filter.compiled().get_data_sources());
 
     let peer_ip = "192.0.2.0".parse().unwrap();
 
@@ -133,13 +125,9 @@ fn test_data(
 
     let context = RouteContext::new(None, NlriStatus::InConvergence, provenance);
 
-    let mut vm = vm::VmBuilder::new()
-        .with_context(context)
-        .with_data_sources(roto_pack.data_sources)
-        .with_mir_code(roto_pack.mir)
-        .build()?;
+    filter.bind_context(context);
 
-    let res = vm.exec(my_payload, None::<Record>, None, mem).unwrap();
+    let res = filter.run(my_payload).unwrap();
 
     println!("\nRESULT");
     println!("action: {}", res.accept_reject);
@@ -153,9 +141,9 @@ fn test_data(
 fn test_list_compare_1() {
     common::init();
     let src_line = src_code("msg.type in [2,3,4,5]; // Peer Down", "reject");
-    let test_run = test_data(FilterMap("in-filter-map".into()), &src_line);
+    let test_run = test_data("in-filter-map", &src_line);
 
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Accept);
 }
 
@@ -164,9 +152,9 @@ fn test_list_compare_2() {
     common::init();
     let src_line =
         src_code("msg.type in [1,2,3,4,5]; // Peer Down", "reject");
-    let test_run = test_data(FilterMap("in-filter-map".into()), &src_line);
+    let test_run = test_data("in-filter-map", &src_line);
 
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Reject);
 }
 
@@ -175,9 +163,9 @@ fn test_list_compare_3() {
     common::init();
     let src_line =
         src_code("msg.type in [1,2,3,4,5]; // Peer Down", "reject");
-    let test_run = test_data(FilterMap("in-filter-map".into()), &src_line);
+    let test_run = test_data("in-filter-map", &src_line);
 
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Reject);
 }
 
@@ -186,9 +174,9 @@ fn test_list_compare_4() {
     common::init();
     let src_line =
         src_code("msg.type in [2,3,4,5,1,9]; // Peer Down", "reject");
-    let test_run = test_data(FilterMap("in-filter-map".into()), &src_line);
+    let test_run = test_data("in-filter-map", &src_line);
 
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Reject);
 }
 
@@ -197,7 +185,7 @@ fn test_list_compare_5() {
     common::init();
     let src_line =
         src_code(r#""stringetje" in [2,3,4,5,1]; // Peer Down"#, "reject");
-    let test_run = test_data(FilterMap("in-filter-map".into()), &src_line);
+    let test_run = test_data("in-filter-map", &src_line);
 
     test_run.unwrap_err();
 }
@@ -207,8 +195,8 @@ fn test_list_compare_6() {
     common::init();
     let src_line =
         src_code(r#"msg.type not in [2,3,4,5,1]; // Peer Down"#, "reject");
-    let test_run = test_data(FilterMap("in-filter-map".into()), &src_line);
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let test_run = test_data("in-filter-map", &src_line);
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Reject);
 }
 
@@ -216,8 +204,8 @@ fn test_list_compare_6() {
 fn test_list_compare_7() {
     common::init();
     let src_line = src_code(r#"a in [2,3,4,5,1]; // Peer Down"#, "reject");
-    let test_run = test_data(FilterMap("in-filter-map".into()), &src_line);
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let test_run = test_data("in-filter-map", &src_line);
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Accept);
 }
 
@@ -225,8 +213,8 @@ fn test_list_compare_7() {
 fn test_list_compare_8() {
     common::init();
     let src_line = src_code(r#"100 in [2,3,4,5,1]; // Peer Down"#, "reject");
-    let test_run = test_data(FilterMap("in-filter-map".into()), &src_line);
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let test_run = test_data("in-filter-map", &src_line);
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Accept);
 }
 
@@ -234,8 +222,8 @@ fn test_list_compare_8() {
 fn test_list_compare_9() {
     common::init();
     let src_line = src_code(r#"100 in b; // Peer Down"#, "reject");
-    let test_run = test_data(FilterMap("in-filter-map".into()), &src_line);
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let test_run = test_data("in-filter-map", &src_line);
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Accept);
 }
 
@@ -243,8 +231,8 @@ fn test_list_compare_9() {
 fn test_list_compare_10() {
     common::init();
     let src_line = src_code(r#"100 in [2,3,4,a]; // Peer Down"#, "reject");
-    let test_run = test_data(FilterMap("in-filter-map".into()), &src_line);
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let test_run = test_data("in-filter-map", &src_line);
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Reject);
 }
 
@@ -252,7 +240,7 @@ fn test_list_compare_10() {
 fn test_list_compare_11() {
     common::init();
     let src_line = src_code(r#"100 in [2,3,4,c]; // Peer Down"#, "reject");
-    let test_run = test_data(FilterMap("in-filter-map".into()), &src_line);
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let test_run = test_data("in-filter-map", &src_line);
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Accept);
 }
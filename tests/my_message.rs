@@ -1,12 +1,10 @@
 use log::trace;
 
-use roto::blocks::Scope;
 use roto::types::builtin::{NlriStatus, PeerId, PeerRibType, Provenance, RouteContext};
-use roto::pipeline;
 use roto::types::collections::{ElementTypeValue, List, Record};
 use roto::types::typedef::TypeDef;
 use roto::types::typevalue::TypeValue;
-use roto::vm::{self, VmResult};
+use roto::runtime::{Runtime, Verdict};
 
 use routecore::bgp::communities::HumanReadableCommunity as Community;
 use inetnum::asn::Asn;
@@ -14,17 +12,17 @@ use inetnum::asn::Asn;
 mod common;
 
 fn test_data(
-    name: Scope,
+    name: &str,
     source_code: &'static str,
-) -> Result<VmResult, Box<dyn std::error::Error>> {
+) -> Result<Verdict, Box<dyn std::error::Error>> {
     println!("Evaluate filter-map {}...", name);
 
     let filter_map_arguments =
         vec![("my_asn", TypeValue::from(Asn::from(65534_u32)))];
 
-    let rotolo =
-        pipeline::run_test(source_code, Some((&name, filter_map_arguments)))?;
-    let roto_pack = rotolo.retrieve_pack_as_refs(&name)?;
+    let runtime = Runtime::from_string(source_code)?;
+    let mut filter = runtime.filter(name)?;
+    filter.bind_arguments(filter_map_arguments)?;
 
     let _count: TypeValue = 1_u32.into();
     let prefix: TypeValue =
@@ -80,21 +78,13 @@ fn test_data(
     )
     .unwrap();
 
-    let mem = &mut vm::LinearMemory::uninit();
-
     println!("Used Arguments");
-    println!("{:#?}", &roto_pack.arguments);
+    println!("{:#?}", filter.compiled().get_arguments());
     println!("Used Data Sources");
-    println!("{:#?}", &roto_pack.data_sources);
-
-    for mb in roto_pack.get_mir().iter() {
-        println!("{}", mb);
-    }
+    println!("{:#?}", filter.compiled().get_data_sources());
 
     let my_payload = TypeValue::Record(my_payload);
-    assert!(roto_pack.check_rx_payload_type(&my_payload));
-
-    let ds_ref = roto_pack.data_sources;
+    assert!(*filter.compiled().get_rx_type() == my_payload);
 
     
     let peer_ip = "192.0.2.0".parse().unwrap();
@@ -111,14 +101,9 @@ fn test_data(
     let context = RouteContext::new(None, NlriStatus::InConvergence, provenance);
 
     println!("Start vm...");
-    let mut vm = vm::VmBuilder::new()
-        // .with_arguments(args)
-        .with_context(context)
-        .with_data_sources(ds_ref)
-        .with_mir_code(roto_pack.mir)
-        .build()?;
+    filter.bind_context(context);
 
-    let res = vm.exec(my_payload, None::<Record>, None, mem).unwrap();
+    let res = filter.run(my_payload).unwrap();
 
     println!("\nRESULT");
     println!("action: {}", res.accept_reject);
@@ -134,7 +119,7 @@ fn test_filter_map_message_1() {
     common::init();
 
     test_data(
-        Scope::FilterMap("my-message-filter-map-1".into()),
+        "my-message-filter-map-1",
         r#"
         filter-map my-message-filter-map-1 with my_asn: Asn {
             define {
@@ -189,7 +174,7 @@ fn test_filter_map_message_2() {
     common::init();
 
     let res = test_data(
-        Scope::FilterMap("my-message-filter-map-2".into()),
+        "my-message-filter-map-2",
         r#"
         filter-map my-message-filter-map-2 with my_asn: Asn {
             define {
@@ -244,7 +229,7 @@ fn test_filter_map_message_2() {
 fn test_filter_map_message_3() {
     common::init();
     test_data(
-        Scope::Filter("my-message-filter-map-3".into()),
+        "my-message-filter-map-3",
         r#"
         filter my-message-filter-map-3 with my_asn: Asn {
             define {
@@ -297,7 +282,7 @@ fn test_filter_map_message_4() {
     common::init();
 
     let res = test_data(
-        Scope::Filter("my-message-filter-map-2".into()),
+        "my-message-filter-map-2",
         r#"
         filter my-message-filter-map-2 with my_asn: Asn {
             define {
@@ -352,7 +337,7 @@ fn test_filter_map_message_4() {
 fn test_filter_map_message_5() {
     common::init();
     let res = test_data(
-        Scope::Filter("my-message-filter-map-5".into()),
+        "my-message-filter-map-5",
         r#"
         filter my-message-filter-map-5 with my_asn: Asn {
             define {
@@ -412,7 +397,7 @@ fn test_filter_map_message_5() {
 fn test_filter_map_message_6() {
     common::init();
     let res = test_data(
-        Scope::Filter("my-message-filter-map-6".into()),
+        "my-message-filter-map-6",
         r#"
         filter my-message-filter-map-6 with my_asn: Asn {
             define {
@@ -0,0 +1,280 @@
+use roto::ast::AcceptReject;
+use roto::runtime::Runtime;
+use roto::types::builtin::StringLiteral;
+use roto::types::collections::Record;
+use roto::types::typedef::TypeDef;
+use roto::types::typevalue::TypeValue;
+
+use inetnum::asn::Asn;
+
+mod common;
+
+use common::context;
+
+const SOURCE_CODE: &str = r###"
+    filter-map import-sanitize {
+        define {
+            rx_tx msg: BmpMsg;
+        }
+
+        term is-private {
+            match {
+                msg.asn == AS64512;
+            }
+        }
+
+        apply {
+            filter match is-private matching {
+                return reject with "private-asn";
+            };
+            continue;
+        }
+    }
+
+    filter-map peer-policy with my_asn: Asn {
+        define {
+            rx_tx msg: BmpMsg;
+        }
+
+        term from-peer {
+            match {
+                msg.asn == my_asn;
+            }
+        }
+
+        term in-customers {
+            match {
+                customer_asns.contains(msg.asn);
+            }
+        }
+
+        apply {
+            filter match from-peer matching { return accept; };
+            filter match in-customers matching { return accept; };
+            return reject with "not-a-customer";
+        }
+    }
+
+    pipeline import {
+        import-sanitize;
+        peer-policy;
+    }
+
+    table customer_asns contains AsnLines {
+        asn: Asn
+    }
+
+    type BmpMsg {
+        asn: Asn
+    }
+"###;
+
+fn records(asns: &[u32]) -> Vec<Record> {
+    let ty = TypeDef::new_record_type(vec![("asn", Box::new(TypeDef::Asn))])
+        .unwrap();
+
+    asns.iter()
+        .map(|asn| {
+            Record::create_instance_with_ordered_fields(
+                &ty,
+                vec![("asn", Asn::from_u32(*asn).into())],
+            )
+            .unwrap()
+        })
+        .collect()
+}
+
+fn payload(asn: u32) -> Record {
+    records(&[asn]).remove(0)
+}
+
+fn reason(s: &str) -> Option<TypeValue> {
+    Some(StringLiteral::new(s.into()).into())
+}
+
+struct PeerArgs {
+    my_asn: u32,
+}
+
+roto::impl_into_roto_args!(PeerArgs { my_asn });
+
+#[test]
+fn test_pipeline_1() {
+    common::init();
+
+    let runtime = Runtime::from_string(SOURCE_CODE).unwrap();
+    let mut pipeline = runtime.pipeline("import").unwrap();
+    assert_eq!(pipeline.get_name(), "import");
+    assert_eq!(pipeline.stages().len(), 2);
+
+    pipeline.bind_table("customer_asns", records(&[65000])).unwrap();
+    pipeline
+        .bind_arguments("peer-policy", PeerArgs { my_asn: 65534 })
+        .unwrap();
+    pipeline.bind_context(context());
+
+    // Rejected by the first stage, the second one doesn't run.
+    let verdict = pipeline.run(payload(64512)).unwrap();
+    assert_eq!(verdict.accept_reject, AcceptReject::Reject);
+    assert_eq!(verdict.reason, reason("private-asn"));
+
+    // Passed on by the first stage, accepted by the second one.
+    let verdict = pipeline.run(payload(65000)).unwrap();
+    assert_eq!(verdict.accept_reject, AcceptReject::Accept);
+    assert!(pipeline.run(payload(65534)).unwrap().is_accepted());
+
+    // Passed on by the first stage, rejected by the second one.
+    let verdict = pipeline.run(payload(65001)).unwrap();
+    assert_eq!(verdict.accept_reject, AcceptReject::Reject);
+    assert_eq!(verdict.reason, reason("not-a-customer"));
+}
+
+#[test]
+fn test_pipeline_2() {
+    common::init();
+
+    let runtime = Runtime::from_string(SOURCE_CODE).unwrap();
+    assert!(runtime.pipeline("export").is_err());
+
+    let mut pipeline = runtime.pipeline("import").unwrap();
+    assert!(pipeline.bind_table("peer_asns", records(&[1])).is_err());
+    assert!(pipeline
+        .bind_arguments("rpki", PeerArgs { my_asn: 65534 })
+        .is_err());
+}
+
+#[test]
+fn test_pipeline_split_rx_tx() {
+    common::init();
+
+    // The tx of `to-route` can't be created by the pipeline, so `on-route`
+    // would be run with a BmpMsg as its Announcement.
+    let source = r###"
+        filter-map to-route {
+            define {
+                rx msg: BmpMsg;
+                tx out: Announcement;
+            }
+
+            apply {
+                accept;
+            }
+        }
+
+        filter-map on-route {
+            define {
+                rx_tx route: Announcement;
+            }
+
+            term is-private {
+                match {
+                    route.origin == AS64512;
+                }
+            }
+
+            apply {
+                filter match is-private matching { return reject; };
+                accept;
+            }
+        }
+
+        pipeline convert {
+            to-route;
+            on-route;
+        }
+
+        type BmpMsg {
+            asn: Asn
+        }
+
+        type Announcement {
+            origin: Asn
+        }
+    "###;
+
+    let runtime = Runtime::from_string(source).unwrap();
+    assert!(runtime.filter("to-route").is_ok());
+    assert!(runtime.pipeline("convert").is_err());
+}
+
+const SHARED_SOURCE_CODE: &str = r###"
+    filter-map first-stage {
+        define {
+            rx_tx msg: BmpMsg;
+        }
+
+        term updated {
+            match {
+                customer_asns.version() > 1;
+            }
+        }
+
+        apply {
+            filter match updated matching {
+                return reject with "updated";
+            };
+            continue;
+        }
+    }
+
+    filter-map second-stage {
+        define {
+            rx_tx msg: BmpMsg;
+        }
+
+        term first-version {
+            match {
+                customer_asns.version() == 1;
+                customer_asns.contains(msg.asn);
+            }
+        }
+
+        apply {
+            filter match first-version matching {
+                return accept with "first-version";
+            };
+            return reject;
+        }
+    }
+
+    pipeline shared {
+        first-stage;
+        second-stage;
+    }
+
+    table customer_asns contains AsnLines {
+        asn: Asn
+    }
+
+    type BmpMsg {
+        asn: Asn
+    }
+"###;
+
+#[test]
+fn test_pipeline_shared_source() {
+    common::init();
+
+    let runtime = Runtime::from_string(SHARED_SOURCE_CODE).unwrap();
+    let mut pipeline = runtime.pipeline("shared").unwrap();
+    pipeline.bind_context(context());
+
+    // One update is one version for all the stages.
+    let customer_asns = pipeline.data_source("customer_asns").unwrap();
+    assert_eq!(customer_asns.insert_records(records(&[65000])).unwrap(), 1);
+    for stage in pipeline.stages() {
+        let handle = stage.data_source("customer_asns").unwrap();
+        assert_eq!(handle.version(), 1);
+    }
+
+    let verdict = pipeline.run(payload(65000)).unwrap();
+    assert_eq!(verdict.accept_reject, AcceptReject::Accept);
+    assert_eq!(verdict.reason, reason("first-version"));
+
+    // Binding the source is an update of all the stages as well.
+    pipeline.bind_table("customer_asns", records(&[65001])).unwrap();
+    assert_eq!(customer_asns.version(), 2);
+    let verdict = pipeline.run(payload(65001)).unwrap();
+    assert_eq!(verdict.accept_reject, AcceptReject::Reject);
+    assert_eq!(verdict.reason, reason("updated"));
+}
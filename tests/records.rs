@@ -1,13 +1,11 @@
 use log::trace;
 use roto::ast::AcceptReject;
 
-use roto::blocks::Scope::{self, FilterMap};
 use roto::types::builtin::{NlriStatus, PeerId, PeerRibType, Provenance, RouteContext};
-use roto::pipeline;
 use roto::types::collections::Record;
 use roto::types::typedef::TypeDef;
 use roto::types::typevalue::TypeValue;
-use roto::vm::{self, VmResult};
+use roto::runtime::{Runtime, Verdict};
 
 use rotonda_store::prelude::MergeUpdate;
 
@@ -106,13 +104,13 @@ fn src_code(
 }
 
 fn test_data(
-    name: Scope,
+    name: &str,
     source_code: &str,
-) -> Result<VmResult, Box<dyn std::error::Error>> {
+) -> Result<Verdict, Box<dyn std::error::Error>> {
     trace!("Evaluate filter-map {}...", name);
 
-    let rotolo = pipeline::run_test(source_code, None)?;
-    let roto_pack = rotolo.retrieve_pack_as_refs(&name)?;
+    let runtime = Runtime::from_string(source_code)?;
+    let mut filter = runtime.filter(name)?;
     let asn: TypeValue = Asn::from_u32(211321).into();
 
     println!("ASN {:?}", asn);
@@ -129,16 +127,10 @@ fn test_data(
     )
     .unwrap();
 
-    let mem = &mut vm::LinearMemory::uninit();
-
     println!("Used Arguments");
-    println!("{:#?}", &roto_pack.arguments);
+    println!("{:#?}", filter.compiled().get_arguments());
     println!("Used Data Sources");
-    println!("{:#?}", &roto_pack.data_sources);
-
-    for mb in roto_pack.get_mir().iter() {
-        println!("{}", mb);
-    }
+    println!("{:#?}", filter.compiled().get_data_sources());
 
     let peer_ip = "192.0.2.0".parse().unwrap();
 
@@ -153,13 +145,9 @@ fn test_data(
 
     let context = RouteContext::new(None, NlriStatus::InConvergence, provenance);
 
-    let mut vm = vm::VmBuilder::new()
-        .with_context(context)
-        .with_data_sources(roto_pack.data_sources)
-        .with_mir_code(roto_pack.mir)
-        .build()?;
+    filter.bind_context(context);
 
-    let res = vm.exec(my_payload, None::<Record>, None, mem).unwrap();
+    let res = filter.run(my_payload).unwrap();
 
     println!("\nRESULT");
     println!("action: {}", res.accept_reject);
@@ -178,9 +166,9 @@ fn test_records_compare_1() {
         "100 in [a.i,2,3,4,5]; // Peer Down",
         "reject",
     );
-    let test_run = test_data(FilterMap("in-filter-map".into()), &src_line);
+    let test_run = test_data("in-filter-map", &src_line);
 
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Accept);
 }
 
@@ -192,7 +180,7 @@ fn test_records_compare_2() {
         "100 in [a.i,2,3,4,5]; // Peer Down",
         "reject",
     );
-    test_data(FilterMap("in-filter-map".into()), &src_line).unwrap_err();
+    test_data("in-filter-map", &src_line).unwrap_err();
 }
 
 #[test]
@@ -203,9 +191,9 @@ fn test_records_compare_3() {
         "100 in [a.i, 2,3,4,5]; // Peer Down",
         "reject",
     );
-    let test_run = test_data(FilterMap("in-filter-map".into()), &src_line);
+    let test_run = test_data("in-filter-map", &src_line);
 
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Accept);
 }
 
@@ -217,7 +205,7 @@ fn test_records_compare_4() {
         "100 in [a.i, 2,3,4,5]; // Peer Down",
         "reject",
     );
-    test_data(FilterMap("in-filter-map".into()), &src_line).unwrap_err();
+    test_data("in-filter-map", &src_line).unwrap_err();
 }
 
 #[test]
@@ -228,7 +216,7 @@ fn test_records_compare_5() {
         "100 in [a.i, 2,3,4,5]; // Peer Down",
         "reject",
     );
-    test_data(FilterMap("in-filter-map".into()), &src_line).unwrap_err();
+    test_data("in-filter-map", &src_line).unwrap_err();
 }
 
 #[test]
@@ -239,7 +227,7 @@ fn test_records_compare_6() {
         "100 in [a.i, 2,3,4,5]; // Peer Down",
         "reject",
     );
-    test_data(FilterMap("in-filter-map".into()), &src_line).unwrap_err();
+    test_data("in-filter-map", &src_line).unwrap_err();
 }
 
 #[test]
@@ -250,7 +238,7 @@ fn test_records_compare_7() {
         "100 in [a.i, 2,3,4,5]; // Peer Down",
         "reject",
     );
-    test_data(FilterMap("in-filter-map".into()), &src_line).unwrap_err();
+    test_data("in-filter-map", &src_line).unwrap_err();
 }
 
 #[test]
@@ -261,7 +249,7 @@ fn test_records_compare_8() {
         "100 in [a.i, 2,3,4,5]; // Peer Down",
         "reject",
     );
-    test_data(FilterMap("in-filter-map".into()), &src_line).unwrap_err();
+    test_data("in-filter-map", &src_line).unwrap_err();
 }
 
 #[test]
@@ -272,7 +260,7 @@ fn test_records_compare_9() {
         "100 in [a.i,2,3,4,5]; // Peer Down",
         "reject",
     );
-    test_data(FilterMap("in-filter-map".into()), &src_line).unwrap_err();
+    test_data("in-filter-map", &src_line).unwrap_err();
 }
 
 #[test]
@@ -283,7 +271,7 @@ fn test_records_compare_10() {
         "100 in [1,2,3,4,5]; // Peer Down",
         "reject",
     );
-    test_data(FilterMap("in-filter-map".into()), &src_line).unwrap_err();
+    test_data("in-filter-map", &src_line).unwrap_err();
 }
 
 #[test]
@@ -294,9 +282,9 @@ fn test_records_compare_11() {
         "100 in [a.i.f,2,3,4,5]; // Peer Down",
         "reject",
     );
-    let test_run = test_data(FilterMap("in-filter-map".into()), &src_line);
+    let test_run = test_data("in-filter-map", &src_line);
 
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Accept);
 }
 
@@ -308,8 +296,8 @@ fn test_records_compare_12() {
         "100 in [a.i.h.k,2,3,4,5]; // Peer Down",
         "reject",
     );
-    let test_run = test_data(FilterMap("in-filter-map".into()), &src_line);
+    let test_run = test_data("in-filter-map", &src_line);
 
-    let VmResult { accept_reject, .. } = test_run.unwrap();
+    let Verdict { accept_reject, .. } = test_run.unwrap();
     assert_eq!(accept_reject, AcceptReject::Reject);
 }
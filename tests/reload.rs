@@ -0,0 +1,184 @@
+use std::sync::Arc;
+
+use roto::ast::AcceptReject;
+
+use roto::blocks::Scope::FilterMap;
+use roto::pipeline::{self, ReloadableFilter, ReloadingExecutionContext};
+use roto::types::collections::Record;
+use roto::types::datasources::DataSource;
+use roto::types::typedef::TypeDef;
+use roto::types::typevalue::TypeValue;
+
+use inetnum::asn::Asn;
+
+mod common;
+
+use common::context;
+
+fn src_code(msg_type: &str, on_match: &str, otherwise: &str) -> String {
+    format!(
+        r###"
+        filter-map in-filter-map {{
+            define {{
+                rx_tx msg: BmpMsg;
+            }}
+
+            term in-source-asns {{
+                match {{
+                    source_asns.contains(msg.asn);
+                }}
+            }}
+
+            apply {{
+                filter match in-source-asns matching {{ return {}; }};
+                return {};
+            }}
+        }}
+
+        table source_asns contains AsnLines {{
+            asn: Asn
+        }}
+
+        type BmpMsg {{
+            type: {},
+            asn: Asn
+        }}
+    "###,
+        on_match, otherwise, msg_type
+    )
+}
+
+fn payload(asn: u32) -> Record {
+    let ty = TypeDef::new_record_type(vec![
+        ("type", Box::new(TypeDef::U8)),
+        ("asn", Box::new(TypeDef::Asn)),
+    ])
+    .unwrap();
+
+    Record::create_instance_with_ordered_fields(
+        &ty,
+        vec![
+            ("type", TypeValue::from(1_u8)),
+            ("asn", Asn::from_u32(asn).into()),
+        ],
+    )
+    .unwrap()
+}
+
+/// A record of the `source_asns` table.
+fn source_asn(asn: u32) -> Record {
+    let ty = TypeDef::new_record_type(vec![("asn", Box::new(TypeDef::Asn))])
+        .unwrap();
+    Record::create_instance_with_ordered_fields(
+        &ty,
+        vec![("asn", Asn::from_u32(asn).into())],
+    )
+    .unwrap()
+}
+
+fn reloadable_filter() -> Arc<ReloadableFilter> {
+    let src = src_code("U8", "accept", "reject");
+    let rotolo = pipeline::run_test(&src, None).unwrap();
+    let filter = rotolo
+        .retrieve_compiled_filter(&FilterMap("in-filter-map".into()))
+        .unwrap();
+
+    filter
+        .set_source(
+            DataSource::table_from_records(
+                "source_asns",
+                vec![source_asn(211321)],
+            )
+            .unwrap(),
+        )
+        .unwrap();
+
+    Arc::new(ReloadableFilter::new(filter))
+}
+
+#[test]
+fn test_reload_1() {
+    common::init();
+    let handle = reloadable_filter();
+    let mut ctx =
+        ReloadingExecutionContext::new(Arc::clone(&handle), context())
+            .unwrap();
+
+    let res = ctx.exec(payload(211321), None::<Record>).unwrap();
+    assert_eq!(res.accept_reject, AcceptReject::Accept);
+
+    // Invert the outcome, the bound table should be carried over.
+    let replaced = handle
+        .reload_string(src_code("U8", "reject", "accept"))
+        .unwrap();
+    assert!(replaced);
+
+    let res = ctx.exec(payload(211321), None::<Record>).unwrap();
+    assert_eq!(res.accept_reject, AcceptReject::Reject);
+    let res = ctx.exec(payload(65000), None::<Record>).unwrap();
+    assert_eq!(res.accept_reject, AcceptReject::Accept);
+}
+
+#[test]
+fn test_reload_2() {
+    common::init();
+    let handle = reloadable_filter();
+    let cur = handle.current();
+
+    // The same source code results in the same filter.
+    let replaced = handle
+        .reload_string(src_code("U8", "accept", "reject"))
+        .unwrap();
+    assert!(!replaced);
+    assert!(Arc::ptr_eq(&cur, &handle.current()));
+}
+
+#[test]
+fn test_reload_3() {
+    common::init();
+    let handle = reloadable_filter();
+    let cur = handle.current();
+
+    // The rx type changes, that's not allowed.
+    let report = handle
+        .reload_string(src_code("U16", "accept", "reject"))
+        .unwrap_err();
+    assert!(report.to_string().contains("rx type"));
+
+    // A syntax error.
+    let report = handle
+        .reload_string(src_code("U8", "accept", "reject").replace(';', ""))
+        .unwrap_err();
+    assert!(report.to_string().contains("Parse error"));
+
+    assert!(Arc::ptr_eq(&cur, &handle.current()));
+}
+
+#[test]
+fn test_reload_4() {
+    common::init();
+    let handle = reloadable_filter();
+    let source_asns = handle.current().data_source("source_asns").unwrap();
+    let mut ctx =
+        ReloadingExecutionContext::new(Arc::clone(&handle), context())
+            .unwrap();
+
+    let replaced = handle
+        .reload_string(src_code("U8", "reject", "accept"))
+        .unwrap();
+    assert!(replaced);
+
+    // The handle of the previous version still updates the source, and
+    // the version of the source is carried over.
+    assert_eq!(source_asns.version(), 1);
+    assert_eq!(source_asns.insert_records(vec![source_asn(65000)]), Ok(2));
+    let current = handle.current().data_source("source_asns").unwrap();
+    assert_eq!(current.version(), 2);
+
+    let res = ctx.exec(payload(65000), None::<Record>).unwrap();
+    assert_eq!(res.accept_reject, AcceptReject::Reject);
+    let res = ctx.exec(payload(211321), None::<Record>).unwrap();
+    assert_eq!(res.accept_reject, AcceptReject::Reject);
+    let res = ctx.exec(payload(65001), None::<Record>).unwrap();
+    assert_eq!(res.accept_reject, AcceptReject::Accept);
+}
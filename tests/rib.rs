@@ -0,0 +1,263 @@
+use roto::runtime::Runtime;
+use roto::traits::RotoRib;
+use roto::types::builtin::StringLiteral;
+use roto::types::collections::{ElementTypeValue, List, Record};
+use roto::types::datasources::{
+    DataSource, DataSourceMethodValue, Rib, RibToken,
+};
+use roto::types::typedef::TypeDef;
+use roto::types::typevalue::TypeValue;
+use roto::unstable::StackValue;
+
+use inetnum::addr::Prefix;
+use rotonda_store::prelude::MergeUpdate;
+use rotonda_store::MultiThreadedStore;
+
+mod common;
+
+use common::{announcement, announcement_type, context};
+
+const SOURCE_CODE: &str = r###"
+    filter-map rov-filter-map {
+        define {
+            rx_tx msg: Announcement;
+        }
+
+        term is-known {
+            match {
+                rov.contains(msg.prefix);
+            }
+        }
+
+        term origin-matches {
+            match {
+                rov.longest_match(msg.prefix).origin == msg.origin;
+            }
+        }
+
+        apply {
+            filter match is-known matching { return accept; };
+            filter match origin-matches matching {
+                return accept with "covered";
+            };
+            return reject;
+        }
+    }
+
+    rib rov contains RovEntry {
+        prefix: Prefix,
+        origin: Asn
+    }
+
+    type Announcement {
+        prefix: Prefix,
+        origin: Asn
+    }
+"###;
+
+/// The records in the store are the records that are exposed to the roto
+/// script.
+#[derive(Debug, Clone)]
+struct RouteRecord(Record);
+
+impl MergeUpdate for RouteRecord {
+    type UserDataIn = ();
+    type UserDataOut = ();
+
+    fn merge_update(
+        &mut self,
+        update_record: RouteRecord,
+        _: Option<&Self::UserDataIn>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.0 = update_record.0;
+        Ok(())
+    }
+
+    fn clone_merge_update(
+        &self,
+        update_meta: &Self,
+        _: Option<&Self::UserDataIn>,
+    ) -> Result<(Self, Self::UserDataOut), Box<dyn std::error::Error>>
+    where
+        Self: std::marker::Sized,
+    {
+        Ok((update_meta.clone(), ()))
+    }
+}
+
+impl std::fmt::Display for RouteRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<RouteRecord> for TypeValue {
+    fn from(value: RouteRecord) -> Self {
+        TypeValue::Record(value.0)
+    }
+}
+
+fn rib(routes: &[(&str, u32)]) -> Rib<RouteRecord> {
+    let store = MultiThreadedStore::<RouteRecord>::new().unwrap();
+    for (prefix, origin) in routes {
+        store
+            .insert(
+                &prefix.parse().unwrap(),
+                RouteRecord(announcement(prefix, *origin)),
+                None,
+            )
+            .unwrap();
+    }
+
+    Rib::new("rov", announcement_type(), store)
+}
+
+#[test]
+fn test_rib_lookups() {
+    common::init();
+
+    let rib = rib(&[("192.0.2.0/24", 65534), ("198.51.100.0/22", 65535)]);
+    assert_eq!(rib.len(), 2);
+    assert!(!rib.is_empty());
+
+    // Only the exact prefix is found by key.
+    assert_eq!(
+        rib.get_by_key("192.0.2.0/24"),
+        Some(TypeValue::Record(announcement("192.0.2.0/24", 65534)))
+    );
+    assert_eq!(rib.get_by_key("192.0.2.0/25"), None);
+    assert_eq!(rib.get_by_key("203.0.113.0/24"), None);
+    assert_eq!(rib.get_by_key("not a prefix"), None);
+}
+
+/// Call a method on the rib, the way the VM does.
+fn call(rib: &Rib<RouteRecord>, token: RibToken, prefix: &str) -> TypeValue {
+    let prefix: Prefix = prefix.parse().unwrap();
+    let args = [StackValue::Owned(prefix.into())];
+
+    match rib
+        .exec_ref_value_method(token.into(), &args, TypeDef::Unknown)
+        .unwrap()
+    {
+        DataSourceMethodValue::TypeValue(tv) => tv,
+        _ => panic!("expected a value"),
+    }
+}
+
+fn len(value: TypeValue) -> usize {
+    match value {
+        TypeValue::List(list) => list.len(),
+        value => panic!("expected a list, got {}", value),
+    }
+}
+
+#[test]
+fn test_rib_specifics() {
+    common::init();
+
+    let rib = rib(&[
+        ("10.0.0.0/8", 65534),
+        ("10.1.0.0/16", 65534),
+        ("10.1.1.0/24", 65535),
+        ("10.1.2.0/24", 65535),
+        ("192.0.2.0/24", 65534),
+    ]);
+
+    // The prefix itself is not one of its more or less specifics.
+    assert_eq!(len(call(&rib, RibToken::MoreSpecifics, "10.1.0.0/16")), 2);
+    assert_eq!(len(call(&rib, RibToken::LessSpecifics, "10.1.0.0/16")), 1);
+    assert_eq!(len(call(&rib, RibToken::MoreSpecifics, "10.0.0.0/8")), 3);
+    assert_eq!(len(call(&rib, RibToken::LessSpecifics, "10.1.1.0/24")), 2);
+
+    // Nothing more or less specific.
+    assert_eq!(len(call(&rib, RibToken::MoreSpecifics, "192.0.2.0/24")), 0);
+    assert_eq!(len(call(&rib, RibToken::LessSpecifics, "10.0.0.0/8")), 0);
+
+    // The records of the matching prefixes are returned.
+    assert_eq!(
+        call(&rib, RibToken::LessSpecifics, "10.1.0.0/16"),
+        TypeValue::List(List::new(vec![ElementTypeValue::Primitive(
+            TypeValue::Record(announcement("10.0.0.0/8", 65534))
+        )]))
+    );
+}
+
+#[test]
+fn test_rib_matches() {
+    common::init();
+
+    let rib = rib(&[
+        ("10.0.0.0/8", 65534),
+        ("10.1.0.0/16", 65534),
+        ("10.1.1.0/24", 65535),
+    ]);
+    let record = |p, origin| TypeValue::Record(announcement(p, origin));
+
+    // The longest match is the record of the most specific covering
+    // prefix, not the prefix itself.
+    assert_eq!(
+        call(&rib, RibToken::LongestMatch, "10.1.1.128/25"),
+        record("10.1.1.0/24", 65535)
+    );
+    assert_eq!(
+        call(&rib, RibToken::LongestMatch, "10.1.2.0/24"),
+        record("10.1.0.0/16", 65534)
+    );
+    assert_eq!(
+        call(&rib, RibToken::LongestMatch, "192.0.2.0/24"),
+        TypeValue::Unknown
+    );
+
+    // `get` only finds an exact match.
+    assert_eq!(
+        call(&rib, RibToken::Get, "10.1.1.0/24"),
+        record("10.1.1.0/24", 65535)
+    );
+    assert_eq!(call(&rib, RibToken::Get, "10.1.2.0/24"), TypeValue::Unknown);
+
+    // `match` finds all of them, starting with the exact match.
+    assert_eq!(
+        call(&rib, RibToken::Match, "10.1.0.0/16"),
+        TypeValue::List(List::new(vec![
+            ElementTypeValue::Primitive(record("10.1.0.0/16", 65534)),
+            ElementTypeValue::Primitive(record("10.0.0.0/8", 65534)),
+        ]))
+    );
+    assert_eq!(len(call(&rib, RibToken::Match, "10.1.1.0/24")), 3);
+    assert_eq!(len(call(&rib, RibToken::Match, "10.1.2.0/24")), 2);
+    assert_eq!(len(call(&rib, RibToken::Match, "192.0.2.0/24")), 0);
+}
+
+#[test]
+fn test_rib_in_filter_map() {
+    common::init();
+
+    let runtime = Runtime::from_string(SOURCE_CODE).unwrap();
+    let mut filter = runtime.filter("rov-filter-map").unwrap();
+    filter.bind_context(context());
+    filter
+        .bind_source(DataSource::from(rib(&[
+            ("192.0.2.0/24", 65534),
+            ("198.51.100.0/22", 65535),
+        ])))
+        .unwrap();
+
+    // An exact match.
+    let verdict = filter.run(announcement("192.0.2.0/24", 1)).unwrap();
+    assert!(verdict.is_accepted());
+    assert_eq!(verdict.reason, None);
+
+    // A more specific of a prefix in the rib with the same origin.
+    let verdict = filter.run(announcement("198.51.100.0/24", 65535)).unwrap();
+    assert!(verdict.is_accepted());
+    assert_eq!(
+        verdict.reason,
+        Some(StringLiteral::new("covered".into()).into())
+    );
+
+    // A more specific with a different origin.
+    assert!(filter
+        .run(announcement("198.51.100.0/24", 1))
+        .unwrap()
+        .is_rejected());
+}
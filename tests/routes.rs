@@ -4,16 +4,15 @@ use std::str::FromStr;
 use log::trace;
 use roto::ast::AcceptReject;
 
-use roto::blocks::Scope::{self, Filter};
-use roto::pipeline;
 use roto::types::builtin::basic_route::{
     BasicRouteToken, PeerId, PeerRibType, Provenance,
 };
 use roto::types::builtin::{explode_announcements, BuiltinTypeValue, NlriStatus, RouteContext};
-use roto::types::collections::{BytesRecord, Record};
+use roto::types::collections::BytesRecord;
 use roto::types::lazyrecord_types::BgpUpdateMessage;
 use roto::types::typevalue::TypeValue;
-use roto::vm::{self, FieldIndex, VmResult};
+use roto::runtime::{Runtime, Verdict};
+use roto::vm::FieldIndex;
 use inetnum::addr::Prefix;
 use inetnum::asn::Asn;
 use routecore::bgp::message::update_builder::UpdateBuilder;
@@ -26,13 +25,13 @@ use routecore::bgp::workshop::route::RouteWorkshop;
 mod common;
 
 fn test_data(
-    name: Scope,
+    name: &str,
     source_code: &str,
-) -> Result<(VmResult, TypeValue, TypeValue), Box<dyn std::error::Error>> {
+) -> Result<(Verdict, TypeValue, TypeValue), Box<dyn std::error::Error>> {
     trace!("Evaluate filter-map {}...", name);
 
-    let rotolo = pipeline::run_test(source_code, None)?;
-    let roto_pack = rotolo.retrieve_pack_as_refs(&name)?;
+    let runtime = Runtime::from_string(source_code)?;
+    let mut filter = runtime.filter(name)?;
 
     // BGP UPDATE message containing MP_REACH_NLRI path attribute,
     // comprising 5 IPv6 NLRIs
@@ -135,24 +134,14 @@ fn test_data(
 
     trace!("peer_ip {:?}", context.provenance().peer_ip());
 
-    let mem = &mut vm::LinearMemory::uninit();
-
     println!("Used Arguments");
-    println!("{:#?}", &roto_pack.arguments);
+    println!("{:#?}", filter.compiled().get_arguments());
     println!("Used Data Sources");
-    println!("{:#?}", &roto_pack.data_sources);
-
-    for mb in roto_pack.get_mir().iter() {
-        println!("{}", mb);
-    }
+    println!("{:#?}", filter.compiled().get_data_sources());
 
-    let mut vm = vm::VmBuilder::new()
-        .with_data_sources(roto_pack.data_sources)
-        .with_mir_code(roto_pack.mir)
-        .with_context(context)
-        .build()?;
+    filter.bind_context(context.clone());
 
-    let res = vm.exec(payload.clone(), None::<Record>, None, mem).unwrap();
+    let res = filter.run(payload.clone()).unwrap();
 
     println!("\nRESULT");
     println!("action: {}", res.accept_reject);
@@ -203,10 +192,10 @@ fn test_routes_1() {
         }
     "#;
 
-    let test_run = test_data(Filter("rib-in-pre-filter".into()), src);
+    let test_run = test_data("rib-in-pre-filter", src);
 
     let (
-        VmResult {
+        Verdict {
             accept_reject,
             output_stream_queue,
             ..
@@ -275,10 +264,10 @@ fn test_routes_2() {
         }
     "#;
 
-    let test_run = test_data(Filter("rib-in-pre-filter".into()), src);
+    let test_run = test_data("rib-in-pre-filter", src);
 
     let (
-        VmResult {
+        Verdict {
             accept_reject,
             output_stream_queue,
             ..
@@ -345,10 +334,10 @@ fn test_routes_3() {
         }
     "#;
 
-    let test_run = test_data(Filter("rib-in-pre-filter".into()), src);
+    let test_run = test_data("rib-in-pre-filter", src);
 
     let (
-        VmResult {
+        Verdict {
             accept_reject,
             output_stream_queue,
             ..
@@ -416,10 +405,10 @@ fn test_routes_4() {
         }
     "#;
 
-    let test_run = test_data(Filter("rib-in-pre-filter".into()), src);
+    let test_run = test_data("rib-in-pre-filter", src);
 
     let (
-        VmResult {
+        Verdict {
             accept_reject,
             output_stream_queue,
             ..
@@ -492,10 +481,10 @@ fn test_routes_5() {
         }
     "#;
 
-    let test_run = test_data(Filter("rib-in-pre-filter".into()), src);
+    let test_run = test_data("rib-in-pre-filter", src);
 
     let (
-        VmResult {
+        Verdict {
             accept_reject,
             output_stream_queue,
             ..
@@ -572,10 +561,10 @@ fn test_routes_6() {
         }
     "#;
 
-    let test_run = test_data(Filter("rib-in-pre-filter".into()), src);
+    let test_run = test_data("rib-in-pre-filter", src);
 
     let (
-        VmResult {
+        Verdict {
             accept_reject,
             output_stream_queue,
             rx,
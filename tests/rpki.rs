@@ -0,0 +1,354 @@
+use roto::runtime::Runtime;
+use roto::traits::RotoRib;
+use roto::types::collections::Record;
+use roto::types::datasources::DataSource;
+use roto::types::rpki::{
+    Aspa, AspaStatus, Aspas, PeerRole, RovStatus, Vrp, Vrps,
+};
+use roto::types::typedef::TypeDef;
+
+use inetnum::addr::Prefix;
+use inetnum::asn::Asn;
+use routecore::bgp::aspath::HopPath;
+
+mod common;
+
+use common::{announcement, context};
+
+const SOURCE_CODE: &str = r###"
+    filter-map rov-filter-map {
+        define {
+            rx_tx msg: Announcement;
+        }
+
+        term rov-invalid {
+            match {
+                vrps.validate(msg.prefix, msg.origin) == ROV_INVALID;
+            }
+        }
+
+        term rov-valid {
+            match {
+                vrps.validate(msg.prefix, msg.origin) == ROV_VALID;
+            }
+        }
+
+        apply {
+            filter match rov-invalid matching { return reject; };
+            filter match rov-valid matching {
+                return accept with "valid";
+            };
+            return accept;
+        }
+    }
+
+    rib vrps contains Vrp
+
+    type Announcement {
+        prefix: Prefix,
+        origin: Asn
+    }
+"###;
+
+fn vrp(prefix: &str, max_length: u8, asn: u32) -> Vrp {
+    Vrp {
+        prefix: prefix.parse().unwrap(),
+        max_length,
+        asn: Asn::from_u32(asn),
+    }
+}
+
+fn vrps() -> Vrps {
+    Vrps::new(
+        "vrps",
+        [
+            vrp("192.0.2.0/24", 24, 65534),
+            vrp("198.51.100.0/22", 24, 65535),
+            vrp("198.51.100.0/22", 22, 65536),
+            vrp("203.0.113.0/24", 24, 0),
+            vrp("2001:db8::/32", 48, 65534),
+        ],
+    )
+}
+
+fn validate(vrps: &Vrps, prefix: &str, origin: u32) -> RovStatus {
+    let prefix: Prefix = prefix.parse().unwrap();
+    vrps.validate(&prefix, Asn::from_u32(origin))
+}
+
+#[test]
+fn test_validate() {
+    common::init();
+
+    let vrps = vrps();
+    assert_eq!(vrps.len(), 5);
+
+    assert_eq!(validate(&vrps, "192.0.2.0/24", 65534), RovStatus::Valid);
+    assert_eq!(validate(&vrps, "198.51.100.0/24", 65535), RovStatus::Valid);
+    assert_eq!(validate(&vrps, "198.51.100.0/22", 65536), RovStatus::Valid);
+    assert_eq!(validate(&vrps, "2001:db8:1::/48", 65534), RovStatus::Valid);
+
+    // The wrong origin.
+    assert_eq!(validate(&vrps, "192.0.2.0/24", 65535), RovStatus::Invalid);
+
+    // Longer than the max length.
+    assert_eq!(validate(&vrps, "192.0.2.0/25", 65534), RovStatus::Invalid);
+    assert_eq!(
+        validate(&vrps, "198.51.100.0/24", 65536),
+        RovStatus::Invalid
+    );
+
+    // A VRP for AS0 never matches.
+    assert_eq!(validate(&vrps, "203.0.113.0/24", 0), RovStatus::Invalid);
+
+    // Not covered by any VRP.
+    assert_eq!(validate(&vrps, "192.0.0.0/16", 65534), RovStatus::NotFound);
+    assert_eq!(validate(&vrps, "10.0.0.0/8", 65534), RovStatus::NotFound);
+    assert_eq!(validate(&vrps, "2001:db9::/32", 65534), RovStatus::NotFound);
+}
+
+#[test]
+fn test_vrps_from_json() {
+    common::init();
+
+    let json = r#"{
+        "roas": [
+            { "asn": "AS65534", "prefix": "192.0.2.0/24", "maxLength": 24 },
+            { "asn": 65535, "prefix": "198.51.100.0/22" }
+        ]
+    }"#;
+    let vrps = Vrps::from_json("vrps", json.as_bytes()).unwrap();
+    assert_eq!(vrps.len(), 2);
+    assert_eq!(validate(&vrps, "192.0.2.0/24", 65534), RovStatus::Valid);
+    assert_eq!(validate(&vrps, "198.51.100.0/22", 65535), RovStatus::Valid);
+    assert_eq!(
+        validate(&vrps, "198.51.100.0/24", 65535),
+        RovStatus::Invalid
+    );
+
+    // The max length is shorter than the prefix.
+    let json = r#"{
+        "roas": [
+            { "asn": "AS65534", "prefix": "192.0.2.0/24", "maxLength": 24 },
+            { "asn": "AS65535", "prefix": "192.0.2.0/24", "maxLength": 16 }
+        ]
+    }"#;
+    let err = Vrps::from_json("vrps", json.as_bytes()).unwrap_err();
+    assert_eq!(err.row, 2);
+    assert_eq!(
+        err.column.as_ref().map(|c| c.to_string()),
+        Some("maxLength".into())
+    );
+
+    let err = Vrps::from_json("vrps", "[]".as_bytes()).unwrap_err();
+    assert_eq!(err.row, 0);
+}
+
+#[test]
+fn test_vrps_in_filter_map() {
+    common::init();
+
+    let runtime = Runtime::from_string(SOURCE_CODE).unwrap();
+    let mut filter = runtime.filter("rov-filter-map").unwrap();
+    filter.bind_context(context());
+    filter.bind_source(DataSource::from(vrps())).unwrap();
+
+    let verdict = filter.run(announcement("192.0.2.0/24", 65534)).unwrap();
+    assert!(verdict.is_accepted());
+    assert!(verdict.reason.is_some());
+
+    let verdict = filter.run(announcement("192.0.2.0/24", 65535)).unwrap();
+    assert!(verdict.is_rejected());
+
+    let verdict = filter.run(announcement("10.0.0.0/8", 65534)).unwrap();
+    assert!(verdict.is_accepted());
+    assert_eq!(verdict.reason, None);
+}
+
+const ASPA_SOURCE_CODE: &str = r###"
+    filter-map aspa-filter-map {
+        define {
+            rx_tx msg: Update;
+        }
+
+        term aspa-invalid {
+            match {
+                aspas.validate(msg.path, ROLE_PROVIDER) == ASPA_INVALID;
+            }
+        }
+
+        term aspa-valid {
+            match {
+                aspas.validate(msg.path, ROLE_PROVIDER) == ASPA_VALID;
+            }
+        }
+
+        apply {
+            filter match aspa-invalid matching { return reject; };
+            filter match aspa-valid matching {
+                return accept with "valid";
+            };
+            return accept;
+        }
+    }
+
+    table aspas contains Aspa
+
+    type Update {
+        path: AsPath
+    }
+"###;
+
+fn aspa(customer: u32, providers: &[u32]) -> Aspa {
+    Aspa {
+        customer: Asn::from_u32(customer),
+        providers: providers.iter().map(|p| Asn::from_u32(*p)).collect(),
+    }
+}
+
+fn aspas() -> Aspas {
+    Aspas::new(
+        "aspas",
+        [
+            aspa(65001, &[65010]),
+            aspa(65010, &[65020]),
+            aspa(65030, &[65020]),
+            aspa(65040, &[]),
+        ],
+    )
+}
+
+/// The AS path, from the neighbor to the origin.
+fn path(asns: &[u32]) -> HopPath {
+    asns.iter()
+        .map(|asn| Asn::from_u32(*asn))
+        .collect::<Vec<_>>()
+        .into()
+}
+
+#[test]
+fn test_aspa_upstream() {
+    common::init();
+
+    let aspas = aspas();
+    let validate =
+        |asns: &[u32]| aspas.validate(&path(asns), PeerRole::Customer);
+
+    assert_eq!(validate(&[65001]), AspaStatus::Valid);
+    assert_eq!(validate(&[65010, 65001]), AspaStatus::Valid);
+    assert_eq!(validate(&[65020, 65010, 65001]), AspaStatus::Valid);
+
+    // Prepends are ignored.
+    assert_eq!(validate(&[65020, 65020, 65010, 65001]), AspaStatus::Valid);
+
+    // The second AS is not a provider of the origin.
+    assert_eq!(validate(&[65099, 65001]), AspaStatus::Invalid);
+
+    // An AS without any providers.
+    assert_eq!(validate(&[65010, 65040]), AspaStatus::Invalid);
+
+    // The origin doesn't have an ASPA record.
+    assert_eq!(validate(&[65001, 65050]), AspaStatus::Unknown);
+
+    // An empty path.
+    assert_eq!(validate(&[]), AspaStatus::Invalid);
+}
+
+#[test]
+fn test_aspa_downstream() {
+    common::init();
+
+    let aspas = aspas();
+    let validate =
+        |asns: &[u32]| aspas.validate(&path(asns), PeerRole::Provider);
+
+    // Up from the origin only.
+    assert_eq!(validate(&[65020, 65010, 65001]), AspaStatus::Valid);
+
+    // Up to 65010, and over to its peer 65030, which is our provider.
+    assert_eq!(validate(&[65030, 65010, 65001]), AspaStatus::Valid);
+
+    // Down from 65030 to 65001, and then up again: a route leak.
+    assert_eq!(
+        validate(&[65020, 65010, 65001, 65030]),
+        AspaStatus::Invalid
+    );
+
+    // None of the ASes have an ASPA record.
+    assert_eq!(
+        validate(&[65080, 65070, 65060, 65050]),
+        AspaStatus::Unknown
+    );
+}
+
+#[test]
+fn test_aspas_from_json() {
+    common::init();
+
+    let json = r#"{
+        "aspas": [
+            { "customer": "AS65001", "providers": ["AS65010", 65011] },
+            { "customer_asid": 65010, "providers": [] }
+        ]
+    }"#;
+    let aspas = Aspas::from_json("aspas", json.as_bytes()).unwrap();
+    assert_eq!(aspas.len(), 2);
+
+    let role = PeerRole::Customer;
+    assert_eq!(
+        aspas.validate(&path(&[65011, 65001]), role),
+        AspaStatus::Valid
+    );
+    assert_eq!(
+        aspas.validate(&path(&[65020, 65010]), role),
+        AspaStatus::Invalid
+    );
+
+    let json = r#"{
+        "aspas": [
+            { "customer": "AS65001", "providers": ["AS65010"] },
+            { "customer": "AS65010", "providers": ["65020x"] }
+        ]
+    }"#;
+    let err = Aspas::from_json("aspas", json.as_bytes()).unwrap_err();
+    assert_eq!(err.row, 2);
+    assert_eq!(
+        err.column.as_ref().map(|c| c.to_string()),
+        Some("providers".into())
+    );
+}
+
+fn update(asns: &[u32]) -> Record {
+    let ty = TypeDef::new_record_type(vec![(
+        "path",
+        Box::new(TypeDef::AsPath),
+    )])
+    .unwrap();
+
+    Record::create_instance_with_ordered_fields(
+        &ty,
+        vec![("path", path(asns).into())],
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_aspas_in_filter_map() {
+    common::init();
+
+    let runtime = Runtime::from_string(ASPA_SOURCE_CODE).unwrap();
+    let mut filter = runtime.filter("aspa-filter-map").unwrap();
+    filter.bind_context(context());
+    filter.bind_source(DataSource::from(aspas())).unwrap();
+
+    let verdict = filter.run(update(&[65020, 65010, 65001])).unwrap();
+    assert!(verdict.is_accepted());
+    assert!(verdict.reason.is_some());
+
+    let verdict = filter.run(update(&[65020, 65010, 65001, 65030])).unwrap();
+    assert!(verdict.is_rejected());
+
+    let verdict = filter.run(update(&[65080, 65070, 65060])).unwrap();
+    assert!(verdict.is_accepted());
+    assert_eq!(verdict.reason, None);
+}
@@ -0,0 +1,158 @@
+use roto::runtime::Runtime;
+use roto::types::collections::Record;
+use roto::types::typedef::TypeDef;
+use roto::types::typevalue::TypeValue;
+use roto::vm::VmError;
+
+use inetnum::asn::Asn;
+
+mod common;
+
+use common::context;
+
+const SOURCE_CODE: &str = r###"
+    filter-map in-filter-map {
+        define {
+            rx_tx msg: BmpMsg;
+        }
+
+        term in-source-asns {
+            match {
+                source_asns.contains(msg.asn);
+            }
+        }
+
+        apply {
+            filter match in-source-asns matching { return accept; };
+            return reject;
+        }
+    }
+
+    filter-map my-filter-map with my_asn: Asn {
+        define {
+            rx_tx msg: BmpMsg;
+        }
+
+        term asn-matches {
+            match {
+                msg.asn == my_asn;
+            }
+        }
+
+        apply {
+            filter match asn-matches matching { return accept; };
+            return reject;
+        }
+    }
+
+    table source_asns contains AsnLines {
+        asn: Asn
+    }
+
+    type BmpMsg {
+        type: U8,
+        asn: Asn
+    }
+"###;
+
+fn source_asns(asns: &[u32]) -> Vec<Record> {
+    let ty = TypeDef::new_record_type(vec![("asn", Box::new(TypeDef::Asn))])
+        .unwrap();
+
+    asns.iter()
+        .map(|asn| {
+            Record::create_instance_with_ordered_fields(
+                &ty,
+                vec![("asn", Asn::from_u32(*asn).into())],
+            )
+            .unwrap()
+        })
+        .collect()
+}
+
+fn payload(asn: u32) -> Record {
+    let ty = TypeDef::new_record_type(vec![
+        ("type", Box::new(TypeDef::U8)),
+        ("asn", Box::new(TypeDef::Asn)),
+    ])
+    .unwrap();
+
+    Record::create_instance_with_ordered_fields(
+        &ty,
+        vec![
+            ("type", TypeValue::from(1_u8)),
+            ("asn", Asn::from_u32(asn).into()),
+        ],
+    )
+    .unwrap()
+}
+
+struct MyArgs {
+    my_asn: u32,
+}
+
+roto::impl_into_roto_args!(MyArgs { my_asn });
+
+// A filter-map with a table bound by name.
+#[test]
+fn test_runtime_1() {
+    common::init();
+
+    let runtime = Runtime::from_string(SOURCE_CODE).unwrap();
+    let mut filter = runtime.filter("in-filter-map").unwrap();
+    assert_eq!(filter.get_name().as_str(), "in-filter-map");
+
+    filter
+        .bind_table("source_asns", source_asns(&[300, 211321]))
+        .unwrap();
+    filter.bind_context(context());
+
+    assert!(filter.run(payload(300)).unwrap().is_accepted());
+    assert!(filter.run(payload(65000)).unwrap().is_rejected());
+
+    // Rebinding the table is picked up by the next run.
+    filter
+        .bind_table("source_asns", source_asns(&[65000]))
+        .unwrap();
+    assert!(filter.run(payload(300)).unwrap().is_rejected());
+    assert!(filter.run(payload(65000)).unwrap().is_accepted());
+
+    assert!(filter.bind_table("dst_asns", source_asns(&[1])).is_err());
+}
+
+// A filter-map with arguments bound from a Rust struct.
+#[test]
+fn test_runtime_2() {
+    common::init();
+
+    let runtime = Runtime::from_string(SOURCE_CODE).unwrap();
+    let mut filter = runtime.filter("my-filter-map").unwrap();
+    filter.bind_context(context());
+
+    filter.bind_arguments(MyArgs { my_asn: 65534 }).unwrap();
+    let verdict = filter.run(payload(65534)).unwrap();
+    assert!(verdict.is_accepted());
+    assert!(matches!(verdict.rx, TypeValue::Record(_)));
+
+    filter.bind_arguments(MyArgs { my_asn: 65535 }).unwrap();
+    assert!(filter.run(payload(65534)).unwrap().is_rejected());
+}
+
+// Errors are reported when binding or running, not silently ignored.
+#[test]
+fn test_runtime_3() {
+    common::init();
+
+    let runtime = Runtime::from_string(SOURCE_CODE).unwrap();
+    assert!(runtime.filter("no-such-filter-map").is_err());
+
+    let mut filter = runtime.filter("in-filter-map").unwrap();
+    let err = filter.run(payload(300)).unwrap_err();
+    assert!(matches!(err.kind(), VmError::IncompleteContext));
+
+    let report = filter.report(err).to_string();
+    assert!(report.contains("Runtime error"));
+
+    // A script that doesn't parse is reported against the source code.
+    assert!(Runtime::from_string("filter-map {").is_err());
+}
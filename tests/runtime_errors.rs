@@ -0,0 +1,220 @@
+use std::sync::Arc;
+
+use roto::parser::span::Span;
+use roto::pipeline::{self, DataSourceRegistry, RotoReport};
+use roto::runtime::Runtime;
+use roto::traits::RotoDataSource;
+use roto::types::collections::Record;
+use roto::types::datasources::DataSourceMethod;
+use roto::types::typedef::TypeDef;
+use roto::types::typevalue::TypeValue;
+use roto::vm::{OpCode, VmError, VmErrorContext};
+
+use inetnum::asn::Asn;
+
+mod common;
+
+use common::context;
+
+const SOURCE_CODE: &str = r###"
+    filter-map my-filter-map {
+        define {
+            rx_tx msg: BmpMsg;
+        }
+
+        term asn-matches {
+            match {
+                msg.asn == AS65534;
+            }
+        }
+
+        apply {
+            filter match asn-matches matching { return accept; };
+            return reject;
+        }
+    }
+
+    type BmpMsg {
+        asn: Asn
+    }
+"###;
+
+fn error_in_command() -> VmError {
+    let start = SOURCE_CODE.find("msg.asn").unwrap();
+
+    VmError::InCommand(Box::new(VmErrorContext {
+        error: VmError::InvalidFieldAccess,
+        op: OpCode::Cmp,
+        block: 1,
+        pc: 3,
+        name: Some("asn".into()),
+        section: Some("asn-matches".into()),
+        span: Some(Span::new(0, start..start + 18)),
+    }))
+}
+
+#[test]
+fn test_runtime_error_display() {
+    common::init();
+
+    let err = error_in_command();
+    assert!(matches!(err.kind(), VmError::InvalidFieldAccess));
+    assert_eq!(err.context().map(|ctx| (ctx.block, ctx.pc)), Some((1, 3)));
+
+    let msg = err.to_string();
+    assert!(msg.contains("InvalidFieldAccess"));
+    assert!(msg.contains("Cmp"));
+    assert!(msg.contains("block 1, pc 3"));
+    assert!(msg.contains("at 'asn' in 'asn-matches'"));
+
+    // Errors without a location are displayed as before.
+    assert_eq!(VmError::InvalidFieldAccess.to_string(), "InvalidFieldAccess");
+    assert!(VmError::InvalidFieldAccess.context().is_none());
+}
+
+#[test]
+fn test_runtime_error_report() {
+    common::init();
+
+    let files = pipeline::test_file(SOURCE_CODE);
+    let report = RotoReport::runtime(&files, error_in_command()).to_string();
+    assert!(report.contains("Runtime error"));
+    assert!(report.contains("at 'asn' while executing 'asn-matches'"));
+
+    // Without a span there's nothing to point at in the source code.
+    let report =
+        RotoReport::runtime(&files, VmError::InvalidFieldAccess).to_string();
+    assert!(report.contains("Runtime error: InvalidFieldAccess"));
+}
+
+const TWO_LINES: &str = r###"
+    filter-map my-filter-map {
+        define {
+            rx_tx msg: BmpMsg;
+        }
+
+        term asn-matches {
+            match {
+                msg.asn == AS65534;
+                msg.origin == AS65535;
+            }
+        }
+
+        apply {
+            filter match asn-matches matching { return accept; };
+            return reject;
+        }
+    }
+
+    type BmpMsg {
+        asn: Asn,
+        origin: Asn
+    }
+"###;
+
+#[test]
+fn test_runtime_error_span() {
+    common::init();
+
+    let runtime = Runtime::from_string(TWO_LINES).unwrap();
+    let mut filter = runtime.filter("my-filter-map").unwrap();
+    filter.bind_context(context());
+
+    // The payload lacks the `origin` field, so the first line of the term
+    // succeeds and the second one fails.
+    let ty =
+        TypeDef::new_record_type(vec![("asn", Box::new(TypeDef::Asn))])
+            .unwrap();
+    let rx = Record::create_instance_with_ordered_fields(
+        &ty,
+        vec![("asn", Asn::from_u32(65534).into())],
+    )
+    .unwrap();
+
+    let err = filter.run(rx).unwrap_err();
+    let ctx = err.context().unwrap();
+    assert_eq!(ctx.section.as_deref(), Some("asn-matches"));
+
+    let start = TWO_LINES.find("msg.origin").unwrap();
+    let span = ctx.span.unwrap();
+    assert_eq!(span.start, start);
+    assert_eq!(&TWO_LINES[span.start..span.end], "msg.origin == AS65535");
+
+    let report = filter.report(err).to_string();
+    assert!(report.contains("while executing 'asn-matches'"));
+}
+
+/// A source of which every method call fails.
+struct Failing;
+
+impl RotoDataSource for Failing {
+    fn methods(&self) -> Vec<DataSourceMethod> {
+        vec![DataSourceMethod::new(
+            "contains",
+            vec![TypeDef::Asn],
+            TypeDef::Bool,
+        )]
+    }
+
+    fn exec_method(
+        &self,
+        _method: usize,
+        _args: &[&TypeValue],
+    ) -> Result<TypeValue, VmError> {
+        Err(VmError::InvalidMethodCall)
+    }
+}
+
+const FAILING_METHOD: &str = r###"
+    filter-map my-filter-map {
+        define {
+            rx_tx msg: BmpMsg;
+        }
+
+        term asn-matches {
+            match {
+                failing.contains(msg.asn);
+            }
+        }
+
+        apply {
+            filter match asn-matches matching { return accept; };
+            return reject;
+        }
+    }
+
+    type BmpMsg {
+        asn: Asn
+    }
+"###;
+
+#[test]
+fn test_runtime_error_method_name() {
+    common::init();
+
+    let mut sources = DataSourceRegistry::new();
+    sources.register("failing", Arc::new(Failing)).unwrap();
+    let runtime =
+        Runtime::from_string_with_sources(FAILING_METHOD, sources).unwrap();
+    let mut filter = runtime.filter("my-filter-map").unwrap();
+    filter.bind_context(context());
+
+    let ty =
+        TypeDef::new_record_type(vec![("asn", Box::new(TypeDef::Asn))])
+            .unwrap();
+    let rx = Record::create_instance_with_ordered_fields(
+        &ty,
+        vec![("asn", Asn::from_u32(65534).into())],
+    )
+    .unwrap();
+
+    let err = filter.run(rx).unwrap_err();
+    assert!(matches!(err.kind(), VmError::InvalidMethodCall));
+    let ctx = err.context().unwrap();
+    assert_eq!(ctx.name.as_deref(), Some("contains"));
+    assert_eq!(ctx.section.as_deref(), Some("asn-matches"));
+    assert!(err.to_string().contains("at 'contains' in 'asn-matches'"));
+
+    let report = filter.report(err).to_string();
+    assert!(report.contains("at 'contains' while executing 'asn-matches'"));
+}
@@ -0,0 +1,144 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use roto::pipeline::DataSourceRegistry;
+use roto::runtime::{Filter, Runtime};
+use roto::traits::RotoDataSource;
+use roto::types::datasources::DataSourceMethod;
+use roto::types::typedef::TypeDef;
+use roto::types::typevalue::TypeValue;
+use roto::vm::VmError;
+
+mod common;
+
+use common::{context, is_accepted};
+
+/// A source that counts how often it is called, to check that the right
+/// hand side of a logical expression is only evaluated when it's needed.
+#[derive(Default)]
+struct Counting {
+    calls: AtomicUsize,
+}
+
+impl Counting {
+    fn calls(&self) -> usize {
+        self.calls.load(Ordering::Relaxed)
+    }
+}
+
+impl RotoDataSource for Counting {
+    fn methods(&self) -> Vec<DataSourceMethod> {
+        vec![DataSourceMethod::new(
+            "contains",
+            vec![TypeDef::Prefix],
+            TypeDef::Bool,
+        )]
+    }
+
+    fn exec_method(
+        &self,
+        _method: usize,
+        _args: &[&TypeValue],
+    ) -> Result<TypeValue, VmError> {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        Ok(true.into())
+    }
+}
+
+const SOURCE_CODE: &str = r###"
+    filter-map or-filter {
+        define {
+            rx_tx msg: Announcement;
+        }
+
+        term holds {
+            match {
+                (msg.origin == AS65000) || counted.contains(msg.prefix);
+            }
+        }
+
+        apply {
+            filter match holds matching { return accept; };
+            return reject;
+        }
+    }
+
+    filter-map and-filter {
+        define {
+            rx_tx msg: Announcement;
+        }
+
+        term holds {
+            match {
+                (msg.origin == AS65000) && counted.contains(msg.prefix);
+            }
+        }
+
+        apply {
+            filter match holds matching { return accept; };
+            return reject;
+        }
+    }
+
+    filter-map lines-filter {
+        define {
+            rx_tx msg: Announcement;
+        }
+
+        term holds {
+            match {
+                msg.origin == AS65000;
+                counted.contains(msg.prefix);
+            }
+        }
+
+        apply {
+            filter match holds matching { return accept; };
+            return reject;
+        }
+    }
+
+    type Announcement {
+        prefix: Prefix,
+        origin: Asn
+    }
+"###;
+
+fn filter(runtime: &Runtime, name: &str) -> Filter {
+    let mut filter = runtime.filter(name).unwrap();
+    filter.bind_context(context());
+    filter
+}
+
+#[test]
+fn test_short_circuit_data_source() {
+    common::init();
+
+    let counted = Arc::new(Counting::default());
+    let mut sources = DataSourceRegistry::new();
+    sources.register("counted", counted.clone()).unwrap();
+    let runtime =
+        Runtime::from_string_with_sources(SOURCE_CODE, sources).unwrap();
+
+    let mut or_filter = filter(&runtime, "or-filter");
+    let mut and_filter = filter(&runtime, "and-filter");
+    let mut lines_filter = filter(&runtime, "lines-filter");
+
+    // The left hand side decides, the source isn't called.
+    assert!(is_accepted(&mut or_filter, "192.0.2.0/24", 65000));
+    assert!(!is_accepted(&mut and_filter, "192.0.2.0/24", 65001));
+    assert_eq!(counted.calls(), 0);
+
+    // The left hand side doesn't decide, the source is called once.
+    assert!(is_accepted(&mut or_filter, "192.0.2.0/24", 65001));
+    assert_eq!(counted.calls(), 1);
+    assert!(is_accepted(&mut and_filter, "192.0.2.0/24", 65000));
+    assert_eq!(counted.calls(), 2);
+
+    // The lines of a match block are joined with an implicit `&&`, a line
+    // that doesn't hold ends the block.
+    assert!(!is_accepted(&mut lines_filter, "192.0.2.0/24", 65001));
+    assert_eq!(counted.calls(), 2);
+    assert!(is_accepted(&mut lines_filter, "192.0.2.0/24", 65000));
+    assert_eq!(counted.calls(), 3);
+}
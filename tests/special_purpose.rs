@@ -0,0 +1,200 @@
+use roto::runtime::{Filter, Runtime};
+use roto::types::builtin::{StringLiteral, SPECIAL_PURPOSE_VERSION};
+use roto::types::typevalue::TypeValue;
+
+mod common;
+
+use common::{announcement, context};
+
+const SOURCE_CODE: &str = r###"
+    filter-map sanity {
+        define {
+            rx_tx msg: Announcement;
+        }
+
+        term martian-prefix {
+            match {
+                msg.prefix.is_martian();
+            }
+        }
+
+        term bogon-prefix {
+            match {
+                msg.prefix.is_bogon();
+            }
+        }
+
+        term reserved-origin {
+            match {
+                msg.origin.is_reserved();
+            }
+        }
+
+        term private-origin {
+            match {
+                msg.origin.is_private();
+            }
+        }
+
+        term documentation-origin {
+            match {
+                msg.origin.is_documentation();
+            }
+        }
+
+        apply {
+            filter match martian-prefix matching {
+                return reject with "martian";
+            };
+            filter match bogon-prefix matching {
+                return reject with "bogon";
+            };
+            filter match reserved-origin matching {
+                return reject with "reserved";
+            };
+            filter match private-origin matching {
+                return reject with "private";
+            };
+            filter match documentation-origin matching {
+                return reject with "documentation";
+            };
+            return accept;
+        }
+    }
+
+    type Announcement {
+        prefix: Prefix,
+        origin: Asn
+    }
+"###;
+
+fn filter() -> Filter {
+    let runtime = Runtime::from_string(SOURCE_CODE).unwrap();
+    let mut filter = runtime.filter("sanity").unwrap();
+    filter.bind_context(context());
+    filter
+}
+
+/// The reason `p` from `origin` is rejected for, or `None` if it is
+/// accepted.
+fn rejected_for(
+    filter: &mut Filter,
+    p: &str,
+    origin: u32,
+) -> Option<TypeValue> {
+    let verdict = filter.run(announcement(p, origin)).unwrap();
+    assert_eq!(verdict.is_accepted(), verdict.reason.is_none());
+    assert_eq!(verdict.special_purpose_version(), SPECIAL_PURPOSE_VERSION);
+    verdict.reason
+}
+
+fn reason(s: &str) -> Option<TypeValue> {
+    Some(StringLiteral::new(s.into()).into())
+}
+
+#[test]
+fn test_martians() {
+    common::init();
+
+    let mut filter = filter();
+    for p in [
+        "0.0.0.0/8",
+        "127.0.0.1/32",
+        "169.254.10.0/24",
+        "224.0.0.0/4",
+        "239.1.0.0/16",
+        "255.255.255.255/32",
+        "::/128",
+        "::1/128",
+        "::ffff:192.0.2.0/120",
+        "fe80::/64",
+        "ff02::/16",
+    ] {
+        let why = rejected_for(&mut filter, p, 3333);
+        assert_eq!(why, reason("martian"), "{p}");
+    }
+}
+
+#[test]
+fn test_bogons() {
+    common::init();
+
+    let mut filter = filter();
+    for p in [
+        "10.0.0.0/8",
+        "10.1.0.0/16",
+        "100.64.0.0/10",
+        "172.20.0.0/16",
+        "192.0.2.0/24",
+        "192.168.1.0/24",
+        "198.18.0.0/15",
+        "198.51.100.0/24",
+        "203.0.113.128/25",
+        "100::/64",
+        "2001:db8::/32",
+        "2002::/16",
+        "3fff::/20",
+        "fd00::/8",
+    ] {
+        let why = rejected_for(&mut filter, p, 3333);
+        assert_eq!(why, reason("bogon"), "{p}");
+    }
+
+    // The default routes, prefixes that cover bogons, and the prefixes
+    // next to them are fine.
+    for p in [
+        "0.0.0.0/0",
+        "8.0.0.0/7",
+        "1.1.1.0/24",
+        "172.32.0.0/16",
+        "192.0.3.0/24",
+        "198.20.0.0/16",
+        "::/0",
+        "2001:db9::/32",
+        "2a00::/12",
+    ] {
+        assert_eq!(rejected_for(&mut filter, p, 3333), None, "{p}");
+    }
+}
+
+#[test]
+fn test_special_purpose_asns() {
+    common::init();
+
+    let mut filter = filter();
+    for (asn, why) in [
+        (0, Some("reserved")),
+        (23456, Some("reserved")),
+        (65535, Some("reserved")),
+        (65552, Some("reserved")),
+        (131071, Some("reserved")),
+        (4294967295, Some("reserved")),
+        (64512, Some("private")),
+        (65534, Some("private")),
+        (4200000000, Some("private")),
+        (4294967294, Some("private")),
+        (64496, Some("documentation")),
+        (64511, Some("documentation")),
+        (65536, Some("documentation")),
+        (65551, Some("documentation")),
+        (1, None),
+        (3333, None),
+        (64495, None),
+        (131072, None),
+        (4199999999, None),
+    ] {
+        assert_eq!(
+            rejected_for(&mut filter, "1.1.1.0/24", asn),
+            why.and_then(reason),
+            "AS{asn}"
+        );
+    }
+}
+
+#[test]
+fn test_unknown_special_purpose_method() {
+    common::init();
+
+    let source = SOURCE_CODE.replace("is_private", "is_bogon");
+    assert!(Runtime::from_string(source).is_err());
+}
@@ -1,12 +1,10 @@
 use log::trace;
 
-use roto::blocks::Scope;
-use roto::pipeline;
 use roto::types::builtin::{NlriStatus, PeerId, PeerRibType, Provenance, RouteContext};
 use roto::types::collections::{ElementTypeValue, List, Record};
 use roto::types::typedef::TypeDef;
 use roto::types::typevalue::TypeValue;
-use roto::vm::{self, VmResult};
+use roto::runtime::{Runtime, Verdict};
 
 use routecore::bgp::communities::HumanReadableCommunity as Community;
 use inetnum::asn::Asn;
@@ -70,17 +68,17 @@ fn src_code(format_line: &str) -> String {
 }
 
 fn test_data(
-    name: Scope,
+    name: &str,
     source_code: &str,
-) -> Result<VmResult, Box<dyn std::error::Error>> {
+) -> Result<Verdict, Box<dyn std::error::Error>> {
     println!("Evaluate filter-map {}...", name);
 
     let filter_map_arguments =
         vec![("my_asn", TypeValue::from(Asn::from(65534_u32)))];
 
-    let rotolo =
-        pipeline::run_test(source_code, Some((&name, filter_map_arguments)))?;
-    let roto_pack = rotolo.retrieve_pack_as_refs(&name)?;
+    let runtime = Runtime::from_string(source_code)?;
+    let mut filter = runtime.filter(name)?;
+    filter.bind_arguments(filter_map_arguments)?;
 
     let _count: TypeValue = 1_u32.into();
     let prefix: TypeValue =
@@ -129,21 +127,13 @@ fn test_data(
     )
     .unwrap();
 
-    let mem = &mut vm::LinearMemory::uninit();
-
     println!("Used Arguments");
-    println!("{:#?}", &roto_pack.arguments);
+    println!("{:#?}", filter.compiled().get_arguments());
     println!("Used Data Sources");
-    println!("{:#?}", &roto_pack.data_sources);
-
-    for mb in roto_pack.get_mir().iter() {
-        println!("{}", mb);
-    }
+    println!("{:#?}", filter.compiled().get_data_sources());
 
     let my_payload = TypeValue::Record(my_payload);
-    assert!(roto_pack.check_rx_payload_type(&my_payload));
-
-    let ds_ref = roto_pack.data_sources;
+    assert!(*filter.compiled().get_rx_type() == my_payload);
 
     let peer_ip = "192.0.2.0".parse().unwrap();
 
@@ -159,14 +149,9 @@ fn test_data(
     let context = RouteContext::new(None, NlriStatus::InConvergence, provenance);
 
     println!("Start vm...");
-    let mut vm = vm::VmBuilder::new()
-        // .with_arguments(args)
-        .with_context(context)
-        .with_data_sources(ds_ref)
-        .with_mir_code(roto_pack.mir)
-        .build()?;
+    filter.bind_context(context);
 
-    let res = vm.exec(my_payload, None::<Record>, None, mem).unwrap();
+    let res = filter.run(my_payload).unwrap();
 
     println!("\nRESULT");
     println!("action: {}", res.accept_reject);
@@ -186,7 +171,7 @@ fn test_filter_map_message_00() {
     );
 
     let res = test_data(
-        Scope::Filter("my-message-filter-map-5".into()),
+        "my-message-filter-map-5",
         &code_line,
     );
 
@@ -213,7 +198,7 @@ fn test_filter_map_message_01() {
     );
 
     let res = test_data(
-        Scope::Filter("my-message-filter-map-5".into()),
+        "my-message-filter-map-5",
         &code_line,
     );
 
@@ -241,7 +226,7 @@ fn test_filter_map_message_02() {
     );
 
     let res = test_data(
-        Scope::Filter("my-message-filter-map-5".into()),
+        "my-message-filter-map-5",
         &code_line,
     );
 
@@ -270,7 +255,7 @@ fn test_filter_map_message_03() {
     );
 
     let res = test_data(
-        Scope::Filter("my-message-filter-map-5".into()),
+        "my-message-filter-map-5",
         &code_line,
     );
 
@@ -299,7 +284,7 @@ fn test_filter_map_message_04() {
     );
 
     let res = test_data(
-        Scope::Filter("my-message-filter-map-5".into()),
+        "my-message-filter-map-5",
         &code_line,
     );
 
@@ -328,7 +313,7 @@ fn test_filter_map_message_05() {
     );
 
     let res = test_data(
-        Scope::Filter("my-message-filter-map-5".into()),
+        "my-message-filter-map-5",
         &code_line,
     );
 
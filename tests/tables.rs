@@ -0,0 +1,367 @@
+use std::io::Cursor;
+
+use roto::runtime::Runtime;
+use roto::types::builtin::StringLiteral;
+use roto::types::collections::{ElementTypeValue, Record};
+use roto::types::datasources::DataSource;
+use roto::types::loaders::{self, LoadError};
+use roto::types::typedef::TypeDef;
+use roto::types::typevalue::TypeValue;
+
+use inetnum::asn::Asn;
+
+mod common;
+
+use common::context;
+
+// The customers are looked up by their id, which is not the first field of
+// the records.
+const SOURCE_CODE: &str = r###"
+    filter-map customer-filter-map {
+        define {
+            rx_tx msg: Msg;
+        }
+
+        term is-customer {
+            match {
+                customers.contains(msg.id);
+            }
+        }
+
+        apply {
+            filter match is-customer matching { return accept; };
+            return reject;
+        }
+    }
+
+    table customers contains Customer key id {
+        asn: Asn,
+        id: U32
+    }
+
+    type Msg {
+        asn: Asn,
+        id: U32
+    }
+"###;
+
+// The records that are found by the key of the table.
+const FIND_SOURCE_CODE: &str = r###"
+    filter-map find-filter-map {
+        define {
+            rx_tx msg: Msg;
+        }
+
+        term found-asn {
+            match {
+                customers.find(msg.id).asn == msg.asn;
+            }
+        }
+
+        term first-found-asn {
+            match {
+                customers.find_all(msg.id).first().asn == msg.asn;
+            }
+        }
+
+        apply {
+            filter match found-asn not matching { return reject; };
+            filter match first-found-asn not matching { return reject; };
+            return accept;
+        }
+    }
+
+    table customers contains Customer key id {
+        asn: Asn,
+        id: U32
+    }
+
+    type Msg {
+        asn: Asn,
+        id: U32
+    }
+"###;
+
+fn customer_type() -> TypeDef {
+    TypeDef::new_record_type(vec![
+        ("asn", Box::new(TypeDef::Asn)),
+        ("id", Box::new(TypeDef::U32)),
+    ])
+    .unwrap()
+}
+
+fn record(asn: u32, id: u32) -> Record {
+    Record::create_instance_with_ordered_fields(
+        &customer_type(),
+        vec![
+            ("asn", Asn::from_u32(asn).into()),
+            ("id", TypeValue::from(id)),
+        ],
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_table_with_key() {
+    common::init();
+
+    let runtime = Runtime::from_string(SOURCE_CODE).unwrap();
+    let mut filter = runtime.filter("customer-filter-map").unwrap();
+    filter.bind_context(context());
+
+    // A large table, with multiple records for some of the ids.
+    let customers = (0..100_000)
+        .map(|i| record(64_512 + i % 1_000, i / 2))
+        .collect();
+    filter.bind_table("customers", customers).unwrap();
+
+    assert!(filter.run(record(1, 0)).unwrap().is_accepted());
+    assert!(filter.run(record(1, 49_999)).unwrap().is_accepted());
+    assert!(filter.run(record(1, 50_000)).unwrap().is_rejected());
+
+    // The asn is not the key, so it isn't looked up.
+    assert!(filter.run(record(64_512, 60_000)).unwrap().is_rejected());
+}
+
+#[test]
+fn test_table_find_with_key() {
+    common::init();
+
+    let runtime = Runtime::from_string(FIND_SOURCE_CODE).unwrap();
+    let mut filter = runtime.filter("find-filter-map").unwrap();
+    filter.bind_context(context());
+
+    // The first record for id 2 is the third record of the table.
+    let customers = vec![
+        record(65531, 1),
+        record(65532, 3),
+        record(65533, 2),
+        record(65534, 2),
+    ];
+    filter.bind_table("customers", customers).unwrap();
+
+    assert!(filter.run(record(65531, 1)).unwrap().is_accepted());
+    assert!(filter.run(record(65533, 2)).unwrap().is_accepted());
+    assert!(filter.run(record(65532, 3)).unwrap().is_accepted());
+
+    // Only the first record for a key is found.
+    assert!(filter.run(record(65534, 2)).unwrap().is_rejected());
+    assert!(filter.run(record(65531, 2)).unwrap().is_rejected());
+    assert!(filter.run(record(65531, 4)).unwrap().is_rejected());
+}
+
+#[test]
+fn test_table_from_csv() {
+    common::init();
+
+    let runtime = Runtime::from_string(SOURCE_CODE).unwrap();
+    let mut filter = runtime.filter("customer-filter-map").unwrap();
+    filter.bind_context(context());
+
+    let csv = "# Our customers\n\
+        id, asn\n\
+        1, AS65534\n\
+        \n\
+        \"2\", 65535\n";
+    let customers = DataSource::table_from_csv(
+        "customers",
+        &customer_type(),
+        Cursor::new(csv),
+    )
+    .unwrap();
+    filter.bind_source(customers).unwrap();
+
+    assert!(filter.run(record(1, 1)).unwrap().is_accepted());
+    assert!(filter.run(record(1, 2)).unwrap().is_accepted());
+    assert!(filter.run(record(1, 3)).unwrap().is_rejected());
+}
+
+#[test]
+fn test_table_from_json_lines() {
+    common::init();
+
+    let runtime = Runtime::from_string(SOURCE_CODE).unwrap();
+    let mut filter = runtime.filter("customer-filter-map").unwrap();
+    filter.bind_context(context());
+
+    let json = r#"
+        {"id": 1, "asn": "AS65534"}
+        {"asn": 65535, "id": 2}
+    "#;
+    let customers = DataSource::table_from_json_lines(
+        "customers",
+        &customer_type(),
+        Cursor::new(json),
+    )
+    .unwrap();
+    filter.bind_source(customers).unwrap();
+
+    assert!(filter.run(record(1, 1)).unwrap().is_accepted());
+    assert!(filter.run(record(1, 2)).unwrap().is_accepted());
+    assert!(filter.run(record(1, 3)).unwrap().is_rejected());
+}
+
+#[test]
+fn test_keyed_table_from_csv() {
+    common::init();
+
+    let runtime = Runtime::from_string(FIND_SOURCE_CODE).unwrap();
+    let mut filter = runtime.filter("find-filter-map").unwrap();
+    filter.bind_context(context());
+
+    // With a byte order mark, and with Windows line endings.
+    let csv = "\u{feff}asn,id\r\n65533,2\r\n\"AS65534\",2\r\n65535,3\r\n";
+    let customers = DataSource::table_from_csv(
+        "customers",
+        &customer_type(),
+        Cursor::new(csv),
+    )
+    .unwrap();
+    filter.bind_source(customers).unwrap();
+
+    assert!(filter.run(record(65533, 2)).unwrap().is_accepted());
+    assert!(filter.run(record(65535, 3)).unwrap().is_accepted());
+    assert!(filter.run(record(65534, 2)).unwrap().is_rejected());
+    assert!(filter.run(record(65535, 1)).unwrap().is_rejected());
+}
+
+#[test]
+fn test_keyed_table_from_json_lines() {
+    common::init();
+
+    let runtime = Runtime::from_string(FIND_SOURCE_CODE).unwrap();
+    let mut filter = runtime.filter("find-filter-map").unwrap();
+    filter.bind_context(context());
+
+    let json = "\u{feff}{\"id\": 2, \"asn\": 65533}\n\
+        {\"id\": 2, \"asn\": \"AS65534\"}\n\
+        {\"id\": 3, \"asn\": 65535}\n";
+    let customers = DataSource::table_from_json_lines(
+        "customers",
+        &customer_type(),
+        Cursor::new(json),
+    )
+    .unwrap();
+    filter.bind_source(customers).unwrap();
+
+    assert!(filter.run(record(65533, 2)).unwrap().is_accepted());
+    assert!(filter.run(record(65535, 3)).unwrap().is_accepted());
+    assert!(filter.run(record(65534, 2)).unwrap().is_rejected());
+    assert!(filter.run(record(65535, 1)).unwrap().is_rejected());
+}
+
+// Quoted fields can span multiple lines, the rows of the errors are the
+// lines the records start on.
+#[test]
+fn test_csv_multi_line_fields() {
+    common::init();
+
+    let ty = TypeDef::new_record_type(vec![
+        ("id", Box::new(TypeDef::U32)),
+        ("name", Box::new(TypeDef::StringLiteral)),
+    ])
+    .unwrap();
+    let name = |rec: &Record| rec.get_value_for_field("name").cloned();
+    let string = |s: &str| {
+        Some(ElementTypeValue::Primitive(
+            StringLiteral::new(s.into()).into(),
+        ))
+    };
+
+    let csv = "id,name\n\
+        1,\"Example\nNetworks\"\n\
+        \n\
+        2,\"Say \"\"hi\"\",\n\n# not a comment\"\n\
+        3,Other\n";
+    let records = loaders::records_from_csv(&ty, csv.as_bytes()).unwrap();
+    assert_eq!(records.len(), 3);
+    assert_eq!(name(&records[0]), string("Example\nNetworks"));
+    assert_eq!(name(&records[1]), string("Say \"hi\",\n\n# not a comment"));
+    assert_eq!(name(&records[2]), string("Other"));
+
+    let csv = "id,name\n1,\"a\nb\"\nx,c\n";
+    let err = loaders::records_from_csv(&ty, csv.as_bytes()).unwrap_err();
+    assert_eq!((err.row, column(&err)), (4, Some("id".into())));
+
+    let csv = "id,name\n1,\"a\nb\n";
+    let err = loaders::records_from_csv(&ty, csv.as_bytes()).unwrap_err();
+    assert_eq!(err.row, 2);
+}
+
+fn csv_error(csv: &str) -> LoadError {
+    match DataSource::table_from_csv(
+        "customers",
+        &customer_type(),
+        csv.as_bytes(),
+    ) {
+        Ok(_) => panic!("expected an error for {:?}", csv),
+        Err(err) => err,
+    }
+}
+
+fn json_error(json: &str) -> LoadError {
+    match DataSource::table_from_json_lines(
+        "customers",
+        &customer_type(),
+        json.as_bytes(),
+    ) {
+        Ok(_) => panic!("expected an error for {:?}", json),
+        Err(err) => err,
+    }
+}
+
+fn column(err: &LoadError) -> Option<String> {
+    err.column.as_ref().map(|c| c.to_string())
+}
+
+#[test]
+fn test_table_load_errors() {
+    common::init();
+
+    let err = csv_error("id,asn\n1,AS65534\nx,65535\n");
+    assert_eq!((err.row, column(&err)), (3, Some("id".into())));
+
+    let err = csv_error("id\n1\n");
+    assert_eq!((err.row, column(&err)), (1, Some("asn".into())));
+
+    let err = csv_error("id,asn,name\n1,65534,example\n");
+    assert_eq!((err.row, column(&err)), (1, None));
+
+    let err = csv_error("id,asn\n1,65534,example\n");
+    assert_eq!((err.row, column(&err)), (2, None));
+
+    let err = json_error("{\"id\": 1}\n");
+    assert_eq!((err.row, column(&err)), (1, Some("asn".into())));
+
+    let err = json_error("\n{\"id\": 1, \"asn\": true}\n");
+    assert_eq!((err.row, column(&err)), (2, Some("asn".into())));
+    assert_eq!(
+        err.to_string(),
+        "row 2, column 'asn': cannot convert 'true' into type Asn"
+    );
+
+    let err = json_error("{\"id\": 1, \"asn\": 1, \"name\": \"x\"}\n");
+    assert_eq!((err.row, column(&err)), (1, None));
+}
+
+#[test]
+fn test_bind_table_from_file() {
+    common::init();
+
+    let runtime = Runtime::from_string(SOURCE_CODE).unwrap();
+    let mut filter = runtime.filter("customer-filter-map").unwrap();
+    filter.bind_context(context());
+
+    let path = std::env::temp_dir()
+        .join(format!("roto-customers-{}.csv", std::process::id()));
+    std::fs::write(&path, "id,asn\n7,AS65534\n").unwrap();
+    let res = filter.bind_table_from_file("customers", &path);
+    std::fs::remove_file(&path).unwrap();
+    res.unwrap();
+
+    assert!(filter.run(record(1, 7)).unwrap().is_accepted());
+    assert!(filter.run(record(1, 8)).unwrap().is_rejected());
+
+    // Only tables that are declared in the source code can be loaded.
+    assert!(filter.bind_table_from_file("suppliers", &path).is_err());
+}
@@ -0,0 +1,176 @@
+use roto::ast::AcceptReject;
+
+use roto::runtime::{Runtime, Verdict};
+use roto::types::builtin::{
+    NlriStatus, PeerId, PeerRibType, Provenance, RouteContext,
+};
+use roto::types::collections::Record;
+use roto::types::typedef::TypeDef;
+use roto::vm::TraceEvent;
+
+use inetnum::asn::Asn;
+
+mod common;
+
+fn src_code(second_line: &str) -> String {
+    format!(
+        r###"
+    filter-map my-filter-map {{
+        define {{
+            rx pph_asn: MyRec;
+            tx out: Asn;
+        }}
+
+        term peer-asn-matches {{
+            match {{
+                pph_asn.asn == AS65534;
+                {}
+            }}
+        }}
+
+        action set-asn {{
+            pph_asn.asn.set(AS200);
+        }}
+
+        apply {{
+            filter match peer-asn-matches matching {{ set-asn; return accept; }};
+            return reject;
+        }}
+    }}
+
+    type MyRec {{
+        asn: Asn
+    }}
+    "###,
+        second_line
+    )
+}
+
+fn test_data(
+    source_code: &str,
+    trace: bool,
+) -> Result<Verdict, Box<dyn std::error::Error>> {
+    let runtime = Runtime::from_string(source_code)?;
+    let mut filter = runtime.filter("my-filter-map")?;
+    filter.set_trace(trace);
+
+    let payload_type =
+        TypeDef::new_record_type(vec![("asn", Box::new(TypeDef::Asn))])?;
+
+    let payload = Record::create_instance_with_ordered_fields(
+        &payload_type,
+        vec![("asn", Asn::from(65534_u32).into())],
+    )?;
+
+    let peer_ip = "192.0.2.0".parse().unwrap();
+
+    let provenance = Provenance {
+        timestamp: chrono::Utc::now(),
+        connection_id: "192.0.2.10:178".parse().unwrap(),
+        peer_id: PeerId {
+            addr: peer_ip,
+            asn: Asn::from(65534),
+        },
+        peer_bgp_id: [0; 4].into(),
+        peer_distuingisher: [0; 8],
+        peer_rib_type: PeerRibType::OutPost,
+    };
+
+    let context = RouteContext::new(None, NlriStatus::Empty, provenance);
+
+    filter.bind_context(context);
+    let res = filter.run(payload)?;
+
+    Ok(res)
+}
+
+#[test]
+fn test_trace_1() {
+    common::init();
+    let src = src_code("pph_asn.asn != AS0;");
+    let res = test_data(&src, true).unwrap();
+
+    assert_eq!(res.accept_reject, AcceptReject::Accept);
+    let trace = res.trace.unwrap();
+
+    let terms = trace
+        .events()
+        .iter()
+        .filter_map(|e| match e {
+            TraceEvent::Term {
+                term,
+                index,
+                result,
+                span,
+            } => Some((term.as_str(), *index, *result, span.unwrap())),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(terms.len(), 2);
+    assert_eq!(terms[0].0, "peer-asn-matches");
+    assert_eq!((terms[0].1, terms[0].2), (0, true));
+    assert_eq!((terms[1].1, terms[1].2), (1, true));
+    assert_eq!(
+        &src[terms[0].3.start..terms[0].3.end],
+        "pph_asn.asn == AS65534"
+    );
+
+    assert_eq!(
+        trace.fired_match_actions().collect::<Vec<_>>(),
+        vec!["peer-asn-matches"]
+    );
+    assert_eq!(
+        trace.executed_actions().collect::<Vec<_>>(),
+        vec!["set-asn"]
+    );
+
+    // The action changed the asn field of the rx value.
+    assert!(trace.events().iter().any(|e| matches!(
+        e,
+        TraceEvent::RxChanged { span: Some(_), old, new } if old != new
+    )));
+}
+
+#[test]
+fn test_trace_2() {
+    common::init();
+    let src = src_code("pph_asn.asn == AS0;");
+    let res = test_data(&src, true).unwrap();
+
+    assert_eq!(res.accept_reject, AcceptReject::Reject);
+    let trace = res.trace.unwrap();
+
+    assert!(trace.events().iter().any(|e| matches!(
+        e,
+        TraceEvent::Term {
+            index: 1,
+            result: false,
+            ..
+        }
+    )));
+    assert!(trace.events().iter().any(|e| matches!(
+        e,
+        TraceEvent::MatchAction {
+            term_result: false,
+            fired: false,
+            ..
+        }
+    )));
+    assert_eq!(trace.fired_match_actions().count(), 0);
+    assert_eq!(trace.executed_actions().count(), 0);
+    assert!(!trace
+        .events()
+        .iter()
+        .any(|e| matches!(e, TraceEvent::RxChanged { .. })));
+}
+
+#[test]
+fn test_trace_3() {
+    common::init();
+    let src = src_code("pph_asn.asn != AS0;");
+    let res = test_data(&src, false).unwrap();
+
+    assert_eq!(res.accept_reject, AcceptReject::Accept);
+    assert!(res.trace.is_none());
+}
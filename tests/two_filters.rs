@@ -1,27 +1,25 @@
 use log::trace;
 use roto::ast::AcceptReject;
 
-use roto::blocks::Scope::{self};
-use roto::pipeline;
+use roto::runtime::{Runtime, Verdict};
 use roto::types::builtin::{NlriStatus, PeerId, PeerRibType, Provenance, RouteContext};
-use roto::types::collections::{BytesRecord, Record};
+use roto::types::collections::BytesRecord;
 use roto::types::lazyrecord_types::BgpUpdateMessage;
 use roto::types::typevalue::TypeValue;
-use roto::vm::{self, VmResult};
 use inetnum::asn::Asn;
 use routecore::bgp::message::SessionConfig;
 
 mod common;
 
 fn test_data(
-    name: Scope,
+    name: &str,
     source_code: &str,
-) -> Result<(VmResult, BytesRecord<BgpUpdateMessage>), Box<dyn std::error::Error>> {
+) -> Result<(Verdict, BytesRecord<BgpUpdateMessage>), Box<dyn std::error::Error>> {
     println!("Evaluate filter {}...", name);
 
     // Compile the source code in this example
-    let rotolo = pipeline::run_test(source_code, None)?;
-    let roto_pack = rotolo.retrieve_pack_as_refs(&name)?;
+    let runtime = Runtime::from_string(source_code)?;
+    let mut filter = runtime.filter(name)?;
 
     // BGP UPDATE message containing MP_REACH_NLRI path attribute,
     // comprising 5 IPv6 NLRIs
@@ -43,19 +41,11 @@ fn test_data(
 
     let payload = BytesRecord::<BgpUpdateMessage>::new(buf, SessionConfig::modern()).unwrap();
 
-    // Create the VM
     trace!("Used Arguments");
-    trace!("{:#?}", &roto_pack.get_arguments());
+    trace!("{:#?}", filter.compiled().get_arguments());
     trace!("Used Data Sources");
-    trace!("{:#?}", &roto_pack.get_data_sources());
+    trace!("{:#?}", filter.compiled().get_data_sources());
 
-    let ds_ref = roto_pack.get_data_sources();
-
-    for mb in roto_pack.get_mir().iter() {
-        println!("{}", mb);
-    }
-
-        
     let peer_ip = "192.0.2.0".parse().unwrap();
 
     let provenance = Provenance {
@@ -69,23 +59,9 @@ fn test_data(
 
     let context = RouteContext::new(None, NlriStatus::InConvergence, provenance);
 
-    let mut vm = vm::VmBuilder::new()
-        // .with_arguments(args)
-        .with_context(context)
-        .with_data_sources(ds_ref)
-        .with_mir_code(roto_pack.get_mir())
-        .build()?;
-
-    let mem = &mut vm::LinearMemory::uninit();
-    let res = vm
-        .exec(
-            TypeValue::from(payload.clone()),
-            None::<Record>,
-            // Some(filter_map_arguments),
-            None,
-            mem,
-        )
-        .unwrap();
+    filter.bind_context(context);
+
+    let res = filter.run(TypeValue::from(payload.clone())).unwrap();
 
     trace!("\nRESULT");
     trace!("action: {}", res.accept_reject);
@@ -101,7 +77,7 @@ fn test_two_filters_1() {
     common::init();
 
     let (_res, _payload) = test_data(
-        Scope::Filter("bmp-in-filter".into()),
+        "bmp-in-filter",
         r###"
         filter bmp-in-filter {
             define {
@@ -132,7 +108,7 @@ fn test_two_filters_2() {
     common::init();
 
     let (res, _payload) = test_data(
-        Scope::Filter("bmp-in-filter".into()),
+        "bmp-in-filter",
         r###"
         filter bmp-in-filter {
             define {
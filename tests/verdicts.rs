@@ -0,0 +1,156 @@
+use roto::ast::AcceptReject;
+use roto::runtime::Runtime;
+use roto::types::builtin::StringLiteral;
+use roto::types::collections::Record;
+use roto::types::rpki::AspaStatus;
+use roto::types::typedef::TypeDef;
+use roto::types::typevalue::TypeValue;
+
+use inetnum::asn::Asn;
+
+mod common;
+
+use common::context;
+
+fn src_code(apply: &str) -> String {
+    format!(
+        r###"
+        filter-map my-filter-map {{
+            define {{
+                rx_tx msg: BmpMsg;
+            }}
+
+            term rpki-invalid {{
+                match {{
+                    msg.asn == AS65534;
+                }}
+            }}
+
+            term from-transit {{
+                match {{
+                    msg.asn == AS65535;
+                }}
+            }}
+
+            apply {{
+                {}
+            }}
+        }}
+
+        type BmpMsg {{
+            asn: Asn
+        }}
+    "###,
+        apply
+    )
+}
+
+fn payload(asn: u32) -> Record {
+    let ty = TypeDef::new_record_type(vec![("asn", Box::new(TypeDef::Asn))])
+        .unwrap();
+
+    Record::create_instance_with_ordered_fields(
+        &ty,
+        vec![("asn", Asn::from_u32(asn).into())],
+    )
+    .unwrap()
+}
+
+fn reason(s: &str) -> Option<TypeValue> {
+    Some(StringLiteral::new(s.into()).into())
+}
+
+fn run(apply: &str, asn: u32) -> (AcceptReject, Option<TypeValue>) {
+    let runtime = Runtime::from_string(src_code(apply)).unwrap();
+    let mut filter = runtime.filter("my-filter-map").unwrap();
+    filter.bind_context(context());
+
+    let verdict = filter.run(payload(asn)).unwrap();
+    (verdict.accept_reject, verdict.reason)
+}
+
+const APPLY: &str = r###"
+    filter match rpki-invalid matching {
+        return reject with "rpki-invalid";
+    };
+    filter match from-transit matching { continue; };
+    return accept with "ok";
+"###;
+
+// Early returns with and without a reason, and the default verdict.
+#[test]
+fn test_verdicts_1() {
+    common::init();
+
+    assert_eq!(
+        run(APPLY, 65534),
+        (AcceptReject::Reject, reason("rpki-invalid"))
+    );
+    assert_eq!(run(APPLY, 65535), (AcceptReject::Continue, None));
+    assert_eq!(run(APPLY, 1), (AcceptReject::Accept, reason("ok")));
+}
+
+// Without reasons, the verdicts are the same as before.
+#[test]
+fn test_verdicts_2() {
+    common::init();
+
+    let apply = r###"
+        filter match rpki-invalid matching { return reject; };
+        continue;
+    "###;
+
+    assert_eq!(run(apply, 65534), (AcceptReject::Reject, None));
+    assert_eq!(run(apply, 1), (AcceptReject::Continue, None));
+}
+
+// A reason should be a string or an enum variant.
+#[test]
+fn test_verdicts_3() {
+    common::init();
+
+    let apply = r###"
+        filter match rpki-invalid matching { return reject with 8; };
+        return accept;
+    "###;
+
+    assert!(Runtime::from_string(src_code(apply)).is_err());
+}
+
+// A reason should be a constant, since it is evaluated when the filter-map
+// is compiled.
+#[test]
+fn test_verdicts_4() {
+    common::init();
+
+    let apply = r###"
+        filter match rpki-invalid matching {
+            return reject with ASPA_INVALID;
+        };
+        return accept;
+    "###;
+    assert_eq!(
+        run(apply, 65534),
+        (
+            AcceptReject::Reject,
+            Some(AspaStatus::Invalid.into())
+        )
+    );
+
+    let src = r###"
+        filter-map my-filter-map with why: String {
+            define {
+                rx_tx msg: BmpMsg;
+            }
+
+            apply {
+                return reject with why;
+            }
+        }
+
+        type BmpMsg {
+            asn: Asn
+        }
+    "###;
+    assert!(Runtime::from_string(src).is_err());
+}